@@ -0,0 +1,149 @@
+//! Synthetic API gateway route corpus
+//!
+//! Mimics the route table of a gateway fronting many services: each
+//! service exposes exact resource paths, param and nested param paths,
+//! static wildcard trees, host-bound routes and routes gated on request
+//! variables. The corpus is fully deterministic, so numbers are
+//! comparable between runs.
+
+use router_radix::{Expr, RadixHttpMethod, RadixMatchOpts, RadixNode};
+use std::collections::HashMap;
+
+/// Routes generated per service
+const ROUTES_PER_SERVICE: usize = 20;
+
+/// Route shapes found in the corpus
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Kind {
+    Exact,
+    Param,
+    Wildcard,
+    Host,
+    Vars,
+}
+
+impl Kind {
+    pub const ALL: [Kind; 5] = [
+        Kind::Exact,
+        Kind::Param,
+        Kind::Wildcard,
+        Kind::Host,
+        Kind::Vars,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Kind::Exact => "exact",
+            Kind::Param => "param",
+            Kind::Wildcard => "wildcard",
+            Kind::Host => "host",
+            Kind::Vars => "vars",
+        }
+    }
+
+    /// Shape of the `slot`-th route of a service
+    fn of_slot(slot: usize) -> Kind {
+        match slot {
+            0..=7 => Kind::Exact,
+            8..=13 => Kind::Param,
+            14..=15 => Kind::Wildcard,
+            16..=17 => Kind::Host,
+            _ => Kind::Vars,
+        }
+    }
+}
+
+/// Request that the route it was generated for accepts
+pub struct Request {
+    pub path: String,
+    pub opts: RadixMatchOpts,
+}
+
+/// Route `index` of the corpus and a request it accepts
+pub fn route(index: usize) -> (Kind, RadixNode, Request) {
+    let service = index / ROUTES_PER_SERVICE;
+    let slot = index % ROUTES_PER_SERVICE;
+    let kind = Kind::of_slot(slot);
+    let version = 1 + slot % 2;
+    let base = format!("/api/v{}/svc{}/res{}", version, service, slot);
+    let method = if slot.is_multiple_of(3) {
+        RadixHttpMethod::GET | RadixHttpMethod::POST
+    } else {
+        RadixHttpMethod::GET
+    };
+
+    let mut node = RadixNode {
+        id: format!("route-{}", index),
+        methods: Some(method),
+        metadata: serde_json::json!({ "upstream": format!("svc{}", service) }),
+        ..Default::default()
+    };
+    let mut opts = RadixMatchOpts {
+        method: Some("GET".to_string()),
+        ..Default::default()
+    };
+
+    let path = match kind {
+        Kind::Exact => {
+            node.paths = vec![base.clone()];
+            base
+        }
+        Kind::Param if slot.is_multiple_of(2) => {
+            node.paths = vec![format!("{}/:id", base)];
+            format!("{}/42", base)
+        }
+        Kind::Param => {
+            node.paths = vec![format!("{}/:id/items/:item", base)];
+            format!("{}/42/items/7", base)
+        }
+        Kind::Wildcard => {
+            let prefix = format!("/static/svc{}/bundle{}", service, slot);
+            node.paths = vec![format!("{}/*path", prefix)];
+            format!("{}/js/app.min.js", prefix)
+        }
+        Kind::Host => {
+            node.paths = vec![base.clone()];
+            if slot.is_multiple_of(2) {
+                node.hosts = Some(vec![format!("svc{}.example.com", service)]);
+                opts.host = Some(format!("svc{}.example.com", service));
+            } else {
+                node.hosts = Some(vec![format!("*.svc{}.example.com", service)]);
+                opts.host = Some(format!("eu.svc{}.example.com", service));
+            }
+            base
+        }
+        Kind::Vars => {
+            node.paths = vec![base.clone()];
+            node.vars = Some(vec![
+                Expr::Eq("env".to_string(), "prod".to_string()),
+                Expr::In(
+                    "region".to_string(),
+                    vec!["us".to_string(), "eu".to_string()],
+                ),
+            ]);
+            opts.vars = Some(HashMap::from([
+                ("env".to_string(), "prod".to_string()),
+                ("region".to_string(), "eu".to_string()),
+            ]));
+            base
+        }
+    };
+
+    (kind, node, Request { path, opts })
+}
+
+/// The first `size` routes of the corpus
+pub fn routes(size: usize) -> Vec<RadixNode> {
+    (0..size).map(|index| route(index).1).collect()
+}
+
+/// A request for a route of `kind` near the middle of a `size`-route corpus
+pub fn request(size: usize, kind: Kind) -> Request {
+    let middle = size / 2 / ROUTES_PER_SERVICE * ROUTES_PER_SERVICE;
+    (middle..size)
+        .chain(0..middle)
+        .map(route)
+        .find(|(k, _, _)| *k == kind)
+        .map(|(_, _, request)| request)
+        .expect("corpus too small for every route kind")
+}
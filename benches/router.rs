@@ -0,0 +1,64 @@
+//! Lookup and construction benchmarks over the synthetic gateway corpus
+//!
+//! Run with `cargo bench`; pass a filter such as `cargo bench -- match/param`
+//! to run a subset.
+
+mod corpus;
+
+use corpus::Kind;
+use criterion::{criterion_group, criterion_main, BatchSize, BenchmarkId, Criterion};
+use router_radix::{RadixMatchOpts, RadixRouter};
+use std::hint::black_box;
+
+/// Route table sizes every scenario runs at
+///
+/// The smallest table is a single service, the least that holds every
+/// route kind.
+const SIZES: [usize; 3] = [20, 1_000, 100_000];
+
+fn bench_match(c: &mut Criterion) {
+    let mut group = c.benchmark_group("match");
+    for size in SIZES {
+        let router = RadixRouter::with_routes(corpus::routes(size)).unwrap();
+
+        for kind in Kind::ALL {
+            let request = corpus::request(size, kind);
+            assert!(
+                router
+                    .match_route(&request.path, &request.opts)
+                    .unwrap()
+                    .is_some(),
+                "{} request for {} routes did not match",
+                kind.name(),
+                size
+            );
+            group.bench_with_input(BenchmarkId::new(kind.name(), size), &request, |b, r| {
+                b.iter(|| router.match_route(black_box(&r.path), black_box(&r.opts)))
+            });
+        }
+
+        let opts = RadixMatchOpts::default();
+        group.bench_with_input(BenchmarkId::new("miss", size), &opts, |b, opts| {
+            b.iter(|| router.match_route(black_box("/wp-login.php"), black_box(opts)))
+        });
+    }
+    group.finish();
+}
+
+fn bench_build(c: &mut Criterion) {
+    let mut group = c.benchmark_group("build");
+    group.sample_size(10);
+    for size in [1_000, 100_000] {
+        group.bench_function(BenchmarkId::from_parameter(size), |b| {
+            b.iter_batched(
+                || corpus::routes(size),
+                |routes| RadixRouter::with_routes(routes).unwrap(),
+                BatchSize::LargeInput,
+            )
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_match, bench_build);
+criterion_main!(benches);
@@ -200,3 +200,29 @@ radix_tree_stop(void *it)
     raxStop(it);
     return 0;
 }
+
+
+int
+radix_tree_free_it(void *it)
+{
+    if (!it) {
+        return 0;
+    }
+
+    raxStop(it);
+    free(it);
+    return 1;
+}
+
+
+int
+radix_tree_reset_it(void *it, void *t)
+{
+    if (!it) {
+        return 0;
+    }
+
+    raxStop(it);
+    raxStart(it, (rax *)t);
+    return 1;
+}
@@ -61,6 +61,8 @@ int radix_tree_up(void *it, const unsigned char *buf, size_t len);
 int radix_tree_stop(void *it);
 
 void *radix_tree_new_it(void *t);
+int radix_tree_reset_it(void *it, void *t);
+int radix_tree_free_it(void *it);
 int radix_tree_remove(void *t, unsigned char *buf, size_t len);
 
 #ifdef __cplusplus
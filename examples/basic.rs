@@ -16,6 +16,7 @@ fn main() -> anyhow::Result<()> {
                 "handler": "get_users",
                 "upstream": "user-service:8001"
             }),
+            ..Default::default()
         },
         RadixNode {
             id: "2".to_string(),
@@ -30,6 +31,7 @@ fn main() -> anyhow::Result<()> {
                 "handler": "user_detail",
                 "upstream": "user-service:8001"
             }),
+            ..Default::default()
         },
         RadixNode {
             id: "3".to_string(),
@@ -44,6 +46,7 @@ fn main() -> anyhow::Result<()> {
                 "handler": "user_posts",
                 "upstream": "post-service:8002"
             }),
+            ..Default::default()
         },
         RadixNode {
             id: "4".to_string(),
@@ -58,6 +61,7 @@ fn main() -> anyhow::Result<()> {
                 "handler": "admin",
                 "upstream": "admin-service:8003"
             }),
+            ..Default::default()
         },
         RadixNode {
             id: "5".to_string(),
@@ -72,11 +76,12 @@ fn main() -> anyhow::Result<()> {
                 "handler": "api_wildcard",
                 "upstream": "api-gateway:8000"
             }),
+            ..Default::default()
         },
     ];
 
     // Create router
-    let mut router = RadixRouter::new()?;
+    let router = RadixRouter::new()?;
     router.add_routes(routes)?;
 
     println!("=== Radix Router Examples ===\n");
@@ -1,6 +1,7 @@
 /// Concurrent performance test demonstrating the router's thread-safety and async-safety
 /// This example shows that multiple threads can query the router simultaneously without contention
 use router_radix::{RadixHttpMethod, RadixMatchOpts, RadixRouter, RadixNode};
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
 use std::thread;
 use std::time::Instant;
@@ -21,6 +22,7 @@ fn main() -> anyhow::Result<()> {
             filter_fn: None,
             priority: 0,
             metadata: serde_json::json!({"type": "exact"}),
+            ..Default::default()
         },
         RadixNode {
             id: "exact_2".to_string(),
@@ -32,6 +34,7 @@ fn main() -> anyhow::Result<()> {
             filter_fn: None,
             priority: 0,
             metadata: serde_json::json!({"type": "exact"}),
+            ..Default::default()
         },
         // Parameter routes
         RadixNode {
@@ -44,6 +47,7 @@ fn main() -> anyhow::Result<()> {
             filter_fn: None,
             priority: 0,
             metadata: serde_json::json!({"type": "param"}),
+            ..Default::default()
         },
         RadixNode {
             id: "param_2".to_string(),
@@ -55,6 +59,7 @@ fn main() -> anyhow::Result<()> {
             filter_fn: None,
             priority: 0,
             metadata: serde_json::json!({"type": "multi_param"}),
+            ..Default::default()
         },
         // Wildcard route
         RadixNode {
@@ -67,10 +72,11 @@ fn main() -> anyhow::Result<()> {
             filter_fn: None,
             priority: 0,
             metadata: serde_json::json!({"type": "wildcard"}),
+            ..Default::default()
         },
     ];
 
-    let mut router = RadixRouter::new()?;
+    let router = RadixRouter::new()?;
     router.add_routes(routes)?;
     let router = Arc::new(router);
 
@@ -157,16 +163,76 @@ fn main() -> anyhow::Result<()> {
         );
     }
 
+    println!("\n=== Read Scaling Under Concurrent Writes ===");
+    println!("A writer thread keeps adding and deleting a route while readers query\n");
+
+    let stop = Arc::new(AtomicBool::new(false));
+    let writer = {
+        let router = Arc::clone(&router);
+        let stop = Arc::clone(&stop);
+        thread::spawn(move || {
+            let mut updates = 0u64;
+            while !stop.load(Ordering::Relaxed) {
+                let node = RadixNode {
+                    id: "churn".to_string(),
+                    paths: vec!["/churn/*".to_string()],
+                    ..Default::default()
+                };
+                router.add_route(node).unwrap();
+                router.delete_by_id("churn").unwrap();
+                updates += 2;
+            }
+            updates
+        })
+    };
+
+    let max_threads = thread::available_parallelism().map_or(8, |n| n.get());
+    let mut num_threads = 1;
+    while num_threads <= max_threads {
+        let iterations_per_thread = 50_000;
+        let start = Instant::now();
+        let handles: Vec<_> = (0..num_threads)
+            .map(|_| {
+                let router = Arc::clone(&router);
+                let opts = opts.clone();
+                thread::spawn(move || {
+                    for _ in 0..iterations_per_thread {
+                        let _ = router.match_route("/api/user/123", &opts).ok();
+                    }
+                })
+            })
+            .collect();
+        for handle in handles {
+            handle.join().unwrap();
+        }
+
+        let elapsed = start.elapsed();
+        let total_ops = num_threads * iterations_per_thread;
+        println!(
+            "{:>3} reader thread(s)            - {:>12.0} ops/sec",
+            num_threads,
+            total_ops as f64 / elapsed.as_secs_f64()
+        );
+        num_threads *= 2;
+    }
+
+    stop.store(true, Ordering::Relaxed);
+    println!(
+        "\nWriter published {} updates meanwhile",
+        writer.join().unwrap()
+    );
+
     println!("\n=== Key Performance Features ===");
-    println!("✅ Lock-free reads: Each query creates its own iterator");
-    println!("✅ Pre-compiled regex: Zero runtime compilation overhead");
+    println!("✅ Lock-free reads: No lock is taken on the match path");
+    println!("✅ Pre-compiled patterns: Zero runtime compilation overhead");
     println!("✅ Thread-safe: Safe for concurrent access from multiple threads");
     println!("✅ Async-safe: Safe for use in async/await contexts (Tokio, async-std)");
     println!("✅ Zero contention: No lock waiting in the critical path");
 
     println!("\n=== Architecture Highlights ===");
-    println!("• RwLock with read-only access during queries");
-    println!("• Temporary iterators (malloc + init only)");
+    println!("• Immutable route table snapshots published through ArcSwap");
+    println!("• Only writers take a lock, to serialize updates");
+    println!("• Tree iterators pooled per thread");
     println!("• Patterns compiled at route registration");
     println!("• Arc-wrapped compiled patterns (cheap clones)");
     println!("• Hash-based fast path for exact matches");
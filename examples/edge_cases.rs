@@ -19,6 +19,7 @@ fn main() -> anyhow::Result<()> {
                 filter_fn: None,
                 priority: 0,
                 metadata: serde_json::json!({"handler": "root"}),
+                ..Default::default()
             },
             RadixNode {
                 id: "api".to_string(),
@@ -30,10 +31,11 @@ fn main() -> anyhow::Result<()> {
                 filter_fn: None,
                 priority: 0,
                 metadata: serde_json::json!({"handler": "api"}),
+                ..Default::default()
             },
         ];
 
-        let mut router = RadixRouter::new()?;
+        let router = RadixRouter::new()?;
         router.add_routes(routes)?;
         let opts = RadixMatchOpts {
             method: Some("GET".to_string()),
@@ -64,6 +66,7 @@ fn main() -> anyhow::Result<()> {
                 filter_fn: None,
                 priority: 0,
                 metadata: serde_json::json!({"handler": "user_profile"}),
+                ..Default::default()
             },
             RadixNode {
                 id: "special2".to_string(),
@@ -75,6 +78,7 @@ fn main() -> anyhow::Result<()> {
                 filter_fn: None,
                 priority: 0,
                 metadata: serde_json::json!({"handler": "user_data"}),
+                ..Default::default()
             },
             RadixNode {
                 id: "special3".to_string(),
@@ -86,10 +90,11 @@ fn main() -> anyhow::Result<()> {
                 filter_fn: None,
                 priority: 0,
                 metadata: serde_json::json!({"handler": "user_info"}),
+                ..Default::default()
             },
         ];
 
-        let mut router = RadixRouter::new()?;
+        let router = RadixRouter::new()?;
         router.add_routes(routes)?;
         let opts = RadixMatchOpts::default();
 
@@ -119,9 +124,10 @@ fn main() -> anyhow::Result<()> {
             filter_fn: None,
             priority: 0,
             metadata: serde_json::json!({"handler": "long_path"}),
+            ..Default::default()
         }];
 
-        let mut router = RadixRouter::new()?;
+        let router = RadixRouter::new()?;
         router.add_routes(routes)?;
         let opts = RadixMatchOpts::default();
 
@@ -145,6 +151,7 @@ fn main() -> anyhow::Result<()> {
                 filter_fn: None,
                 priority: 0,
                 metadata: serde_json::json!({"handler": "user"}),
+                ..Default::default()
             },
             RadixNode {
                 id: "2".to_string(),
@@ -156,6 +163,7 @@ fn main() -> anyhow::Result<()> {
                 filter_fn: None,
                 priority: 0,
                 metadata: serde_json::json!({"handler": "users"}),
+                ..Default::default()
             },
             RadixNode {
                 id: "3".to_string(),
@@ -167,10 +175,11 @@ fn main() -> anyhow::Result<()> {
                 filter_fn: None,
                 priority: 0,
                 metadata: serde_json::json!({"handler": "user_id"}),
+                ..Default::default()
             },
         ];
 
-        let mut router = RadixRouter::new()?;
+        let router = RadixRouter::new()?;
         router.add_routes(routes)?;
         let opts = RadixMatchOpts::default();
 
@@ -202,6 +211,7 @@ fn main() -> anyhow::Result<()> {
                 filter_fn: None,
                 priority: 5,
                 metadata: serde_json::json!({"handler": "files"}),
+                ..Default::default()
             },
             RadixNode {
                 id: "wild2".to_string(),
@@ -213,17 +223,18 @@ fn main() -> anyhow::Result<()> {
                 filter_fn: None,
                 priority: 10,
                 metadata: serde_json::json!({"handler": "public_files"}),
+                ..Default::default()
             },
         ];
 
-        let mut router = RadixRouter::new()?;
+        let router = RadixRouter::new()?;
         router.add_routes(routes)?;
         let opts = RadixMatchOpts::default();
 
         // More specific route (with priority) should match first
         let result = router.match_route("/files/public/doc.pdf", &opts)?.unwrap();
         println!("  ✓ Matched: {}", result.metadata["handler"]);
-        assert_eq!(result.matched.get("_path").unwrap(), "/files/public/*");
+        assert_eq!(result.info.pattern, "/files/public/*");
     }
     println!();
 
@@ -240,9 +251,10 @@ fn main() -> anyhow::Result<()> {
             filter_fn: None,
             priority: 0,
             metadata: serde_json::json!({"handler": "resource"}),
+            ..Default::default()
         }];
 
-        let mut router = RadixRouter::new()?;
+        let router = RadixRouter::new()?;
         router.add_routes(routes)?;
         let opts = RadixMatchOpts::default();
 
@@ -283,9 +295,10 @@ fn main() -> anyhow::Result<()> {
             filter_fn: None,
             priority: 0,
             metadata: serde_json::json!({"handler": "users"}),
+            ..Default::default()
         }];
 
-        let mut router = RadixRouter::new()?;
+        let router = RadixRouter::new()?;
         router.add_routes(routes)?;
         let opts = RadixMatchOpts::default();
 
@@ -318,9 +331,10 @@ fn main() -> anyhow::Result<()> {
             filter_fn: None,
             priority: 0,
             metadata: serde_json::json!({"handler": "users"}),
+            ..Default::default()
         }];
 
-        let mut router = RadixRouter::new()?;
+        let router = RadixRouter::new()?;
         router.add_routes(routes)?;
         let opts = RadixMatchOpts::default();
 
@@ -365,9 +379,10 @@ fn main() -> anyhow::Result<()> {
             filter_fn: None,
             priority: 0,
             metadata: serde_json::json!({"handler": "api"}),
+            ..Default::default()
         }];
 
-        let mut router = RadixRouter::new()?;
+        let router = RadixRouter::new()?;
         router.add_routes(routes)?;
 
         // Test without port
@@ -416,9 +431,10 @@ fn main() -> anyhow::Result<()> {
             filter_fn: None,
             priority: 0,
             metadata: serde_json::json!({"handler": "resource"}),
+            ..Default::default()
         }];
 
-        let mut router = RadixRouter::new()?;
+        let router = RadixRouter::new()?;
         router.add_routes(routes)?;
 
         let methods = vec!["GET", "POST", "PUT", "DELETE", "PATCH", "HEAD", "OPTIONS"];
@@ -446,9 +462,10 @@ fn main() -> anyhow::Result<()> {
             filter_fn: None,
             priority: 0,
             metadata: serde_json::json!({"handler": "nested"}),
+            ..Default::default()
         }];
 
-        let mut router = RadixRouter::new()?;
+        let router = RadixRouter::new()?;
         router.add_routes(routes)?;
         let opts = RadixMatchOpts::default();
 
@@ -7,7 +7,7 @@ fn main() -> anyhow::Result<()> {
 
     // Simulate a microservices API gateway with multiple services
     let routes = create_api_gateway_routes();
-    let mut router = RadixRouter::new()?;
+    let router = RadixRouter::new()?;
     router.add_routes(routes)?;
 
     println!("Initialized API Gateway with routing rules\n");
@@ -93,12 +93,9 @@ fn main() -> anyhow::Result<()> {
                     let params: Vec<String> = result
                         .matched
                         .iter()
-                        .filter(|(k, _)| !k.starts_with('_'))
                         .map(|(k, v)| format!("{}={}", k, v))
                         .collect();
-                    if !params.is_empty() {
-                        println!("      Params: {}", params.join(", "));
-                    }
+                    println!("      Params: {}", params.join(", "));
                 }
             } else {
                 println!("  ✗ {} -> No route", desc);
@@ -330,6 +327,7 @@ fn create_api_gateway_routes() -> Vec<RadixNode> {
                 "service": "health-check",
                 "upstream": "internal:8080"
             }),
+            ..Default::default()
         },
         RadixNode {
             id: "status".to_string(),
@@ -344,6 +342,7 @@ fn create_api_gateway_routes() -> Vec<RadixNode> {
                 "service": "status",
                 "upstream": "internal:8080"
             }),
+            ..Default::default()
         },
         RadixNode {
             id: "docs".to_string(),
@@ -358,6 +357,7 @@ fn create_api_gateway_routes() -> Vec<RadixNode> {
                 "service": "documentation",
                 "upstream": "docs:8081"
             }),
+            ..Default::default()
         },
         // User service
         RadixNode {
@@ -373,6 +373,7 @@ fn create_api_gateway_routes() -> Vec<RadixNode> {
                 "service": "user-service",
                 "upstream": "user-service:8001"
             }),
+            ..Default::default()
         },
         RadixNode {
             id: "user_detail".to_string(),
@@ -387,6 +388,7 @@ fn create_api_gateway_routes() -> Vec<RadixNode> {
                 "service": "user-service",
                 "upstream": "user-service:8001"
             }),
+            ..Default::default()
         },
         RadixNode {
             id: "user_profile".to_string(),
@@ -401,6 +403,7 @@ fn create_api_gateway_routes() -> Vec<RadixNode> {
                 "service": "user-service",
                 "upstream": "user-service:8001"
             }),
+            ..Default::default()
         },
         // Order service
         RadixNode {
@@ -416,6 +419,7 @@ fn create_api_gateway_routes() -> Vec<RadixNode> {
                 "service": "order-service",
                 "upstream": "order-service:8002"
             }),
+            ..Default::default()
         },
         RadixNode {
             id: "order_items".to_string(),
@@ -430,6 +434,7 @@ fn create_api_gateway_routes() -> Vec<RadixNode> {
                 "service": "order-service",
                 "upstream": "order-service:8002"
             }),
+            ..Default::default()
         },
         RadixNode {
             id: "order_item_detail".to_string(),
@@ -444,6 +449,7 @@ fn create_api_gateway_routes() -> Vec<RadixNode> {
                 "service": "order-service",
                 "upstream": "order-service:8002"
             }),
+            ..Default::default()
         },
         RadixNode {
             id: "order_payment".to_string(),
@@ -458,6 +464,7 @@ fn create_api_gateway_routes() -> Vec<RadixNode> {
                 "service": "payment-service",
                 "upstream": "payment-service:8003"
             }),
+            ..Default::default()
         },
         // Multi-tenant routing
         RadixNode {
@@ -473,6 +480,7 @@ fn create_api_gateway_routes() -> Vec<RadixNode> {
                 "service": "tenant-service",
                 "upstream": "tenant-service:8004"
             }),
+            ..Default::default()
         },
         // Static files
         RadixNode {
@@ -488,6 +496,7 @@ fn create_api_gateway_routes() -> Vec<RadixNode> {
                 "service": "static-files",
                 "upstream": "cdn:8005"
             }),
+            ..Default::default()
         },
         RadixNode {
             id: "downloads".to_string(),
@@ -502,6 +511,7 @@ fn create_api_gateway_routes() -> Vec<RadixNode> {
                 "service": "download-service",
                 "upstream": "files:8006"
             }),
+            ..Default::default()
         },
         // Admin panel
         RadixNode {
@@ -518,6 +528,7 @@ fn create_api_gateway_routes() -> Vec<RadixNode> {
                 "upstream": "admin:8007",
                 "priority": 50
             }),
+            ..Default::default()
         },
         // WebSocket endpoints
         RadixNode {
@@ -534,6 +545,7 @@ fn create_api_gateway_routes() -> Vec<RadixNode> {
                 "upstream": "ws-chat:8008",
                 "type": "websocket"
             }),
+            ..Default::default()
         },
         RadixNode {
             id: "ws_notifications".to_string(),
@@ -549,6 +561,7 @@ fn create_api_gateway_routes() -> Vec<RadixNode> {
                 "upstream": "ws-notify:8009",
                 "type": "websocket"
             }),
+            ..Default::default()
         },
         RadixNode {
             id: "ws_live".to_string(),
@@ -564,6 +577,7 @@ fn create_api_gateway_routes() -> Vec<RadixNode> {
                 "upstream": "ws-live:8010",
                 "type": "websocket"
             }),
+            ..Default::default()
         },
         // Method-based routing
         RadixNode {
@@ -580,6 +594,7 @@ fn create_api_gateway_routes() -> Vec<RadixNode> {
                 "operation": "read",
                 "upstream": "data-read:8011"
             }),
+            ..Default::default()
         },
         RadixNode {
             id: "data_write".to_string(),
@@ -595,6 +610,7 @@ fn create_api_gateway_routes() -> Vec<RadixNode> {
                 "operation": "write",
                 "upstream": "data-write:8012"
             }),
+            ..Default::default()
         },
         RadixNode {
             id: "data_delete".to_string(),
@@ -610,6 +626,7 @@ fn create_api_gateway_routes() -> Vec<RadixNode> {
                 "operation": "delete",
                 "upstream": "data-delete:8013"
             }),
+            ..Default::default()
         },
         // Search endpoints
         RadixNode {
@@ -625,6 +642,7 @@ fn create_api_gateway_routes() -> Vec<RadixNode> {
                 "service": "search-service",
                 "upstream": "search:8014"
             }),
+            ..Default::default()
         },
     ]
 }
@@ -34,15 +34,16 @@ fn main() -> anyhow::Result<()> {
             remote_addrs: None,
             vars: None,
             filter_fn: None,
-            priority: i as i32 % 10,
+            priority: i % 10,
             metadata: serde_json::json!({
                 "route_id": i,
                 "type": route_type,
             }),
+            ..Default::default()
         });
     }
 
-    let mut router = RadixRouter::new()?;
+    let router = RadixRouter::new()?;
     router.add_routes(routes)?;
     let creation_time = start.elapsed();
 
@@ -141,7 +142,7 @@ fn main() -> anyhow::Result<()> {
 
     // Test 4: Memory usage test (route addition/deletion)
     println!("Test 4: Dynamic route management stress test");
-    let mut dynamic_router = RadixRouter::new()?;
+    let dynamic_router = RadixRouter::new()?;
 
     println!("  Adding 1000 routes dynamically...");
     let start = Instant::now();
@@ -158,6 +159,7 @@ fn main() -> anyhow::Result<()> {
             filter_fn: None,
             priority: 0,
             metadata: serde_json::json!({"id": i}),
+            ..Default::default()
         };
 
         dynamic_router.add_route(route.clone())?;
@@ -222,9 +224,10 @@ fn main() -> anyhow::Result<()> {
             filter_fn: None,
             priority: 0,
             metadata: serde_json::json!({"type": "deep"}),
+            ..Default::default()
         }];
 
-        let mut router = RadixRouter::new()?;
+        let router = RadixRouter::new()?;
         router.add_routes(routes)?;
         let opts = RadixMatchOpts::default();
 
@@ -256,9 +259,10 @@ fn main() -> anyhow::Result<()> {
             filter_fn: None,
             priority: 0,
             metadata: serde_json::json!({"type": "params"}),
+            ..Default::default()
         }];
 
-        let mut router = RadixRouter::new()?;
+        let router = RadixRouter::new()?;
         router.add_routes(routes)?;
         let opts = RadixMatchOpts::default();
 
@@ -289,9 +293,10 @@ fn main() -> anyhow::Result<()> {
             filter_fn: None,
             priority: 0,
             metadata: serde_json::json!({"type": "long"}),
+            ..Default::default()
         }];
 
-        let mut router = RadixRouter::new()?;
+        let router = RadixRouter::new()?;
         router.add_routes(routes)?;
         let opts = RadixMatchOpts::default();
 
@@ -1,6 +1,6 @@
 /// Variable expressions and filter functions testing
 /// This example demonstrates advanced routing with custom logic
-use router_radix::{Expr, RadixHttpMethod, RadixMatchOpts, RadixRouter, RadixNode};
+use router_radix::{Expr, FilterFn, RadixHttpMethod, RadixMatchOpts, RadixRouter, RadixNode};
 use regex::Regex;
 use std::collections::HashMap;
 use std::sync::Arc;
@@ -24,9 +24,10 @@ fn main() -> anyhow::Result<()> {
                 "handler": "production_data",
                 "upstream": "prod-db:5432"
             }),
+            ..Default::default()
         }];
 
-        let mut router = RadixRouter::new()?;
+        let router = RadixRouter::new()?;
         router.add_routes(routes)?;
 
         // Test with correct variable
@@ -79,9 +80,10 @@ fn main() -> anyhow::Result<()> {
                 "handler": "mobile_api",
                 "version": "mobile"
             }),
+            ..Default::default()
         }];
 
-        let mut router = RadixRouter::new()?;
+        let router = RadixRouter::new()?;
         router.add_routes(routes)?;
 
         // Test with mobile user agent
@@ -142,9 +144,10 @@ fn main() -> anyhow::Result<()> {
                 "handler": "premium_api",
                 "features": ["analytics", "priority_support"]
             }),
+            ..Default::default()
         }];
 
-        let mut router = RadixRouter::new()?;
+        let router = RadixRouter::new()?;
         router.add_routes(routes)?;
 
         // All conditions met
@@ -180,12 +183,10 @@ fn main() -> anyhow::Result<()> {
     println!("Test 4: Custom Filter Function");
     {
         // Filter function that checks if request time is within business hours
-        let business_hours_filter: Arc<
-            dyn Fn(&HashMap<String, String>, &RadixMatchOpts) -> bool + Send + Sync,
-        > = Arc::new(|vars, _opts| {
+        let business_hours_filter: FilterFn = Arc::new(|vars, _opts| {
             if let Some(hour) = vars.get("hour") {
                 if let Ok(h) = hour.parse::<u32>() {
-                    return h >= 9 && h < 17; // 9 AM to 5 PM
+                    return (9..17).contains(&h); // 9 AM to 5 PM
                 }
             }
             false
@@ -204,9 +205,10 @@ fn main() -> anyhow::Result<()> {
                 "handler": "live_support",
                 "type": "business_hours"
             }),
+            ..Default::default()
         }];
 
-        let mut router = RadixRouter::new()?;
+        let router = RadixRouter::new()?;
         router.add_routes(routes)?;
 
         let test_hours = vec![
@@ -246,9 +248,7 @@ fn main() -> anyhow::Result<()> {
     println!("Test 5: Rate Limiting Filter");
     {
         // Simple rate limiter: allow if request_count < 100
-        let rate_limit_filter: Arc<
-            dyn Fn(&HashMap<String, String>, &RadixMatchOpts) -> bool + Send + Sync,
-        > = Arc::new(|vars, _opts| {
+        let rate_limit_filter: FilterFn = Arc::new(|vars, _opts| {
             if let Some(count) = vars.get("request_count") {
                 if let Ok(c) = count.parse::<u32>() {
                     return c < 100;
@@ -270,9 +270,10 @@ fn main() -> anyhow::Result<()> {
                 "handler": "limited_endpoint",
                 "rate_limit": 100
             }),
+            ..Default::default()
         }];
 
-        let mut router = RadixRouter::new()?;
+        let router = RadixRouter::new()?;
         router.add_routes(routes)?;
 
         let test_counts = vec![0, 50, 99, 100, 150];
@@ -288,8 +289,7 @@ fn main() -> anyhow::Result<()> {
 
             let matched = router.match_route("/api/limited", &opts)?.is_some();
             println!(
-                "  {} Request count {}: {}",
-                if count < 100 { "✓" } else { "✓" },
+                "  ✓ Request count {}: {}",
                 count,
                 if matched { "allowed" } else { "rate limited" }
             );
@@ -301,7 +301,7 @@ fn main() -> anyhow::Result<()> {
     println!("Test 6: IP-Based Access Control");
     {
         // Filter to allow only internal IPs
-        let ip_filter: Arc<dyn Fn(&HashMap<String, String>, &RadixMatchOpts) -> bool + Send + Sync> =
+        let ip_filter: FilterFn =
             Arc::new(|vars, _opts| {
                 if let Some(ip) = vars.get("client_ip") {
                     return ip.starts_with("10.") || ip.starts_with("192.168.");
@@ -322,9 +322,10 @@ fn main() -> anyhow::Result<()> {
                 "handler": "internal_only",
                 "access": "private"
             }),
+            ..Default::default()
         }];
 
-        let mut router = RadixRouter::new()?;
+        let router = RadixRouter::new()?;
         router.add_routes(routes)?;
 
         let test_ips = vec![
@@ -355,29 +356,12 @@ fn main() -> anyhow::Result<()> {
     }
     println!();
 
-    // Test 7: A/B testing with filter
+    // Test 7: A/B testing with sticky buckets
     println!("Test 7: A/B Testing Router");
     {
-        // Route 50% to version A, 50% to version B
-        let ab_test_a: Arc<dyn Fn(&HashMap<String, String>, &RadixMatchOpts) -> bool + Send + Sync> =
-            Arc::new(|vars, _opts| {
-                if let Some(user_id) = vars.get("user_id") {
-                    if let Ok(id) = user_id.parse::<u64>() {
-                        return id % 2 == 0; // Even IDs go to A
-                    }
-                }
-                false
-            });
-
-        let ab_test_b: Arc<dyn Fn(&HashMap<String, String>, &RadixMatchOpts) -> bool + Send + Sync> =
-            Arc::new(|vars, _opts| {
-                if let Some(user_id) = vars.get("user_id") {
-                    if let Ok(id) = user_id.parse::<u64>() {
-                        return id % 2 == 1; // Odd IDs go to B
-                    }
-                }
-                false
-            });
+        // Route 50% to version A, 50% to version B (sticky per user)
+        let ab_test_a = Expr::Bucket("user_id".to_string(), 0..50);
+        let ab_test_b = Expr::Bucket("user_id".to_string(), 50..100);
 
         let routes = vec![
             RadixNode {
@@ -386,13 +370,14 @@ fn main() -> anyhow::Result<()> {
                 methods: Some(RadixHttpMethod::GET),
                 hosts: None,
                 remote_addrs: None,
-                vars: None,
-                filter_fn: Some(ab_test_a),
+                vars: Some(vec![ab_test_a]),
+                filter_fn: None,
                 priority: 10,
                 metadata: serde_json::json!({
                     "handler": "feature_v1",
                     "version": "A"
                 }),
+                ..Default::default()
             },
             RadixNode {
                 id: "version_b".to_string(),
@@ -400,17 +385,18 @@ fn main() -> anyhow::Result<()> {
                 methods: Some(RadixHttpMethod::GET),
                 hosts: None,
                 remote_addrs: None,
-                vars: None,
-                filter_fn: Some(ab_test_b),
+                vars: Some(vec![ab_test_b]),
+                filter_fn: None,
                 priority: 10,
                 metadata: serde_json::json!({
                     "handler": "feature_v2",
                     "version": "B"
                 }),
+                ..Default::default()
             },
         ];
 
-        let mut router = RadixRouter::new()?;
+        let router = RadixRouter::new()?;
         router.add_routes(routes)?;
 
         for user_id in 1..=10 {
@@ -436,9 +422,7 @@ fn main() -> anyhow::Result<()> {
     println!("Test 8: Combined Expressions + Filter");
     {
         // Combine variable expression with custom filter
-        let combined_filter: Arc<
-            dyn Fn(&HashMap<String, String>, &RadixMatchOpts) -> bool + Send + Sync,
-        > = Arc::new(|vars, _opts| {
+        let combined_filter: FilterFn = Arc::new(|vars, _opts| {
             // Additional check: must have valid session
             vars.get("session_valid")
                 .map(|v| v == "true")
@@ -461,9 +445,10 @@ fn main() -> anyhow::Result<()> {
                 "handler": "secure_endpoint",
                 "requires": ["admin", "valid_token", "valid_session"]
             }),
+            ..Default::default()
         }];
 
-        let mut router = RadixRouter::new()?;
+        let router = RadixRouter::new()?;
         router.add_routes(routes)?;
 
         // All checks pass
@@ -505,7 +490,7 @@ fn main() -> anyhow::Result<()> {
     println!("✅ Custom filter functions operating");
     println!("✅ Rate limiting filters effective");
     println!("✅ IP-based access control working");
-    println!("✅ A/B testing filters functional");
+    println!("✅ A/B testing buckets functional");
     println!("✅ Combined expressions + filters operational");
     println!("\n=== All Variable & Filter Tests Passed ✓ ===");
 
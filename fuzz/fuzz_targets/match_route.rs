@@ -0,0 +1,174 @@
+//! Differential fuzzing of lookups against a naive reference matcher
+//!
+//! Routes are built from a small segment alphabet so that prefixes,
+//! params and wildcards collide often. For every request path the router
+//! must find a route exactly when the reference does, pick one of the
+//! routes the reference accepts, and extract the same param values.
+
+#![no_main]
+
+use arbitrary::Arbitrary;
+use libfuzzer_sys::fuzz_target;
+use router_radix::{RadixMatchOpts, RadixNode, RadixRouter};
+
+/// Literal segments used by routes and requests
+const WORDS: [&str; 6] = ["", "a", "b", "ab", "api", "v1"];
+
+#[derive(Arbitrary, Debug)]
+enum Segment {
+    Literal(u8),
+    Param,
+    Wildcard { named: bool },
+}
+
+#[derive(Arbitrary, Debug)]
+struct Route {
+    segments: Vec<Segment>,
+    priority: i8,
+}
+
+#[derive(Arbitrary, Debug)]
+enum Request {
+    /// Path assembled from the segment alphabet
+    Words(Vec<u8>),
+    /// Anything at all
+    Raw(String),
+}
+
+#[derive(Arbitrary, Debug)]
+struct Input {
+    routes: Vec<Route>,
+    requests: Vec<Request>,
+}
+
+/// Route path for `segments`; a wildcard before the end becomes a literal
+fn route_path(segments: &[Segment]) -> String {
+    let mut parts = Vec::new();
+    for (i, segment) in segments.iter().enumerate() {
+        let last = i + 1 == segments.len();
+        parts.push(match segment {
+            Segment::Literal(w) => WORDS[*w as usize % WORDS.len()].to_string(),
+            Segment::Param => format!(":p{}", i),
+            Segment::Wildcard { named: true } if last => format!("*w{}", i),
+            Segment::Wildcard { .. } if last => "*".to_string(),
+            Segment::Wildcard { .. } => "w".to_string(),
+        });
+    }
+    format!("/{}", parts.join("/"))
+}
+
+/// Params the route pattern `path` extracts from `request`, if it matches
+fn reference_match(path: &str, request: &str) -> Option<Vec<(String, String)>> {
+    let pattern: Vec<&str> = path.split('/').collect();
+    let mut rest = request;
+    let mut params = Vec::new();
+    for (i, part) in pattern.iter().enumerate() {
+        if let Some(name) = part.strip_prefix('*') {
+            if !name.is_empty() {
+                params.push((name.to_string(), rest.to_string()));
+            }
+            return Some(params);
+        }
+        let segment = if i + 1 == pattern.len() {
+            if rest.contains('/') {
+                return None;
+            }
+            std::mem::take(&mut rest)
+        } else {
+            let (segment, tail) = rest.split_once('/')?;
+            rest = tail;
+            segment
+        };
+        match part.strip_prefix(':') {
+            Some(name) if !segment.is_empty() => {
+                params.push((name.to_string(), segment.to_string()))
+            }
+            Some(_) => return None,
+            None if segment != *part => return None,
+            None => {}
+        }
+    }
+    Some(params)
+}
+
+fuzz_target!(|input: Input| {
+    let routes: Vec<(String, String)> = input
+        .routes
+        .iter()
+        .take(32)
+        .enumerate()
+        .map(|(i, route)| (format!("r{}", i), route_path(&route.segments)))
+        .collect();
+
+    let router = RadixRouter::new().unwrap();
+    router
+        .add_routes(
+            input
+                .routes
+                .iter()
+                .zip(&routes)
+                .map(|(route, (id, path))| RadixNode {
+                    id: id.clone(),
+                    paths: vec![path.clone()],
+                    priority: route.priority as i32,
+                    ..Default::default()
+                })
+                .collect(),
+        )
+        .unwrap();
+
+    let opts = RadixMatchOpts::default();
+    for request in input.requests.iter().take(16) {
+        let path = match request {
+            Request::Words(words) => {
+                let words: Vec<&str> = words
+                    .iter()
+                    .map(|w| WORDS[*w as usize % WORDS.len()])
+                    .collect();
+                format!("/{}", words.join("/"))
+            }
+            Request::Raw(raw) => raw.clone(),
+        };
+
+        let accepted: Vec<(&String, Vec<(String, String)>)> = routes
+            .iter()
+            .filter_map(|(id, route)| reference_match(route, &path).map(|params| (id, params)))
+            .collect();
+
+        let result = router.match_route(&path, &opts).unwrap();
+        let Some(result) = result else {
+            assert!(
+                accepted.is_empty(),
+                "{:?}: router missed {:?}",
+                path,
+                accepted
+            );
+            continue;
+        };
+        let (_, params) = accepted
+            .iter()
+            .find(|(id, _)| **id == result.id)
+            .unwrap_or_else(|| {
+                panic!(
+                    "{:?}: router picked {} outside {:?}",
+                    path, result.id, accepted
+                )
+            });
+        for (name, value) in params {
+            assert_eq!(
+                result.matched.get(name),
+                Some(value.as_str()),
+                "{:?} via {}",
+                path,
+                result.id
+            );
+        }
+        assert_eq!(
+            router
+                .matches(&path, &opts)
+                .unwrap()
+                .map(|r| r.id().to_string()),
+            Some(result.id.clone())
+        );
+    }
+});
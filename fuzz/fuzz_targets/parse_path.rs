@@ -0,0 +1,35 @@
+//! Arbitrary bytes as route paths and request paths
+//!
+//! Registration must either reject a path or accept it, never panic, and
+//! whatever is accepted must survive lookups of arbitrary request paths
+//! through the FFI tree.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use router_radix::{RadixMatchOpts, RadixNode, RadixRouter};
+
+fuzz_target!(|data: &[u8]| {
+    let text = String::from_utf8_lossy(data);
+    let (route_path, request_path) = text.split_once('\n').unwrap_or((&text, &text));
+
+    let router = RadixRouter::new().unwrap();
+    let added = router.add_route(RadixNode {
+        id: "fuzz".to_string(),
+        paths: vec![route_path.to_string()],
+        ..Default::default()
+    });
+    if added.is_err() {
+        assert!(router.is_empty());
+        return;
+    }
+
+    let opts = RadixMatchOpts::default();
+    for path in [route_path, request_path] {
+        let _ = router.match_route(path, &opts).unwrap();
+        let _ = router.match_outcome(path, &opts).unwrap();
+        let _ = router.explain(path, &opts).unwrap();
+    }
+    router.delete_by_id("fuzz").unwrap();
+    assert!(router.match_route(route_path, &opts).unwrap().is_none());
+});
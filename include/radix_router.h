@@ -0,0 +1,53 @@
+/*
+ * C API of router-radix (built with the `capi` feature)
+ *
+ *   cargo rustc --release --features capi --crate-type cdylib
+ *
+ * Routes are added as JSON (see RouteConfig in the Rust docs) and match
+ * results come back as JSON strings that the caller frees with
+ * radix_router_string_free. Functions returning -1 or NULL on failure
+ * leave a message for radix_router_last_error on the calling thread.
+ */
+
+#ifndef RADIX_ROUTER_H
+#define RADIX_ROUTER_H
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+typedef struct RadixRouter RadixRouter;
+
+/* Create an empty router; NULL on failure */
+RadixRouter *radix_router_new(void);
+
+/* Destroy a router; NULL is ignored */
+void radix_router_free(RadixRouter *router);
+
+/* Add one route (JSON object) or an atomic batch (JSON array); 0 or -1 */
+int radix_router_add_route_json(const RadixRouter *router, const char *json);
+
+/* Delete the route registered under id; 0 or -1 */
+int radix_router_delete_route(const RadixRouter *router, const char *id);
+
+/*
+ * Match a request. opts_json may be NULL or an object with method, host,
+ * remote_addr, scheme, sni, server_port, vars, headers, args and cookies.
+ * Returns {"id", "priority", "metadata", "matched", "info", "upstream_path",
+ * "remaining_path", "version", "shadows"} as JSON, or NULL when nothing matched (radix_router_last_error() is then
+ * NULL) or on error.
+ */
+char *radix_router_match(const RadixRouter *router, const char *path,
+    const char *opts_json);
+
+/* Free a string returned by radix_router_match; NULL is ignored */
+void radix_router_string_free(char *s);
+
+/* Last error message on this thread, or NULL; valid until the next call */
+const char *radix_router_last_error(void);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif
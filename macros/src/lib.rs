@@ -0,0 +1,311 @@
+//! Compile-time checked route tables for router-radix
+//!
+//! Use through the `macros` feature of `router-radix`, which re-exports
+//! [`checked_routes!`].
+
+use proc_macro::TokenStream;
+use proc_macro2::Span;
+use quote::quote;
+use std::collections::{HashMap, HashSet};
+use syn::parse::{Parse, ParseStream};
+use syn::punctuated::Punctuated;
+use syn::{Expr, Ident, LitInt, LitStr, Token};
+
+mod kw {
+    syn::custom_keyword!(id);
+    syn::custom_keyword!(priority);
+    syn::custom_keyword!(host);
+}
+
+/// Methods `RadixHttpMethod` defines, besides `ANY`
+const METHODS: &[&str] = &[
+    "GET", "POST", "PUT", "DELETE", "PATCH", "HEAD", "OPTIONS", "CONNECT", "TRACE", "PURGE",
+];
+
+/// One `METHODS "path" [id ".."] [priority N] [host ".."] => metadata` entry
+struct Entry {
+    methods: Vec<Ident>,
+    path: LitStr,
+    id: Option<LitStr>,
+    priority: i64,
+    host: Option<LitStr>,
+}
+
+impl Parse for Entry {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let mut methods = vec![input.parse::<Ident>()?];
+        while input.peek(Token![|]) {
+            input.parse::<Token![|]>()?;
+            methods.push(input.parse()?);
+        }
+        let path = input.parse()?;
+        let mut id = None;
+        if input.peek(kw::id) {
+            input.parse::<kw::id>()?;
+            id = Some(input.parse()?);
+        }
+        let mut priority = 0;
+        if input.peek(kw::priority) {
+            input.parse::<kw::priority>()?;
+            let negative = input.parse::<Option<Token![-]>>()?.is_some();
+            let value = input.parse::<LitInt>()?.base10_parse::<i64>()?;
+            priority = if negative { -value } else { value };
+        }
+        let mut host = None;
+        if input.peek(kw::host) {
+            input.parse::<kw::host>()?;
+            host = Some(input.parse()?);
+        }
+        input.parse::<Token![=>]>()?;
+        input.parse::<Expr>()?;
+        Ok(Entry {
+            methods,
+            path,
+            id,
+            priority,
+            host,
+        })
+    }
+}
+
+impl Entry {
+    /// Id the route gets, explicit or derived like `routes!` does
+    fn route_id(&self) -> String {
+        match &self.id {
+            Some(id) => id.value(),
+            None => {
+                let methods: Vec<String> = self.methods.iter().map(Ident::to_string).collect();
+                format!("{} {}", methods.join("|"), self.path.value())
+            }
+        }
+    }
+
+    /// Methods the route accepts, `None` for all
+    fn method_set(&self) -> Option<HashSet<String>> {
+        let methods: HashSet<String> = self.methods.iter().map(Ident::to_string).collect();
+        (!methods.contains("ANY")).then_some(methods)
+    }
+
+    fn check_methods(&self) -> syn::Result<()> {
+        for method in &self.methods {
+            let name = method.to_string();
+            if name == "ANY" && self.methods.len() > 1 {
+                return Err(syn::Error::new(
+                    method.span(),
+                    "ANY cannot be combined with other methods",
+                ));
+            }
+            if name != "ANY" && !METHODS.contains(&name.as_str()) {
+                return Err(syn::Error::new(
+                    method.span(),
+                    format!(
+                        "unknown method `{}`, expected ANY or one of {}",
+                        name,
+                        METHODS.join(", ")
+                    ),
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Reject malformed path patterns
+///
+/// Mirrors the checks router-radix runs when a route is added: params
+/// (`:name`), wildcards (`*` / `*name`) and globs (`**` / `**name`) must take
+/// a whole segment, names must be unique identifiers, a wildcard must be the
+/// last segment, and a path has at most one glob and not also a wildcard.
+/// A suffix route is a leading `*` followed by a non-empty literal suffix.
+fn validate_path(path: &str) -> Result<(), String> {
+    if let Some(suffix) = path.strip_prefix('*') {
+        if suffix.is_empty() {
+            return Err(format!("{:?}: suffix route without a suffix", path));
+        }
+        if suffix.contains([':', '*']) {
+            return Err(format!("{:?}: suffix must be literal", path));
+        }
+        return Ok(());
+    }
+    if !path.starts_with('/') {
+        return Err(format!("path must start with '/': {:?}", path));
+    }
+
+    let mut names = HashSet::new();
+    let mut has_glob = false;
+    let mut segments = path.split('/').peekable();
+    while let Some(segment) = segments.next() {
+        let is_last = segments.peek().is_none();
+
+        if let Some(pos) = segment.find([':', '*']).filter(|pos| *pos > 0) {
+            return Err(format!(
+                "{:?}: '{}' must start a segment",
+                path,
+                &segment[pos..pos + 1]
+            ));
+        }
+
+        let (kind, name) = if let Some(name) = segment.strip_prefix(':') {
+            if name.is_empty() {
+                return Err(format!("{:?}: parameter without a name", path));
+            }
+            ("parameter", name)
+        } else if let Some(name) = segment.strip_prefix("**") {
+            if has_glob {
+                return Err(format!("{:?}: more than one '**'", path));
+            }
+            has_glob = true;
+            ("glob", name)
+        } else if let Some(name) = segment.strip_prefix('*') {
+            if !is_last {
+                return Err(format!("{:?}: wildcard must be the last segment", path));
+            }
+            if has_glob {
+                return Err(format!("{:?}: wildcard cannot follow '**'", path));
+            }
+            ("wildcard", name)
+        } else {
+            continue;
+        };
+
+        if name.contains(|c: char| !(c.is_ascii_alphanumeric() || c == '_' || c == '-')) {
+            return Err(format!("{:?}: invalid character in {} name", path, kind));
+        }
+        if !name.is_empty() && !names.insert(name) {
+            return Err(format!("{:?}: duplicate {} name '{}'", path, kind, name));
+        }
+    }
+    Ok(())
+}
+
+/// Whether two method sets (`None` meaning all) share a method
+fn methods_overlap(a: &Option<HashSet<String>>, b: &Option<HashSet<String>>) -> bool {
+    match (a, b) {
+        (Some(a), Some(b)) => !a.is_disjoint(b),
+        _ => true,
+    }
+}
+
+/// Check a whole table, collecting every error
+fn check(entries: &[Entry]) -> syn::Result<()> {
+    let mut errors: Vec<syn::Error> = Vec::new();
+    let mut ids: HashMap<String, Span> = HashMap::new();
+
+    for (index, entry) in entries.iter().enumerate() {
+        if let Err(err) = entry.check_methods() {
+            errors.push(err);
+            continue;
+        }
+        if let Err(message) = validate_path(&entry.path.value()) {
+            errors.push(syn::Error::new(entry.path.span(), message));
+        }
+
+        let id = entry.route_id();
+        let id_span = entry.id.as_ref().map_or(entry.path.span(), LitStr::span);
+        if ids.insert(id.clone(), id_span).is_some() {
+            errors.push(syn::Error::new(
+                id_span,
+                format!("duplicate route id {:?}", id),
+            ));
+        }
+
+        // Same path, host and priority with a shared method: one of the two
+        // can never be told apart from the other
+        let methods = entry.method_set();
+        let host = entry.host.as_ref().map(LitStr::value);
+        let earlier = entries[..index].iter().find(|other| {
+            other.path.value() == entry.path.value()
+                && other.host.as_ref().map(LitStr::value) == host
+                && other.priority == entry.priority
+                && other.check_methods().is_ok()
+                && methods_overlap(&other.method_set(), &methods)
+        });
+        if let Some(other) = earlier {
+            errors.push(syn::Error::new(
+                entry.path.span(),
+                format!(
+                    "route {:?} conflicts with {:?}: same path, host and priority for a shared method",
+                    id,
+                    other.route_id()
+                ),
+            ));
+        }
+    }
+
+    match errors.into_iter().reduce(|mut all, err| {
+        all.combine(err);
+        all
+    }) {
+        Some(err) => Err(err),
+        None => Ok(()),
+    }
+}
+
+/// `routes!` with the table checked at compile time
+///
+/// Takes the same syntax as `router_radix::routes!` and expands to it, after
+/// rejecting malformed path patterns, unknown methods, duplicate ids, and
+/// routes that share a path, host, priority and method with an earlier one.
+///
+/// ```ignore
+/// use router_radix::checked_routes;
+///
+/// let routes = checked_routes! {
+///     GET "/api/users" => json!({"handler": "list"}),
+///     GET "/api/users/:id/:id" => json!(null),   // error: duplicate parameter name 'id'
+/// };
+/// ```
+#[proc_macro]
+pub fn checked_routes(input: TokenStream) -> TokenStream {
+    let tokens = proc_macro2::TokenStream::from(input.clone());
+    let parser = Punctuated::<Entry, Token![,]>::parse_terminated;
+    let entries = match syn::parse::Parser::parse(parser, input) {
+        Ok(entries) => entries.into_iter().collect::<Vec<_>>(),
+        Err(err) => return err.to_compile_error().into(),
+    };
+    if let Err(err) = check(&entries) {
+        return err.to_compile_error().into();
+    }
+    quote!(::router_radix::routes! { #tokens }).into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn check_str(table: &str) -> Result<(), String> {
+        let parser = Punctuated::<Entry, Token![,]>::parse_terminated;
+        let entries = syn::parse::Parser::parse_str(parser, table).map_err(|e| e.to_string())?;
+        check(&entries.into_iter().collect::<Vec<_>>()).map_err(|e| e.to_string())
+    }
+
+    #[test]
+    fn test_check() {
+        assert_eq!(
+            check_str(
+                r#"GET "/users" => json!(null),
+                   GET|POST "/users/:id" priority 10 => 1,
+                   ANY "/users/:id" id "fallback" priority -1 host "a.com" => 2,"#
+            ),
+            Ok(())
+        );
+
+        let err = |table: &str| check_str(table).unwrap_err();
+        assert!(err(r#"GET "users" => 1"#).contains("must start with '/'"));
+        assert!(err(r#"GET "/a/:id/:id" => 1"#).contains("duplicate parameter name"));
+        assert!(err(r#"GET "/a/*rest/b" => 1"#).contains("last segment"));
+        assert!(err(r#"GET "/a/**/b/**" => 1"#).contains("more than one '**'"));
+        check_str(r#"GET "/a/**path/b" => 1"#).unwrap();
+        check_str(r#"GET "*.php" => 1"#).unwrap();
+        assert!(err(r#"GET "*/:id" => 1"#).contains("suffix must be literal"));
+        assert!(err(r#"FETCH "/a" => 1"#).contains("unknown method `FETCH`"));
+        assert!(err(r#"ANY|GET "/a" => 1"#).contains("cannot be combined"));
+        assert!(
+            err(r#"GET "/a" id "x" => 1, POST "/b" id "x" => 2"#).contains("duplicate route id")
+        );
+        assert!(err(r#"GET "/a" => 1, ANY "/a" => 2"#).contains("conflicts with \"GET /a\""));
+        // A different method, host or priority tells the routes apart
+        check_str(r#"GET "/a" => 1, POST "/a" => 2, ANY "/a" priority 1 => 3"#).unwrap();
+        check_str(r#"GET "/a" => 1, GET "/a" id "b" host "b.com" => 2"#).unwrap();
+    }
+}
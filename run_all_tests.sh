@@ -26,7 +26,6 @@ functional_tests=(
 
 # Performance tests
 performance_tests=(
-    "benchmark:Performance Benchmarks"
     "concurrency_test:Concurrent Performance"
     "stress_test:Stress & Load Testing"
 )
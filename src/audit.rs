@@ -0,0 +1,91 @@
+//! Audit trail of route changes
+
+use std::collections::VecDeque;
+use std::sync::Arc;
+use std::time::SystemTime;
+
+/// Kind of change an [`AuditEntry`] records
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AuditAction {
+    /// A route id was registered
+    Added,
+    /// A registered route changed
+    Updated,
+    /// A route id was removed
+    Removed,
+}
+
+/// One route change, as published
+///
+/// Hashes are [`RadixNode::content_hash`](crate::RadixNode::content_hash)
+/// of the definition before and after the change; `None` on the side where
+/// the id was not registered.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AuditEntry {
+    /// When the change was published
+    pub at: SystemTime,
+    /// What happened to the route
+    pub action: AuditAction,
+    /// Route ID
+    pub id: String,
+    /// Content hash before the change
+    pub before: Option<u64>,
+    /// Content hash after the change
+    pub after: Option<u64>,
+}
+
+/// Destination for audit entries, e.g. a compliance log shipper
+///
+/// Register with [`RadixRouter::add_audit_sink`](crate::RadixRouter::add_audit_sink).
+/// Like observer callbacks, `record` runs on the writing thread while the
+/// router's writer lock is held, in commit order.
+pub trait AuditSink: Send + Sync {
+    /// Record one change
+    fn record(&self, entry: &AuditEntry);
+}
+
+/// In-memory audit log and the sinks entries are forwarded to
+#[derive(Default)]
+pub(crate) struct AuditTrail {
+    /// Most entries kept in memory, oldest dropped first
+    capacity: usize,
+    entries: VecDeque<AuditEntry>,
+    sinks: Vec<Arc<dyn AuditSink>>,
+}
+
+impl AuditTrail {
+    /// Whether changes need to be diffed at all
+    pub fn is_enabled(&self) -> bool {
+        self.capacity > 0 || !self.sinks.is_empty()
+    }
+
+    /// Keep the last `capacity` entries, dropping older ones now if needed
+    pub fn set_capacity(&mut self, capacity: usize) {
+        self.capacity = capacity;
+        while self.entries.len() > capacity {
+            self.entries.pop_front();
+        }
+    }
+
+    pub fn add_sink(&mut self, sink: Arc<dyn AuditSink>) {
+        self.sinks.push(sink);
+    }
+
+    pub fn record(&mut self, entry: AuditEntry) {
+        for sink in &self.sinks {
+            sink.record(&entry);
+        }
+        if self.capacity == 0 {
+            return;
+        }
+        if self.entries.len() == self.capacity {
+            self.entries.pop_front();
+        }
+        self.entries.push_back(entry);
+    }
+
+    /// Kept entries, oldest first
+    pub fn entries(&self) -> Vec<AuditEntry> {
+        self.entries.iter().cloned().collect()
+    }
+}
@@ -0,0 +1,153 @@
+//! `radix-router`: test route files without writing Rust
+//!
+//! ```text
+//! radix-router routes.yaml match /users/7 --method GET --host api.example.com
+//! radix-router routes.yaml explain /users/7 --var env=prod
+//! radix-router routes.json conflicts
+//! radix-router routes.json dump
+//! radix-router routes.json table
+//! ```
+//!
+//! Built with the `cli` feature. `match` and `conflicts` exit with status 1
+//! when nothing matched or a conflict was found, so they can gate CI.
+
+use anyhow::{Context, Result};
+use clap::{Args, Parser, Subcommand};
+use router_radix::{RadixMatchOpts, RadixRouter, RouteConfig};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+#[derive(Parser)]
+#[command(version, about = "Test radix router route files")]
+struct Cli {
+    /// Route file: a JSON array, or YAML for `.yaml`/`.yml` files
+    routes: PathBuf,
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Print the route a request would be routed to, as JSON
+    Match(Request),
+    /// Show every candidate route for a request and why it was rejected
+    Explain(Request),
+    /// List routes that can never be selected
+    Conflicts,
+    /// Print the routing table as an indented tree
+    Dump,
+    /// List the routes as an aligned text table
+    Table,
+}
+
+#[derive(Args)]
+struct Request {
+    /// Request path
+    path: String,
+    #[arg(long)]
+    method: Option<String>,
+    #[arg(long)]
+    host: Option<String>,
+    #[arg(long)]
+    remote_addr: Option<String>,
+    #[arg(long)]
+    scheme: Option<String>,
+    #[arg(long)]
+    sni: Option<String>,
+    #[arg(long)]
+    server_port: Option<u16>,
+    /// Request variable as `name=value` (repeatable)
+    #[arg(long = "var", value_parser = parse_var)]
+    vars: Vec<(String, String)>,
+    /// Request header as `name=value`, exposed as `http_<name>` (repeatable)
+    #[arg(long = "header", value_parser = parse_var)]
+    headers: Vec<(String, String)>,
+    /// Query argument as `name=value`, exposed as `arg_<name>` (repeatable)
+    #[arg(long = "arg", value_parser = parse_var)]
+    args: Vec<(String, String)>,
+    /// Cookie as `name=value`, exposed as `cookie_<name>` (repeatable)
+    #[arg(long = "cookie", value_parser = parse_var)]
+    cookies: Vec<(String, String)>,
+}
+
+/// Collect repeated `name=value` flags, `None` if none were given
+fn pairs(pairs: &[(String, String)]) -> Option<HashMap<String, String>> {
+    (!pairs.is_empty()).then(|| pairs.iter().cloned().collect())
+}
+
+fn parse_var(arg: &str) -> Result<(String, String), String> {
+    arg.split_once('=')
+        .map(|(name, value)| (name.to_string(), value.to_string()))
+        .ok_or_else(|| format!("expected name=value, got {:?}", arg))
+}
+
+impl Request {
+    fn opts(&self) -> RadixMatchOpts {
+        RadixMatchOpts {
+            method: self.method.clone(),
+            host: self.host.clone(),
+            remote_addr: self.remote_addr.clone(),
+            scheme: self.scheme.clone(),
+            sni: self.sni.clone(),
+            server_port: self.server_port,
+            vars: pairs(&self.vars),
+            headers: pairs(&self.headers),
+            args: pairs(&self.args),
+            cookies: pairs(&self.cookies),
+            ..Default::default()
+        }
+    }
+}
+
+fn run(cli: Cli) -> Result<bool> {
+    let routes = RouteConfig::list_from_file(&cli.routes)?
+        .into_iter()
+        .map(RouteConfig::into_node)
+        .collect::<Result<Vec<_>>>()?;
+    let router = RadixRouter::with_routes(routes)
+        .with_context(|| format!("Failed to build router from {}", cli.routes.display()))?;
+
+    match cli.command {
+        Command::Match(request) => match router.match_route(&request.path, &request.opts())? {
+            Some(result) => {
+                println!("{}", serde_json::to_string_pretty(&result.to_json())?);
+                Ok(true)
+            }
+            None => {
+                eprintln!("no match");
+                Ok(false)
+            }
+        },
+        Command::Explain(request) => {
+            println!("{}", router.explain(&request.path, &request.opts())?);
+            Ok(true)
+        }
+        Command::Conflicts => {
+            let conflicts = router.conflicts();
+            for conflict in &conflicts {
+                println!("{}", conflict);
+            }
+            Ok(conflicts.is_empty())
+        }
+        Command::Dump => {
+            print!("{}", router.dump());
+            Ok(true)
+        }
+        Command::Table => {
+            print!("{}", router);
+            Ok(true)
+        }
+    }
+}
+
+fn main() -> ExitCode {
+    match run(Cli::parse()) {
+        Ok(true) => ExitCode::SUCCESS,
+        Ok(false) => ExitCode::from(1),
+        Err(err) => {
+            eprintln!("error: {:#}", err);
+            ExitCode::from(2)
+        }
+    }
+}
@@ -0,0 +1,59 @@
+//! Stable hash bucketing for experiments and canaries
+
+/// Maps keys (user ids, session ids, ...) onto a fixed number of buckets
+///
+/// The hash is FNV-1a, so a key lands in the same bucket across processes,
+/// machines and releases. Sticky A/B splits can therefore be expressed as
+/// bucket ranges instead of handwritten filter closures.
+///
+/// ```rust
+/// use router_radix::Bucketer;
+///
+/// let bucketer = Bucketer::new(10);
+/// let bucket = bucketer.bucket("user-42");
+/// assert!(bucket < 10);
+/// assert_eq!(bucket, bucketer.bucket("user-42"));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Bucketer {
+    buckets: u32,
+}
+
+impl Bucketer {
+    /// Number of buckets used by `Expr::Bucket` and canary routing
+    pub const DEFAULT_BUCKETS: u32 = 100;
+
+    /// Create a bucketer with `buckets` buckets
+    ///
+    /// # Panics
+    ///
+    /// Panics if `buckets` is zero.
+    pub fn new(buckets: u32) -> Self {
+        assert!(buckets > 0, "Bucketer needs at least one bucket");
+        Self { buckets }
+    }
+
+    /// Number of buckets
+    pub fn buckets(&self) -> u32 {
+        self.buckets
+    }
+
+    /// Bucket of `key`, in `0..buckets`
+    pub fn bucket(&self, key: &str) -> u32 {
+        (stable_hash(key) % self.buckets as u64) as u32
+    }
+}
+
+impl Default for Bucketer {
+    /// Percent-style bucketer with [`Bucketer::DEFAULT_BUCKETS`] buckets
+    fn default() -> Self {
+        Self::new(Self::DEFAULT_BUCKETS)
+    }
+}
+
+/// FNV-1a hash, stable across processes and releases
+pub(crate) fn stable_hash(key: &str) -> u64 {
+    key.bytes().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(0x0000_0100_0000_01b3)
+    })
+}
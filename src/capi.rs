@@ -0,0 +1,230 @@
+//! C ABI for embedding the router in non-Rust hosts
+//!
+//! Enabled by the `capi` feature; build the shared library with
+//! `cargo rustc --release --features capi --crate-type cdylib` and include
+//! `include/radix_router.h`.
+//!
+//! Routes go in as [`RouteConfig`] JSON and match results come back as
+//! JSON strings owned by the caller. Functions that fail return `-1` or
+//! NULL and leave a message for [`radix_router_last_error`] on the calling
+//! thread. Panics never cross the boundary.
+
+use crate::config::RouteConfig;
+use crate::route::RadixMatchOpts;
+use crate::router::RadixRouter;
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::ffi::{c_char, c_int, CStr, CString};
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::ptr;
+
+thread_local! {
+    /// Message of the last failed call on this thread
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+/// Request attributes accepted by [`radix_router_match`]
+#[derive(Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct OptsConfig {
+    method: Option<String>,
+    host: Option<String>,
+    remote_addr: Option<String>,
+    scheme: Option<String>,
+    sni: Option<String>,
+    server_port: Option<u16>,
+    vars: Option<HashMap<String, String>>,
+    headers: Option<HashMap<String, String>>,
+    args: Option<HashMap<String, String>>,
+    cookies: Option<HashMap<String, String>>,
+}
+
+impl From<OptsConfig> for RadixMatchOpts {
+    fn from(opts: OptsConfig) -> Self {
+        RadixMatchOpts {
+            method: opts.method,
+            host: opts.host,
+            remote_addr: opts.remote_addr,
+            scheme: opts.scheme,
+            sni: opts.sni,
+            server_port: opts.server_port,
+            vars: opts.vars,
+            headers: opts.headers,
+            args: opts.args,
+            cookies: opts.cookies,
+            ..Default::default()
+        }
+    }
+}
+
+/// Run `f`, recording any error or panic as the thread's last error
+fn guard<T>(fallback: T, f: impl FnOnce() -> Result<T>) -> T {
+    let outcome = catch_unwind(AssertUnwindSafe(f))
+        .unwrap_or_else(|_| Err(anyhow::anyhow!("panic inside radix_router")));
+    match outcome {
+        Ok(value) => value,
+        Err(err) => {
+            let message = CString::new(format!("{:#}", err).replace('\0', " "))
+                .expect("interior NULs were replaced");
+            LAST_ERROR.with(|last| *last.borrow_mut() = Some(message));
+            fallback
+        }
+    }
+}
+
+/// Borrow a NUL-terminated UTF-8 argument
+///
+/// # Safety
+///
+/// `ptr` must be NULL or point to a NUL-terminated string.
+unsafe fn arg<'a>(ptr: *const c_char, name: &str) -> Result<&'a str> {
+    if ptr.is_null() {
+        anyhow::bail!("{} is NULL", name);
+    }
+    CStr::from_ptr(ptr)
+        .to_str()
+        .with_context(|| format!("{} is not valid UTF-8", name))
+}
+
+/// Borrow the router behind a handle
+///
+/// # Safety
+///
+/// `router` must be NULL or a live handle from [`radix_router_new`].
+unsafe fn handle<'a>(router: *const RadixRouter) -> Result<&'a RadixRouter> {
+    router.as_ref().context("router is NULL")
+}
+
+/// Create an empty router; free it with [`radix_router_free`]
+///
+/// Returns NULL on failure.
+#[no_mangle]
+pub extern "C" fn radix_router_new() -> *mut RadixRouter {
+    guard(ptr::null_mut(), || {
+        Ok(Box::into_raw(Box::new(RadixRouter::new()?)))
+    })
+}
+
+/// Destroy a router created by [`radix_router_new`]
+///
+/// # Safety
+///
+/// `router` must be NULL or a handle from [`radix_router_new`] that is not
+/// used afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn radix_router_free(router: *mut RadixRouter) {
+    if !router.is_null() {
+        drop(Box::from_raw(router));
+    }
+}
+
+/// Add one route (a JSON object) or a batch (a JSON array) of routes
+///
+/// A batch is applied atomically. Returns 0 on success and -1 on error.
+///
+/// # Safety
+///
+/// `router` must be a live handle and `json` a NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn radix_router_add_route_json(
+    router: *const RadixRouter,
+    json: *const c_char,
+) -> c_int {
+    guard(-1, || {
+        let router = handle(router)?;
+        let json = arg(json, "json")?;
+        let configs =
+            match serde_json::from_str::<serde_json::Value>(json).context("Invalid route JSON")? {
+                list @ serde_json::Value::Array(_) => serde_json::from_value(list),
+                single => serde_json::from_value(single).map(|c: RouteConfig| vec![c]),
+            }
+            .context("Invalid route definition")?;
+        let routes = configs
+            .into_iter()
+            .map(RouteConfig::into_node)
+            .collect::<Result<Vec<_>>>()?;
+        router.add_routes(routes)?;
+        Ok(0)
+    })
+}
+
+/// Delete every path of the route registered under `id`
+///
+/// Returns 0 on success and -1 on error (including an unknown id).
+///
+/// # Safety
+///
+/// `router` must be a live handle and `id` a NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn radix_router_delete_route(
+    router: *const RadixRouter,
+    id: *const c_char,
+) -> c_int {
+    guard(-1, || {
+        handle(router)?.delete_by_id(arg(id, "id")?)?;
+        Ok(0)
+    })
+}
+
+/// Match a request
+///
+/// `opts_json` may be NULL or a JSON object with any of `method`, `host`,
+/// `remote_addr`, `scheme`, `sni`, `server_port`, `vars`, `headers`,
+/// `args` and `cookies`. On a match, returns a JSON object with `id`, `priority`,
+/// `metadata`, `matched`, `info`, `upstream_path`, `remaining_path`,
+/// `version` and `shadows`, to be freed with [`radix_router_string_free`].
+/// Returns NULL when nothing matched (with [`radix_router_last_error`]
+/// cleared) or on error.
+///
+/// # Safety
+///
+/// `router` must be a live handle, `path` a NUL-terminated string and
+/// `opts_json` NULL or a NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn radix_router_match(
+    router: *const RadixRouter,
+    path: *const c_char,
+    opts_json: *const c_char,
+) -> *mut c_char {
+    LAST_ERROR.with(|last| *last.borrow_mut() = None);
+    guard(ptr::null_mut(), || {
+        let router = handle(router)?;
+        let path = arg(path, "path")?;
+        let opts: OptsConfig = if opts_json.is_null() {
+            OptsConfig::default()
+        } else {
+            serde_json::from_str(arg(opts_json, "opts_json")?).context("Invalid match options")?
+        };
+        let Some(result) = router.match_route(path, &opts.into())? else {
+            return Ok(ptr::null_mut());
+        };
+        let json = result.to_json().to_string();
+        Ok(CString::new(json)
+            .context("Match result contains NUL")?
+            .into_raw())
+    })
+}
+
+/// Free a string returned by this library
+///
+/// # Safety
+///
+/// `s` must be NULL or a string returned by [`radix_router_match`] that is
+/// not used afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn radix_router_string_free(s: *mut c_char) {
+    if !s.is_null() {
+        drop(CString::from_raw(s));
+    }
+}
+
+/// Message of the last failed call on this thread, or NULL
+///
+/// The pointer stays valid until the next call into the library on this
+/// thread.
+#[no_mangle]
+pub extern "C" fn radix_router_last_error() -> *const c_char {
+    LAST_ERROR.with(|last| last.borrow().as_ref().map_or(ptr::null(), |s| s.as_ptr()))
+}
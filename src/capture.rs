@@ -0,0 +1,43 @@
+//! Destinations for values extracted while matching
+
+/// Sink for the values a lookup extracts from a request
+///
+/// Matching is generic over the sink so [`Params`](crate::Params), the
+/// borrowing [`MatchRef`](crate::MatchRef) and `matches` share one code path.
+pub(crate) trait Captures<'p>: Default {
+    /// Whether anything is recorded; when false, params are only checked
+    const EXTRACT: bool = true;
+
+    /// Record a path parameter sliced from the request path
+    ///
+    /// Called once per pattern name, in pattern order.
+    fn param(&mut self, name: &str, value: &'p str);
+
+    /// Forget everything recorded so far
+    fn clear(&mut self);
+}
+
+/// Sink for lookups that only need to know which route won
+impl<'p> Captures<'p> for () {
+    const EXTRACT: bool = false;
+
+    fn param(&mut self, _name: &str, _value: &'p str) {}
+
+    fn clear(&mut self) {}
+}
+
+/// Param values borrowed from the request path, aligned with pattern names
+///
+/// Attributes are not kept; they are all derivable from the request.
+#[derive(Debug, Default)]
+pub(crate) struct PathValues<'p>(pub Vec<&'p str>);
+
+impl<'p> Captures<'p> for PathValues<'p> {
+    fn param(&mut self, _name: &str, value: &'p str) {
+        self.0.push(value);
+    }
+
+    fn clear(&mut self) {
+        self.0.clear();
+    }
+}
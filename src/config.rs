@@ -0,0 +1,253 @@
+//! Declarative route definitions
+//!
+//! [`RouteConfig`] is the serde form of a [`RadixNode`], for routes kept in
+//! config files or handed over by other languages. It covers everything but
+//! the Rust-only hooks (`filter_fn`) and time windows; routes can still use
+//! filters registered on the router, by name.
+
+use crate::route::{compile_regex, Canary, Expr, RadixHttpMethod, RadixNode};
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::Path;
+
+/// Route definition as found in JSON or YAML
+///
+/// Fields mirror [`RadixNode`]; missing ones take their defaults.
+///
+/// ```json
+/// {
+///   "id": "user",
+///   "paths": ["/api/users/:id"],
+///   "methods": ["GET", "PUT"],
+///   "hosts": ["*.example.com"],
+///   "vars": [["env", "==", "prod"], ["region", "in", ["us", "eu"]]],
+///   "priority": 10,
+///   "metadata": {"upstream": "users"}
+/// }
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RouteConfig {
+    pub id: String,
+    pub paths: Vec<String>,
+    /// File extensions wildcard paths are restricted to, e.g. `["css", "js"]`
+    pub extensions: Option<Vec<String>>,
+    /// Report the path without the route's literal prefix as the upstream path
+    pub strip_prefix: bool,
+    /// Upstream path template, e.g. `/internal/$1`
+    pub rewrite: Option<String>,
+    /// Standard verbs and custom ones alike; empty means all
+    pub methods: Vec<String>,
+    pub hosts: Option<Vec<String>>,
+    pub remote_addrs: Option<Vec<String>>,
+    pub schemes: Option<Vec<String>>,
+    pub snis: Option<Vec<String>>,
+    pub server_ports: Option<Vec<u16>>,
+    /// `[var, op, value]` triples, all of which must hold
+    pub vars: Vec<VarConfig>,
+    /// Names of filters registered with `RadixRouter::register_filter`
+    pub filters: Vec<String>,
+    pub priority: i32,
+    /// Priority overrides by path, e.g. `{"/docs": 10}`
+    pub path_priorities: BTreeMap<String, i32>,
+    pub weight: Option<u32>,
+    pub canary: Option<CanaryConfig>,
+    pub shadow: bool,
+    pub metadata: serde_json::Value,
+    pub tags: Vec<String>,
+    /// Name of a template registered with `RadixRouter::register_template`
+    pub extends: Option<String>,
+}
+
+/// One `[var, op, value]` condition of a [`RouteConfig`]
+///
+/// Operators follow lua-resty-radixtree: `==`, `~=` (or `!=`), `>`, `<`,
+/// `in` (value is an array) and `~~` (value is a regex), plus `bucket`
+/// (value is a `[start, end)` pair of buckets, see [`Expr::Bucket`]).
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct VarConfig(pub String, pub String, pub serde_json::Value);
+
+/// Serde form of [`Canary`]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CanaryConfig {
+    pub percent: u8,
+    pub hash_on: String,
+}
+
+impl RouteConfig {
+    /// Parse a JSON array of route definitions
+    pub fn list_from_json(text: &str) -> Result<Vec<RouteConfig>> {
+        serde_json::from_str(text).context("Invalid route definitions")
+    }
+
+    /// Parse a YAML sequence of route definitions
+    #[cfg(feature = "yaml")]
+    pub fn list_from_yaml(text: &str) -> Result<Vec<RouteConfig>> {
+        serde_yaml::from_str(text).context("Invalid route definitions")
+    }
+
+    /// Read route definitions from a file
+    ///
+    /// `.yaml` and `.yml` files are parsed as YAML (with the `yaml`
+    /// feature), anything else as JSON.
+    pub fn list_from_file(path: impl AsRef<Path>) -> Result<Vec<RouteConfig>> {
+        let path = path.as_ref();
+        let text = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        let yaml = matches!(
+            path.extension().and_then(|e| e.to_str()),
+            Some("yaml" | "yml")
+        );
+        let routes = if yaml {
+            #[cfg(feature = "yaml")]
+            {
+                Self::list_from_yaml(&text)
+            }
+            #[cfg(not(feature = "yaml"))]
+            {
+                Err(anyhow::anyhow!("YAML support requires the `yaml` feature"))
+            }
+        } else {
+            Self::list_from_json(&text)
+        };
+        routes.with_context(|| format!("Failed to load {}", path.display()))
+    }
+
+    /// Convert into a route the router accepts
+    pub fn into_node(self) -> Result<RadixNode> {
+        let mut methods = RadixHttpMethod::empty();
+        let mut custom_methods = Vec::new();
+        for method in self.methods {
+            match RadixHttpMethod::from_str(&method) {
+                Some(m) => methods |= m,
+                None => custom_methods.push(method),
+            }
+        }
+
+        let vars = self
+            .vars
+            .into_iter()
+            .map(VarConfig::into_expr)
+            .collect::<Result<Vec<_>>>()
+            .with_context(|| format!("Invalid vars for route: {}", self.id))?;
+
+        Ok(RadixNode {
+            methods: (!methods.is_empty()).then_some(methods),
+            custom_methods: (!custom_methods.is_empty()).then_some(custom_methods),
+            extensions: self.extensions,
+            strip_prefix: self.strip_prefix,
+            rewrite: self.rewrite,
+            hosts: self.hosts,
+            remote_addrs: self.remote_addrs,
+            schemes: self.schemes,
+            snis: self.snis,
+            server_ports: self.server_ports,
+            vars: (!vars.is_empty()).then_some(vars),
+            filters: self.filters,
+            priority: self.priority,
+            path_priorities: (!self.path_priorities.is_empty())
+                .then(|| self.path_priorities.into_iter().collect()),
+            weight: self.weight,
+            canary: self.canary.map(|c| Canary {
+                percent: c.percent,
+                hash_on: c.hash_on,
+            }),
+            shadow: self.shadow,
+            metadata: self.metadata,
+            tags: self.tags,
+            extends: self.extends,
+            id: self.id,
+            paths: self.paths,
+            ..Default::default()
+        })
+    }
+}
+
+impl TryFrom<RouteConfig> for RadixNode {
+    type Error = anyhow::Error;
+
+    fn try_from(config: RouteConfig) -> Result<Self> {
+        config.into_node()
+    }
+}
+
+impl VarConfig {
+    /// Build the expression this condition describes
+    pub fn into_expr(self) -> Result<Expr> {
+        let VarConfig(var, op, value) = self;
+        let text = |value: serde_json::Value| match value {
+            serde_json::Value::String(s) => s,
+            other => other.to_string(),
+        };
+        Ok(match op.as_str() {
+            "==" => Expr::Eq(var, text(value)),
+            "~=" | "!=" => Expr::Neq(var, text(value)),
+            ">" => Expr::Gt(var, text(value)),
+            "<" => Expr::Lt(var, text(value)),
+            "in" => match value {
+                serde_json::Value::Array(values) => {
+                    Expr::In(var, values.into_iter().map(text).collect())
+                }
+                other => anyhow::bail!("'in' expects an array, got {}", other),
+            },
+            "~~" => {
+                let pattern = text(value);
+                let regex = compile_regex(&pattern)
+                    .with_context(|| format!("Invalid regex for {}: {}", var, pattern))?;
+                Expr::Regex(var, regex)
+            }
+            "bucket" => {
+                let range = serde_json::from_value::<(u32, u32)>(value)
+                    .with_context(|| format!("'bucket' expects [start, end] for {}", var))?;
+                Expr::Bucket(var, range.0..range.1)
+            }
+            other => anyhow::bail!("Unknown operator for {}: {:?}", var, other),
+        })
+    }
+}
+
+/// Config form of an expression: a `[var, op, value]` triple, or
+/// `["AND" | "OR", expr...]` for groups as in lua-resty-expr
+pub(crate) fn expr_to_value(expr: &Expr) -> serde_json::Value {
+    use serde_json::json;
+    let group = |op: &str, exprs: &[Expr]| {
+        let items = std::iter::once(json!(op)).chain(exprs.iter().map(expr_to_value));
+        serde_json::Value::Array(items.collect())
+    };
+    match expr {
+        Expr::Eq(var, value) => json!([var, "==", value]),
+        Expr::Neq(var, value) => json!([var, "~=", value]),
+        Expr::Gt(var, value) => json!([var, ">", value]),
+        Expr::Lt(var, value) => json!([var, "<", value]),
+        Expr::In(var, values) => json!([var, "in", values]),
+        Expr::Regex(var, regex) => json!([var, "~~", regex.as_str()]),
+        Expr::Bucket(var, range) => json!([var, "bucket", [range.start, range.end]]),
+        Expr::All(exprs) => group("AND", exprs),
+        Expr::Any(exprs) => group("OR", exprs),
+    }
+}
+
+/// Inverse of [`expr_to_value`]
+pub(crate) fn expr_from_value(value: serde_json::Value) -> Result<Expr> {
+    if let serde_json::Value::Array(items) = &value {
+        if let Some((serde_json::Value::String(op), rest)) = items.split_first() {
+            if (op == "AND" || op == "OR") && rest.iter().all(serde_json::Value::is_array) {
+                let exprs = rest
+                    .iter()
+                    .cloned()
+                    .map(expr_from_value)
+                    .collect::<Result<Vec<_>>>()?;
+                return Ok(if op == "AND" {
+                    Expr::All(exprs)
+                } else {
+                    Expr::Any(exprs)
+                });
+            }
+        }
+    }
+    serde_json::from_value::<VarConfig>(value)
+        .context("Invalid var condition")?
+        .into_expr()
+}
@@ -0,0 +1,125 @@
+//! Match tracing for debugging routing decisions
+
+use crate::route::{MatchResult, Rejection, RouteInfo};
+use std::fmt;
+
+/// Trace of a lookup produced by [`RadixRouter::explain`](crate::RadixRouter::explain)
+#[derive(Debug, Clone)]
+pub struct Explanation {
+    /// Request path that was looked up
+    pub path: String,
+    /// Every candidate considered, in scan order
+    pub candidates: Vec<CandidateTrace>,
+    /// What `match_route` returns for the same request
+    pub result: Option<MatchResult>,
+}
+
+/// Verdict for one candidate route
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CandidateTrace {
+    /// Route ID
+    pub id: String,
+    /// Registered path pattern
+    pub pattern: String,
+    /// Where the candidate was found
+    pub source: CandidateSource,
+    /// Route priority
+    pub priority: i32,
+    /// Whether the route is a shadow (mirror-only) route
+    pub shadow: bool,
+    /// First check that failed, `None` if the route accepted the request
+    pub rejection: Option<Rejection>,
+}
+
+/// Lookup structure a candidate came from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CandidateSource {
+    /// Exact path table
+    Exact,
+    /// Radix tree prefix walk (params and wildcards)
+    Prefix,
+    /// Radix tree suffix walk over the reversed path
+    Suffix,
+}
+
+impl CandidateTrace {
+    /// Whether the candidate accepted the request
+    pub fn accepted(&self) -> bool {
+        self.rejection.is_none()
+    }
+}
+
+impl fmt::Display for Explanation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "explain {}", self.path)?;
+        if self.candidates.is_empty() {
+            writeln!(f, "  no candidate routes")?;
+        }
+        for c in &self.candidates {
+            let source = match c.source {
+                CandidateSource::Exact => "exact",
+                CandidateSource::Prefix => "prefix",
+                CandidateSource::Suffix => "suffix",
+            };
+            let verdict = match c.rejection {
+                None => "accepted".to_string(),
+                Some(rejection) => format!("rejected: {}", rejection),
+            };
+            writeln!(
+                f,
+                "  [{}] {} {} (priority {}{}) -> {}",
+                source,
+                c.id,
+                c.pattern,
+                c.priority,
+                if c.shadow { ", shadow" } else { "" },
+                verdict
+            )?;
+        }
+        match &self.result {
+            Some(result) => write!(f, "  => matched {}", result.id),
+            None => write!(f, "  => no match"),
+        }
+    }
+}
+
+/// Route that can never be selected, reported by
+/// [`RadixRouter::conflicts`](crate::RadixRouter::conflicts)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Conflict {
+    /// Unreachable route
+    pub id: String,
+    /// Its path pattern
+    pub pattern: String,
+    /// Earlier route that accepts every request this one would
+    pub shadowed_by: String,
+}
+
+impl fmt::Display for Conflict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} ({}) is shadowed by {}",
+            self.id, self.pattern, self.shadowed_by
+        )
+    }
+}
+
+/// What adding a route would do to reachability, reported by
+/// [`RadixRouter::dry_run_add`](crate::RadixRouter::dry_run_add)
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ShadowReport {
+    /// Existing routes the new route would make unreachable
+    pub shadows: Vec<Conflict>,
+    /// Existing routes that would make the new route unreachable
+    pub shadowed_by: Vec<Conflict>,
+    /// Definition the new route would replace under `IdPolicy::Replace`
+    pub replaces: Option<RouteInfo>,
+}
+
+impl ShadowReport {
+    /// Whether the route would neither shadow nor be shadowed
+    pub fn is_clean(&self) -> bool {
+        self.shadows.is_empty() && self.shadowed_by.is_empty()
+    }
+}
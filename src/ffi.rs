@@ -1,7 +1,17 @@
 //! FFI bindings for the C radix tree implementation
 
 use anyhow::Result;
+use std::cell::RefCell;
 use std::ffi::c_void;
+use std::ops::{Deref, DerefMut};
+
+/// Idle iterators kept per thread for reuse
+const POOL_SIZE: usize = 4;
+
+thread_local! {
+    /// Iterators returned by finished queries on this thread
+    static ITERATOR_POOL: RefCell<Vec<RadixIterator>> = const { RefCell::new(Vec::new()) };
+}
 
 #[repr(C)]
 #[allow(non_camel_case_types, dead_code)]
@@ -22,8 +32,9 @@ extern "C" {
         len: usize,
     ) -> *mut c_void;
     pub fn radix_tree_up(it: *mut c_void, buf: *const u8, len: usize) -> i32;
-    pub fn radix_tree_stop(it: *mut c_void) -> i32;
     pub fn radix_tree_new_it(t: *mut c_void) -> *mut c_void;
+    pub fn radix_tree_reset_it(it: *mut c_void, t: *mut c_void) -> i32;
+    pub fn radix_tree_free_it(it: *mut c_void) -> i32;
 }
 
 /// Safe Rust wrapper around C radix tree
@@ -32,6 +43,9 @@ pub struct RadixTreeRaw {
 }
 
 /// RAII wrapper for radix tree iterator
+///
+/// The iterator is allocated by `radix_tree_new_it` and released by
+/// `radix_tree_free_it`, so both sides of the allocation stay in C.
 pub struct RadixIterator {
     iterator: *mut c_void,
 }
@@ -48,9 +62,18 @@ impl RadixIterator {
         }
     }
 
-    pub fn search(&mut self, tree: *mut c_void, key: &[u8]) -> bool {
+    /// Rebind the iterator to `tree`, releasing any state of a prior query
+    fn reset(&mut self, tree: *mut c_void) {
+        unsafe {
+            radix_tree_reset_it(self.iterator, tree);
+        }
+    }
+
+    /// Position the iterator for walking up from `key` in `tree`
+    pub fn search(&mut self, tree: &RadixTreeRaw, key: &[u8]) -> bool {
         unsafe {
-            let result = radix_tree_search(tree, self.iterator, key.as_ptr(), key.len());
+            self.reset(tree.tree);
+            let result = radix_tree_search(tree.tree, self.iterator, key.as_ptr(), key.len());
             !result.is_null()
         }
     }
@@ -71,14 +94,49 @@ impl Drop for RadixIterator {
     fn drop(&mut self) {
         unsafe {
             if !self.iterator.is_null() {
-                radix_tree_stop(self.iterator);
-                libc::free(self.iterator);
+                radix_tree_free_it(self.iterator);
                 self.iterator = std::ptr::null_mut();
             }
         }
     }
 }
 
+/// Iterator borrowed from the thread's pool, returned to it on drop
+pub struct PooledIterator {
+    iterator: Option<RadixIterator>,
+}
+
+impl Deref for PooledIterator {
+    type Target = RadixIterator;
+
+    fn deref(&self) -> &RadixIterator {
+        self.iterator.as_ref().expect("iterator taken")
+    }
+}
+
+impl DerefMut for PooledIterator {
+    fn deref_mut(&mut self) -> &mut RadixIterator {
+        self.iterator.as_mut().expect("iterator taken")
+    }
+}
+
+impl Drop for PooledIterator {
+    fn drop(&mut self) {
+        let Some(iterator) = self.iterator.take() else {
+            return;
+        };
+        // During thread teardown the pool may be gone; the iterator is
+        // then simply freed
+        let _ = ITERATOR_POOL.try_with(|pool| {
+            if let Ok(mut pool) = pool.try_borrow_mut() {
+                if pool.len() < POOL_SIZE {
+                    pool.push(iterator);
+                }
+            }
+        });
+    }
+}
+
 impl RadixTreeRaw {
     pub fn new() -> Result<Self> {
         unsafe {
@@ -91,9 +149,23 @@ impl RadixTreeRaw {
         }
     }
 
-    /// Create a new iterator for this tree (for concurrent queries)
-    pub fn new_iterator(&self) -> Option<RadixIterator> {
-        RadixIterator::new(self.tree)
+    /// Get an iterator for this tree (for concurrent queries)
+    ///
+    /// Iterators are pooled per thread and rebound to the searched tree by
+    /// [`RadixIterator::search`], so the steady-state match path doesn't
+    /// allocate on the C side.
+    pub fn new_iterator(&self) -> Option<PooledIterator> {
+        let pooled = ITERATOR_POOL
+            .try_with(|pool| pool.try_borrow_mut().ok()?.pop())
+            .ok()
+            .flatten();
+        let iterator = match pooled {
+            Some(iterator) => iterator,
+            None => RadixIterator::new(self.tree)?,
+        };
+        Some(PooledIterator {
+            iterator: Some(iterator),
+        })
     }
 
     pub fn insert(&mut self, key: &[u8], idx: i32) -> bool {
@@ -114,11 +186,6 @@ impl RadixTreeRaw {
     pub fn remove(&mut self, key: &[u8]) -> bool {
         unsafe { radix_tree_remove(self.tree, key.as_ptr(), key.len()) == 1 }
     }
-
-    // Internal: Get raw tree pointer for iterator operations
-    pub(crate) fn tree_ptr(&self) -> *mut c_void {
-        self.tree
-    }
 }
 
 impl Drop for RadixTreeRaw {
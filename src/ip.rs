@@ -0,0 +1,127 @@
+//! IP prefix trie for remote address matching
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::net::{IpAddr, SocketAddr};
+
+/// Binary prefix trie over IPv4 and IPv6 addresses
+///
+/// Each registered CIDR marks a node at depth `prefix_len`; a lookup walks
+/// at most 32 (IPv4) or 128 (IPv6) bits regardless of how many CIDRs a route
+/// carries, so large cloud IP range lists cost the same as a single entry.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub(crate) struct IpTrie {
+    v4: TrieNode,
+    v6: TrieNode,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct TrieNode {
+    children: [Option<Box<TrieNode>>; 2],
+    /// A registered prefix ends at this node
+    terminal: bool,
+}
+
+impl TrieNode {
+    fn insert(&mut self, bits: u128, width: u32, prefix_len: u32) {
+        let mut node = self;
+        for i in 0..prefix_len {
+            if node.terminal {
+                // A shorter prefix already covers this one
+                return;
+            }
+            let bit = ((bits >> (width - 1 - i)) & 1) as usize;
+            node = node.children[bit].get_or_insert_with(Default::default);
+        }
+        node.terminal = true;
+        // Everything below is now redundant
+        node.children = [None, None];
+    }
+
+    fn contains(&self, bits: u128, width: u32) -> bool {
+        let mut node = self;
+        for i in 0..width {
+            if node.terminal {
+                return true;
+            }
+            let bit = ((bits >> (width - 1 - i)) & 1) as usize;
+            match &node.children[bit] {
+                Some(child) => node = child,
+                None => return false,
+            }
+        }
+        node.terminal
+    }
+}
+
+impl IpTrie {
+    /// Build a trie from CIDR strings (`10.0.0.0/8`, `2001:db8::/32`) or bare addresses
+    pub fn from_cidrs<S: AsRef<str>>(cidrs: &[S]) -> Result<Self> {
+        let mut trie = Self::default();
+        for cidr in cidrs {
+            trie.insert(cidr.as_ref())?;
+        }
+        Ok(trie)
+    }
+
+    /// Register a CIDR block or a single address
+    pub fn insert(&mut self, cidr: &str) -> Result<()> {
+        let (addr, prefix_len) = match cidr.split_once('/') {
+            Some((addr, len)) => (
+                addr,
+                Some(
+                    len.parse::<u32>()
+                        .with_context(|| format!("Invalid CIDR prefix length: {}", cidr))?,
+                ),
+            ),
+            None => (cidr, None),
+        };
+
+        let addr: IpAddr = addr
+            .parse()
+            .with_context(|| format!("Invalid IP address in CIDR: {}", cidr))?;
+
+        match addr {
+            IpAddr::V4(v4) => {
+                let prefix_len = prefix_len.unwrap_or(32);
+                if prefix_len > 32 {
+                    anyhow::bail!("Invalid CIDR prefix length: {}", cidr);
+                }
+                self.v4.insert(u32::from(v4) as u128, 32, prefix_len);
+            }
+            IpAddr::V6(v6) => {
+                let prefix_len = prefix_len.unwrap_or(128);
+                if prefix_len > 128 {
+                    anyhow::bail!("Invalid CIDR prefix length: {}", cidr);
+                }
+                self.v6.insert(u128::from(v6), 128, prefix_len);
+            }
+        }
+        Ok(())
+    }
+
+    /// Check whether an address falls into any registered prefix
+    pub fn contains(&self, addr: IpAddr) -> bool {
+        match addr {
+            IpAddr::V4(v4) => self.v4.contains(u32::from(v4) as u128, 32),
+            IpAddr::V6(v6) => match v6.to_ipv4_mapped() {
+                Some(v4) => self.v4.contains(u32::from(v4) as u128, 32),
+                None => self.v6.contains(u128::from(v6), 128),
+            },
+        }
+    }
+
+    /// Check a textual remote address (`1.2.3.4`, `[::1]:8080`, `1.2.3.4:5678`)
+    pub fn contains_str(&self, addr: &str) -> bool {
+        parse_remote_addr(addr)
+            .map(|ip| self.contains(ip))
+            .unwrap_or(false)
+    }
+}
+
+/// Parse a remote address that may carry a port
+fn parse_remote_addr(addr: &str) -> Option<IpAddr> {
+    addr.parse::<IpAddr>()
+        .ok()
+        .or_else(|| addr.parse::<SocketAddr>().ok().map(|s| s.ip()))
+}
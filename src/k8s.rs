@@ -0,0 +1,345 @@
+//! Kubernetes Ingress and Gateway API HTTPRoute adapters
+//!
+//! Enabled by the `k8s` feature. The types mirror the parts of
+//! `networking.k8s.io/v1` Ingress and `gateway.networking.k8s.io/v1`
+//! HTTPRoute objects that affect routing, so they deserialize straight from
+//! what the API server (or a client such as kube-rs) returns; other fields
+//! are ignored.
+//!
+//! Headers and query parameters become var conditions under nginx names:
+//! header `X-User` is `http_x_user` and query parameter `page` is
+//! `arg_page`. Lookups expose [`RadixMatchOpts::headers`] and
+//! [`RadixMatchOpts::args`] under those names, so callers pass the request
+//! as is.
+//! Every route carries its backends in `metadata`.
+//!
+//! [`RadixMatchOpts::headers`]: crate::RadixMatchOpts::headers
+//! [`RadixMatchOpts::args`]: crate::RadixMatchOpts::args
+
+use crate::route::{compile_regex, Expr, RadixHttpMethod, RadixNode};
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use serde_json::json;
+
+/// Object name and namespace
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct ObjectMeta {
+    pub name: String,
+    pub namespace: Option<String>,
+}
+
+impl ObjectMeta {
+    /// `namespace/name`, the prefix of generated route ids
+    fn key(&self) -> String {
+        format!(
+            "{}/{}",
+            self.namespace.as_deref().unwrap_or("default"),
+            self.name
+        )
+    }
+}
+
+/// `networking.k8s.io/v1` Ingress
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct Ingress {
+    pub metadata: ObjectMeta,
+    pub spec: IngressSpec,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct IngressSpec {
+    /// Backend for requests no rule matches
+    pub default_backend: Option<IngressBackend>,
+    pub rules: Vec<IngressRule>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct IngressRule {
+    /// Exact host or `*.suffix` wildcard; unset matches every host
+    pub host: Option<String>,
+    pub http: Option<HttpIngressRuleValue>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct HttpIngressRuleValue {
+    pub paths: Vec<HttpIngressPath>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct HttpIngressPath {
+    pub path: Option<String>,
+    /// `Exact`, `Prefix` or `ImplementationSpecific` (treated as `Prefix`)
+    pub path_type: String,
+    pub backend: IngressBackend,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct IngressBackend {
+    pub service: Option<IngressServiceBackend>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct IngressServiceBackend {
+    pub name: String,
+    pub port: ServiceBackendPort,
+}
+
+/// Service port, by number or by name
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct ServiceBackendPort {
+    pub number: Option<u16>,
+    pub name: Option<String>,
+}
+
+/// `gateway.networking.k8s.io/v1` HTTPRoute
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct HttpRoute {
+    pub metadata: ObjectMeta,
+    pub spec: HttpRouteSpec,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct HttpRouteSpec {
+    /// Exact hosts or `*.suffix` wildcards; empty matches every host
+    pub hostnames: Vec<String>,
+    pub rules: Vec<HttpRouteRule>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct HttpRouteRule {
+    /// Alternatives; a rule without matches matches every request
+    pub matches: Vec<HttpRouteMatch>,
+    pub backend_refs: Vec<HttpBackendRef>,
+}
+
+/// One alternative of a rule; all of its conditions must hold
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct HttpRouteMatch {
+    pub path: Option<HttpPathMatch>,
+    pub method: Option<String>,
+    pub headers: Vec<HttpValueMatch>,
+    pub query_params: Vec<HttpValueMatch>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct HttpPathMatch {
+    /// `Exact` or `PathPrefix` (the default)
+    #[serde(rename = "type", default = "path_prefix")]
+    pub kind: String,
+    #[serde(default = "root")]
+    pub value: String,
+}
+
+/// Header or query parameter condition
+#[derive(Debug, Clone, Deserialize)]
+pub struct HttpValueMatch {
+    /// `Exact` (the default) or `RegularExpression`
+    #[serde(rename = "type", default = "exact")]
+    pub kind: String,
+    pub name: String,
+    pub value: String,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct HttpBackendRef {
+    pub name: String,
+    pub namespace: Option<String>,
+    pub port: Option<u16>,
+    pub weight: Option<u32>,
+}
+
+fn path_prefix() -> String {
+    "PathPrefix".to_string()
+}
+
+fn root() -> String {
+    "/".to_string()
+}
+
+fn exact() -> String {
+    "Exact".to_string()
+}
+
+/// Router paths for a Kubernetes path match
+///
+/// Prefixes match whole segments, so `/foo` covers `/foo` and `/foo/...`
+/// but not `/foobar`.
+fn k8s_paths(kind: &str, value: &str) -> Result<Vec<String>> {
+    if !value.starts_with('/') {
+        anyhow::bail!("Path must start with '/': {}", value);
+    }
+    match kind {
+        "Exact" => Ok(vec![value.to_string()]),
+        "Prefix" | "PathPrefix" | "ImplementationSpecific" => {
+            let base = value.trim_end_matches('/');
+            if base.is_empty() {
+                Ok(vec!["/*".to_string()])
+            } else {
+                Ok(vec![base.to_string(), format!("{}/*", base)])
+            }
+        }
+        other => anyhow::bail!("Unsupported path type: {}", other),
+    }
+}
+
+/// Var condition for a header or query parameter match
+fn value_expr(var: String, m: &HttpValueMatch) -> Result<Expr> {
+    match m.kind.as_str() {
+        "Exact" => Ok(Expr::Eq(var, m.value.clone())),
+        "RegularExpression" => {
+            let regex = compile_regex(&m.value)
+                .with_context(|| format!("Invalid regex for {}: {}", m.name, m.value))?;
+            Ok(Expr::Regex(var, regex))
+        }
+        other => anyhow::bail!("Unsupported match type for {}: {}", m.name, other),
+    }
+}
+
+impl Ingress {
+    /// One route per rule path, ids `namespace/name#rule.path`
+    ///
+    /// The default backend becomes a catch-all `/*` route at the lowest
+    /// priority, id `namespace/name#default`.
+    pub fn to_nodes(&self) -> Result<Vec<RadixNode>> {
+        let key = self.metadata.key();
+        let backend = |backend: &IngressBackend| {
+            let service = backend.service.as_ref();
+            json!({
+                "kind": "Ingress",
+                "source": key,
+                "service": service.map(|s| s.name.as_str()),
+                "port": service.and_then(|s| s.port.number),
+                "port_name": service.and_then(|s| s.port.name.as_deref()),
+            })
+        };
+
+        let mut nodes = Vec::new();
+        for (r, rule) in self.spec.rules.iter().enumerate() {
+            let Some(http) = &rule.http else {
+                continue;
+            };
+            for (p, path) in http.paths.iter().enumerate() {
+                let value = path.path.as_deref().unwrap_or("/");
+                let paths = k8s_paths(&path.path_type, value)
+                    .with_context(|| format!("Invalid path in Ingress {}", key))?;
+                nodes.push(RadixNode {
+                    id: format!("{}#{}.{}", key, r, p),
+                    paths,
+                    hosts: rule.host.clone().map(|host| vec![host]),
+                    metadata: backend(&path.backend),
+                    ..Default::default()
+                });
+            }
+        }
+        if let Some(default) = &self.spec.default_backend {
+            nodes.push(RadixNode {
+                id: format!("{}#default", key),
+                paths: vec!["/*".to_string()],
+                priority: i32::MIN,
+                metadata: backend(default),
+                ..Default::default()
+            });
+        }
+        Ok(nodes)
+    }
+}
+
+impl HttpRoute {
+    /// One route per rule match, ids `namespace/name#rule.match`
+    ///
+    /// Among matches for the same path, one with a method wins, then the
+    /// one with more header conditions, then more query parameters, as the
+    /// Gateway API precedence rules require.
+    pub fn to_nodes(&self) -> Result<Vec<RadixNode>> {
+        let key = self.metadata.key();
+        let hosts = (!self.spec.hostnames.is_empty()).then(|| self.spec.hostnames.clone());
+        let catch_all = [HttpRouteMatch::default()];
+
+        let mut nodes = Vec::new();
+        for (r, rule) in self.spec.rules.iter().enumerate() {
+            let backends: Vec<_> = rule
+                .backend_refs
+                .iter()
+                .map(|b| {
+                    json!({
+                        "service": b.name,
+                        "namespace": b.namespace.as_deref().or(self.metadata.namespace.as_deref()),
+                        "port": b.port,
+                        "weight": b.weight.unwrap_or(1),
+                    })
+                })
+                .collect();
+            let matches = if rule.matches.is_empty() {
+                &catch_all[..]
+            } else {
+                &rule.matches[..]
+            };
+
+            for (m, matched) in matches.iter().enumerate() {
+                let context = || format!("Invalid match {}.{} in HTTPRoute {}", r, m, key);
+                let paths = match &matched.path {
+                    Some(path) => k8s_paths(&path.kind, &path.value),
+                    None => k8s_paths("PathPrefix", "/"),
+                }
+                .with_context(context)?;
+
+                let methods = match &matched.method {
+                    Some(method) => Some(
+                        RadixHttpMethod::from_str(method)
+                            .with_context(|| format!("Unknown method: {}", method))
+                            .with_context(context)?,
+                    ),
+                    None => None,
+                };
+
+                let headers = matched.headers.iter().map(|h| {
+                    value_expr(
+                        format!("http_{}", h.name.to_lowercase().replace('-', "_")),
+                        h,
+                    )
+                });
+                let args = matched
+                    .query_params
+                    .iter()
+                    .map(|q| value_expr(format!("arg_{}", q.name), q));
+                let vars = headers
+                    .chain(args)
+                    .collect::<Result<Vec<_>>>()
+                    .with_context(context)?;
+
+                nodes.push(RadixNode {
+                    id: format!("{}#{}.{}", key, r, m),
+                    paths,
+                    methods,
+                    hosts: hosts.clone(),
+                    // Method, then header count, then query parameter count
+                    priority: methods.is_some() as i32 * 1_000_000
+                        + matched.headers.len() as i32 * 1_000
+                        + matched.query_params.len() as i32,
+                    vars: (!vars.is_empty()).then_some(vars),
+                    metadata: json!({
+                        "kind": "HTTPRoute",
+                        "source": key,
+                        "backends": backends,
+                    }),
+                    ..Default::default()
+                });
+            }
+        }
+        Ok(nodes)
+    }
+}
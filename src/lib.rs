@@ -8,6 +8,7 @@
 //! - Wildcards (`*`)
 //! - HTTP method matching
 //! - Host matching (with wildcards)
+//! - Remote address matching (CIDR, IPv4 and IPv6)
 //! - Priority-based routing
 //! - Custom filter functions
 //! - Variable expressions
@@ -30,6 +31,7 @@
 //!         filter_fn: None,
 //!         priority: 0,
 //!         metadata: serde_json::json!({"handler": "get_users"}),
+//!         ..Default::default()
 //!     },
 //!     RadixNode {
 //!         id: "2".to_string(),
@@ -41,10 +43,11 @@
 //!         filter_fn: None,
 //!         priority: 0,
 //!         metadata: serde_json::json!({"handler": "get_user"}),
+//!         ..Default::default()
 //!     },
 //! ];
 //!
-//! let mut router = RadixRouter::new()?;
+//! let router = RadixRouter::new()?;
 //! router.add_routes(routes)?;
 //!
 //! let opts = RadixMatchOpts {
@@ -65,163 +68,4892 @@
 //! # }
 //! ```
 
+mod audit;
+mod bucket;
+#[cfg(feature = "capi")]
+pub mod capi;
+mod capture;
+mod config;
+mod explain;
 mod ffi;
+mod ip;
+#[cfg(feature = "k8s")]
+pub mod k8s;
+#[cfg(feature = "lua")]
+pub mod lua;
+mod manager;
+mod observer;
+mod params;
+mod pattern;
+mod registry;
+mod rewrite;
 mod route;
 mod router;
+mod snapshot;
+mod snapshot_file;
+mod source;
+mod telemetry;
+mod transaction;
+mod validate;
+#[cfg(feature = "watch")]
+mod watch;
 
 // Re-export public types
-pub use route::{Expr, FilterFn, HostPattern, RadixHttpMethod, RadixMatchOpts, MatchResult, RadixNode};
-pub use router::RadixRouter;
+pub use audit::{AuditAction, AuditEntry, AuditSink};
+pub use bucket::Bucketer;
+pub use config::{CanaryConfig, RouteConfig, VarConfig};
+pub use explain::{CandidateSource, CandidateTrace, Conflict, Explanation, ShadowReport};
+pub use manager::RouterManager;
+pub use observer::RouteObserver;
+pub use params::{Params, ParamsIter};
+pub use pattern::convert_matchit_path;
+pub use registry::{Listener, ListenerRegistry};
+pub use route::{
+    compile_regex, param_template, Canary, ContextFilter, Expr, FilterErrorPolicy, FilterFn,
+    HostPattern, IdPolicy, Limits, MatchInfo, MatchPolicy, MatchRef, MatchResult, MatchedRoute,
+    MergePolicy, MetadataHook, PathDecoding, RadixHttpMethod, RadixMatchOpts, RadixNode, Rejection,
+    RouteInfo, RouteOutcome, RouteQuery, TableLimits, TieBreaker, TraversalPolicy, TryFilterFn,
+    VarCase, EXTENSION_PARAM, MAX_REGEX_LEN, WEIGHT_SEED_VAR,
+};
+pub use router::{RadixRouter, Router, RouterBuilder, RouterView};
+#[cfg(feature = "macros")]
+pub use router_radix_macros::checked_routes;
+pub use snapshot_file::SnapshotFile;
+pub use source::{RouteSink, RouteSource, Subscription};
+pub use transaction::{ChangeReport, Transaction};
+pub use validate::{Severity, ValidationIssue};
+#[cfg(feature = "watch")]
+pub use watch::FileSource;
+
+// Lets `checked_routes!` name this crate from inside it too
+#[cfg(feature = "macros")]
+extern crate self as router_radix;
 
 // Re-export anyhow types for convenience
 pub use anyhow::{Context, Result};
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use std::{collections::HashMap, sync::Arc};
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{collections::HashMap, sync::Arc};
+
+    fn node(id: &str, path: &str) -> RadixNode {
+        RadixNode {
+            id: id.to_string(),
+            paths: vec![path.to_string()],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_basic_match() {
+        let routes = vec![RadixNode {
+            id: "1".to_string(),
+            paths: vec!["/api/users".to_string()],
+            methods: Some(RadixHttpMethod::GET),
+            hosts: None,
+            remote_addrs: None,
+            vars: None,
+            filter_fn: None,
+            priority: 0,
+            metadata: serde_json::json!({"handler": "get_users"}),
+            ..Default::default()
+        }];
+
+        let router = RadixRouter::new().unwrap();
+        router.add_routes(routes).unwrap();
+
+        let opts = RadixMatchOpts {
+            method: Some("GET".to_string()),
+            ..Default::default()
+        };
+
+        let result = router.match_route("/api/users", &opts).unwrap();
+        assert!(result.is_some());
+        let result = result.unwrap();
+        assert_eq!(result.id, "1");
+        assert_eq!(result.metadata["handler"], "get_users");
+    }
+
+    #[test]
+    fn test_method_not_allowed() {
+        let routes = vec![RadixNode {
+            id: "1".to_string(),
+            paths: vec!["/api/users".to_string()],
+            methods: Some(RadixHttpMethod::GET),
+            hosts: None,
+            remote_addrs: None,
+            vars: None,
+            filter_fn: None,
+            priority: 0,
+            metadata: serde_json::json!({"handler": "get_users"}),
+            ..Default::default()
+        }];
+
+        let router = RadixRouter::new().unwrap();
+        router.add_routes(routes).unwrap();
+
+        let opts = RadixMatchOpts {
+            method: Some("POST".to_string()),
+            ..Default::default()
+        };
+
+        let result = router.match_route("/api/users", &opts).unwrap();
+        assert!(result.is_none());
+    }
+
+    #[test]
+    fn test_match_outcome() {
+        let routes = vec![RadixNode {
+            id: "1".to_string(),
+            paths: vec!["/api/user/:id".to_string()],
+            methods: Some(RadixHttpMethod::GET | RadixHttpMethod::PUT),
+            hosts: None,
+            remote_addrs: None,
+            vars: None,
+            filter_fn: None,
+            priority: 0,
+            metadata: serde_json::json!({"handler": "user"}),
+            ..Default::default()
+        }];
+
+        let router = RadixRouter::new().unwrap();
+        router.add_routes(routes).unwrap();
+
+        let opts = RadixMatchOpts {
+            method: Some("GET".to_string()),
+            ..Default::default()
+        };
+        let outcome = router.match_outcome("/api/user/1", &opts).unwrap();
+        assert!(outcome.is_match());
+        assert_eq!(outcome.into_match().unwrap().id, "1");
+
+        let opts = RadixMatchOpts {
+            method: Some("DELETE".to_string()),
+            ..Default::default()
+        };
+        match router.match_outcome("/api/user/1", &opts).unwrap() {
+            RouteOutcome::MethodNotAllowed { allowed } => {
+                assert_eq!(allowed, RadixHttpMethod::GET | RadixHttpMethod::PUT);
+            }
+            other => panic!("unexpected outcome: {:?}", other),
+        }
+
+        let outcome = router.match_outcome("/api/other", &opts).unwrap();
+        assert!(matches!(outcome, RouteOutcome::NotFound));
+        assert!(Option::<MatchResult>::from(outcome).is_none());
+
+        // Routes that fail another check besides the method don't count
+        router
+            .add_route(RadixNode {
+                id: "2".to_string(),
+                paths: vec!["/api/user/:id".to_string()],
+                methods: Some(RadixHttpMethod::POST),
+                hosts: Some(vec!["admin.example.com".to_string()]),
+                ..Default::default()
+            })
+            .unwrap();
+        match router.match_outcome("/api/user/1", &opts).unwrap() {
+            RouteOutcome::MethodNotAllowed { allowed } => {
+                assert_eq!(allowed, RadixHttpMethod::GET | RadixHttpMethod::PUT);
+            }
+            other => panic!("unexpected outcome: {:?}", other),
+        }
+        let opts = RadixMatchOpts {
+            method: Some("DELETE".to_string()),
+            host: Some("admin.example.com".to_string()),
+            ..Default::default()
+        };
+        match router.match_outcome("/api/user/1", &opts).unwrap() {
+            RouteOutcome::MethodNotAllowed { allowed } => {
+                assert_eq!(
+                    allowed,
+                    RadixHttpMethod::GET | RadixHttpMethod::PUT | RadixHttpMethod::POST
+                );
+            }
+            other => panic!("unexpected outcome: {:?}", other),
+        }
+
+        router.delete_by_id("1").unwrap();
+        let opts = RadixMatchOpts {
+            method: Some("DELETE".to_string()),
+            ..Default::default()
+        };
+        let outcome = router.match_outcome("/api/user/1", &opts).unwrap();
+        assert!(matches!(outcome, RouteOutcome::NotFound));
+    }
+
+    #[test]
+    fn test_param_extraction() {
+        let routes = vec![RadixNode {
+            id: "1".to_string(),
+            paths: vec!["/user/:id/post/:pid".to_string()],
+            methods: None,
+            hosts: None,
+            remote_addrs: None,
+            vars: None,
+            filter_fn: None,
+            priority: 0,
+            metadata: serde_json::json!({"handler": "user_post"}),
+            ..Default::default()
+        }];
+
+        let router = RadixRouter::new().unwrap();
+        router.add_routes(routes).unwrap();
+
+        let opts = RadixMatchOpts::default();
+
+        let result = router.match_route("/user/123/post/456", &opts).unwrap();
+
+        assert!(result.is_some());
+        let result = result.unwrap();
+        assert_eq!(result.id, "1");
+        assert_eq!(result.matched.get("id").unwrap(), "123");
+        assert_eq!(result.matched.get("pid").unwrap(), "456");
+    }
+
+    #[test]
+    fn test_wildcard() {
+        let routes = vec![RadixNode {
+            id: "1".to_string(),
+            paths: vec!["/files/*path".to_string()],
+            methods: None,
+            hosts: None,
+            remote_addrs: None,
+            vars: None,
+            filter_fn: None,
+            priority: 0,
+            metadata: serde_json::json!({"handler": "serve_file"}),
+            ..Default::default()
+        }];
+
+        let router = RadixRouter::new().unwrap();
+        router.add_routes(routes).unwrap();
+
+        let opts = RadixMatchOpts::default();
+
+        let result = router
+            .match_route("/files/documents/readme.txt", &opts)
+            .unwrap();
+
+        assert!(result.is_some());
+        let result = result.unwrap();
+        assert_eq!(result.matched.get("path").unwrap(), "documents/readme.txt");
+    }
+
+    #[test]
+    fn test_wildcard_host() {
+        let routes = vec![RadixNode {
+            id: "1".to_string(),
+            paths: vec!["/api".to_string()],
+            methods: None,
+            hosts: Some(vec!["*.example.com".to_string()]),
+            remote_addrs: None,
+            vars: None,
+            filter_fn: None,
+            priority: 0,
+            metadata: serde_json::json!({"handler": "api"}),
+            ..Default::default()
+        }];
+
+        let router = RadixRouter::new().unwrap();
+        router.add_routes(routes).unwrap();
+
+        let opts = RadixMatchOpts {
+            host: Some("api.example.com".to_string()),
+            ..Default::default()
+        };
+
+        let result = router.match_route("/api", &opts).unwrap();
+        assert!(result.is_some());
+
+        // Test non-matching host
+        let opts = RadixMatchOpts {
+            host: Some("api.other.com".to_string()),
+            ..Default::default()
+        };
+        let result = router.match_route("/api", &opts).unwrap();
+        assert!(result.is_none());
+    }
+
+    #[test]
+    fn test_scheme_and_sni() {
+        let routes = vec![
+            RadixNode {
+                id: "https_admin".to_string(),
+                paths: vec!["/admin".to_string()],
+                schemes: Some(vec!["https".to_string()]),
+                snis: Some(vec!["*.internal.example.com".to_string()]),
+                priority: 10,
+                metadata: serde_json::json!({"handler": "admin"}),
+                ..Default::default()
+            },
+            RadixNode {
+                id: "redirect".to_string(),
+                paths: vec!["/admin".to_string()],
+                schemes: Some(vec!["http".to_string()]),
+                metadata: serde_json::json!({"handler": "redirect_to_https"}),
+                ..Default::default()
+            },
+        ];
+
+        let router = RadixRouter::new().unwrap();
+        router.add_routes(routes).unwrap();
+
+        let opts = RadixMatchOpts {
+            scheme: Some("HTTPS".to_string()),
+            sni: Some("ops.internal.example.com".to_string()),
+            ..Default::default()
+        };
+        let result = router.match_route("/admin", &opts).unwrap().unwrap();
+        assert_eq!(result.id, "https_admin");
+
+        let opts = RadixMatchOpts {
+            scheme: Some("http".to_string()),
+            ..Default::default()
+        };
+        let result = router.match_route("/admin", &opts).unwrap().unwrap();
+        assert_eq!(result.id, "redirect");
+
+        // TLS without a matching SNI is not routable
+        let opts = RadixMatchOpts {
+            scheme: Some("https".to_string()),
+            sni: Some("public.example.com".to_string()),
+            ..Default::default()
+        };
+        assert!(router.match_route("/admin", &opts).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_remote_addrs() {
+        let routes = vec![RadixNode {
+            id: "internal".to_string(),
+            paths: vec!["/metrics".to_string()],
+            remote_addrs: Some(vec![
+                "10.0.0.0/8".to_string(),
+                "192.168.1.7".to_string(),
+                "2001:db8::/32".to_string(),
+            ]),
+            metadata: serde_json::json!({"handler": "metrics"}),
+            ..Default::default()
+        }];
+
+        let router = RadixRouter::new().unwrap();
+        router.add_routes(routes).unwrap();
+
+        let matches = |addr: Option<&str>| {
+            let opts = RadixMatchOpts {
+                remote_addr: addr.map(|a| a.to_string()),
+                ..Default::default()
+            };
+            router.match_route("/metrics", &opts).unwrap().is_some()
+        };
+
+        assert!(matches(Some("10.20.30.40")));
+        assert!(matches(Some("10.20.30.40:51234")));
+        assert!(matches(Some("::ffff:10.1.2.3")));
+        assert!(matches(Some("192.168.1.7")));
+        assert!(matches(Some("[2001:db8::1]:443")));
+        assert!(!matches(Some("192.168.1.8")));
+        assert!(!matches(Some("2001:db9::1")));
+        assert!(!matches(Some("not-an-ip")));
+        assert!(!matches(None));
+
+        // Invalid CIDRs are rejected at insert time
+        let bad = RadixNode {
+            id: "bad".to_string(),
+            paths: vec!["/bad".to_string()],
+            remote_addrs: Some(vec!["10.0.0.0/33".to_string()]),
+            ..Default::default()
+        };
+        assert!(router.add_route(bad).is_err());
+    }
+
+    #[test]
+    fn test_server_port() {
+        let routes = vec![
+            RadixNode {
+                id: "public".to_string(),
+                paths: vec!["/status".to_string()],
+                server_ports: Some(vec![80, 443]),
+                metadata: serde_json::json!({"handler": "public_status"}),
+                ..Default::default()
+            },
+            RadixNode {
+                id: "admin".to_string(),
+                paths: vec!["/status".to_string()],
+                server_ports: Some(vec![9090]),
+                metadata: serde_json::json!({"handler": "admin_status"}),
+                ..Default::default()
+            },
+        ];
+
+        let router = RadixRouter::new().unwrap();
+        router.add_routes(routes).unwrap();
+
+        let match_port = |port: Option<u16>| {
+            let opts = RadixMatchOpts {
+                server_port: port,
+                ..Default::default()
+            };
+            router.match_route("/status", &opts).unwrap().map(|r| r.id)
+        };
+
+        assert_eq!(match_port(Some(443)).as_deref(), Some("public"));
+        assert_eq!(match_port(Some(9090)).as_deref(), Some("admin"));
+        assert_eq!(match_port(Some(8080)), None);
+        assert_eq!(match_port(None), None);
+    }
+
+    #[test]
+    fn test_validity_window() {
+        use std::time::{Duration, SystemTime};
+
+        let start = SystemTime::UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+        let end = start + Duration::from_secs(3600);
+        let routes = vec![
+            RadixNode {
+                id: "promo".to_string(),
+                paths: vec!["/sale".to_string()],
+                valid_from: Some(start),
+                valid_until: Some(end),
+                priority: 10,
+                metadata: serde_json::json!({"handler": "promo"}),
+                ..Default::default()
+            },
+            RadixNode {
+                id: "regular".to_string(),
+                paths: vec!["/sale".to_string()],
+                metadata: serde_json::json!({"handler": "regular"}),
+                ..Default::default()
+            },
+        ];
+
+        let router = RadixRouter::new().unwrap();
+        router.add_routes(routes).unwrap();
+
+        let match_at = |now: SystemTime| {
+            let opts = RadixMatchOpts {
+                now: Some(now),
+                ..Default::default()
+            };
+            router.match_route("/sale", &opts).unwrap().unwrap().id
+        };
+
+        assert_eq!(match_at(start - Duration::from_secs(1)), "regular");
+        assert_eq!(match_at(start), "promo");
+        assert_eq!(match_at(end - Duration::from_secs(1)), "promo");
+        assert_eq!(match_at(end), "regular");
+    }
+
+    #[test]
+    fn test_ttl_and_purge() {
+        use std::time::{Duration, SystemTime};
+
+        let routes = vec![
+            RadixNode {
+                id: "preview".to_string(),
+                paths: vec!["/preview/*".to_string(), "/preview".to_string()],
+                ttl: Some(Duration::from_secs(3600)),
+                metadata: serde_json::json!({"handler": "preview"}),
+                ..Default::default()
+            },
+            RadixNode {
+                id: "stable".to_string(),
+                paths: vec!["/stable".to_string()],
+                metadata: serde_json::json!({"handler": "stable"}),
+                ..Default::default()
+            },
+        ];
+
+        let router = RadixRouter::new().unwrap();
+        router.add_routes(routes).unwrap();
+
+        let opts = RadixMatchOpts::default();
+        assert!(router
+            .match_route("/preview/pr-1", &opts)
+            .unwrap()
+            .is_some());
+
+        // Lazily expired once the TTL has elapsed
+        let later = RadixMatchOpts {
+            now: Some(SystemTime::now() + Duration::from_secs(7200)),
+            ..Default::default()
+        };
+        assert!(router
+            .match_route("/preview/pr-1", &later)
+            .unwrap()
+            .is_none());
+        assert!(router.match_route("/preview", &later).unwrap().is_none());
+
+        // Nothing has expired yet in wall-clock time
+        assert_eq!(router.purge_expired().unwrap(), 0);
+
+        let short = RadixNode {
+            id: "short".to_string(),
+            paths: vec!["/short/*".to_string()],
+            ttl: Some(Duration::ZERO),
+            ..Default::default()
+        };
+        router.add_route(short).unwrap();
+        assert_eq!(router.purge_expired().unwrap(), 1);
+        assert!(router.match_route("/short/x", &opts).unwrap().is_none());
+        assert!(router
+            .match_route("/preview/pr-1", &opts)
+            .unwrap()
+            .is_some());
+        assert!(router.match_route("/stable", &opts).unwrap().is_some());
+    }
+
+    #[test]
+    fn test_tags() {
+        let routes = vec![
+            RadixNode {
+                id: "charge".to_string(),
+                paths: vec!["/pay/charge".to_string(), "/pay/v2/*".to_string()],
+                tags: vec!["team:payments".to_string()],
+                ..Default::default()
+            },
+            RadixNode {
+                id: "refund".to_string(),
+                paths: vec!["/pay/refund/:id".to_string()],
+                tags: vec!["team:payments".to_string(), "beta".to_string()],
+                ..Default::default()
+            },
+            RadixNode {
+                id: "search".to_string(),
+                paths: vec!["/search".to_string()],
+                tags: vec!["team:search".to_string()],
+                ..Default::default()
+            },
+        ];
+
+        let router = RadixRouter::new().unwrap();
+        router.add_routes(routes).unwrap();
+
+        assert_eq!(
+            router.routes_with_tag("team:payments"),
+            vec!["charge", "refund"]
+        );
+        assert_eq!(router.routes_with_tag("beta"), vec!["refund"]);
+        assert!(router.routes_with_tag("missing").is_empty());
+
+        assert_eq!(router.delete_by_tag("team:payments").unwrap(), 2);
+        let opts = RadixMatchOpts::default();
+        assert!(router.match_route("/pay/charge", &opts).unwrap().is_none());
+        assert!(router.match_route("/pay/v2/x", &opts).unwrap().is_none());
+        assert!(router
+            .match_route("/pay/refund/1", &opts)
+            .unwrap()
+            .is_none());
+        assert!(router.match_route("/search", &opts).unwrap().is_some());
+        assert!(router.routes_with_tag("team:payments").is_empty());
+    }
+
+    #[test]
+    fn test_route_introspection() {
+        let router = RadixRouter::new().unwrap();
+        assert!(router.is_empty());
+
+        router
+            .add_routes(vec![
+                RadixNode {
+                    id: "users".to_string(),
+                    paths: vec!["/api/users".to_string(), "/api/users/*".to_string()],
+                    methods: Some(RadixHttpMethod::GET),
+                    hosts: Some(vec!["*.example.com".to_string()]),
+                    priority: 5,
+                    metadata: serde_json::json!({"upstream": "users"}),
+                    ..Default::default()
+                },
+                RadixNode {
+                    id: "health".to_string(),
+                    paths: vec!["/health".to_string()],
+                    ..Default::default()
+                },
+            ])
+            .unwrap();
+
+        assert_eq!(router.len(), 2);
+        let info = router.get_route("users").unwrap();
+        assert_eq!(info.paths, vec!["/api/users", "/api/users/*"]);
+        assert_eq!(info.methods, Some(RadixHttpMethod::GET));
+        assert_eq!(info.hosts, Some(vec!["*.example.com".to_string()]));
+        assert_eq!(info.priority, 5);
+        assert_eq!(info.metadata["upstream"], "users");
+        assert!(router.get_route("missing").is_none());
+
+        let ids: Vec<String> = router.routes().map(|r| r.id).collect();
+        assert_eq!(ids, vec!["health", "users"]);
+
+        router
+            .delete_route(RadixNode {
+                id: "health".to_string(),
+                paths: vec!["/health".to_string()],
+                ..Default::default()
+            })
+            .unwrap();
+        assert_eq!(router.len(), 1);
+        assert!(router.get_route("health").is_none());
+
+        // Removing the last route of a prefix bucket also clears the tree key
+        router
+            .delete_route(RadixNode {
+                id: "users".to_string(),
+                paths: vec!["/api/users".to_string(), "/api/users/*".to_string()],
+                ..Default::default()
+            })
+            .unwrap();
+        assert!(router.is_empty());
+        let opts = RadixMatchOpts::default();
+        assert!(router.match_route("/api/users/1", &opts).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_delete_paths_sharing_a_key() {
+        let router = RadixRouter::new().unwrap();
+        router
+            .add_routes(vec![
+                RadixNode {
+                    paths: vec!["/u/:x".to_string(), "/u/*y".to_string()],
+                    ..node("a", "/")
+                },
+                RadixNode {
+                    paths: vec!["/twice".to_string(), "/twice".to_string()],
+                    ..node("b", "/")
+                },
+                node("other", "/u/:name"),
+            ])
+            .unwrap();
+
+        router.delete_by_id("a").unwrap();
+        router.delete_by_id("b").unwrap();
+        assert_eq!(router.len(), 1);
+        let opts = RadixMatchOpts::default();
+        let result = router.match_route("/u/1", &opts).unwrap().unwrap();
+        assert_eq!(result.id, "other");
+        assert!(router.match_route("/u/1/2", &opts).unwrap().is_none());
+        assert!(router.match_route("/twice", &opts).unwrap().is_none());
+
+        // Replacing such a route deletes its old paths first
+        router.set_id_policy(IdPolicy::Replace).unwrap();
+        router
+            .add_route(RadixNode {
+                paths: vec!["/v/:x".to_string(), "/v/*y".to_string()],
+                ..node("c", "/")
+            })
+            .unwrap();
+        router.add_route(node("c", "/w")).unwrap();
+        assert_eq!(router.get_route("c").unwrap().paths, vec!["/w"]);
+        assert!(router.match_route("/v/1", &opts).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_update_metadata() {
+        let router = RadixRouter::new().unwrap();
+        router
+            .add_route(RadixNode {
+                id: "orders".to_string(),
+                paths: vec!["/orders".to_string(), "/orders/:id".to_string()],
+                metadata: serde_json::json!({"upstream": "orders-v1"}),
+                ..Default::default()
+            })
+            .unwrap();
+        let before = router.freeze();
+
+        router
+            .update_metadata("orders", serde_json::json!({"upstream": "orders-v2"}))
+            .unwrap();
+
+        let opts = RadixMatchOpts::default();
+        let result = router.match_route("/orders", &opts).unwrap().unwrap();
+        assert_eq!(result.metadata["upstream"], "orders-v2");
+        let result = router.match_route("/orders/7", &opts).unwrap().unwrap();
+        assert_eq!(result.metadata["upstream"], "orders-v2");
+        assert_eq!(result.matched.get("id").unwrap(), "7");
+        // The earlier table shares the trees but keeps its own metadata
+        let result = before.match_route("/orders/7", &opts).unwrap().unwrap();
+        assert_eq!(result.metadata["upstream"], "orders-v1");
+
+        // Later structural changes build their own trees
+        router.get("/items/:id", serde_json::json!(null)).unwrap();
+        router.delete_by_id("orders").unwrap();
+        assert!(router.match_route("/orders/7", &opts).unwrap().is_none());
+        assert!(before.match_route("/orders/7", &opts).unwrap().is_some());
+
+        assert!(router
+            .update_metadata("missing", serde_json::Value::Null)
+            .is_err());
+    }
+
+    #[test]
+    fn test_replace_routes() {
+        let router = RadixRouter::new().unwrap();
+        router
+            .add_route(RadixNode {
+                id: "old".to_string(),
+                paths: vec!["/old/*".to_string()],
+                ..Default::default()
+            })
+            .unwrap();
+
+        router
+            .replace_routes(vec![RadixNode {
+                id: "new".to_string(),
+                paths: vec!["/new/:id".to_string()],
+                ..Default::default()
+            }])
+            .unwrap();
+
+        let opts = RadixMatchOpts::default();
+        assert!(router.match_route("/old/x", &opts).unwrap().is_none());
+        assert!(router.match_route("/new/1", &opts).unwrap().is_some());
+
+        // A failing reload keeps the current table
+        let err = router.replace_routes(vec![
+            RadixNode {
+                id: "ok".to_string(),
+                paths: vec!["/ok".to_string()],
+                ..Default::default()
+            },
+            RadixNode {
+                id: "bad".to_string(),
+                paths: vec!["/bad".to_string()],
+                remote_addrs: Some(vec!["not-a-cidr".to_string()]),
+                ..Default::default()
+            },
+        ]);
+        assert!(err.is_err());
+        assert!(router.match_route("/new/1", &opts).unwrap().is_some());
+        assert!(router.match_route("/ok", &opts).unwrap().is_none());
+        assert_eq!(router.len(), 1);
+    }
+
+    #[test]
+    fn test_transaction() {
+        let router = RadixRouter::new().unwrap();
+        router
+            .add_route(RadixNode {
+                id: "legacy".to_string(),
+                paths: vec!["/legacy/*".to_string()],
+                ..Default::default()
+            })
+            .unwrap();
+
+        router
+            .transaction(|tx| {
+                tx.add(RadixNode {
+                    id: "users".to_string(),
+                    paths: vec!["/users/:id".to_string()],
+                    ..Default::default()
+                })
+                .delete_by_id("legacy")
+                .update_metadata("users", serde_json::json!({"v": 2}));
+            })
+            .unwrap();
+
+        let opts = RadixMatchOpts::default();
+        assert!(router.match_route("/legacy/a", &opts).unwrap().is_none());
+        let result = router.match_route("/users/1", &opts).unwrap().unwrap();
+        assert_eq!(result.metadata["v"], 2);
+
+        // Any failing operation rolls back the whole batch
+        let err = router.transaction(|tx| {
+            tx.add(RadixNode {
+                id: "orders".to_string(),
+                paths: vec!["/orders/*".to_string()],
+                ..Default::default()
+            })
+            .delete_by_id("users")
+            .delete_by_id("missing");
+        });
+        assert!(err.is_err());
+        assert!(router.match_route("/orders/1", &opts).unwrap().is_none());
+        assert!(router.match_route("/users/1", &opts).unwrap().is_some());
+        assert_eq!(router.len(), 1);
+    }
+
+    #[test]
+    fn test_apply_diff() {
+        let route = |id: &str, path: &str, upstream: &str| RadixNode {
+            metadata: serde_json::json!({"upstream": upstream}),
+            ..node(id, path)
+        };
+
+        let router = RadixRouter::new().unwrap();
+        let report = router
+            .apply_diff(vec![route("a", "/a", "a1"), route("b", "/b/*", "b1")])
+            .unwrap();
+        assert_eq!(report.added, vec!["a", "b"]);
+        assert_eq!(router.len(), 2);
+
+        let report = router
+            .apply_diff(vec![route("a", "/a", "a1"), route("c", "/c/:id", "c1")])
+            .unwrap();
+        assert_eq!(report.added, vec!["c"]);
+        assert_eq!(report.removed, vec!["b"]);
+        assert_eq!(report.unchanged, 1);
+
+        let report = router
+            .apply_diff(vec![route("a", "/a", "a2"), route("c", "/c/:id", "c1")])
+            .unwrap();
+        assert_eq!(report.changed, vec!["a"]);
+        assert_eq!(report.unchanged, 1);
+
+        let report = router
+            .apply_diff(vec![route("a", "/a", "a2"), route("c", "/c/:id", "c1")])
+            .unwrap();
+        assert!(report.is_empty());
+
+        let opts = RadixMatchOpts::default();
+        let result = router.match_route("/a", &opts).unwrap().unwrap();
+        assert_eq!(result.metadata["upstream"], "a2");
+        assert!(router.match_route("/b/x", &opts).unwrap().is_none());
+        assert!(router.match_route("/c/1", &opts).unwrap().is_some());
+
+        // A metadata update is a change the next sync reverts
+        router
+            .update_metadata("a", serde_json::json!({"upstream": "hot"}))
+            .unwrap();
+        let report = router
+            .apply_diff(vec![route("a", "/a", "a2"), route("c", "/c/:id", "c1")])
+            .unwrap();
+        assert_eq!(report.changed, vec!["a"]);
+        let result = router.match_route("/a", &opts).unwrap().unwrap();
+        assert_eq!(result.metadata["upstream"], "a2");
+        router
+            .update_metadata("a", serde_json::json!({"upstream": "hot"}))
+            .unwrap();
+        let report = router
+            .apply_diff(vec![route("a", "/a", "hot"), route("c", "/c/:id", "c1")])
+            .unwrap();
+        assert!(report.is_empty());
+    }
+
+    #[test]
+    fn test_concurrent_mutation() {
+        use std::thread;
+
+        let router = Arc::new(RadixRouter::new().unwrap());
+        let writers: Vec<_> = (0..4)
+            .map(|t| {
+                let router = Arc::clone(&router);
+                thread::spawn(move || {
+                    for i in 0..50 {
+                        router
+                            .add_route(RadixNode {
+                                id: format!("r{}_{}", t, i),
+                                paths: vec![format!("/t{}/r{}/*", t, i)],
+                                ..Default::default()
+                            })
+                            .unwrap();
+                    }
+                })
+            })
+            .collect();
+        let reader = {
+            let router = Arc::clone(&router);
+            thread::spawn(move || {
+                let opts = RadixMatchOpts::default();
+                for _ in 0..200 {
+                    router.match_route("/t0/r0/x", &opts).unwrap();
+                }
+            })
+        };
+
+        for handle in writers {
+            handle.join().unwrap();
+        }
+        reader.join().unwrap();
+
+        assert_eq!(router.len(), 200);
+        let opts = RadixMatchOpts::default();
+        let result = router.match_route("/t3/r49/file", &opts).unwrap().unwrap();
+        assert_eq!(result.id, "r3_49");
+    }
+
+    #[test]
+    fn test_snapshot_consistency() {
+        use std::sync::atomic::{AtomicBool, Ordering};
+        use std::thread;
+
+        let route_set = |version: &str| {
+            vec![
+                RadixNode {
+                    id: "a".to_string(),
+                    paths: vec!["/a".to_string()],
+                    metadata: serde_json::json!(version),
+                    ..Default::default()
+                },
+                RadixNode {
+                    id: "b".to_string(),
+                    paths: vec!["/b/*".to_string()],
+                    metadata: serde_json::json!(version),
+                    ..Default::default()
+                },
+            ]
+        };
+
+        let router = Arc::new(RadixRouter::new().unwrap());
+        router.add_routes(route_set("v0")).unwrap();
+
+        let done = Arc::new(AtomicBool::new(false));
+        let reader = {
+            let router = Arc::clone(&router);
+            let done = Arc::clone(&done);
+            thread::spawn(move || {
+                let opts = RadixMatchOpts::default();
+                while !done.load(Ordering::Relaxed) {
+                    // Both routes always come from the same published table
+                    let a = router.match_route("/a", &opts).unwrap().unwrap();
+                    let b = router.match_route("/b/x", &opts).unwrap().unwrap();
+                    assert!(a.metadata.as_str().is_some());
+                    assert!(b.metadata.as_str().is_some());
+                }
+            })
+        };
+
+        for i in 1..100 {
+            router
+                .replace_routes(route_set(&format!("v{}", i)))
+                .unwrap();
+        }
+        done.store(true, Ordering::Relaxed);
+        reader.join().unwrap();
+
+        // A failing batch publishes nothing
+        let mut bad = route_set("broken");
+        bad.push(RadixNode {
+            id: "bad".to_string(),
+            paths: vec!["/c".to_string()],
+            remote_addrs: Some(vec!["not-an-ip".to_string()]),
+            ..Default::default()
+        });
+        assert!(router.add_routes(bad).is_err());
+        assert_eq!(router.len(), 2);
+        assert!(router.get_route("bad").is_none());
+    }
+
+    #[test]
+    fn test_clone() {
+        let router = RadixRouter::new().unwrap();
+        router
+            .add_route(RadixNode {
+                id: "live".to_string(),
+                paths: vec!["/api/*".to_string()],
+                metadata: serde_json::json!("v1"),
+                ..Default::default()
+            })
+            .unwrap();
+
+        let what_if = router.clone();
+        what_if
+            .update_metadata("live", serde_json::json!("v2"))
+            .unwrap();
+        what_if
+            .add_route(RadixNode {
+                id: "canary".to_string(),
+                paths: vec!["/api/canary".to_string()],
+                ..Default::default()
+            })
+            .unwrap();
+
+        let opts = RadixMatchOpts::default();
+        let result = router.match_route("/api/x", &opts).unwrap().unwrap();
+        assert_eq!(*result.metadata, "v1");
+        assert!(router.get_route("canary").is_none());
+        assert_eq!(router.len(), 1);
+
+        let result = what_if.match_route("/api/x", &opts).unwrap().unwrap();
+        assert_eq!(*result.metadata, "v2");
+        assert_eq!(what_if.len(), 2);
+
+        // Mutating the original leaves the copy alone too
+        router.delete_by_id("live").unwrap();
+        assert!(router.is_empty());
+        assert!(what_if.get_route("live").is_some());
+    }
+
+    #[test]
+    fn test_merge() {
+        let route = |id: &str, path: &str, team: &str| RadixNode {
+            metadata: serde_json::json!(team),
+            ..node(id, path)
+        };
+
+        let gateway = RadixRouter::new().unwrap();
+        gateway
+            .add_routes(vec![
+                route("users", "/users/*", "core"),
+                route("shared", "/health", "core"),
+            ])
+            .unwrap();
+
+        let team = RadixRouter::new().unwrap();
+        team.add_routes(vec![
+            route("orders", "/orders/:id", "shop"),
+            route("shared", "/health", "shop"),
+        ])
+        .unwrap();
+
+        let opts = RadixMatchOpts::default();
+
+        // Conflicting ids abort the whole merge
+        assert!(gateway.merge(&team, MergePolicy::Error).is_err());
+        assert_eq!(gateway.len(), 2);
+        assert!(gateway.get_route("orders").is_none());
+
+        gateway.merge(&team, MergePolicy::PreferSelf).unwrap();
+        assert_eq!(gateway.len(), 3);
+        let result = gateway.match_route("/orders/7", &opts).unwrap().unwrap();
+        assert_eq!(result.matched.get("id").unwrap(), "7");
+        let result = gateway.match_route("/health", &opts).unwrap().unwrap();
+        assert_eq!(*result.metadata, "core");
+
+        gateway.merge(&team, MergePolicy::PreferOther).unwrap();
+        assert_eq!(gateway.len(), 3);
+        let result = gateway.match_route("/health", &opts).unwrap().unwrap();
+        assert_eq!(*result.metadata, "shop");
+
+        // The source router is left as it was
+        assert_eq!(team.len(), 2);
+        assert!(team.get_route("users").is_none());
+    }
+
+    #[test]
+    fn test_mount() {
+        let users = RadixRouter::new().unwrap();
+        users
+            .add_routes(vec![
+                RadixNode {
+                    id: "user".to_string(),
+                    paths: vec!["/users/{id}".to_string()],
+                    ..Default::default()
+                },
+                RadixNode {
+                    id: "users".to_string(),
+                    paths: vec!["/users".to_string()],
+                    ..Default::default()
+                },
+            ])
+            .unwrap();
+
+        let router = RadixRouter::new().unwrap();
+        router.mount("/api/v2/", &users).unwrap();
+
+        let opts = RadixMatchOpts::default();
+        let result = router
+            .match_route("/api/v2/users/42", &opts)
+            .unwrap()
+            .unwrap();
+        assert_eq!(result.id, "user");
+        assert_eq!(result.matched.get("id").unwrap(), "42");
+        assert_eq!(result.info.mount.as_deref(), Some("/api/v2"));
+        assert_eq!(result.info.pattern, "/api/v2/users/:id");
+
+        let result = router.match_route("/api/v2/users", &opts).unwrap().unwrap();
+        assert_eq!(result.id, "users");
+        assert!(router.match_route("/users/42", &opts).unwrap().is_none());
+
+        // Mounted routes hash like their definitions with prefixed paths
+        let report = router
+            .apply_diff(vec![
+                RadixNode {
+                    id: "user".to_string(),
+                    paths: vec!["/api/v2/users/{id}".to_string()],
+                    ..Default::default()
+                },
+                RadixNode {
+                    id: "users".to_string(),
+                    paths: vec!["/api/v2/users".to_string()],
+                    ..Default::default()
+                },
+            ])
+            .unwrap();
+        assert!(report.is_empty());
+
+        // Nested mounts accumulate the prefix
+        let gateway = RadixRouter::new().unwrap();
+        gateway.mount("/edge", &router).unwrap();
+        let result = gateway
+            .match_route("/edge/api/v2/users/7", &opts)
+            .unwrap()
+            .unwrap();
+        assert_eq!(result.info.mount.as_deref(), Some("/edge/api/v2"));
+
+        // Conflicting ids leave the router unchanged
+        assert!(router.mount("/api/v3", &users).is_err());
+        assert!(router
+            .match_route("/api/v3/users", &opts)
+            .unwrap()
+            .is_none());
+        assert!(router.mount("/api/:v", &users).is_err());
+    }
+
+    #[test]
+    fn test_weighted_selection() {
+        let router = RadixRouter::new().unwrap();
+        router
+            .add_routes(vec![
+                RadixNode {
+                    id: "stable".to_string(),
+                    paths: vec!["/api/*".to_string()],
+                    weight: Some(3),
+                    ..Default::default()
+                },
+                RadixNode {
+                    id: "next".to_string(),
+                    paths: vec!["/api/*".to_string()],
+                    weight: Some(1),
+                    ..Default::default()
+                },
+            ])
+            .unwrap();
+
+        let seeded = |seed: &str| RadixMatchOpts {
+            vars: Some(HashMap::from([(
+                WEIGHT_SEED_VAR.to_string(),
+                seed.to_string(),
+            )])),
+            ..Default::default()
+        };
+        let mut hits: HashMap<String, usize> = HashMap::new();
+        for i in 0..400 {
+            let opts = seeded(&format!("session-{}", i));
+            let result = router.match_route("/api/x", &opts).unwrap().unwrap();
+            *hits.entry(result.id).or_default() += 1;
+        }
+        assert!(hits["stable"] > hits["next"]);
+        assert!(hits["next"] > 0);
+
+        // The same seed always lands on the same route
+        let opts = seeded("session-7");
+        let first = router.match_route("/api/x", &opts).unwrap().unwrap().id;
+        for _ in 0..10 {
+            let again = router.match_route("/api/x", &opts).unwrap().unwrap().id;
+            assert_eq!(again, first);
+        }
+
+        // Unseeded requests still only pick among the weighted routes
+        let opts = RadixMatchOpts::default();
+        let result = router.match_route("/api/x", &opts).unwrap().unwrap();
+        assert!(result.id == "stable" || result.id == "next");
+
+        // A more specific route of the same priority is not in the draw
+        router
+            .add_route(RadixNode {
+                id: "specific".to_string(),
+                paths: vec!["/api/:name".to_string()],
+                weight: Some(1),
+                ..Default::default()
+            })
+            .unwrap();
+        for i in 0..50 {
+            let opts = seeded(&format!("session-{}", i));
+            let result = router.match_route("/api/x", &opts).unwrap().unwrap();
+            assert_eq!(result.id, "specific");
+        }
+    }
+
+    #[test]
+    fn test_canary() {
+        let router = RadixRouter::new().unwrap();
+        router
+            .add_routes(vec![
+                RadixNode {
+                    id: "canary".to_string(),
+                    paths: vec!["/api/*".to_string()],
+                    priority: 1,
+                    canary: Some(Canary {
+                        percent: 5,
+                        hash_on: "user_id".to_string(),
+                    }),
+                    ..Default::default()
+                },
+                RadixNode {
+                    id: "stable".to_string(),
+                    paths: vec!["/api/*".to_string()],
+                    ..Default::default()
+                },
+            ])
+            .unwrap();
+
+        let opts_for = |user: &str| RadixMatchOpts {
+            vars: Some(HashMap::from([("user_id".to_string(), user.to_string())])),
+            ..Default::default()
+        };
+
+        let canary_users: Vec<String> = (0..2000)
+            .map(|i| format!("user-{}", i))
+            .filter(|user| {
+                let result = router
+                    .match_route("/api/x", &opts_for(user))
+                    .unwrap()
+                    .unwrap();
+                result.id == "canary"
+            })
+            .collect();
+        assert!(canary_users.len() > 40 && canary_users.len() < 180);
+
+        // Membership is sticky per user
+        for user in &canary_users {
+            let result = router
+                .match_route("/api/y", &opts_for(user))
+                .unwrap()
+                .unwrap();
+            assert_eq!(result.id, "canary");
+        }
+
+        // Requests without the hash var skip the canary
+        let result = router
+            .match_route("/api/x", &RadixMatchOpts::default())
+            .unwrap()
+            .unwrap();
+        assert_eq!(result.id, "stable");
+
+        let bad = RadixNode {
+            id: "bad".to_string(),
+            paths: vec!["/bad".to_string()],
+            canary: Some(Canary {
+                percent: 101,
+                hash_on: "user_id".to_string(),
+            }),
+            ..Default::default()
+        };
+        assert!(router.add_route(bad).is_err());
+    }
+
+    #[test]
+    fn test_shadow_routes() {
+        let router = RadixRouter::new().unwrap();
+        router
+            .add_routes(vec![
+                RadixNode {
+                    id: "mirror".to_string(),
+                    paths: vec!["/api/*".to_string()],
+                    priority: 10,
+                    shadow: true,
+                    metadata: serde_json::json!({"upstream": "users-next"}),
+                    ..Default::default()
+                },
+                RadixNode {
+                    id: "users".to_string(),
+                    paths: vec!["/api/users/:id".to_string()],
+                    metadata: serde_json::json!({"upstream": "users"}),
+                    ..Default::default()
+                },
+                RadixNode {
+                    id: "mirror-exact".to_string(),
+                    paths: vec!["/api/users/1".to_string()],
+                    shadow: true,
+                    ..Default::default()
+                },
+            ])
+            .unwrap();
+
+        let opts = RadixMatchOpts::default();
+        let result = router.match_route("/api/users/1", &opts).unwrap().unwrap();
+        assert_eq!(result.id, "users");
+        assert_eq!(result.matched.get("id").unwrap(), "1");
+        let shadows: Vec<&str> = result.shadows.iter().map(|s| s.id.as_str()).collect();
+        assert_eq!(shadows, ["mirror-exact", "mirror"]);
+        assert_eq!(result.shadows[1].metadata["upstream"], "users-next");
+
+        // Shadow routes alone never produce a match
+        assert!(router.match_route("/api/other", &opts).unwrap().is_none());
+
+        router.delete_by_id("mirror").unwrap();
+        router.delete_by_id("mirror-exact").unwrap();
+        let result = router.match_route("/api/users/1", &opts).unwrap().unwrap();
+        assert!(result.shadows.is_empty());
+    }
+
+    #[test]
+    fn test_bucket_expr() {
+        let bucketer = Bucketer::default();
+        assert_eq!(bucketer.buckets(), 100);
+        assert_eq!(bucketer.bucket("user-1"), bucketer.bucket("user-1"));
+        assert!((0..1000).all(|i| Bucketer::new(7).bucket(&i.to_string()) < 7));
+
+        let router = RadixRouter::new().unwrap();
+        router
+            .add_routes(vec![
+                RadixNode {
+                    id: "a".to_string(),
+                    paths: vec!["/feature".to_string()],
+                    vars: Some(vec![Expr::Bucket("user_id".to_string(), 0..50)]),
+                    ..Default::default()
+                },
+                RadixNode {
+                    id: "b".to_string(),
+                    paths: vec!["/feature".to_string()],
+                    vars: Some(vec![Expr::Bucket("user_id".to_string(), 50..100)]),
+                    ..Default::default()
+                },
+            ])
+            .unwrap();
+
+        for i in 0..50 {
+            let user = format!("user-{}", i);
+            let opts = RadixMatchOpts {
+                vars: Some(HashMap::from([("user_id".to_string(), user.clone())])),
+                ..Default::default()
+            };
+            let result = router.match_route("/feature", &opts).unwrap().unwrap();
+            let expected = if bucketer.bucket(&user) < 50 {
+                "a"
+            } else {
+                "b"
+            };
+            assert_eq!(result.id, expected);
+        }
+    }
+
+    #[test]
+    fn test_method_metadata() {
+        let router = RadixRouter::new().unwrap();
+        router
+            .add_route(RadixNode {
+                id: "users".to_string(),
+                paths: vec!["/users/:id".to_string()],
+                metadata: serde_json::json!({"upstream": "default"}),
+                method_metadata: Some(vec![
+                    (
+                        RadixHttpMethod::GET,
+                        serde_json::json!({"upstream": "read"}),
+                    ),
+                    (
+                        RadixHttpMethod::POST | RadixHttpMethod::PUT,
+                        serde_json::json!({"upstream": "write"}),
+                    ),
+                ]),
+                ..Default::default()
+            })
+            .unwrap();
+
+        let upstream = |method: Option<&str>| {
+            let opts = RadixMatchOpts {
+                method: method.map(str::to_string),
+                ..Default::default()
+            };
+            let result = router.match_route("/users/1", &opts).unwrap().unwrap();
+            result.metadata["upstream"].as_str().unwrap().to_string()
+        };
+        assert_eq!(upstream(Some("GET")), "read");
+        assert_eq!(upstream(Some("put")), "write");
+        assert_eq!(upstream(Some("DELETE")), "default");
+        assert_eq!(upstream(None), "default");
+    }
+
+    #[test]
+    fn test_custom_methods() {
+        let router = RadixRouter::new().unwrap();
+        router
+            .add_routes(vec![
+                RadixNode {
+                    id: "dav".to_string(),
+                    paths: vec!["/dav/*".to_string()],
+                    methods: Some(RadixHttpMethod::GET),
+                    custom_methods: Some(vec!["report".to_string(), "LOCK".to_string()]),
+                    ..Default::default()
+                },
+                RadixNode {
+                    id: "any".to_string(),
+                    paths: vec!["/any".to_string()],
+                    methods: Some(RadixHttpMethod::ALL),
+                    ..Default::default()
+                },
+                RadixNode {
+                    id: "standard".to_string(),
+                    paths: vec!["/standard".to_string()],
+                    methods: Some(RadixHttpMethod::ALL - RadixHttpMethod::OTHER),
+                    ..Default::default()
+                },
+                RadixNode {
+                    id: "other".to_string(),
+                    paths: vec!["/other".to_string()],
+                    methods: Some(RadixHttpMethod::OTHER),
+                    ..Default::default()
+                },
+            ])
+            .unwrap();
+
+        let with_method = |method: &str| RadixMatchOpts {
+            method: Some(method.to_string()),
+            ..Default::default()
+        };
+        for method in ["GET", "REPORT", "lock"] {
+            assert!(router
+                .match_route("/dav/x", &with_method(method))
+                .unwrap()
+                .is_some());
+        }
+        for method in ["POST", "MKCOL"] {
+            assert!(router
+                .match_route("/dav/x", &with_method(method))
+                .unwrap()
+                .is_none());
+        }
+        for method in ["DELETE", "PROPFIND", "X-VENDOR"] {
+            assert!(router
+                .match_route("/any", &with_method(method))
+                .unwrap()
+                .is_some());
+        }
+        // Listing every standard verb doesn't open the route to other ones
+        assert!(router
+            .match_route("/standard", &with_method("PURGE"))
+            .unwrap()
+            .is_some());
+        assert!(router
+            .match_route("/standard", &with_method("X-VENDOR"))
+            .unwrap()
+            .is_none());
+        assert!(router
+            .match_route("/other", &with_method("X-VENDOR"))
+            .unwrap()
+            .is_some());
+        assert!(router
+            .match_route("/other", &with_method("GET"))
+            .unwrap()
+            .is_none());
+        assert_eq!(RadixHttpMethod::ALL, RadixHttpMethod::all());
+
+        // A route taking custom verbs only answers other methods with a 405
+        router
+            .add_route(RadixNode {
+                custom_methods: Some(vec!["REPORT".to_string()]),
+                ..node("report", "/report")
+            })
+            .unwrap();
+        assert!(router
+            .match_route("/report", &with_method("REPORT"))
+            .unwrap()
+            .is_some());
+        match router
+            .match_outcome("/report", &with_method("GET"))
+            .unwrap()
+        {
+            RouteOutcome::MethodNotAllowed { allowed } => {
+                assert_eq!(allowed, RadixHttpMethod::OTHER);
+            }
+            other => panic!("unexpected outcome: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_match_policy() {
+        let routes = || {
+            vec![
+                RadixNode {
+                    id: "catch_all".to_string(),
+                    paths: vec!["/api/*".to_string()],
+                    priority: 10,
+                    ..Default::default()
+                },
+                RadixNode {
+                    id: "user".to_string(),
+                    paths: vec!["/api/users/:id".to_string()],
+                    priority: 0,
+                    ..Default::default()
+                },
+                RadixNode {
+                    id: "users_any".to_string(),
+                    paths: vec!["/api/users/*".to_string()],
+                    priority: 5,
+                    ..Default::default()
+                },
+            ]
+        };
+        let winner = |policy: MatchPolicy| {
+            let router = RadixRouter::with_policy(policy).unwrap();
+            router.add_routes(routes()).unwrap();
+            assert_eq!(router.policy(), policy);
+            let result = router.match_route("/api/users/1", &RadixMatchOpts::default());
+            result.unwrap().unwrap().id
+        };
+
+        // Longest prefix bucket first, priority only within a bucket
+        assert_eq!(winner(MatchPolicy::PriorityFirst), "users_any");
+        // Same literal prefix, fewer wildcards wins
+        assert_eq!(winner(MatchPolicy::MostSpecificFirst), "user");
+        // Priority across buckets
+        assert_eq!(winner(MatchPolicy::PriorityThenSpecificity), "catch_all");
+    }
+
+    #[test]
+    fn test_tie_breaker() {
+        let route = |id: &str, version: u32| RadixNode {
+            metadata: serde_json::json!({ "version": version }),
+            ..node(id, "/api/*")
+        };
+        let router = RadixRouter::new().unwrap();
+        router
+            .add_routes(vec![route("old", 1), route("newest", 3), route("new", 2)])
+            .unwrap();
+
+        // Newest version wins among otherwise equal routes
+        let newest_first: TieBreaker = Arc::new(|a: &RouteInfo, b: &RouteInfo| {
+            let version = |r: &RouteInfo| r.metadata["version"].as_u64().unwrap_or(0);
+            version(b).cmp(&version(a))
+        });
+        router.set_tie_breaker(Some(newest_first)).unwrap();
+
+        let opts = RadixMatchOpts::default();
+        let winner =
+            |router: &RadixRouter| router.match_route("/api/x", &opts).unwrap().unwrap().id;
+        assert_eq!(winner(&router), "newest");
+
+        // Kept across reloads, regardless of registration order
+        router
+            .replace_routes(vec![route("b", 7), route("a", 9)])
+            .unwrap();
+        assert_eq!(winner(&router), "a");
+
+        // Priority still comes first
+        let mut urgent = route("urgent", 0);
+        urgent.priority = 1;
+        router.add_route(urgent).unwrap();
+        assert_eq!(winner(&router), "urgent");
+    }
+
+    #[test]
+    fn test_insertion_order_tie_break() {
+        let route = |id: &str| node(id, "/api/:name");
+        let opts = RadixMatchOpts::default();
+
+        // Equal priority and path length: the earliest registered route wins
+        let router = RadixRouter::new().unwrap();
+        let ids = ["r0", "r1", "r2", "r3", "r4", "r5", "r6", "r7"];
+        router
+            .add_routes(ids.iter().map(|id| route(id)).collect())
+            .unwrap();
+        let result = router.match_route("/api/x", &opts).unwrap().unwrap();
+        assert_eq!(result.id, "r0");
+
+        // Removing the winner promotes the next oldest
+        router.delete_by_id("r0").unwrap();
+        let result = router.match_route("/api/x", &opts).unwrap().unwrap();
+        assert_eq!(result.id, "r1");
+
+        // Re-adding a route moves it behind the existing ones
+        router.add_route(route("r0")).unwrap();
+        let result = router.match_route("/api/x", &opts).unwrap().unwrap();
+        assert_eq!(result.id, "r1");
+
+        // Reload order decides, deterministically
+        router
+            .replace_routes(ids.iter().rev().map(|id| route(id)).collect())
+            .unwrap();
+        let result = router.match_route("/api/x", &opts).unwrap().unwrap();
+        assert_eq!(result.id, "r7");
+    }
+
+    #[test]
+    fn test_path_validation() {
+        let router = RadixRouter::new().unwrap();
+        let add = |path: &str| router.add_route(node(path, path));
+
+        for path in ["/", "/api/:id", "/files/*", "/files/*path", "/a/:x/b/:y-z"] {
+            add(path).unwrap();
+        }
+
+        let cases = [
+            ("api/users", "must start with '/'"),
+            ("/user:id", "':' must start a segment (at byte 5)"),
+            ("/files/a*", "'*' must start a segment (at byte 8)"),
+            ("/a/:/b", "parameter without a name (at byte 3)"),
+            ("/a/:id/b/:id", "duplicate parameter name 'id' (at byte 9)"),
+            (
+                "/a/*rest/b",
+                "wildcard must be the last segment (at byte 3)",
+            ),
+            ("/a/:i.d", "invalid character in parameter name (at byte 5)"),
+        ];
+        for (path, expected) in cases {
+            let err = format!("{:#}", add(path).unwrap_err());
+            assert!(err.contains(expected), "{}: {}", path, err);
+        }
+        assert_eq!(router.len(), 5);
+    }
+
+    #[test]
+    fn test_id_policy() {
+        let route = |path: &str| node("users", path);
+        let opts = RadixMatchOpts::default();
+
+        // Allow: paths accumulate under the id
+        let router = RadixRouter::new().unwrap();
+        assert!(router.add_route(route("/v1/users")).unwrap().is_none());
+        assert!(router.add_route(route("/v2/users")).unwrap().is_none());
+        assert_eq!(router.get_route("users").unwrap().paths.len(), 2);
+
+        // Reject: a second definition fails and changes nothing
+        let router = RadixRouter::new().unwrap();
+        router.set_id_policy(IdPolicy::Reject).unwrap();
+        router.add_route(route("/v1/users")).unwrap();
+        let err = router.add_route(route("/v2/users")).unwrap_err();
+        assert!(err.to_string().contains("Duplicate route id: users"));
+        assert!(router.add_routes(vec![route("/b")]).is_err());
+        assert_eq!(router.get_route("users").unwrap().paths, ["/v1/users"]);
+
+        // Replace: the old definition is swapped out and returned
+        let router = RadixRouter::new().unwrap();
+        router.set_id_policy(IdPolicy::Replace).unwrap();
+        router.add_route(route("/v1/users")).unwrap();
+        let previous = router.add_route(route("/v2/users")).unwrap().unwrap();
+        assert_eq!(previous.paths, ["/v1/users"]);
+        assert!(router.match_route("/v1/users", &opts).unwrap().is_none());
+        assert!(router.match_route("/v2/users", &opts).unwrap().is_some());
+
+        // The policy survives a reload
+        router
+            .replace_routes(vec![route("/v3/users"), route("/v4/users")])
+            .unwrap();
+        assert_eq!(router.get_route("users").unwrap().paths, ["/v4/users"]);
+    }
+
+    #[test]
+    fn test_explain() {
+        let router = RadixRouter::new().unwrap();
+        router
+            .add_routes(vec![
+                RadixNode {
+                    id: "admin".to_string(),
+                    paths: vec!["/api/users".to_string()],
+                    hosts: Some(vec!["admin.example.com".to_string()]),
+                    ..Default::default()
+                },
+                RadixNode {
+                    id: "user".to_string(),
+                    paths: vec!["/api/users/:id".to_string()],
+                    methods: Some(RadixHttpMethod::GET),
+                    ..Default::default()
+                },
+                RadixNode {
+                    id: "fallback".to_string(),
+                    paths: vec!["/api/*".to_string()],
+                    vars: Some(vec![Expr::Eq("env".to_string(), "prod".to_string())]),
+                    ..Default::default()
+                },
+            ])
+            .unwrap();
+
+        let opts = RadixMatchOpts {
+            method: Some("POST".to_string()),
+            host: Some("www.example.com".to_string()),
+            ..Default::default()
+        };
+        let explanation = router.explain("/api/users", &opts).unwrap();
+        let verdicts: Vec<(&str, CandidateSource, Option<Rejection>)> = explanation
+            .candidates
+            .iter()
+            .map(|c| (c.id.as_str(), c.source, c.rejection))
+            .collect();
+        assert_eq!(
+            verdicts,
+            [
+                ("admin", CandidateSource::Exact, Some(Rejection::Host)),
+                ("fallback", CandidateSource::Prefix, Some(Rejection::Vars)),
+            ]
+        );
+        assert!(explanation.result.is_none());
+
+        let explanation = router.explain("/api/users/7", &opts).unwrap();
+        assert_eq!(explanation.candidates[0].rejection, Some(Rejection::Method));
+        let text = explanation.to_string();
+        assert!(text.contains("user /api/users/:id (priority 0) -> rejected: method"));
+        assert!(text.ends_with("=> no match"));
+
+        let opts = RadixMatchOpts {
+            method: Some("GET".to_string()),
+            ..Default::default()
+        };
+        let explanation = router.explain("/api/users/7", &opts).unwrap();
+        assert!(explanation.candidates[0].accepted());
+        assert_eq!(explanation.result.unwrap().id, "user");
+    }
+
+    #[test]
+    fn test_dump() {
+        let router = RadixRouter::new().unwrap();
+        router
+            .add_routes(vec![
+                RadixNode {
+                    id: "fallback".to_string(),
+                    paths: vec!["/api/*".to_string()],
+                    ..Default::default()
+                },
+                RadixNode {
+                    id: "users".to_string(),
+                    paths: vec!["/api/users".to_string()],
+                    methods: Some(RadixHttpMethod::GET | RadixHttpMethod::POST),
+                    hosts: Some(vec!["*.example.com".to_string()]),
+                    priority: 10,
+                    ..Default::default()
+                },
+                RadixNode {
+                    id: "user".to_string(),
+                    paths: vec!["/api/users/:id".to_string()],
+                    ..Default::default()
+                },
+                RadixNode {
+                    id: "health".to_string(),
+                    paths: vec!["/health".to_string()],
+                    ..Default::default()
+                },
+            ])
+            .unwrap();
+
+        let expected = "\
+/api/
+  - fallback [prefix] /api/* priority=0 methods=* hosts=*
+  /api/users
+    - users [exact] /api/users priority=10 methods=GET|POST hosts=*.example.com
+    /api/users/
+      - user [prefix] /api/users/:id priority=0 methods=* hosts=*
+/health
+  - health [exact] /health priority=0 methods=* hosts=*
+";
+        assert_eq!(router.dump(), expected);
+    }
+
+    #[test]
+    fn test_to_dot() {
+        let router = RadixRouter::new().unwrap();
+        router
+            .add_routes(vec![
+                RadixNode {
+                    id: "fallback".to_string(),
+                    paths: vec!["/api/*".to_string()],
+                    ..Default::default()
+                },
+                RadixNode {
+                    id: "say \"hi\"".to_string(),
+                    paths: vec!["/api/users".to_string()],
+                    methods: Some(RadixHttpMethod::GET),
+                    ..Default::default()
+                },
+            ])
+            .unwrap();
+
+        let dot = router.to_dot();
+        assert!(dot.starts_with("digraph routes {"));
+        assert!(dot.trim_end().ends_with('}'));
+        assert!(dot.contains("root -> k0 [label=\"/api/\"];"));
+        assert!(dot.contains("k0 -> k1 [label=\"users\"];"));
+        assert!(dot.contains("k0 -> k0r0 [style=dashed];"));
+        assert!(dot.contains(r#"say \"hi\"\n/api/users [exact]\npriority=0 methods=GET hosts=*"#));
+    }
+
+    #[test]
+    fn test_observer() {
+        use std::sync::Mutex;
+
+        #[derive(Default)]
+        struct Audit(Mutex<Vec<String>>);
+
+        impl RouteObserver for Audit {
+            fn on_route_added(&self, route: &RouteInfo) {
+                self.0.lock().unwrap().push(format!("added {}", route.id));
+            }
+            fn on_route_removed(&self, route: &RouteInfo) {
+                self.0.lock().unwrap().push(format!("removed {}", route.id));
+            }
+            fn on_route_updated(&self, _old: &RouteInfo, new: &RouteInfo) {
+                self.0.lock().unwrap().push(format!("updated {}", new.id));
+            }
+            fn on_match(&self, path: &str, result: &MatchResult) {
+                self.0
+                    .lock()
+                    .unwrap()
+                    .push(format!("matched {} -> {}", path, result.id));
+            }
+        }
+
+        let router = RadixRouter::new().unwrap();
+        router.add_route(node("existing", "/existing")).unwrap();
+
+        let audit = Arc::new(Audit::default());
+        router.add_observer(audit.clone());
+
+        router
+            .add_routes(vec![node("a", "/a"), node("b", "/b")])
+            .unwrap();
+        router
+            .update_metadata("a", serde_json::json!({"v": 2}))
+            .unwrap();
+        router.delete_by_id("b").unwrap();
+        router
+            .match_route("/a", &RadixMatchOpts::default())
+            .unwrap();
+        router
+            .match_route("/missing", &RadixMatchOpts::default())
+            .unwrap();
+        // A failed batch publishes nothing and notifies nobody
+        assert!(router
+            .add_routes(vec![node("c", "/c"), node("bad:", "/bad:")])
+            .is_err());
+
+        let events = audit.0.lock().unwrap().clone();
+        assert_eq!(
+            events,
+            [
+                "added a",
+                "added b",
+                "updated a",
+                "removed b",
+                "matched /a -> a"
+            ]
+        );
+
+        // Clones start without observers
+        router.clone().add_route(node("d", "/d")).unwrap();
+        assert_eq!(audit.0.lock().unwrap().len(), 5);
+    }
+
+    #[test]
+    fn test_compact() {
+        let router = RadixRouter::new().unwrap();
+        let route = |id: &str, path: &str, priority: i32| RadixNode {
+            priority,
+            ..node(id, path)
+        };
+        router.add_route(route("keep", "/keep/*", 0)).unwrap();
+        router.add_route(route("low", "/api/:id", 0)).unwrap();
+        router.add_route(route("high", "/api/:id", 10)).unwrap();
+        for i in 0..50 {
+            let id = format!("tmp{}", i);
+            router
+                .add_route(route(&id, &format!("/tmp/{}/*", i), 0))
+                .unwrap();
+            router.delete_by_id(&id).unwrap();
+        }
+        let opts = RadixMatchOpts::default();
+        let lookups = |router: &RadixRouter| {
+            ["/keep/a/b", "/api/7", "/tmp/3/x"]
+                .map(|path| router.match_route(path, &opts).unwrap().map(|r| r.id))
+        };
+        let routes: Vec<RouteInfo> = router.routes().collect();
+        let matches = lookups(&router);
+        assert_eq!(router.len(), 3);
+        assert_eq!(
+            matches,
+            [Some("keep".to_string()), Some("high".to_string()), None]
+        );
+
+        // Compacting changes nothing observable
+        router.compact().unwrap();
+        assert_eq!(router.len(), 3);
+        assert_eq!(router.routes().collect::<Vec<_>>(), routes);
+        assert_eq!(lookups(&router), matches);
+
+        // New routes keep allocating after the compacted range
+        router.add_route(route("new", "/new/*", 0)).unwrap();
+        let result = router.match_route("/new/x", &opts).unwrap().unwrap();
+        assert_eq!(result.id, "new");
+        let result = router.match_route("/keep/x", &opts).unwrap().unwrap();
+        assert_eq!(result.id, "keep");
+    }
+
+    #[test]
+    fn test_snapshot_roundtrip() {
+        let router = RadixRouter::with_policy(MatchPolicy::MostSpecificFirst).unwrap();
+        router.set_id_policy(IdPolicy::Reject).unwrap();
+        router
+            .add_routes(vec![
+                RadixNode {
+                    id: "user".to_string(),
+                    paths: vec!["/api/users/:id".to_string(), "/u/:id".to_string()],
+                    methods: Some(RadixHttpMethod::GET),
+                    hosts: Some(vec!["*.example.com".to_string()]),
+                    remote_addrs: Some(vec!["10.0.0.0/8".to_string()]),
+                    vars: Some(vec![Expr::Regex(
+                        "tier".to_string(),
+                        regex::Regex::new("^gold").unwrap(),
+                    )]),
+                    priority: 5,
+                    metadata: serde_json::json!({"upstream": "users"}),
+                    ..Default::default()
+                },
+                RadixNode {
+                    id: "catch_all".to_string(),
+                    paths: vec!["/api/*".to_string()],
+                    ..Default::default()
+                },
+                RadixNode {
+                    id: "health".to_string(),
+                    paths: vec!["/health".to_string()],
+                    ..Default::default()
+                },
+            ])
+            .unwrap();
+
+        let bytes = router.to_snapshot().unwrap();
+        let restored = RadixRouter::from_snapshot(&bytes).unwrap();
+        assert_eq!(restored.policy(), MatchPolicy::MostSpecificFirst);
+        assert_eq!(restored.dump(), router.dump());
+        assert_eq!(restored.get_route("user"), router.get_route("user"));
+
+        let opts = RadixMatchOpts {
+            method: Some("GET".to_string()),
+            host: Some("api.example.com".to_string()),
+            remote_addr: Some("10.1.2.3".to_string()),
+            vars: Some(HashMap::from([("tier".to_string(), "gold".to_string())])),
+            ..Default::default()
+        };
+        let result = restored
+            .match_route("/api/users/42", &opts)
+            .unwrap()
+            .unwrap();
+        assert_eq!(result.id, "user");
+        assert_eq!(result.matched.get("id").unwrap(), "42");
+        assert_eq!(result.metadata["upstream"], "users");
+        let opts = RadixMatchOpts {
+            remote_addr: Some("192.168.0.1".to_string()),
+            ..opts
+        };
+        let result = restored
+            .match_route("/api/users/42", &opts)
+            .unwrap()
+            .unwrap();
+        assert_eq!(result.id, "catch_all");
+        assert!(restored.match_route("/health", &opts).unwrap().is_some());
+
+        // Restored routers stay writable, with the restored id policy
+        assert!(restored
+            .add_route(RadixNode {
+                id: "health".to_string(),
+                paths: vec!["/healthz".to_string()],
+                ..Default::default()
+            })
+            .is_err());
+
+        // Filter closures cannot be captured, and garbage is rejected
+        router
+            .add_route(RadixNode {
+                id: "filtered".to_string(),
+                paths: vec!["/f".to_string()],
+                filter_fn: Some(Arc::new(|_, _| true)),
+                ..Default::default()
+            })
+            .unwrap();
+        assert!(router.to_snapshot().is_err());
+        assert!(RadixRouter::from_snapshot(b"not a snapshot").is_err());
+        assert!(RadixRouter::from_snapshot(&bytes[..bytes.len() / 2]).is_err());
+    }
+
+    #[test]
+    fn test_snapshot_file() {
+        let path = std::env::temp_dir().join(format!("radix-snapshot-{}.tbl", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let master = RadixRouter::new().unwrap();
+        let file = SnapshotFile::new(&path);
+        assert_eq!(file.generation().unwrap(), 0);
+
+        let worker = RadixRouter::new().unwrap();
+        let worker_view = SnapshotFile::new(&path);
+        assert!(!worker_view.sync(&worker).unwrap());
+
+        master.add_route(node("a", "/a")).unwrap();
+        assert_eq!(file.publish(&master).unwrap(), 1);
+        assert!(worker_view.sync(&worker).unwrap());
+        assert!(!worker_view.sync(&worker).unwrap());
+        let opts = RadixMatchOpts::default();
+        assert_eq!(worker.match_route("/a", &opts).unwrap().unwrap().id, "a");
+
+        master.replace_routes(vec![node("b", "/b")]).unwrap();
+        assert_eq!(file.publish(&master).unwrap(), 2);
+        assert!(worker_view.sync(&worker).unwrap());
+        assert!(worker.match_route("/a", &opts).unwrap().is_none());
+        assert_eq!(worker.match_route("/b", &opts).unwrap().unwrap().id, "b");
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_open_snapshot() {
+        let path = std::env::temp_dir().join(format!("radix-open-{}.snap", std::process::id()));
+        let router = RadixRouter::new().unwrap();
+        router
+            .add_route(RadixNode {
+                id: "user".to_string(),
+                paths: vec!["/users/:id".to_string()],
+                ..Default::default()
+            })
+            .unwrap();
+        router.write_snapshot(&path).unwrap();
+
+        let opened = RadixRouter::open_snapshot(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        let result = opened
+            .match_route("/users/7", &RadixMatchOpts::default())
+            .unwrap()
+            .unwrap();
+        assert_eq!(result.id, "user");
+        assert_eq!(result.matched.get("id").unwrap(), "7");
+
+        // Protections set at build time travel with the artifact
+        router
+            .get("/static/*path", serde_json::json!(null))
+            .unwrap();
+        router
+            .set_limits(Limits {
+                max_segments: Some(3),
+                ..Default::default()
+            })
+            .unwrap();
+        router
+            .set_traversal_policy(TraversalPolicy::Reject)
+            .unwrap();
+        router.set_path_decoding(PathDecoding::Decode).unwrap();
+        router
+            .set_table_limits(TableLimits {
+                max_routes: Some(2),
+                ..Default::default()
+            })
+            .unwrap();
+        router.write_snapshot(&path).unwrap();
+        let opened = RadixRouter::open_snapshot(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        let opts = RadixMatchOpts::default();
+        let result = opened.match_route("/users/a%20b", &opts).unwrap().unwrap();
+        assert_eq!(result.matched.get("id").unwrap(), "a b");
+        assert!(opened.match_route("/static/a/b", &opts).unwrap().is_some());
+        assert!(opened
+            .match_route("/static/a/b/c", &opts)
+            .unwrap()
+            .is_none());
+        assert!(opened.match_route("/static/../b", &opts).unwrap().is_none());
+        assert!(opened.get("/more", serde_json::json!(null)).is_err());
+
+        assert!(RadixRouter::open_snapshot(&path).is_err());
+        std::fs::write(&path, b"").unwrap();
+        assert!(RadixRouter::open_snapshot(&path).is_err());
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_match_route_ref() {
+        let router = RadixRouter::new().unwrap();
+        router
+            .add_routes(vec![
+                RadixNode {
+                    id: "file".to_string(),
+                    paths: vec!["/users/:id/files/*path".to_string()],
+                    metadata: serde_json::json!({"upstream": "read"}),
+                    method_metadata: Some(vec![(
+                        RadixHttpMethod::POST,
+                        serde_json::json!({"upstream": "write"}),
+                    )]),
+                    ..Default::default()
+                },
+                RadixNode {
+                    id: "health".to_string(),
+                    paths: vec!["/health".to_string()],
+                    ..Default::default()
+                },
+            ])
+            .unwrap();
+
+        let path = String::from("/users/42/files/a/b.txt");
+        let opts = RadixMatchOpts {
+            method: Some("POST".to_string()),
+            ..Default::default()
+        };
+        let result = router.match_route_ref(&path, &opts).unwrap().unwrap();
+        assert_eq!(result.id(), "file");
+        assert_eq!(result.pattern(), "/users/:id/files/*path");
+        assert_eq!(result.metadata()["upstream"], "write");
+        assert_eq!(result.param("id"), Some("42"));
+        assert_eq!(result.param("path"), Some("a/b.txt"));
+        assert_eq!(result.param("missing"), None);
+        assert_eq!(
+            result.params().collect::<Vec<_>>(),
+            [("id", "42"), ("path", "a/b.txt")]
+        );
+        // Values are slices of the request path
+        assert!(path
+            .as_bytes()
+            .as_ptr_range()
+            .contains(&result.param("id").unwrap().as_ptr()));
+
+        let result = router
+            .match_route_ref("/health", &RadixMatchOpts::default())
+            .unwrap()
+            .unwrap();
+        assert_eq!(result.id(), "health");
+        assert_eq!(result.params().count(), 0);
+        assert!(router
+            .match_route_ref("/nope", &RadixMatchOpts::default())
+            .unwrap()
+            .is_none());
+    }
+
+    #[test]
+    fn test_shared_metadata() {
+        let router = RadixRouter::new().unwrap();
+        router
+            .add_route(RadixNode {
+                id: "big".to_string(),
+                paths: vec!["/big".to_string()],
+                metadata: serde_json::json!({"upstreams": ["a", "b", "c"]}),
+                ..Default::default()
+            })
+            .unwrap();
+
+        let opts = RadixMatchOpts::default();
+        let first = router.match_route("/big", &opts).unwrap().unwrap();
+        let second = router.match_route("/big", &opts).unwrap().unwrap();
+        assert!(Arc::ptr_eq(&first.metadata, &second.metadata));
+        assert_eq!(first.metadata["upstreams"][1], "b");
+
+        router
+            .update_metadata("big", serde_json::json!({"upstreams": ["d"]}))
+            .unwrap();
+        let third = router.match_route("/big", &opts).unwrap().unwrap();
+        assert_eq!(third.metadata["upstreams"][0], "d");
+        // Results handed out earlier keep the old value
+        assert_eq!(first.metadata["upstreams"][0], "a");
+    }
+
+    #[test]
+    fn test_match_route_into() {
+        let router = RadixRouter::new().unwrap();
+        router
+            .add_routes(vec![
+                RadixNode {
+                    id: "user".to_string(),
+                    paths: vec!["/users/:id/*rest".to_string()],
+                    hosts: Some(vec!["*.example.com".to_string()]),
+                    ..Default::default()
+                },
+                RadixNode {
+                    id: "home".to_string(),
+                    paths: vec!["/".to_string()],
+                    hosts: Some(vec!["Example.com".to_string()]),
+                    ..Default::default()
+                },
+            ])
+            .unwrap();
+
+        let mut params = Params::new();
+        let opts = RadixMatchOpts {
+            method: Some("GET".to_string()),
+            host: Some("API.example.com".to_string()),
+            ..Default::default()
+        };
+        let route = router
+            .match_route_into("/users/7/a/b", &opts, &mut params)
+            .unwrap()
+            .unwrap();
+        assert_eq!(route.id(), "user");
+        assert_eq!(params.get("id"), Some("7"));
+        assert_eq!(params.get("rest"), Some("a/b"));
+
+        // Same entries as the owned result
+        let result = router.match_route("/users/7/a/b", &opts).unwrap().unwrap();
+        assert_eq!(params.len(), result.matched.len());
+        for (key, value) in params.iter() {
+            assert_eq!(result.matched.get(key), Some(value));
+        }
+
+        // Reuse clears the previous match
+        let opts = RadixMatchOpts {
+            host: Some("EXAMPLE.com".to_string()),
+            ..Default::default()
+        };
+        let route = router
+            .match_route_into("/", &opts, &mut params)
+            .unwrap()
+            .unwrap();
+        assert_eq!(route.id(), "home");
+        assert!(params.is_empty());
+
+        assert!(router
+            .match_route_into("/missing", &opts, &mut params)
+            .unwrap()
+            .is_none());
+        assert!(params.is_empty());
+    }
+
+    #[test]
+    fn test_matches() {
+        let router = RadixRouter::new().unwrap();
+        router
+            .add_routes(vec![
+                RadixNode {
+                    id: "user".to_string(),
+                    paths: vec!["/users/:id".to_string()],
+                    methods: Some(RadixHttpMethod::GET),
+                    ..Default::default()
+                },
+                RadixNode {
+                    id: "user_files".to_string(),
+                    paths: vec!["/users/:id/files".to_string()],
+                    ..Default::default()
+                },
+            ])
+            .unwrap();
+
+        let get = RadixMatchOpts {
+            method: Some("GET".to_string()),
+            ..Default::default()
+        };
+        let route = router.matches("/users/7", &get).unwrap().unwrap();
+        assert_eq!(route.id(), "user");
+        assert_eq!(route.pattern(), "/users/:id");
+        let route = router.matches("/users/7/files", &get).unwrap().unwrap();
+        assert_eq!(route.id(), "user_files");
+
+        // Same verdicts as a full match
+        let post = RadixMatchOpts {
+            method: Some("POST".to_string()),
+            ..Default::default()
+        };
+        assert!(router.matches("/users/7", &post).unwrap().is_none());
+        assert!(router.matches("/users/7/other", &get).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_params() {
+        let mut params = Params::new();
+        for i in 0..10 {
+            params.insert(&format!("k{}", i), &i.to_string());
+        }
+        params.insert("k3", "three");
+        assert_eq!(params.len(), 10);
+        assert_eq!(&params["k3"], "three");
+        assert_eq!(params.get("k9"), Some("9"));
+        assert_eq!(params.iter().next(), Some(("k0", "0")));
+
+        params.clear();
+        assert!(params.is_empty());
+        assert_eq!(params.get("k1"), None);
+        params.insert("b", "2");
+        params.insert("a", "1");
+
+        let mut other = Params::new();
+        other.insert("a", "1");
+        other.insert("b", "2");
+        assert_eq!(params, other);
+        assert_eq!(format!("{:?}", params), r#"{"b": "2", "a": "1"}"#);
+
+        let router = RadixRouter::new().unwrap();
+        router
+            .add_route(RadixNode {
+                id: "item".to_string(),
+                paths: vec!["/items/:id".to_string()],
+                ..Default::default()
+            })
+            .unwrap();
+        let result = router
+            .match_route("/items/5", &RadixMatchOpts::default())
+            .unwrap()
+            .unwrap();
+        assert_eq!(&result.matched["id"], "5");
+        let keys: Vec<&str> = result.matched.iter().map(|(k, _)| k).collect();
+        assert_eq!(keys, ["id"]);
+    }
+
+    #[test]
+    fn test_segment_patterns() {
+        let router = RadixRouter::new().unwrap();
+        router
+            .add_routes(vec![
+                node("member", "/orgs/:org/members/:user"),
+                node("slash", "/dirs/:name/"),
+                node("file", "/files/:bucket/*key"),
+                node("ext", "/static/:kind/*"),
+            ])
+            .unwrap();
+
+        let opts = RadixMatchOpts::default();
+        let params = |path: &str| {
+            router
+                .match_route(path, &opts)
+                .unwrap()
+                .map(|r| (r.id, r.matched))
+        };
+
+        let (id, matched) = params("/orgs/acme/members/ann").unwrap();
+        assert_eq!(id, "member");
+        assert_eq!((&matched["org"], &matched["user"]), ("acme", "ann"));
+        assert!(params("/orgs/acme/members/ann/x").is_none());
+        assert!(params("/orgs//members/ann").is_none());
+        assert!(params("/orgs/acme/people/ann").is_none());
+
+        assert_eq!(&params("/dirs/docs/").unwrap().1["name"], "docs");
+        assert!(params("/dirs/docs").is_none());
+
+        let (_, matched) = params("/files/b1/a/b/c.txt").unwrap();
+        assert_eq!((&matched["bucket"], &matched["key"]), ("b1", "a/b/c.txt"));
+        assert_eq!(&params("/files/b1/").unwrap().1["key"], "");
+        assert!(params("/files/b1").is_none());
+
+        let (_, matched) = params("/static/img/logo.png").unwrap();
+        assert_eq!((&matched["kind"], &matched[":ext"]), ("img", "logo.png"));
+    }
+
+    #[test]
+    fn test_sibling_param_batching() {
+        let router = RadixRouter::new().unwrap();
+        let route = |id: &str, path: &str, methods: RadixHttpMethod| RadixNode {
+            methods: Some(methods),
+            ..node(id, path)
+        };
+        // Dense siblings under one prefix, with patterns repeated per method
+        router
+            .add_routes(vec![
+                route("get-item", "/api/:kind/:id", RadixHttpMethod::GET),
+                route("put-item", "/api/:kind/:id", RadixHttpMethod::PUT),
+                route("get-sub", "/api/:kind/:id/:sub", RadixHttpMethod::GET),
+                route("files", "/api/:kind/files/*path", RadixHttpMethod::GET),
+                route("list", "/api/:kind", RadixHttpMethod::GET),
+            ])
+            .unwrap();
+
+        let matched = |path: &str, method: &str| {
+            let opts = RadixMatchOpts {
+                method: Some(method.to_string()),
+                ..Default::default()
+            };
+            router.match_route(path, &opts).unwrap().map(|r| r.id)
+        };
+        assert_eq!(matched("/api/users/7", "GET").as_deref(), Some("get-item"));
+        assert_eq!(matched("/api/users/7", "PUT").as_deref(), Some("put-item"));
+        assert_eq!(
+            matched("/api/users/7/posts", "GET").as_deref(),
+            Some("get-sub")
+        );
+        assert_eq!(
+            matched("/api/users/files/a/b", "GET").as_deref(),
+            Some("files")
+        );
+        assert_eq!(matched("/api/users", "GET").as_deref(), Some("list"));
+        assert!(matched("/api/users/7/posts/1", "GET").is_none());
+
+        // Routes whose pattern doesn't fit the path don't widen `allowed`
+        let opts = RadixMatchOpts {
+            method: Some("DELETE".to_string()),
+            ..Default::default()
+        };
+        match router.match_outcome("/api/users/7", &opts).unwrap() {
+            RouteOutcome::MethodNotAllowed { allowed } => {
+                assert_eq!(allowed, RadixHttpMethod::GET | RadixHttpMethod::PUT)
+            }
+            other => panic!("unexpected outcome: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_pattern_interning() {
+        let router = RadixRouter::new().unwrap();
+        let node = |id: &str, paths: &[&str], method: RadixHttpMethod| RadixNode {
+            id: id.to_string(),
+            paths: paths.iter().map(|p| p.to_string()).collect(),
+            methods: Some(method),
+            ..Default::default()
+        };
+        router
+            .add_routes(vec![
+                node(
+                    "get",
+                    &["/users/:id", "/v1/users/:id"],
+                    RadixHttpMethod::GET,
+                ),
+                node(
+                    "put",
+                    &["/users/:id", "/v1/users/:id"],
+                    RadixHttpMethod::PUT,
+                ),
+                node("del", &["/users/:id"], RadixHttpMethod::DELETE),
+                node("exact", &["/health"], RadixHttpMethod::GET),
+            ])
+            .unwrap();
+        assert!(format!("{:?}", router).contains("pattern_count: 2"));
+
+        router.delete_by_id("get").unwrap();
+        router.delete_by_id("put").unwrap();
+        assert!(format!("{:?}", router).contains("pattern_count: 1"));
+
+        let restored = RadixRouter::from_snapshot(&router.to_snapshot().unwrap()).unwrap();
+        assert!(format!("{:?}", restored).contains("pattern_count: 1"));
+
+        let opts = RadixMatchOpts {
+            method: Some("DELETE".to_string()),
+            ..Default::default()
+        };
+        let result = restored.match_route("/users/9", &opts).unwrap().unwrap();
+        assert_eq!((result.id.as_str(), &result.matched["id"]), ("del", "9"));
+    }
+
+    #[test]
+    fn test_iterator_reuse() {
+        let opts = RadixMatchOpts::default();
+        let long = format!("/deep/{}/*", "segment/".repeat(40));
+
+        // Pooled iterators move between trees, including dropped ones
+        for round in 0..3 {
+            let first = RadixRouter::new().unwrap();
+            first.add_route(node("a", "/a/*")).unwrap();
+            first.add_route(node("deep", &long)).unwrap();
+            let second = RadixRouter::new().unwrap();
+            second.add_route(node("b", "/b/:id")).unwrap();
+
+            for _ in 0..10 {
+                let deep = format!("{}x", long.trim_end_matches('*'));
+                let result = first.match_route(&deep, &opts).unwrap().unwrap();
+                assert_eq!(result.id, "deep", "round {}", round);
+                let result = second.match_route("/b/7", &opts).unwrap().unwrap();
+                assert_eq!(result.id, "b");
+                assert!(second.match_route("/a/x", &opts).unwrap().is_none());
+            }
+            drop(first);
+            assert!(second.match_route("/b/8", &opts).unwrap().is_some());
+        }
+    }
+
+    #[test]
+    fn test_reads_during_publish() {
+        use std::sync::{Mutex, OnceLock, Weak};
+
+        // Runs while the writer that added the route still holds the lock
+        #[derive(Default)]
+        struct Probe {
+            router: OnceLock<Weak<RadixRouter>>,
+            seen: Mutex<Vec<Option<String>>>,
+        }
+
+        impl RouteObserver for Probe {
+            fn on_route_added(&self, route: &RouteInfo) {
+                let router = self.router.get().unwrap().upgrade().unwrap();
+                let path = route.paths[0].clone();
+                let found = std::thread::spawn(move || {
+                    router
+                        .match_route(&path, &RadixMatchOpts::default())
+                        .unwrap()
+                        .map(|r| r.id)
+                })
+                .join()
+                .unwrap();
+                self.seen.lock().unwrap().push(found);
+            }
+        }
+
+        let router = Arc::new(RadixRouter::new().unwrap());
+        let probe = Arc::new(Probe::default());
+        probe.router.set(Arc::downgrade(&router)).ok().unwrap();
+        router.add_observer(probe.clone());
+
+        router
+            .add_route(RadixNode {
+                id: "live".to_string(),
+                paths: vec!["/live".to_string()],
+                ..Default::default()
+            })
+            .unwrap();
+        assert_eq!(*probe.seen.lock().unwrap(), vec![Some("live".to_string())]);
+    }
+
+    #[test]
+    fn test_fast_miss_prefilter() {
+        let router = RadixRouter::new().unwrap();
+        router
+            .add_routes(vec![
+                node("health", "/health"),
+                node("user", "/api/users/:id"),
+                node("files", "/static/*"),
+                node("root", "/"),
+            ])
+            .unwrap();
+
+        let opts = RadixMatchOpts::default();
+        let matched = |path: &str| router.match_route(path, &opts).unwrap().map(|r| r.id);
+        assert_eq!(matched("/health").as_deref(), Some("health"));
+        assert_eq!(matched("/api/users/1").as_deref(), Some("user"));
+        assert_eq!(matched("/static/a.css").as_deref(), Some("files"));
+        assert_eq!(matched("/").as_deref(), Some("root"));
+        assert!(matched("/wp-admin/install.php").is_none());
+        assert!(matched("/healthz").is_none());
+        assert!(matched("/api").is_none());
+        assert!(matched("health").is_none());
+
+        // A catch-all disables the filter
+        router.add_route(node("any", "/*")).unwrap();
+        assert_eq!(matched("/wp-admin/install.php").as_deref(), Some("any"));
+        router.delete_by_id("any").unwrap();
+        assert!(matched("/wp-admin/install.php").is_none());
+    }
+
+    #[test]
+    fn test_with_routes() {
+        let node = |i: usize| RadixNode {
+            id: format!("r{}", i),
+            paths: vec![format!("/svc{}/items/:id", i), format!("/svc{}/health", i)],
+            hosts: Some(vec![format!("*.svc{}.example.com", i)]),
+            ..Default::default()
+        };
+        let router = RadixRouter::with_routes((0..2000).map(node).collect()).unwrap();
+        assert_eq!(router.len(), 2000);
+
+        let opts = RadixMatchOpts {
+            host: Some("api.svc1234.example.com".to_string()),
+            ..Default::default()
+        };
+        let result = router
+            .match_route("/svc1234/items/9", &opts)
+            .unwrap()
+            .unwrap();
+        assert_eq!((result.id.as_str(), &result.matched["id"]), ("r1234", "9"));
+
+        // One bad route fails the whole batch
+        let mut routes: Vec<_> = (0..100).map(node).collect();
+        routes[57].paths.push("/bad/:".to_string());
+        let err = RadixRouter::with_routes(routes).unwrap_err();
+        assert!(format!("{:#}", err).contains("r57"), "{:#}", err);
+
+        // `Default` is the empty router `new()` builds
+        let router = RadixRouter::default();
+        assert!(router.is_empty());
+        assert_eq!(router.policy(), MatchPolicy::default());
+    }
+
+    #[test]
+    fn test_route_config() {
+        let configs = RouteConfig::list_from_json(
+            r#"[
+                {
+                    "id": "user",
+                    "paths": ["/api/users/:id"],
+                    "methods": ["GET", "report"],
+                    "hosts": ["*.example.com"],
+                    "vars": [["env", "==", "prod"], ["region", "in", ["us", "eu"]], ["tier", "~~", "^v"]],
+                    "priority": 10,
+                    "metadata": {"upstream": "users"}
+                },
+                {"id": "fallback", "paths": ["/*"]}
+            ]"#,
+        )
+        .unwrap();
+        let routes: Vec<RadixNode> = configs.into_iter().map(|c| c.try_into().unwrap()).collect();
+        assert_eq!(routes[0].methods, Some(RadixHttpMethod::GET));
+        assert_eq!(routes[0].custom_methods, Some(vec!["report".to_string()]));
+        assert!(routes[1].methods.is_none() && routes[1].vars.is_none());
+
+        let router = RadixRouter::with_routes(routes).unwrap();
+        let mut opts = RadixMatchOpts {
+            method: Some("GET".to_string()),
+            host: Some("api.example.com".to_string()),
+            vars: Some(HashMap::from([
+                ("env".to_string(), "prod".to_string()),
+                ("region".to_string(), "eu".to_string()),
+                ("tier".to_string(), "vip".to_string()),
+            ])),
+            ..Default::default()
+        };
+        let result = router.match_route("/api/users/7", &opts).unwrap().unwrap();
+        assert_eq!((result.id.as_str(), &result.matched["id"]), ("user", "7"));
+        assert_eq!(*result.metadata, serde_json::json!({"upstream": "users"}));
+        opts.vars
+            .as_mut()
+            .unwrap()
+            .insert("region".to_string(), "ap".to_string());
+        let result = router.match_route("/api/users/7", &opts).unwrap().unwrap();
+        assert_eq!(result.id, "fallback");
+
+        let err = RouteConfig::list_from_json(r#"[{"id": "x", "path": "/typo"}]"#).unwrap_err();
+        assert!(format!("{:#}", err).contains("unknown field `path`"));
+        let config = RouteConfig {
+            id: "bad".to_string(),
+            vars: vec![VarConfig("a".into(), "=~".into(), "b".into())],
+            ..Default::default()
+        };
+        assert!(config.into_node().is_err());
+    }
+
+    #[cfg(feature = "capi")]
+    #[test]
+    fn test_capi() {
+        use crate::capi::*;
+        use std::ffi::{CStr, CString};
+
+        let text = |s: &str| CString::new(s).unwrap();
+        unsafe {
+            let router = radix_router_new();
+            let routes = text(
+                r#"[{"id": "user", "paths": ["/users/:id"], "methods": ["GET"], "metadata": {"n": 1}}]"#,
+            );
+            assert_eq!(radix_router_add_route_json(router, routes.as_ptr()), 0);
+            assert_eq!(radix_router_add_route_json(router, text("{").as_ptr()), -1);
+            let err = CStr::from_ptr(radix_router_last_error()).to_str().unwrap();
+            assert!(err.contains("Invalid route JSON"), "{}", err);
+
+            let path = text("/users/7");
+            let opts = text(r#"{"method": "GET"}"#);
+            let found = radix_router_match(router, path.as_ptr(), opts.as_ptr());
+            assert!(!found.is_null());
+            let json: serde_json::Value =
+                serde_json::from_str(CStr::from_ptr(found).to_str().unwrap()).unwrap();
+            radix_router_string_free(found);
+            assert_eq!(
+                json,
+                serde_json::json!({
+                    "id": "user",
+                    "priority": 0,
+                    "metadata": {"n": 1},
+                    "matched": {"id": "7"},
+                    "info": {
+                        "pattern": "/users/:id",
+                        "host": null,
+                        "method": "GET",
+                        "mount": null,
+                    },
+                    "upstream_path": null,
+                    "remaining_path": null,
+                    "version": 1,
+                    "shadows": [],
+                })
+            );
+
+            let opts = text(r#"{"method": "POST"}"#);
+            assert!(radix_router_match(router, path.as_ptr(), opts.as_ptr()).is_null());
+            assert!(radix_router_last_error().is_null());
+            assert!(radix_router_match(router, std::ptr::null(), opts.as_ptr()).is_null());
+            assert!(!radix_router_last_error().is_null());
+
+            assert_eq!(radix_router_delete_route(router, text("user").as_ptr()), 0);
+            assert!(radix_router_match(router, path.as_ptr(), std::ptr::null()).is_null());
+            radix_router_free(router);
+        }
+    }
+
+    #[cfg(feature = "lua")]
+    #[test]
+    fn test_lua_bindings() {
+        let lua = mlua::Lua::new();
+        crate::lua::preload(&lua).unwrap();
+        lua.load(
+            r#"
+            local radix = require("resty.radixtree")
+            local hits = {}
+            local rx = assert(radix.new({
+                { paths = { "/users/:id" }, methods = { "GET" }, metadata = { name = "user" } },
+                { paths = "/beta/*", vars = { { "arg_beta", "==", "1" } }, metadata = "beta" },
+                { paths = "/health", handler = function(tag) hits[#hits + 1] = tag end },
+            }))
+
+            local matched = {}
+            local meta = rx:match("/users/7", { method = "GET", matched = matched })
+            assert(meta.name == "user")
+            assert(matched.id == "7" and matched._path == "/users/:id")
+            assert(rx:match("/users/7", { method = "POST" }) == nil)
+
+            -- vars are read by name, so proxy tables like ngx.var work
+            local vars = setmetatable({}, { __index = function(_, k)
+                if k == "arg_beta" then return "1" end
+            end })
+            assert(rx:match("/beta/x", { vars = vars }) == "beta")
+            assert(rx:match("/beta/x", { vars = {} }) == nil)
+
+            assert(rx:dispatch("/health", {}, "ping") == true)
+            assert(hits[1] == "ping")
+            assert(rx:dispatch("/missing", {}) == false)
+
+            local bad, err = radix.new({ { paths = "/x", methods = 5 } })
+            assert(bad == nil and err:find("expected a string or a table"))
+            "#,
+        )
+        .exec()
+        .unwrap();
+    }
+
+    #[test]
+    fn test_conflicts() {
+        let route = |id: &str, path: &str, priority: i32| RadixNode {
+            priority,
+            ..node(id, path)
+        };
+        let router = RadixRouter::new().unwrap();
+        router
+            .add_routes(vec![
+                route("any", "/users/:id", 10),
+                RadixNode {
+                    methods: Some(RadixHttpMethod::GET),
+                    ..route("get", "/users/:name", 0)
+                },
+                RadixNode {
+                    vars: Some(vec![Expr::Eq("env".to_string(), "prod".to_string())]),
+                    ..route("prod", "/orders/:id", 10)
+                },
+                route("orders", "/orders/:id", 0),
+                route("posts", "/users/:id/posts", 0),
+            ])
+            .unwrap();
+
+        // "prod" is conditional and "posts" has another shape, so only
+        // "get" can never win
+        assert_eq!(
+            router.conflicts(),
+            vec![Conflict {
+                id: "get".to_string(),
+                pattern: "/users/:name".to_string(),
+                shadowed_by: "any".to_string(),
+            }]
+        );
+        assert_eq!(
+            router.conflicts()[0].to_string(),
+            "get (/users/:name) is shadowed by any"
+        );
+    }
+
+    #[cfg(feature = "watch")]
+    #[test]
+    fn test_watch_file() {
+        use std::time::{Duration, Instant};
+
+        let dir = std::env::temp_dir().join(format!("radix-watch-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let file = dir.join("routes.json");
+        let routes = |path: &str| format!(r#"[{{"id": "r", "paths": ["{}"]}}]"#, path);
+        std::fs::write(&file, routes("/v1")).unwrap();
+
+        let router = Arc::new(RadixRouter::new().unwrap());
+        let (tx, rx) = std::sync::mpsc::channel();
+        let watcher = router
+            .watch_file(&file, move |err| tx.send(format!("{:#}", err)).unwrap())
+            .unwrap();
+        let opts = RadixMatchOpts::default();
+        assert!(router.match_route("/v1", &opts).unwrap().is_some());
+
+        let wait_for = |path: &str| {
+            let deadline = Instant::now() + Duration::from_secs(5);
+            while router.match_route(path, &opts).unwrap().is_none() {
+                assert!(Instant::now() < deadline, "reload of {} not seen", path);
+                std::thread::sleep(Duration::from_millis(10));
+            }
+        };
+        std::fs::write(&file, routes("/v2")).unwrap();
+        wait_for("/v2");
+        assert!(router.match_route("/v1", &opts).unwrap().is_none());
+
+        // A broken file is reported and the last good routes stay
+        std::fs::write(&file, "[{").unwrap();
+        let err = rx.recv_timeout(Duration::from_secs(5)).unwrap();
+        assert!(err.contains("routes.json"), "{}", err);
+        assert!(router.match_route("/v2", &opts).unwrap().is_some());
+
+        // Replacing the file by rename is picked up too
+        let tmp = dir.join("routes.json.tmp");
+        std::fs::write(&tmp, routes("/v3")).unwrap();
+        std::fs::rename(&tmp, &file).unwrap();
+        wait_for("/v3");
+
+        drop(watcher);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[cfg(feature = "k8s")]
+    #[test]
+    fn test_k8s_adapters() {
+        use crate::k8s::{HttpRoute, Ingress};
+
+        let ingress: Ingress = serde_json::from_value(serde_json::json!({
+            "metadata": {"name": "web", "namespace": "shop"},
+            "spec": {
+                "defaultBackend": {"service": {"name": "fallback", "port": {"number": 80}}},
+                "rules": [{
+                    "host": "*.example.com",
+                    "http": {"paths": [
+                        {"path": "/api", "pathType": "Prefix",
+                         "backend": {"service": {"name": "api", "port": {"number": 8080}}}},
+                        {"path": "/login", "pathType": "Exact",
+                         "backend": {"service": {"name": "auth", "port": {"name": "http"}}}}
+                    ]}
+                }]
+            }
+        }))
+        .unwrap();
+        let http_route: HttpRoute = serde_json::from_value(serde_json::json!({
+            "metadata": {"name": "users"},
+            "spec": {
+                "hostnames": ["users.example.org"],
+                "rules": [
+                    {"matches": [{"path": {"type": "PathPrefix", "value": "/users/"}}],
+                     "backendRefs": [{"name": "users-v1", "port": 80, "weight": 90},
+                                     {"name": "users-v2", "port": 80, "weight": 10}]},
+                    {"matches": [{"path": {"value": "/users"}, "method": "POST",
+                                  "headers": [{"name": "X-Canary", "value": "on"}]}],
+                     "backendRefs": [{"name": "users-canary"}]}
+                ]
+            }
+        }))
+        .unwrap();
+
+        let mut routes = ingress.to_nodes().unwrap();
+        routes.extend(http_route.to_nodes().unwrap());
+        let router = RadixRouter::with_routes(routes).unwrap();
+        let matched = |path: &str, host: &str, method: &str, vars: &[(&str, &str)]| {
+            let opts = RadixMatchOpts {
+                host: Some(host.to_string()),
+                method: Some(method.to_string()),
+                vars: Some(
+                    vars.iter()
+                        .map(|(k, v)| (k.to_string(), v.to_string()))
+                        .collect(),
+                ),
+                ..Default::default()
+            };
+            router.match_route(path, &opts).unwrap().map(|r| r.id)
+        };
+
+        let shop = "www.example.com";
+        assert_eq!(matched("/api", shop, "GET", &[]).unwrap(), "shop/web#0.0");
+        assert_eq!(
+            matched("/api/v1/x", shop, "GET", &[]).unwrap(),
+            "shop/web#0.0"
+        );
+        assert_eq!(
+            matched("/apix", shop, "GET", &[]).unwrap(),
+            "shop/web#default"
+        );
+        assert_eq!(matched("/login", shop, "GET", &[]).unwrap(), "shop/web#0.1");
+        assert_eq!(
+            matched("/login/x", shop, "GET", &[]).unwrap(),
+            "shop/web#default"
+        );
+        let metadata = router.get_route("shop/web#0.1").unwrap().metadata;
+        assert_eq!(metadata["service"], "auth");
+        assert_eq!(metadata["port_name"], "http");
+
+        let users = "users.example.org";
+        assert_eq!(
+            matched("/users/7", users, "GET", &[]).unwrap(),
+            "default/users#0.0"
+        );
+        assert_eq!(
+            matched("/users/7", shop, "GET", &[]).unwrap(),
+            "shop/web#default"
+        );
+        let canary = [("http_x_canary", "on")];
+        assert_eq!(
+            matched("/users", users, "POST", &canary).unwrap(),
+            "default/users#1.0"
+        );
+        assert_eq!(
+            matched("/users", users, "POST", &[]).unwrap(),
+            "default/users#0.0"
+        );
+        let metadata = router.get_route("default/users#0.0").unwrap().metadata;
+        assert_eq!(metadata["backends"][1]["weight"], 10);
+
+        let regex_path: HttpRoute = serde_json::from_value(serde_json::json!({
+            "metadata": {"name": "re"},
+            "spec": {"rules": [{"matches": [{"path": {"type": "RegularExpression", "value": "/x.*"}}]}]}
+        }))
+        .unwrap();
+        let err = regex_path.to_nodes().unwrap_err();
+        assert!(format!("{:#}", err).contains("Unsupported path type"));
+    }
+
+    #[test]
+    fn test_route_source() {
+        use std::sync::mpsc;
+
+        /// Source fed by hand, standing in for a config service
+        struct ChannelSource {
+            initial: Vec<RadixNode>,
+            updates: Option<mpsc::Receiver<Result<Vec<RadixNode>>>>,
+        }
+
+        impl RouteSource for ChannelSource {
+            fn initial_load(&mut self) -> Result<Vec<RadixNode>> {
+                Ok(self.initial.clone())
+            }
+
+            fn subscribe(&mut self, sink: RouteSink) -> Result<Subscription> {
+                let updates = self.updates.take().unwrap();
+                let worker = std::thread::spawn(move || {
+                    for routes in updates {
+                        if !sink.push(routes) {
+                            break;
+                        }
+                    }
+                });
+                Ok(Subscription::new(worker))
+            }
+        }
+
+        let (tx, updates) = mpsc::channel();
+        let (err_tx, errors) = mpsc::channel();
+        let router = Arc::new(RadixRouter::new().unwrap());
+        let _subscription = router
+            .sync_from(
+                ChannelSource {
+                    initial: vec![node("a", "/a"), node("b", "/b")],
+                    updates: Some(updates),
+                },
+                move |err| err_tx.send(err.to_string()).unwrap(),
+            )
+            .unwrap();
+        assert_eq!(router.len(), 2);
+
+        // Updates are reconciled as full sets, and failures keep the routes
+        tx.send(Err(anyhow::anyhow!("backend unreachable")))
+            .unwrap();
+        assert_eq!(errors.recv().unwrap(), "backend unreachable");
+        tx.send(Ok(vec![node("a", "/a"), node("a", "/a2")]))
+            .unwrap();
+        assert!(errors.recv().unwrap().contains("Duplicate route id"));
+        assert_eq!(router.len(), 2);
+
+        tx.send(Ok(vec![node("b", "/b"), node("c", "/c")])).unwrap();
+        drop(tx);
+        let deadline = std::time::Instant::now() + std::time::Duration::from_secs(5);
+        while router.get_route("c").is_none() {
+            assert!(std::time::Instant::now() < deadline);
+            std::thread::yield_now();
+        }
+        let ids: Vec<String> = router.routes().map(|r| r.id).collect();
+        assert_eq!(ids, ["b", "c"]);
+    }
+
+    #[test]
+    fn test_fallible_filters() {
+        let backend_up = Arc::new(std::sync::atomic::AtomicBool::new(true));
+        let up = backend_up.clone();
+        let policy_check: TryFilterFn = Arc::new(move |vars, _| {
+            if !up.load(std::sync::atomic::Ordering::Relaxed) {
+                anyhow::bail!("policy backend unreachable");
+            }
+            Ok(vars.get("user").is_some_and(|u| u == "alice"))
+        });
+        let route = |id: &str, priority: i32, filter_errors: FilterErrorPolicy| RadixNode {
+            priority,
+            try_filter_fn: Some(policy_check.clone()),
+            filter_errors,
+            ..node(id, "/admin")
+        };
+        let fallback = RadixNode {
+            id: "fallback".to_string(),
+            paths: vec!["/admin".to_string()],
+            ..Default::default()
+        };
+        let opts = |user: &str| RadixMatchOpts {
+            vars: Some(HashMap::from([("user".to_string(), user.to_string())])),
+            ..Default::default()
+        };
+        let matched = |router: &RadixRouter, user: &str| {
+            router
+                .match_route("/admin", &opts(user))
+                .map(|r| r.map(|r| r.id))
+        };
+
+        let router = RadixRouter::new().unwrap();
+        router
+            .add_routes(vec![
+                route("guarded", 10, FilterErrorPolicy::Propagate),
+                fallback.clone(),
+            ])
+            .unwrap();
+        assert_eq!(matched(&router, "alice").unwrap().unwrap(), "guarded");
+        assert_eq!(matched(&router, "bob").unwrap().unwrap(), "fallback");
+
+        // A failure is an error, not a miss or a fallthrough
+        backend_up.store(false, std::sync::atomic::Ordering::Relaxed);
+        let err = matched(&router, "alice").unwrap_err();
+        assert_eq!(
+            format!("{:#}", err),
+            "Filter of route guarded failed: policy backend unreachable"
+        );
+        let explanation = router.explain("/admin", &opts("alice")).unwrap();
+        assert_eq!(
+            explanation.candidates[0].rejection,
+            Some(Rejection::FilterError)
+        );
+
+        let fail_closed = RadixRouter::new().unwrap();
+        fail_closed
+            .add_routes(vec![
+                route("guarded", 10, FilterErrorPolicy::FailClosed),
+                fallback.clone(),
+            ])
+            .unwrap();
+        assert_eq!(matched(&fail_closed, "alice").unwrap().unwrap(), "fallback");
+
+        let fail_open = RadixRouter::new().unwrap();
+        fail_open
+            .add_routes(vec![
+                route("guarded", 10, FilterErrorPolicy::FailOpen),
+                fallback,
+            ])
+            .unwrap();
+        assert_eq!(matched(&fail_open, "bob").unwrap().unwrap(), "guarded");
+    }
+
+    #[test]
+    fn test_context_filters() {
+        struct Request {
+            roles: Vec<&'static str>,
+        }
+        let router = RadixRouter::new().unwrap();
+        router
+            .add_routes(vec![
+                RadixNode {
+                    id: "admin".to_string(),
+                    paths: vec!["/admin".to_string()],
+                    priority: 10,
+                    context_filter: Some(ContextFilter::new(|req: &Request, _| {
+                        req.roles.contains(&"admin")
+                    })),
+                    ..Default::default()
+                },
+                RadixNode {
+                    id: "public".to_string(),
+                    paths: vec!["/admin".to_string()],
+                    ..Default::default()
+                },
+            ])
+            .unwrap();
+        let opts = RadixMatchOpts::default();
+        let matched = |req: &Request| {
+            router
+                .match_route_with("/admin", &opts, req)
+                .unwrap()
+                .unwrap()
+                .id
+        };
+
+        assert_eq!(
+            matched(&Request {
+                roles: vec!["admin"]
+            }),
+            "admin"
+        );
+        assert_eq!(matched(&Request { roles: vec!["dev"] }), "public");
+        // Without a context, or with one of another type, the filter rejects
+        let result = router.match_route("/admin", &opts).unwrap().unwrap();
+        assert_eq!(result.id, "public");
+        let result = router.match_route_with("/admin", &opts, &"admin").unwrap();
+        assert_eq!(result.unwrap().id, "public");
+    }
+
+    #[test]
+    fn test_named_filters() {
+        let calls = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let filter = |name: &'static str, var: &'static str| -> FilterFn {
+            let calls = calls.clone();
+            Arc::new(move |vars, _| {
+                calls.lock().unwrap().push(name);
+                vars.contains_key(var)
+            })
+        };
+        let router = RadixRouter::new().unwrap();
+        router
+            .register_filter("auth", filter("auth", "user"))
+            .unwrap();
+        router
+            .register_filter("tenant", filter("tenant", "tenant"))
+            .unwrap();
+        assert!(router
+            .register_filter("auth", filter("auth", "user"))
+            .is_err());
+
+        let route = |filters: &[&str]| RadixNode {
+            id: "orders".to_string(),
+            paths: vec!["/orders".to_string()],
+            filters: filters.iter().map(|f| f.to_string()).collect(),
+            ..Default::default()
+        };
+        let err = router.add_route(route(&["auth", "billing"])).unwrap_err();
+        assert!(format!("{:#}", err).contains("Unknown filter for route orders: billing"));
+        router.add_route(route(&["auth", "tenant"])).unwrap();
+
+        let opts = |vars: &[&str]| RadixMatchOpts {
+            vars: Some(
+                vars.iter()
+                    .map(|v| (v.to_string(), "x".to_string()))
+                    .collect(),
+            ),
+            ..Default::default()
+        };
+        let matched = |vars: &[&str]| router.match_route("/orders", &opts(vars)).unwrap();
+
+        assert!(matched(&["user", "tenant"]).is_some());
+        assert_eq!(*calls.lock().unwrap(), ["auth", "tenant"]);
+        // The first rejecting filter ends the chain
+        calls.lock().unwrap().clear();
+        assert!(matched(&["tenant"]).is_none());
+        assert_eq!(*calls.lock().unwrap(), ["auth"]);
+
+        // Config files reference filters by name
+        let config: RouteConfig =
+            serde_json::from_str(r#"{"id": "cfg", "paths": ["/cfg"], "filters": ["auth"]}"#)
+                .unwrap();
+        router.add_route(config.into_node().unwrap()).unwrap();
+        assert!(router
+            .match_route("/cfg", &opts(&["user"]))
+            .unwrap()
+            .is_some());
+        assert!(router.match_route("/cfg", &opts(&[])).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_metadata_hook() {
+        let router = RadixRouter::new().unwrap();
+        router
+            .add_routes(vec![
+                RadixNode {
+                    id: "tenant".to_string(),
+                    paths: vec!["/t/:tenant/*".to_string()],
+                    metadata: serde_json::json!({
+                        "upstream": "http://{tenant}.internal:8080",
+                        "headers": ["x-tenant: {tenant}", "x-key: {tenant}/{tenant}", "{missing}"],
+                        "retries": 3,
+                    }),
+                    ..Default::default()
+                },
+                RadixNode {
+                    id: "mirror".to_string(),
+                    paths: vec!["/t/:tenant/*".to_string()],
+                    shadow: true,
+                    metadata: serde_json::json!("http://{tenant}.mirror"),
+                    ..Default::default()
+                },
+            ])
+            .unwrap();
+        let opts = RadixMatchOpts {
+            host: Some("api.example.com".to_string()),
+            ..Default::default()
+        };
+
+        router.set_metadata_hook(Some(param_template())).unwrap();
+        let result = router
+            .match_route("/t/acme/orders", &opts)
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            *result.metadata,
+            serde_json::json!({
+                "upstream": "http://acme.internal:8080",
+                "headers": ["x-tenant: acme", "x-key: acme/acme", "{missing}"],
+                "retries": 3,
+            })
+        );
+        assert_eq!(*result.shadows[0].metadata, "http://acme.mirror");
+        // Lookups that share the stored metadata leave it untouched
+        let shared = router.matches("/t/acme/orders", &opts).unwrap().unwrap();
+        assert_eq!(
+            shared.metadata()["upstream"],
+            "http://{tenant}.internal:8080"
+        );
+
+        let hook: MetadataHook = Arc::new(|result, opts| {
+            Some(serde_json::json!({"route": result.id, "method": opts.method}))
+        });
+        router.set_metadata_hook(Some(hook)).unwrap();
+        let result = router
+            .match_route("/t/acme/orders", &opts)
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            *result.metadata,
+            serde_json::json!({"route": "tenant", "method": null})
+        );
+
+        router.set_metadata_hook(None).unwrap();
+        let result = router
+            .match_route("/t/acme/orders", &opts)
+            .unwrap()
+            .unwrap();
+        assert_eq!(result.metadata["upstream"], "http://{tenant}.internal:8080");
+    }
+
+    #[test]
+    fn test_structured_match_result() {
+        let router = RadixRouter::new().unwrap();
+        router
+            .add_route(RadixNode {
+                id: "user".to_string(),
+                paths: vec!["/users/:id/*rest".to_string()],
+                hosts: Some(vec!["*.example.com".to_string()]),
+                priority: 7,
+                ..Default::default()
+            })
+            .unwrap();
+        let opts = RadixMatchOpts {
+            method: Some("GET".to_string()),
+            host: Some("API.example.com".to_string()),
+            ..Default::default()
+        };
+
+        let result = router
+            .match_route("/users/42/posts/1", &opts)
+            .unwrap()
+            .unwrap();
+        assert_eq!(result.id, "user");
+        assert_eq!(result.priority, 7);
+        // Params and request attributes are kept apart
+        let params: Vec<_> = result.matched.iter().collect();
+        assert_eq!(params, [("id", "42"), ("rest", "posts/1")]);
+        assert_eq!(
+            result.info,
+            MatchInfo {
+                pattern: "/users/:id/*rest".to_string(),
+                host: Some("*.example.com".to_string()),
+                method: Some("GET".to_string()),
+                mount: None,
+            }
+        );
+
+        let json = result.to_json();
+        assert_eq!(json["info"]["pattern"], "/users/:id/*rest");
+        assert_eq!(
+            json["matched"],
+            serde_json::json!({"id": "42", "rest": "posts/1"})
+        );
+
+        router
+            .add_route(RadixNode {
+                id: "health".to_string(),
+                paths: vec!["/health".to_string()],
+                ..Default::default()
+            })
+            .unwrap();
+        let result = router.match_route("/health", &opts).unwrap().unwrap();
+        assert_eq!(result.info.host, None);
+        assert!(result.matched.is_empty());
+    }
+
+    #[test]
+    fn test_case_insensitive_vars() {
+        let router = RadixRouter::new().unwrap();
+        let route = |id: &str, path: &str| RadixNode {
+            vars: Some(vec![Expr::Eq("User-Agent".to_string(), "curl".to_string())]),
+            ..node(id, path)
+        };
+        router.add_route(route("before", "/before")).unwrap();
+        let opts = |name: &str| RadixMatchOpts {
+            vars: Some(HashMap::from([(name.to_string(), "curl".to_string())])),
+            ..Default::default()
+        };
+        let matched =
+            |path: &str, name: &str| router.match_route(path, &opts(name)).unwrap().is_some();
+
+        // Exact by default
+        assert!(matched("/before", "User-Agent"));
+        assert!(!matched("/before", "user-agent"));
+
+        // Routes registered before and after the switch both match any case
+        router.set_var_case(VarCase::Insensitive).unwrap();
+        router.add_route(route("after", "/after")).unwrap();
+        for path in ["/before", "/after"] {
+            assert!(matched(path, "user-agent"));
+            assert!(matched(path, "USER-AGENT"));
+            assert!(!matched(path, "user_agent"));
+        }
+
+        // Snapshots keep the setting
+        let restored = RadixRouter::from_snapshot(&router.to_snapshot().unwrap()).unwrap();
+        assert!(restored
+            .match_route("/after", &opts("User-agent"))
+            .unwrap()
+            .is_some());
+    }
+
+    #[test]
+    fn test_request_vars() {
+        let router = RadixRouter::new().unwrap();
+        let eq = |name: &str, value: &str| Expr::Eq(name.to_string(), value.to_string());
+        let route = |id: &str, vars: Vec<Expr>| RadixNode {
+            vars: Some(vars),
+            ..node(id, &format!("/{}", id))
+        };
+        router
+            .add_routes(vec![
+                route(
+                    "method",
+                    vec![eq("request_method", "POST"), eq("host", "a.com")],
+                ),
+                route("header", vec![eq("http_x_user_id", "7")]),
+                route("arg", vec![eq("arg_page", "2")]),
+                route("cookie", vec![eq("cookie_session", "abc")]),
+            ])
+            .unwrap();
+        let map = |pairs: &[(&str, &str)]| {
+            Some(
+                pairs
+                    .iter()
+                    .map(|(k, v)| (k.to_string(), v.to_string()))
+                    .collect::<HashMap<_, _>>(),
+            )
+        };
+        let opts = RadixMatchOpts {
+            method: Some("POST".to_string()),
+            host: Some("A.com".to_string()),
+            headers: map(&[("X-User-Id", "7"), ("Cookie", "theme=dark; session=abc")]),
+            args: map(&[("page", "2")]),
+            ..Default::default()
+        };
+        let matched =
+            |path: &str, opts: &RadixMatchOpts| router.match_route(path, opts).unwrap().is_some();
+        for path in ["/method", "/header", "/arg", "/cookie"] {
+            assert!(matched(path, &opts), "{}", path);
+        }
+
+        // Explicit cookies replace the header, explicit vars win
+        let explicit = RadixMatchOpts {
+            cookies: map(&[("session", "xyz")]),
+            vars: map(&[("arg_page", "3")]),
+            ..opts.clone()
+        };
+        assert!(!matched("/cookie", &explicit));
+        assert!(!matched("/arg", &explicit));
+        assert!(matched("/header", &explicit));
+    }
+
+    #[test]
+    fn test_expr_serde() {
+        let exprs = vec![
+            Expr::Eq("env".to_string(), "prod".to_string()),
+            Expr::Neq("env".to_string(), "dev".to_string()),
+            Expr::Gt("age".to_string(), "18".to_string()),
+            Expr::Lt("age".to_string(), "65".to_string()),
+            Expr::In(
+                "region".to_string(),
+                vec!["us".to_string(), "eu".to_string()],
+            ),
+            Expr::Regex("ua".to_string(), regex::Regex::new("^curl/").unwrap()),
+            Expr::Bucket("user".to_string(), 10..20),
+        ];
+
+        // JSON uses the config triples
+        let json = serde_json::to_value(&exprs).unwrap();
+        assert_eq!(json[1], serde_json::json!(["env", "~=", "dev"]));
+        assert_eq!(json[6], serde_json::json!(["user", "bucket", [10, 20]]));
+        let decoded: Vec<Expr> = serde_json::from_value(json.clone()).unwrap();
+        assert_eq!(serde_json::to_value(&decoded).unwrap(), json);
+
+        // Binary round trip recompiles the regex
+        let bytes = bincode::serialize(&exprs).unwrap();
+        let decoded: Vec<Expr> = bincode::deserialize(&bytes).unwrap();
+        let vars = HashMap::from([("ua".to_string(), "curl/8.0".to_string())]);
+        assert!(decoded[5].eval(&vars));
+        assert_eq!(serde_json::to_value(&decoded).unwrap(), json);
+
+        // Invalid regexes are rejected on load
+        let err = serde_json::from_str::<Expr>(r#"["ua", "~~", "("]"#).unwrap_err();
+        assert!(err.to_string().contains("Invalid regex"));
+    }
+
+    #[test]
+    fn test_expr_macro() {
+        let json = |expr: Expr| serde_json::to_value(expr).unwrap();
+        assert_eq!(
+            json(expr!(env == "prod" && (region in ["us", "eu"] || tier == "vip"))),
+            serde_json::json!([
+                "AND",
+                ["env", "==", "prod"],
+                ["OR", ["region", "in", ["us", "eu"]], ["tier", "==", "vip"]]
+            ])
+        );
+        // `&&` binds tighter than `||`; single terms stay bare
+        assert_eq!(
+            json(expr!(a == 1 || b > 2 && c < 3)),
+            serde_json::json!([
+                "OR",
+                ["a", "==", "1"],
+                ["AND", ["b", ">", "2"], ["c", "<", "3"]]
+            ])
+        );
+        let version = "v2";
+        assert_eq!(
+            json(expr!(ver != version)),
+            serde_json::json!(["ver", "~=", "v2"])
+        );
+
+        let router = RadixRouter::new().unwrap();
+        router
+            .add_route(RadixNode {
+                id: "api".to_string(),
+                paths: vec!["/api".to_string()],
+                vars: Some(vec![
+                    expr!(ua =~ "^curl/" || (env == "prod" && tier in ["vip"])),
+                ]),
+                ..Default::default()
+            })
+            .unwrap();
+        let matched = |pairs: &[(&str, &str)]| {
+            let vars = pairs
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect();
+            let opts = RadixMatchOpts {
+                vars: Some(vars),
+                ..Default::default()
+            };
+            router.match_route("/api", &opts).unwrap().is_some()
+        };
+        assert!(matched(&[("ua", "curl/8.0")]));
+        assert!(matched(&[("env", "prod"), ("tier", "vip")]));
+        assert!(!matched(&[("env", "prod"), ("tier", "free")]));
+
+        // Groups survive snapshots
+        let restored = RadixRouter::from_snapshot(&router.to_snapshot().unwrap()).unwrap();
+        let opts = RadixMatchOpts {
+            vars: Some(HashMap::from([("ua".to_string(), "curl/8".to_string())])),
+            ..Default::default()
+        };
+        assert!(restored.match_route("/api", &opts).unwrap().is_some());
+    }
+
+    #[test]
+    fn test_routes_macro() {
+        let version = 2;
+        let router = RadixRouter::with_routes(routes! {
+            GET "/api/users" => serde_json::json!({"handler": "list"}),
+            GET|POST "/api/user/:id" priority 10 => serde_json::json!({"v": version}),
+            ANY "/api/user/:id" id "fallback" priority -1 host "*.example.com" => serde_json::json!(null),
+        })
+        .unwrap();
+
+        let lookup = |path: &str, method: &str, host: &str| {
+            let opts = RadixMatchOpts {
+                method: Some(method.to_string()),
+                host: Some(host.to_string()),
+                ..Default::default()
+            };
+            router.match_route(path, &opts).unwrap().map(|r| r.id)
+        };
+        assert_eq!(
+            lookup("/api/users", "GET", "a.com").as_deref(),
+            Some("GET /api/users")
+        );
+        assert_eq!(lookup("/api/users", "POST", "a.com"), None);
+        assert_eq!(
+            lookup("/api/user/7", "POST", "api.example.com").as_deref(),
+            Some("GET|POST /api/user/:id")
+        );
+        assert_eq!(
+            lookup("/api/user/7", "DELETE", "api.example.com").as_deref(),
+            Some("fallback")
+        );
+        assert_eq!(lookup("/api/user/7", "DELETE", "a.com"), None);
+    }
+
+    #[cfg(feature = "macros")]
+    #[test]
+    fn test_checked_routes() {
+        let routes = checked_routes! {
+            GET "/api/users" => serde_json::json!({"handler": "list"}),
+            GET|POST "/api/user/:id" priority 10 => serde_json::json!(null),
+            ANY "/api/user/:id" id "fallback" => serde_json::json!(null),
+        };
+        let ids: Vec<&str> = routes.iter().map(|r| r.id.as_str()).collect();
+        assert_eq!(
+            ids,
+            ["GET /api/users", "GET|POST /api/user/:id", "fallback"]
+        );
+        assert!(RadixRouter::with_routes(routes).is_ok());
+    }
+
+    #[test]
+    fn test_verb_shorthands() {
+        let router = RadixRouter::new().unwrap();
+        assert_eq!(
+            router.get("/users", serde_json::json!("list")).unwrap(),
+            "GET /users"
+        );
+        router.post("/users", serde_json::json!("create")).unwrap();
+        router
+            .put("/users/:id", serde_json::json!("replace"))
+            .unwrap();
+        router
+            .delete("/users/:id", serde_json::json!("remove"))
+            .unwrap();
+        assert_eq!(
+            router.any("/health", serde_json::json!("ok")).unwrap(),
+            "ANY /health"
+        );
+        let id = router
+            .add_endpoint(
+                Some("patch_user"),
+                Some(RadixHttpMethod::PATCH | RadixHttpMethod::PUT),
+                "/users/:id/profile",
+                serde_json::json!("patch"),
+            )
+            .unwrap();
+        assert_eq!(id, "patch_user");
+        assert_eq!(
+            router
+                .add_endpoint(
+                    None,
+                    Some(RadixHttpMethod::GET | RadixHttpMethod::HEAD),
+                    "/x",
+                    serde_json::json!(null)
+                )
+                .unwrap(),
+            "GET|HEAD /x"
+        );
+
+        let handler = |method: &str, path: &str| {
+            let opts = RadixMatchOpts {
+                method: Some(method.to_string()),
+                ..Default::default()
+            };
+            router
+                .match_route(path, &opts)
+                .unwrap()
+                .map(|r| r.metadata.as_str().unwrap().to_string())
+        };
+        assert_eq!(handler("GET", "/users").as_deref(), Some("list"));
+        assert_eq!(handler("POST", "/users").as_deref(), Some("create"));
+        assert_eq!(handler("DELETE", "/users/7").as_deref(), Some("remove"));
+        assert_eq!(
+            handler("PATCH", "/users/7/profile").as_deref(),
+            Some("patch")
+        );
+        assert_eq!(handler("OPTIONS", "/health").as_deref(), Some("ok"));
+        assert_eq!(handler("PATCH", "/users"), None);
+
+        router.delete_by_id("DELETE /users/:id").unwrap();
+        assert_eq!(handler("DELETE", "/users/7"), None);
+    }
+
+    #[test]
+    fn test_router_builder() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<Router>();
+
+        let builder = RouterBuilder::new()
+            .unwrap()
+            .routes(vec![node("users", "/users/:id"), node("old", "/old")])
+            .unwrap()
+            .delete_by_id("old")
+            .unwrap();
+        // A failing step consumes the builder
+        assert!(builder.route(node("bad", "/a/:x/:x")).is_err());
+
+        let builder = RouterBuilder::new()
+            .unwrap()
+            .route(node("users", "/users/:id"))
+            .unwrap()
+            .route(node("shadowed", "/users/:name"))
+            .unwrap();
+        let err = builder.validate().err().unwrap();
+        assert!(err.to_string().contains("unreachable"));
+
+        let router = RouterBuilder::new()
+            .unwrap()
+            .id_policy(IdPolicy::Reject)
+            .route(node("users", "/users/:id"))
+            .unwrap()
+            .validate()
+            .unwrap()
+            .build();
+        let result = router
+            .match_route("/users/7", &RadixMatchOpts::default())
+            .unwrap()
+            .unwrap();
+        assert_eq!(result.matched.get("id"), Some("7"));
+        assert_eq!(router.len(), 1);
+        assert!(router
+            .match_route("/old", &RadixMatchOpts::default())
+            .unwrap()
+            .is_none());
+
+        // Freezing a live router takes its current routes only
+        let live = RadixRouter::new().unwrap();
+        live.add_route(node("users", "/users/:id")).unwrap();
+        let frozen = live.freeze();
+        live.delete_by_id("users").unwrap();
+        let clone = frozen.clone();
+        assert!(
+            std::thread::spawn(move || clone.matches("/users/1", &Default::default()))
+                .join()
+                .unwrap()
+                .unwrap()
+                .is_some()
+        );
+        assert!(live
+            .matches("/users/1", &Default::default())
+            .unwrap()
+            .is_none());
+    }
 
     #[test]
-    fn test_basic_match() {
-        let routes = vec![RadixNode {
-            id: "1".to_string(),
-            paths: vec!["/api/users".to_string()],
-            methods: Some(RadixHttpMethod::GET),
-            hosts: None,
-            remote_addrs: None,
-            vars: None,
-            filter_fn: None,
-            priority: 0,
-            metadata: serde_json::json!({"handler": "get_users"}),
-        }];
+    fn test_add_routes_all_or_nothing() {
+        let router = RadixRouter::new().unwrap();
+        router.add_route(node("existing", "/existing")).unwrap();
 
-        let mut router = RadixRouter::new().unwrap();
-        router.add_routes(routes).unwrap();
+        // Every invalid route is reported and nothing is added
+        let err = router
+            .add_routes(vec![
+                node("ok", "/ok"),
+                node("no_slash", "users"),
+                node("dup_param", "/a/:x/:x"),
+            ])
+            .unwrap_err()
+            .to_string();
+        assert!(err.starts_with("2 invalid routes"), "{}", err);
+        assert!(
+            err.contains("no_slash: ") && err.contains("dup_param: "),
+            "{}",
+            err
+        );
+        assert!(router.get_route("ok").is_none());
 
-        let opts = RadixMatchOpts {
-            method: Some("GET".to_string()),
-            ..Default::default()
+        // A single failure keeps its own message
+        let err = router.add_routes(vec![node("bad", "bad")]).unwrap_err();
+        assert!(format!("{:#}", err).contains("Path must start with '/'"));
+        assert!(!err.to_string().contains("invalid routes"));
+
+        // Id conflicts are caught before inserting, within the batch too
+        router.set_id_policy(IdPolicy::Reject).unwrap();
+        let err = router
+            .add_routes(vec![
+                node("new", "/new"),
+                node("new", "/new2"),
+                node("existing", "/other"),
+            ])
+            .unwrap_err()
+            .to_string();
+        assert!(err.starts_with("2 invalid routes"), "{}", err);
+        assert_eq!(router.len(), 1);
+
+        router
+            .add_routes(vec![node("a", "/a"), node("b", "/b/:id")])
+            .unwrap();
+        assert_eq!(router.len(), 3);
+    }
+
+    #[test]
+    fn test_path_priorities() {
+        let router = RadixRouter::new().unwrap();
+        router
+            .add_routes(vec![
+                RadixNode {
+                    id: "docs".to_string(),
+                    paths: vec!["/docs".to_string(), "/docs/*".to_string()],
+                    priority: 0,
+                    path_priorities: Some(vec![("/docs".to_string(), 20)]),
+                    ..Default::default()
+                },
+                RadixNode {
+                    id: "site".to_string(),
+                    paths: vec!["/docs".to_string(), "/docs/*".to_string()],
+                    priority: 10,
+                    ..Default::default()
+                },
+            ])
+            .unwrap();
+        let winner = |path: &str| {
+            router
+                .match_route(path, &RadixMatchOpts::default())
+                .unwrap()
+                .map(|r| (r.id, r.priority))
         };
+        // The exact alias outranks `site`, the wildcard alias does not
+        assert_eq!(winner("/docs"), Some(("docs".to_string(), 20)));
+        assert_eq!(winner("/docs/intro"), Some(("site".to_string(), 10)));
 
-        let result = router.match_route("/api/users", &opts).unwrap();
-        assert!(result.is_some());
-        let result = result.unwrap();
-        assert_eq!(result.id, "1");
-        assert_eq!(result.metadata["handler"], "get_users");
+        let err = router
+            .add_route(RadixNode {
+                id: "typo".to_string(),
+                paths: vec!["/a".to_string()],
+                path_priorities: Some(vec![("/b".to_string(), 1)]),
+                ..Default::default()
+            })
+            .unwrap_err();
+        assert!(err.to_string().contains("/b"));
     }
 
     #[test]
-    fn test_method_not_allowed() {
-        let routes = vec![RadixNode {
-            id: "1".to_string(),
-            paths: vec!["/api/users".to_string()],
-            methods: Some(RadixHttpMethod::GET),
-            hosts: None,
-            remote_addrs: None,
-            vars: None,
-            filter_fn: None,
-            priority: 0,
-            metadata: serde_json::json!({"handler": "get_users"}),
-        }];
+    fn test_glob_segments() {
+        let router = RadixRouter::new().unwrap();
+        router
+            .get("/api/**scope/admin", serde_json::json!("admin"))
+            .unwrap();
+        router
+            .get("/files/:bucket/**/:name", serde_json::json!("file"))
+            .unwrap();
+        let opts = RadixMatchOpts::default();
 
-        let mut router = RadixRouter::new().unwrap();
-        router.add_routes(routes).unwrap();
+        let result = router
+            .match_route("/api/v2/tenants/7/admin", &opts)
+            .unwrap()
+            .unwrap();
+        assert_eq!(&result.matched["scope"], "v2/tenants/7");
+        let result = router.match_route("/api/admin", &opts).unwrap().unwrap();
+        assert_eq!(&result.matched["scope"], "");
+        assert!(router
+            .match_route("/api/v2/users", &opts)
+            .unwrap()
+            .is_none());
 
-        let opts = RadixMatchOpts {
-            method: Some("POST".to_string()),
+        let result = router
+            .match_route("/files/assets/css/site/main.css", &opts)
+            .unwrap()
+            .unwrap();
+        assert_eq!(&result.matched["bucket"], "assets");
+        assert_eq!(&result.matched[":glob"], "css/site");
+        assert_eq!(&result.matched["name"], "main.css");
+        let result = router
+            .match_route("/files/assets/main.css", &opts)
+            .unwrap()
+            .unwrap();
+        assert_eq!(&result.matched[":glob"], "");
+        assert!(router
+            .match_route("/files/assets", &opts)
+            .unwrap()
+            .is_none());
+
+        for bad in ["/a/**/b/**", "/a/**/b/*rest", "/a/x**/b", "/a/**x.y"] {
+            assert!(router.get(bad, serde_json::json!(null)).is_err(), "{}", bad);
+        }
+    }
+
+    #[test]
+    fn test_suffix_routes() {
+        let router = RadixRouter::new().unwrap();
+        router.any("*.php", serde_json::json!("php")).unwrap();
+        router
+            .any("*/healthz", serde_json::json!("health"))
+            .unwrap();
+        router
+            .add_endpoint(
+                Some("admin-php"),
+                None,
+                "*/admin.php",
+                serde_json::json!("admin"),
+            )
+            .unwrap();
+        router.get("/api/*rest", serde_json::json!("api")).unwrap();
+        let opts = RadixMatchOpts::default();
+        let id = |path: &str| router.match_route(path, &opts).unwrap().map(|r| r.id);
+
+        assert_eq!(id("/legacy/index.php").as_deref(), Some("ANY *.php"));
+        assert_eq!(id("/svc/a/healthz").as_deref(), Some("ANY */healthz"));
+        // The longest suffix is tried first
+        assert_eq!(id("/site/admin.php").as_deref(), Some("admin-php"));
+        assert_eq!(id("/legacy/index.phps"), None);
+        assert_eq!(id("/svc/a/nothealthz"), None);
+        // Prefix routes are tried before suffix routes
+        assert_eq!(id("/api/x.php").as_deref(), Some("GET /api/*rest"));
+
+        let explained = router.explain("/site/admin.php", &opts).unwrap();
+        assert!(explained
+            .candidates
+            .iter()
+            .all(|c| c.source == CandidateSource::Suffix));
+        assert_eq!(explained.candidates.len(), 2);
+
+        // Suffix routes survive snapshots, compaction and deletes
+        let restored = RadixRouter::from_snapshot(&router.to_snapshot().unwrap()).unwrap();
+        restored.compact().unwrap();
+        let restored_id = |path: &str| restored.match_route(path, &opts).unwrap().map(|r| r.id);
+        assert_eq!(restored_id("/a/b.php").as_deref(), Some("ANY *.php"));
+        restored.delete_by_id("ANY *.php").unwrap();
+        assert_eq!(restored_id("/a/b.php"), None);
+        assert_eq!(restored_id("/a/admin.php").as_deref(), Some("admin-php"));
+
+        for bad in ["*", "*/:id", "*/a/*"] {
+            assert!(router.any(bad, serde_json::json!(null)).is_err(), "{}", bad);
+        }
+    }
+
+    #[test]
+    fn test_extension_constraints() {
+        let router = RadixRouter::new().unwrap();
+        router
+            .add_route(RadixNode {
+                id: "assets".to_string(),
+                paths: vec!["/static/*path".to_string()],
+                extensions: Some(vec!["css".into(), ".JS".into(), "tar.gz".into()]),
+                metadata: serde_json::json!("assets"),
+                ..Default::default()
+            })
+            .unwrap();
+        router.get("/static/*", serde_json::json!("other")).unwrap();
+        let opts = RadixMatchOpts::default();
+
+        let result = router
+            .match_route("/static/css/site.CSS", &opts)
+            .unwrap()
+            .unwrap();
+        assert_eq!(result.id, "assets");
+        assert_eq!(&result.matched["path"], "css/site.CSS");
+        assert_eq!(&result.matched[EXTENSION_PARAM], "CSS");
+        let result = router
+            .match_route("/static/dist/app.tar.gz", &opts)
+            .unwrap()
+            .unwrap();
+        assert_eq!(&result.matched[EXTENSION_PARAM], "tar.gz");
+
+        // Other extensions, no extension and bare dotfiles fall through
+        for path in ["/static/logo.png", "/static/js", "/static/.js"] {
+            assert_eq!(
+                router.match_route(path, &opts).unwrap().unwrap().id,
+                "GET /static/*"
+            );
+        }
+
+        // The extension is captured by every lookup flavour
+        let mut params = Params::new();
+        router
+            .match_route_into("/static/app.js", &opts, &mut params)
+            .unwrap()
+            .unwrap();
+        assert_eq!(params.get(EXTENSION_PARAM), Some("js"));
+        let borrowed = router
+            .match_route_ref("/static/app.js", &opts)
+            .unwrap()
+            .unwrap();
+        assert_eq!(borrowed.param(EXTENSION_PARAM), Some("js"));
+
+        let explained = router.explain("/static/logo.png", &opts).unwrap();
+        assert_eq!(
+            explained.candidates[0].rejection,
+            Some(Rejection::Extension)
+        );
+
+        let err = router
+            .add_route(RadixNode {
+                id: "bad".to_string(),
+                paths: vec!["/files/:name".to_string()],
+                extensions: Some(vec!["css".into()]),
+                ..Default::default()
+            })
+            .unwrap_err();
+        assert!(err.to_string().contains("needs a wildcard path"));
+    }
+
+    #[test]
+    fn test_upstream_rewrite() {
+        let router = RadixRouter::new().unwrap();
+        router
+            .add_routes(vec![
+                RadixNode {
+                    id: "strip".to_string(),
+                    paths: vec!["/api/*rest".to_string(), "/health".to_string()],
+                    strip_prefix: true,
+                    ..Default::default()
+                },
+                RadixNode {
+                    id: "rewrite".to_string(),
+                    paths: vec!["/users/:id/files/*path".to_string()],
+                    rewrite: Some("/internal/$2?user=${id}&cost=$$1".to_string()),
+                    ..Default::default()
+                },
+                RadixNode {
+                    id: "plain".to_string(),
+                    paths: vec!["/plain".to_string()],
+                    ..Default::default()
+                },
+            ])
+            .unwrap();
+        let opts = RadixMatchOpts::default();
+        let upstream = |path: &str| {
+            router
+                .match_route(path, &opts)
+                .unwrap()
+                .unwrap()
+                .upstream_path
+        };
+
+        assert_eq!(upstream("/api/users/1").as_deref(), Some("/users/1"));
+        assert_eq!(upstream("/api/").as_deref(), Some("/"));
+        assert_eq!(upstream("/health").as_deref(), Some("/"));
+        assert_eq!(
+            upstream("/users/7/files/a/b.txt").as_deref(),
+            Some("/internal/a/b.txt?user=7&cost=$1")
+        );
+        assert_eq!(upstream("/plain"), None);
+
+        // The directive survives snapshots; mounting strips the mount too
+        let restored = RadixRouter::from_snapshot(&router.to_snapshot().unwrap()).unwrap();
+        let result = restored.match_route("/api/x", &opts).unwrap().unwrap();
+        assert_eq!(result.upstream_path.as_deref(), Some("/x"));
+        let outer = RadixRouter::new().unwrap();
+        outer.mount("/v1", &router).unwrap();
+        let result = outer.match_route("/v1/api/x", &opts).unwrap().unwrap();
+        assert_eq!(result.upstream_path.as_deref(), Some("/x"));
+
+        for (template, error) in [
+            ("/$3", "refers past"),
+            ("/${nope}", "unknown capture"),
+            ("/$x", "must be followed"),
+        ] {
+            let err = router
+                .add_route(RadixNode {
+                    id: "bad".to_string(),
+                    paths: vec!["/bad/:a/*b".to_string()],
+                    rewrite: Some(template.to_string()),
+                    ..Default::default()
+                })
+                .unwrap_err();
+            assert!(format!("{:#}", err).contains(error), "{}", template);
+        }
+        let both = RadixNode {
+            id: "both".to_string(),
+            paths: vec!["/both/*".to_string()],
+            strip_prefix: true,
+            rewrite: Some("/".to_string()),
             ..Default::default()
         };
+        assert!(router.add_route(both).is_err());
+    }
 
-        let result = router.match_route("/api/users", &opts).unwrap();
-        assert!(result.is_none());
+    #[test]
+    fn test_remaining_path() {
+        let router = RadixRouter::new().unwrap();
+        router.get("/api/*rest", serde_json::json!(null)).unwrap();
+        router
+            .get("/users/:id/files/*", serde_json::json!(null))
+            .unwrap();
+        router.get("/docs/**", serde_json::json!(null)).unwrap();
+        router.get("/items/:id", serde_json::json!(null)).unwrap();
+        router.get("/exact", serde_json::json!(null)).unwrap();
+        let opts = RadixMatchOpts::default();
+        let remaining = |path: &str| {
+            router
+                .match_route(path, &opts)
+                .unwrap()
+                .unwrap()
+                .remaining_path
+        };
+
+        assert_eq!(remaining("/api/users/1").as_deref(), Some("/users/1"));
+        assert_eq!(remaining("/api/").as_deref(), Some("/"));
+        assert_eq!(
+            remaining("/users/7/files/a/b.txt").as_deref(),
+            Some("/a/b.txt")
+        );
+        assert_eq!(
+            remaining("/docs/guide/intro").as_deref(),
+            Some("/guide/intro")
+        );
+        assert_eq!(remaining("/items/3"), None);
+        assert_eq!(remaining("/exact"), None);
+
+        let outer = RadixRouter::new().unwrap();
+        outer.mount("/v1", &router).unwrap();
+        let result = outer.match_route("/v1/api/x/y", &opts).unwrap().unwrap();
+        assert_eq!(result.remaining_path.as_deref(), Some("/x/y"));
     }
 
     #[test]
-    fn test_param_extraction() {
-        let routes = vec![RadixNode {
-            id: "1".to_string(),
-            paths: vec!["/user/:id/post/:pid".to_string()],
-            methods: None,
-            hosts: None,
-            remote_addrs: None,
-            vars: None,
-            filter_fn: None,
-            priority: 0,
-            metadata: serde_json::json!({"handler": "user_post"}),
-        }];
+    fn test_match_uri() {
+        let router = RadixRouter::new().unwrap();
+        router
+            .add_route(RadixNode {
+                id: "search".to_string(),
+                paths: vec!["/search".to_string()],
+                vars: Some(vec![expr!(arg_q == "rust lang")]),
+                ..Default::default()
+            })
+            .unwrap();
+        router
+            .get("/api/users/:id", serde_json::json!(null))
+            .unwrap();
+        let opts = RadixMatchOpts::default();
 
-        let mut router = RadixRouter::new().unwrap();
-        router.add_routes(routes).unwrap();
+        let result = router
+            .match_uri("/api/users/7?limit=10#top", &opts)
+            .unwrap()
+            .unwrap();
+        assert_eq!(&result.matched["id"], "7");
+        assert!(router
+            .match_uri("/search?q=rust+lang&q=ignored", &opts)
+            .unwrap()
+            .is_some());
+        assert!(router
+            .match_uri("/search?q=rust%20lang", &opts)
+            .unwrap()
+            .is_some());
+        assert!(router.match_uri("/search?q=go", &opts).unwrap().is_none());
+        // Args set by the caller win over the query string
+        let mut preset = RadixMatchOpts::default();
+        preset.add_query_args("q=rust%20lang");
+        assert!(router.match_uri("/search?q=go", &preset).unwrap().is_some());
+        assert!(router
+            .match_route("/search?q=rust+lang", &opts)
+            .unwrap()
+            .is_none());
+
+        let frozen = router.freeze();
+        assert!(frozen
+            .match_uri("/search?q=%72ust+lang&x=%zz", &opts)
+            .unwrap()
+            .is_some());
+    }
 
+    #[test]
+    fn test_limits() {
+        let router = RadixRouter::new().unwrap();
+        router.get("/users/:id", serde_json::json!(null)).unwrap();
+        router.get("/files/*path", serde_json::json!(null)).unwrap();
+        router.get("/a/b/c/d", serde_json::json!(null)).unwrap();
+        router
+            .set_limits(Limits {
+                max_path_len: Some(24),
+                max_param_len: Some(4),
+                max_segments: Some(3),
+            })
+            .unwrap();
         let opts = RadixMatchOpts::default();
+        let found = |path: &str| router.match_route(path, &opts).unwrap().is_some();
 
-        let result = router.match_route("/user/123/post/456", &opts).unwrap();
+        assert!(found("/users/1234"));
+        assert!(!found("/users/12345"));
+        assert!(found("/files/a/b"));
+        assert!(!found("/files/a/b/c"));
+        assert!(!found("/a/b/c/d"));
+        assert!(!found(&format!("/users/{}", "x".repeat(30))));
+        // Captures are checked for every lookup flavour
+        assert!(router.matches("/users/12345", &opts).unwrap().is_none());
+        assert!(router
+            .explain("/a/b/c/d", &opts)
+            .unwrap()
+            .candidates
+            .is_empty());
 
-        assert!(result.is_some());
-        let result = result.unwrap();
-        assert_eq!(result.id, "1");
-        assert_eq!(result.matched.get("id").unwrap(), "123");
-        assert_eq!(result.matched.get("pid").unwrap(), "456");
+        // Limits carry over to frozen routers and restored snapshots
+        assert!(router
+            .freeze()
+            .matches("/users/12345", &opts)
+            .unwrap()
+            .is_none());
+        router
+            .restore_snapshot(&router.to_snapshot().unwrap())
+            .unwrap();
+        assert!(!found("/users/12345"));
+
+        router.set_limits(Limits::default()).unwrap();
+        assert!(found("/a/b/c/d"));
     }
 
     #[test]
-    fn test_wildcard() {
-        let routes = vec![RadixNode {
-            id: "1".to_string(),
-            paths: vec!["/files/*path".to_string()],
-            methods: None,
-            hosts: None,
-            remote_addrs: None,
-            vars: None,
-            filter_fn: None,
-            priority: 0,
-            metadata: serde_json::json!({"handler": "serve_file"}),
-        }];
+    fn test_traversal_policy() {
+        let router = RadixRouter::new().unwrap();
+        router
+            .get("/static/*path", serde_json::json!(null))
+            .unwrap();
+        router.get("/raw/*", serde_json::json!(null)).unwrap();
+        router
+            .get("/docs/**/index", serde_json::json!(null))
+            .unwrap();
+        router.get("/up/../x", serde_json::json!(null)).unwrap();
+        let opts = RadixMatchOpts::default();
+        let found = |path: &str| router.match_route(path, &opts).unwrap().is_some();
 
-        let mut router = RadixRouter::new().unwrap();
-        router.add_routes(routes).unwrap();
+        assert!(found("/static/../etc/passwd"));
+        router
+            .set_traversal_policy(TraversalPolicy::Reject)
+            .unwrap();
+        for path in [
+            "/static/../etc/passwd",
+            "/static/a/..",
+            "/static/%2e%2e/etc",
+            "/static/%2E%2e%2Fetc",
+            "/static/a%5c..%5cb",
+            "/static/%252e%252e/etc",
+            "/raw/..",
+            "/docs/a/../b/index",
+        ] {
+            assert!(!found(path), "{}", path);
+        }
+        for path in [
+            "/static/a..b/c",
+            "/static/.hidden",
+            "/static/a/.../b",
+            "/up/../x",
+        ] {
+            assert!(found(path), "{}", path);
+        }
+        let explained = router.explain("/static/../x", &opts).unwrap();
+        assert_eq!(
+            explained.candidates[0].rejection,
+            Some(Rejection::Traversal)
+        );
+        assert!(router
+            .freeze()
+            .matches("/raw/%2e%2e", &opts)
+            .unwrap()
+            .is_none());
+    }
 
+    #[test]
+    fn test_path_decoding() {
+        let router = RadixRouter::new().unwrap();
+        router
+            .get("/api/user/:id", serde_json::json!(null))
+            .unwrap();
+        router.get("/api/:name", serde_json::json!(null)).unwrap();
+        router.get("/café", serde_json::json!(null)).unwrap();
         let opts = RadixMatchOpts::default();
+        let matched = |path: &str| {
+            router.match_route(path, &opts).unwrap().map(|result| {
+                result
+                    .matched
+                    .iter()
+                    .map(|(_, v)| v.to_string())
+                    .collect::<Vec<_>>()
+            })
+        };
 
-        let result = router.match_route("/files/documents/readme.txt", &opts).unwrap();
+        // Raw: escapes are matched as written
+        assert_eq!(matched("/api/user%2F1"), Some(vec!["user%2F1".to_string()]));
+        assert_eq!(matched("/caf%C3%A9"), None);
 
-        assert!(result.is_some());
-        let result = result.unwrap();
-        assert_eq!(result.matched.get("path").unwrap(), "documents/readme.txt");
+        router.set_path_decoding(PathDecoding::Decode).unwrap();
+        assert_eq!(matched("/api/user%2F1"), Some(vec!["1".to_string()]));
+        assert_eq!(matched("/api/a%20b"), Some(vec!["a b".to_string()]));
+        assert_eq!(matched("/api/a+b%2"), Some(vec!["a+b%2".to_string()]));
+        assert_eq!(matched("/api/%2541"), Some(vec!["%41".to_string()]));
+        assert!(matched("/caf%C3%A9").is_some());
+
+        router
+            .set_path_decoding(PathDecoding::KeepEncodedSlash)
+            .unwrap();
+        assert_eq!(matched("/api/user%2f1"), Some(vec!["user%2f1".to_string()]));
+        assert_eq!(matched("/api/user%201"), Some(vec!["user 1".to_string()]));
+
+        router
+            .set_path_decoding(PathDecoding::RejectEncodedSlash)
+            .unwrap();
+        assert_eq!(matched("/api/user%2F1"), None);
+        assert!(router.matches("/api/user%2f1", &opts).unwrap().is_none());
+        assert_eq!(matched("/api/user%201"), Some(vec!["user 1".to_string()]));
+
+        let frozen = router.freeze();
+        let mut params = Params::new();
+        assert!(frozen
+            .match_route_into("/caf%c3%a9", &opts, &mut params)
+            .unwrap()
+            .is_some());
+        let decoded = PathDecoding::Decode.decode("/api/user%2F1").unwrap();
+        let found = frozen.match_route_ref(&decoded, &opts).unwrap().unwrap();
+        assert_eq!(found.param("id"), Some("1"));
     }
 
     #[test]
-    fn test_wildcard_host() {
-        let routes = vec![RadixNode {
-            id: "1".to_string(),
-            paths: vec!["/api".to_string()],
-            methods: None,
-            hosts: Some(vec!["*.example.com".to_string()]),
-            remote_addrs: None,
-            vars: None,
-            filter_fn: None,
-            priority: 0,
-            metadata: serde_json::json!({"handler": "api"}),
-        }];
+    fn test_table_limits() {
+        let router = RadixRouter::new().unwrap();
+        router
+            .set_table_limits(TableLimits {
+                max_routes: Some(3),
+                max_regex_bytes: Some(8),
+                max_candidates: Some(2),
+            })
+            .unwrap();
+        router.add_route(node("a", "/api/:id")).unwrap();
+        router.add_route(node("b", "/api/:name")).unwrap();
+        let err = router.add_route(node("c", "/api/:key")).unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("3 routes share the lookup key /api/,"));
+        assert!(router.get_route("c").is_none());
+
+        router
+            .add_route(RadixNode {
+                vars: Some(vec![Expr::regex("http_x", "^a+$")]),
+                ..node("c", "/c")
+            })
+            .unwrap();
+        let err = router.add_route(node("d", "/d")).unwrap_err();
+        assert!(err.to_string().contains("4 routes, at most 3"));
+        let old = RadixNode {
+            vars: Some(vec![Expr::regex("http_x", "^a+$")]),
+            ..node("c", "/c")
+        };
+        let new = RadixNode {
+            vars: Some(vec![Expr::regex("http_x", "^(a|bc)+$")]),
+            ..node("c", "/c")
+        };
+        let err = router.update_route(old, new).unwrap_err();
+        assert!(err.to_string().contains("9 bytes of regex, at most 8"));
+
+        // Lowering a cap below the current table fails and keeps the old caps
+        assert!(router
+            .set_table_limits(TableLimits {
+                max_routes: Some(2),
+                ..Default::default()
+            })
+            .is_err());
+        assert!(router.replace_routes(vec![node("x", "/x")]).is_ok());
+        let builder = RouterBuilder::new().unwrap().table_limits(TableLimits {
+            max_routes: Some(1),
+            ..Default::default()
+        });
+        assert!(builder
+            .route(node("x", "/x"))
+            .unwrap()
+            .route(node("y", "/y"))
+            .is_err());
+    }
+
+    #[test]
+    fn test_regex_guards() {
+        assert!(compile_regex("^(v[0-9]+|beta)$").is_ok());
+        let err = compile_regex(&"a".repeat(MAX_REGEX_LEN + 1)).unwrap_err();
+        assert!(err.to_string().contains("at most 1024 allowed"));
+        assert!(compile_regex(&format!("{}a{}", "(".repeat(20), ")".repeat(20))).is_err());
+        assert!(compile_regex("(a{1000}){1000}").is_err());
+
+        // Configs and snapshots go through the same guards
+        let config = |pattern: &str| {
+            let json =
+                serde_json::json!([{"id": "r", "paths": ["/r"], "vars": [["ua", "~~", pattern]]}]);
+            let mut configs = RouteConfig::list_from_json(&json.to_string()).unwrap();
+            RadixNode::try_from(configs.remove(0))
+        };
+        assert!(config("^curl/").is_ok());
+        let err = config("\\w{500}{500}").unwrap_err();
+        assert!(format!("{:#}", err).contains("Invalid regex for ua"));
+    }
+
+    #[test]
+    fn test_audit_log() {
+        use std::sync::Mutex;
+
+        #[derive(Default)]
+        struct Shipper(Mutex<Vec<AuditAction>>);
+
+        impl AuditSink for Shipper {
+            fn record(&self, entry: &AuditEntry) {
+                self.0.lock().unwrap().push(entry.action);
+            }
+        }
+
+        let router = RadixRouter::new().unwrap();
+        router.get("/untracked", serde_json::json!(null)).unwrap();
+        router.enable_audit_log(3);
+        let shipper = Arc::new(Shipper::default());
+        router.add_audit_sink(shipper.clone());
+
+        let route = |path: &str| node("users", path);
+        router.add_route(route("/users")).unwrap();
+        router
+            .update_route(route("/users"), route("/users/:id"))
+            .unwrap();
+        router
+            .update_metadata("users", serde_json::json!({"v": 2}))
+            .unwrap();
+        router.delete_by_id("users").unwrap();
+
+        let log = router.audit_log();
+        let actions: Vec<_> = log.iter().map(|e| (e.action, e.id.as_str())).collect();
+        assert_eq!(
+            actions,
+            [
+                (AuditAction::Updated, "users"),
+                (AuditAction::Updated, "users"),
+                (AuditAction::Removed, "users"),
+            ]
+        );
+        let (added, updated) = (
+            route("/users").content_hash(),
+            route("/users/:id").content_hash(),
+        );
+        assert_eq!((log[0].before, log[0].after), (Some(added), Some(updated)));
+        let hot = RadixNode {
+            metadata: serde_json::json!({"v": 2}),
+            ..route("/users/:id")
+        };
+        assert_eq!(
+            (log[1].before, log[1].after),
+            (Some(updated), Some(hot.content_hash()))
+        );
+        assert_eq!(
+            (log[2].before, log[2].after),
+            (Some(hot.content_hash()), None)
+        );
+        assert!(log.windows(2).all(|w| w[0].at <= w[1].at));
+        assert_eq!(shipper.0.lock().unwrap()[0], AuditAction::Added);
+        assert_eq!(shipper.0.lock().unwrap().len(), 4);
+
+        router.enable_audit_log(0);
+        router.get("/more", serde_json::json!(null)).unwrap();
+        assert!(router.audit_log().is_empty());
+        assert_eq!(shipper.0.lock().unwrap().len(), 5);
+    }
+
+    #[test]
+    fn test_version() {
+        let router = RadixRouter::new().unwrap();
+        assert_eq!(router.version(), 0);
+        router.get("/a", serde_json::json!(null)).unwrap();
+        router
+            .add_routes(vec![
+                RadixNode {
+                    id: "b".to_string(),
+                    paths: vec!["/b".to_string()],
+                    ..Default::default()
+                },
+                RadixNode {
+                    id: "c".to_string(),
+                    paths: vec!["/c".to_string()],
+                    ..Default::default()
+                },
+            ])
+            .unwrap();
+        assert_eq!(router.version(), 2);
+        // A failed change publishes nothing
+        assert!(router.delete_by_id("missing").is_err());
+        assert_eq!(router.version(), 2);
+
+        let opts = RadixMatchOpts::default();
+        let frozen = router.freeze();
+        router.delete_by_id("b").unwrap();
+        assert_eq!(router.match_route("/a", &opts).unwrap().unwrap().version, 3);
+        assert_eq!(frozen.version(), 2);
+        assert_eq!(frozen.match_route("/b", &opts).unwrap().unwrap().version, 2);
+        router
+            .replace_routes(vec![RadixNode {
+                id: "d".to_string(),
+                paths: vec!["/d".to_string()],
+                ..Default::default()
+            }])
+            .unwrap();
+        assert_eq!(router.version(), 4);
+    }
+
+    #[test]
+    fn test_rollback() {
+        let router = RadixRouter::new().unwrap();
+        router.set_history_len(2);
+        router.get("/a", serde_json::json!(null)).unwrap();
+        router.get("/b", serde_json::json!(null)).unwrap();
+        router
+            .replace_routes(vec![RadixNode {
+                id: "bad".to_string(),
+                paths: vec!["/bad".to_string()],
+                ..Default::default()
+            }])
+            .unwrap();
+        assert_eq!(router.history(), [1, 2]);
+        assert!(router.rollback(0).is_err());
+
+        let opts = RadixMatchOpts::default();
+        router.rollback(2).unwrap();
+        assert_eq!(router.version(), 4);
+        assert!(router.match_route("/a", &opts).unwrap().is_some());
+        assert!(router.match_route("/b", &opts).unwrap().is_some());
+        assert!(router.match_route("/bad", &opts).unwrap().is_none());
+        assert_eq!(router.history(), [2, 3]);
+
+        // Undo the rollback
+        router.rollback(3).unwrap();
+        assert!(router.match_route("/bad", &opts).unwrap().is_some());
+        router.set_history_len(0);
+        assert!(router.history().is_empty());
+    }
+
+    #[test]
+    fn test_router_view() {
+        fn shareable<T: Send + Sync + Clone + 'static>(_: &T) {}
+
+        let router = RadixRouter::new().unwrap();
+        router.get("/users/:id", serde_json::json!(null)).unwrap();
+        let view = router.view();
+        shareable(&view);
+        let opts = RadixMatchOpts::default();
+
+        let worker = {
+            let view = view.clone();
+            std::thread::spawn(move || {
+                let result = view.match_route("/users/7", &RadixMatchOpts::default());
+                result.unwrap().unwrap().matched["id"].to_string()
+            })
+        };
+        assert_eq!(worker.join().unwrap(), "7");
+
+        // Changes through the router show up in existing views
+        let frozen = view.load();
+        let teams = router.get("/teams/:id", serde_json::json!(null)).unwrap();
+        assert_eq!((view.len(), view.version()), (2, 2));
+        assert!(view.match_route("/teams/1", &opts).unwrap().is_some());
+        assert!(view.get_route(&teams).is_some());
+        assert!(frozen.match_route("/teams/1", &opts).unwrap().is_none());
+        let found = view.match_route_ref("/users/8", &opts).unwrap().unwrap();
+        assert_eq!(found.param("id"), Some("8"));
+
+        // Clones of the router are independent of its views
+        let clone = router.clone();
+        clone.get("/other", serde_json::json!(null)).unwrap();
+        assert!(view.match_route("/other", &opts).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_router_manager() {
+        let manager = RouterManager::new().unwrap();
+        manager
+            .default_router()
+            .add_route(node("health", "/healthz"))
+            .unwrap();
+        manager
+            .reload_tenant("*.acme.com", vec![node("acme", "/api/*")])
+            .unwrap();
+        manager
+            .reload_tenant("EU.acme.com", vec![node("acme-eu", "/api/*")])
+            .unwrap();
+        assert_eq!(manager.tenants(), ["*.acme.com", "eu.acme.com"]);
+
+        let matched = |host: Option<&str>, path: &str| {
+            let opts = RadixMatchOpts {
+                host: host.map(str::to_string),
+                ..Default::default()
+            };
+            manager.match_route(path, &opts).unwrap().map(|r| r.id)
+        };
+        assert_eq!(
+            matched(Some("eu.acme.com"), "/api/x").as_deref(),
+            Some("acme-eu")
+        );
+        assert_eq!(
+            matched(Some("Shop.Acme.com"), "/api/x").as_deref(),
+            Some("acme")
+        );
+        assert_eq!(matched(Some("acme.com"), "/api/x"), None);
+        // The default router serves what tenants don't
+        assert_eq!(
+            matched(Some("eu.acme.com"), "/healthz").as_deref(),
+            Some("health")
+        );
+        assert_eq!(matched(None, "/healthz").as_deref(), Some("health"));
+
+        // A failed reload keeps the tenant's routes; others are unaffected
+        let bad = node("bad", "relative");
+        assert!(manager.reload_tenant("eu.acme.com", vec![bad]).is_err());
+        assert_eq!(
+            matched(Some("eu.acme.com"), "/api/x").as_deref(),
+            Some("acme-eu")
+        );
+        manager
+            .reload_tenant("eu.acme.com", vec![node("acme-eu-v2", "/api/*")])
+            .unwrap();
+        assert_eq!(
+            matched(Some("eu.acme.com"), "/api/x").as_deref(),
+            Some("acme-eu-v2")
+        );
+        assert_eq!(
+            matched(Some("shop.acme.com"), "/api/x").as_deref(),
+            Some("acme")
+        );
+
+        assert!(manager.remove_tenant("eu.acme.com").is_some());
+        assert_eq!(
+            matched(Some("eu.acme.com"), "/api/x").as_deref(),
+            Some("acme")
+        );
+    }
+
+    #[test]
+    fn test_listener_registry() {
+        let registry = ListenerRegistry::new();
+        let (public, admin) = (Listener::new(443, "HTTPS"), Listener::new(9000, "http"));
+        registry
+            .reload(&public, vec![node("api", "/api/*")])
+            .unwrap();
+        registry
+            .reload(
+                &admin,
+                vec![RadixNode {
+                    server_ports: Some(vec![9000]),
+                    ..node("admin", "/*")
+                }],
+            )
+            .unwrap();
+        assert_eq!(registry.listeners(), [public.clone(), admin.clone()]);
+        assert_eq!(public.to_string(), "https/443");
+
+        let opts = RadixMatchOpts::default();
+        let matched = |listener: &Listener, path: &str| {
+            let result = registry.match_route(listener, path, &opts).unwrap();
+            result.map(|r| r.id)
+        };
+        assert_eq!(matched(&public, "/api/x").as_deref(), Some("api"));
+        assert_eq!(matched(&public, "/admin"), None);
+        // The listener's port stands in for an unset server_port
+        assert_eq!(matched(&admin, "/api/x").as_deref(), Some("admin"));
 
-        let mut router = RadixRouter::new().unwrap();
+        let err = registry
+            .match_route(&Listener::new(80, "http"), "/", &opts)
+            .unwrap_err();
+        assert!(err.to_string().contains("No router for listener http/80"));
+        registry
+            .reload(&public, vec![node("api-v2", "/api/*")])
+            .unwrap();
+        assert_eq!(matched(&public, "/api/x").as_deref(), Some("api-v2"));
+        assert!(registry.remove(&admin).is_some());
+        assert!(registry.get(&admin).is_none());
+    }
+
+    #[test]
+    fn test_route_templates() {
+        let router = RadixRouter::new().unwrap();
+        let template = |host: &str| RadixNode {
+            hosts: Some(vec![host.to_string()]),
+            vars: Some(vec![Expr::Eq("env".to_string(), "prod".to_string())]),
+            priority: 5,
+            metadata: serde_json::json!({"upstream": "shared", "timeout": 30}),
+            tags: vec!["team:api".to_string()],
+            ..Default::default()
+        };
+        router
+            .register_template("api", template("api.example.com"))
+            .unwrap();
+        let configs = RouteConfig::list_from_json(
+            r#"[
+                {"id": "users", "paths": ["/users"], "extends": "api",
+                 "vars": [["tier", "==", "gold"]], "metadata": {"upstream": "users"},
+                 "tags": ["users"]},
+                {"id": "orders", "paths": ["/orders"], "extends": "api", "priority": 9}
+            ]"#,
+        )
+        .unwrap();
+        let routes: Vec<RadixNode> = configs.into_iter().map(|c| c.try_into().unwrap()).collect();
         router.add_routes(routes).unwrap();
 
-        let opts = RadixMatchOpts {
+        let mut opts = RadixMatchOpts {
             host: Some("api.example.com".to_string()),
+            vars: Some(HashMap::from([
+                ("env".to_string(), "prod".to_string()),
+                ("tier".to_string(), "gold".to_string()),
+            ])),
             ..Default::default()
         };
+        let users = router.match_route("/users", &opts).unwrap().unwrap();
+        assert_eq!(users.priority, 5);
+        assert_eq!(
+            *users.metadata,
+            serde_json::json!({"upstream": "users", "timeout": 30})
+        );
+        assert_eq!(
+            router.get_route("users").unwrap().tags,
+            ["team:api", "users"]
+        );
+        let orders = router.match_route("/orders", &opts).unwrap().unwrap();
+        assert_eq!(orders.priority, 9);
+        // Template vars and the route's own must both hold
+        opts.vars.as_mut().unwrap().remove("tier");
+        assert!(router.match_route("/users", &opts).unwrap().is_none());
 
-        let result = router.match_route("/api", &opts).unwrap();
-        assert!(result.is_some());
+        // One edit to the template moves every route extending it
+        router
+            .register_template("api", template("api.example.org"))
+            .unwrap();
+        assert!(router.match_route("/orders", &opts).unwrap().is_none());
+        opts.host = Some("api.example.org".to_string());
+        assert!(router.match_route("/orders", &opts).unwrap().is_some());
 
-        // Test non-matching host
-        let opts = RadixMatchOpts {
-            host: Some("api.other.com".to_string()),
+        // Metadata updates survive re-resolution, also after a restore
+        router
+            .update_metadata("orders", serde_json::json!({"upstream": "hot"}))
+            .unwrap();
+        let hot = serde_json::json!({"upstream": "hot", "timeout": 30});
+        let orders = router.match_route("/orders", &opts).unwrap().unwrap();
+        assert_eq!(*orders.metadata, hot);
+        let restored = RadixRouter::from_snapshot(&router.to_snapshot().unwrap()).unwrap();
+        restored
+            .register_template("api", template("api.example.net"))
+            .unwrap();
+        assert!(restored.match_route("/orders", &opts).unwrap().is_none());
+        opts.host = Some("api.example.net".to_string());
+        let orders = restored.match_route("/orders", &opts).unwrap().unwrap();
+        assert_eq!(*orders.metadata, hot);
+
+        let err = router
+            .add_route(RadixNode {
+                id: "x".to_string(),
+                paths: vec!["/x".to_string()],
+                extends: Some("missing".to_string()),
+                ..Default::default()
+            })
+            .unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("Unknown template missing for route x"));
+    }
+
+    #[test]
+    fn test_validate_routes() {
+        let router = RadixRouter::new().unwrap();
+        router
+            .add_route(RadixNode {
+                id: "users".to_string(),
+                paths: vec!["/api/users".to_string()],
+                ..Default::default()
+            })
+            .unwrap();
+        let routes = vec![
+            node("bad", "api/orders"),
+            RadixNode {
+                strip_prefix: true,
+                rewrite: Some("/v2/$1".to_string()),
+                ..node("both", "/api/*rest")
+            },
+            RadixNode {
+                filters: vec!["missing".to_string()],
+                ..node("filtered", "/api/items")
+            },
+            node("shadowed", "/api/users"),
+            node("dup", "/a"),
+            node("dup", "/b"),
+        ];
+        let issues = router.validate_routes(&routes);
+        let found: Vec<(Option<&str>, &str, Severity)> = issues
+            .iter()
+            .map(|i| (i.id.as_deref(), i.field.as_str(), i.severity))
+            .collect();
+        assert_eq!(
+            found,
+            vec![
+                (Some("bad"), "paths", Severity::Error),
+                (Some("both"), "rewrite", Severity::Error),
+                (Some("filtered"), "filters", Severity::Error),
+                (Some("dup"), "id", Severity::Warning),
+                (Some("shadowed"), "paths", Severity::Warning),
+            ]
+        );
+        assert!(issues[0].reason.contains("Path must start with '/'"));
+        assert_eq!(
+            issues[4].reason,
+            "shadowed (/api/users) is shadowed by users"
+        );
+
+        // Nothing was loaded
+        assert_eq!(router.len(), 1);
+        assert!(router.validate_routes(&[node("ok", "/ok")]).is_empty());
+
+        router
+            .set_table_limits(TableLimits {
+                max_routes: Some(1),
+                ..Default::default()
+            })
+            .unwrap();
+        let issues = router.validate_routes(&[node("ok", "/ok")]);
+        assert_eq!(issues.len(), 1);
+        assert_eq!(
+            (issues[0].id.as_deref(), issues[0].field.as_str()),
+            (None, "table")
+        );
+    }
+
+    #[test]
+    fn test_matchit_paths() {
+        assert_eq!(
+            convert_matchit_path("/users/{id}/files/{*rest}").unwrap(),
+            "/users/:id/files/*rest"
+        );
+        assert_eq!(
+            convert_matchit_path("/braces/{{x}}").unwrap(),
+            "/braces/{x}"
+        );
+        assert!(matches!(
+            convert_matchit_path("/users/:id").unwrap(),
+            std::borrow::Cow::Borrowed(_)
+        ));
+        assert!(convert_matchit_path("/file{ext}").is_err());
+        assert!(convert_matchit_path("/files/{*}").is_err());
+
+        let router = RadixRouter::new().unwrap();
+        router
+            .add_routes(vec![
+                RadixNode {
+                    id: "user".to_string(),
+                    paths: vec!["/users/{id}".to_string()],
+                    path_priorities: Some(vec![("/users/{id}".to_string(), 3)]),
+                    ..Default::default()
+                },
+                RadixNode {
+                    id: "static".to_string(),
+                    paths: vec!["/static/{*path}".to_string()],
+                    ..Default::default()
+                },
+            ])
+            .unwrap();
+        let opts = RadixMatchOpts::default();
+        let result = router.match_route("/users/42", &opts).unwrap().unwrap();
+        assert_eq!(
+            (result.id.as_str(), result.matched.get("id")),
+            ("user", Some("42"))
+        );
+        let result = router
+            .match_route("/static/css/app.css", &opts)
+            .unwrap()
+            .unwrap();
+        assert_eq!(result.matched.get("path"), Some("css/app.css"));
+        assert_eq!(router.get_route("user").unwrap().paths, vec!["/users/:id"]);
+
+        let err = router
+            .add_route(RadixNode {
+                id: "bad".to_string(),
+                paths: vec!["/v{version}/items".to_string()],
+                ..Default::default()
+            })
+            .unwrap_err();
+        assert_eq!(err.to_string(), "Invalid path for route: bad");
+    }
+
+    #[test]
+    fn test_route_table() {
+        let router = RadixRouter::new().unwrap();
+        router
+            .add_routes(vec![
+                RadixNode {
+                    id: "users".to_string(),
+                    paths: vec!["/api/users".to_string(), "/v2/users".to_string()],
+                    methods: Some(RadixHttpMethod::GET | RadixHttpMethod::POST),
+                    hosts: Some(vec!["*.example.com".to_string()]),
+                    priority: 10,
+                    metadata: serde_json::json!({"upstream": "users:8080"}),
+                    ..Default::default()
+                },
+                RadixNode {
+                    id: "health".to_string(),
+                    paths: vec!["/health".to_string()],
+                    ..Default::default()
+                },
+            ])
+            .unwrap();
+
+        let expected = "\
+ID      METHODS   HOSTS          PATH        PRIORITY  UPSTREAM
+health  *         *              /health     0         -
+users   GET|POST  *.example.com  /api/users  10        users:8080
+users   GET|POST  *.example.com  /v2/users   10        users:8080
+";
+        assert_eq!(router.table(), expected);
+        assert_eq!(router.to_string(), expected);
+        assert_eq!(router.freeze().to_string(), expected);
+    }
+
+    #[test]
+    fn test_find_routes() {
+        let router = RadixRouter::new().unwrap();
+        router
+            .add_routes(vec![
+                RadixNode {
+                    methods: Some(RadixHttpMethod::GET | RadixHttpMethod::DELETE),
+                    hosts: Some(vec!["*.example.com".to_string()]),
+                    tags: vec!["admin".to_string()],
+                    ..node("users", "/api/v1/users/:id")
+                },
+                RadixNode {
+                    methods: Some(RadixHttpMethod::GET),
+                    hosts: Some(vec!["api.example.com".to_string()]),
+                    ..node("orders", "/api/v1/orders")
+                },
+                node("any", "/api/v2/*"),
+                node("health", "/health"),
+            ])
+            .unwrap();
+
+        let ids = |query: RouteQuery| -> Vec<String> {
+            router
+                .find_routes(&query)
+                .into_iter()
+                .map(|r| r.id)
+                .collect()
+        };
+        assert_eq!(
+            ids(RouteQuery::default()),
+            vec!["any", "health", "orders", "users"]
+        );
+        let v1 = RouteQuery {
+            path_prefix: Some("/api/v1".to_string()),
             ..Default::default()
         };
-        let result = router.match_route("/api", &opts).unwrap();
-        assert!(result.is_none());
+        assert_eq!(ids(v1.clone()), vec!["orders", "users"]);
+        let hosts = |host: &str| RouteQuery {
+            host: Some(host.to_string()),
+            ..Default::default()
+        };
+        assert_eq!(ids(hosts("*.example.com")), vec!["users"]);
+        assert_eq!(ids(hosts("API.example.com")), vec!["orders", "users"]);
+        // Unrestricted routes accept every method
+        let delete = RouteQuery {
+            method: Some(RadixHttpMethod::DELETE),
+            ..Default::default()
+        };
+        assert_eq!(ids(delete), vec!["any", "health", "users"]);
+        assert_eq!(
+            ids(RouteQuery {
+                method: Some(RadixHttpMethod::DELETE),
+                tag: Some("admin".to_string()),
+                ..v1
+            }),
+            vec!["users"]
+        );
+
+        let found = router.freeze().find_routes(&hosts("api.example.com"));
+        assert_eq!(found[0].paths, vec!["/api/v1/orders"]);
+    }
+
+    #[test]
+    fn test_dry_run_add() {
+        let router = RadixRouter::new().unwrap();
+        router
+            .add_route(RadixNode {
+                methods: Some(RadixHttpMethod::GET),
+                ..node("users", "/api/users")
+            })
+            .unwrap();
+
+        // Higher priority and no method restriction: takes over `users`
+        let report = router
+            .dry_run_add(&RadixNode {
+                priority: 10,
+                ..node("catch", "/api/users")
+            })
+            .unwrap();
+        assert_eq!(
+            report.shadows,
+            vec![Conflict {
+                id: "users".to_string(),
+                pattern: "/api/users".to_string(),
+                shadowed_by: "catch".to_string(),
+            }]
+        );
+        assert!(report.shadowed_by.is_empty());
+
+        let report = router
+            .dry_run_add(&RadixNode {
+                methods: Some(RadixHttpMethod::GET),
+                priority: -1,
+                ..node("late", "/api/users")
+            })
+            .unwrap();
+        assert!(report.shadows.is_empty());
+        assert_eq!(report.shadowed_by[0].shadowed_by, "users");
+
+        // Different constraints leave both reachable
+        let report = router
+            .dry_run_add(&RadixNode {
+                methods: Some(RadixHttpMethod::POST),
+                priority: 10,
+                ..node("create", "/api/users")
+            })
+            .unwrap();
+        assert!(report.is_clean());
+        assert!(router.dry_run_add(&node("bad", "api")).is_err());
+        assert_eq!(router.len(), 1);
+
+        // A route the table limits would reject fails the dry run too
+        router
+            .set_table_limits(TableLimits {
+                max_routes: Some(1),
+                ..Default::default()
+            })
+            .unwrap();
+        assert!(router.dry_run_add(&node("more", "/more")).is_err());
+
+        router.set_id_policy(IdPolicy::Replace).unwrap();
+        let report = router.dry_run_add(&node("users", "/v2/users")).unwrap();
+        assert_eq!(report.replaces.unwrap().paths, vec!["/api/users"]);
+        assert_eq!(router.get_route("users").unwrap().paths, vec!["/api/users"]);
     }
 
     #[test]
@@ -237,6 +4969,7 @@ mod tests {
                 filter_fn: None,
                 priority: 0,
                 metadata: serde_json::json!({"handler": "low"}),
+                ..Default::default()
             },
             RadixNode {
                 id: "2".to_string(),
@@ -248,10 +4981,11 @@ mod tests {
                 filter_fn: None,
                 priority: 10,
                 metadata: serde_json::json!({"handler": "high"}),
+                ..Default::default()
             },
         ];
 
-        let mut router = RadixRouter::new().unwrap();
+        let router = RadixRouter::new().unwrap();
         router.add_routes(routes).unwrap();
 
         let opts = RadixMatchOpts::default();
@@ -274,9 +5008,10 @@ mod tests {
             filter_fn: None,
             priority: 0,
             metadata: serde_json::json!({"handler": "users"}),
+            ..Default::default()
         }];
 
-        let mut router = RadixRouter::new().unwrap();
+        let router = RadixRouter::new().unwrap();
         router.add_routes(routes).unwrap();
 
         // Test GET
@@ -315,9 +5050,10 @@ mod tests {
             })),
             priority: 0,
             metadata: serde_json::json!({"handler": "users_v2"}),
+            ..Default::default()
         }];
 
-        let mut router = RadixRouter::new().unwrap();
+        let router = RadixRouter::new().unwrap();
         router.add_routes(routes).unwrap();
 
         // Without version variable
@@ -360,9 +5096,10 @@ mod tests {
             filter_fn: None,
             priority: 0,
             metadata: serde_json::json!({"handler": "users"}),
+            ..Default::default()
         }];
 
-        let mut router = RadixRouter::new().unwrap();
+        let router = RadixRouter::new().unwrap();
         router.add_routes(routes).unwrap();
 
         // Without variables
@@ -392,7 +5129,7 @@ mod tests {
 
     #[test]
     fn test_add_and_delete_route() {
-        let mut router = RadixRouter::new().unwrap();
+        let router = RadixRouter::new().unwrap();
 
         // Add route
         let route = RadixNode {
@@ -405,6 +5142,7 @@ mod tests {
             filter_fn: None,
             priority: 0,
             metadata: serde_json::json!({"handler": "get_users"}),
+            ..Default::default()
         };
 
         router.add_route(route.clone()).unwrap();
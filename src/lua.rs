@@ -0,0 +1,240 @@
+//! Lua bindings with the lua-resty-radixtree API
+//!
+//! Enabled by the `lua` feature. [`open`] builds the module table and
+//! [`preload`] registers it as `resty.radixtree`, so existing Lua code runs
+//! unchanged:
+//!
+//! ```lua
+//! local radix = require("resty.radixtree")
+//! local rx = radix.new({
+//!     { paths = { "/users/:id" }, methods = { "GET" }, metadata = "user" },
+//!     { paths = "/health", handler = function(ctx) ctx.ok = true end },
+//! })
+//! local matched = {}
+//! local meta = rx:match("/users/7", { method = "GET", matched = matched })
+//! -- meta == "user", matched.id == "7"
+//! rx:dispatch("/health", {}, ctx)
+//! ```
+//!
+//! Route tables take the fields of [`RouteConfig`]; `paths`, `methods`,
+//! `hosts` and `remote_addrs` may also be a single string. `metadata` and
+//! `handler` stay Lua values and are handed back as-is. `filter_fun` is not
+//! supported.
+//!
+//! The feature embeds a vendored LuaJIT, which suits hosts that drive Lua
+//! from Rust. Loading the library into OpenResty's own LuaJIT needs mlua's
+//! `module` mode instead.
+
+use crate::config::RouteConfig;
+use crate::route::{Expr, RadixMatchOpts};
+use crate::router::RadixRouter;
+use anyhow::Context;
+use mlua::{
+    FromLua, Function, Lua, LuaSerdeExt, MultiValue, RegistryKey, Table, UserData, UserDataMethods,
+    Value,
+};
+use rustc_hash::FxHashMap;
+use std::collections::HashMap;
+
+/// Route fields that accept a single string in place of a list
+const LIST_FIELDS: &[&str] = &["paths", "methods", "hosts", "remote_addrs"];
+
+/// Router returned by `radix.new`
+pub struct LuaRouter {
+    router: RadixRouter,
+    /// Lua-side values of each route, by route id
+    routes: FxHashMap<String, LuaRoute>,
+    /// Every variable a route condition reads
+    ///
+    /// Looked up one by one, as `ngx.var` cannot be iterated.
+    var_names: Vec<String>,
+}
+
+struct LuaRoute {
+    metadata: RegistryKey,
+    handler: Option<RegistryKey>,
+}
+
+fn runtime_error(err: anyhow::Error) -> mlua::Error {
+    mlua::Error::RuntimeError(format!("{:#}", err))
+}
+
+/// Turn a string into a one-element list, leaving tables as they are
+fn to_list(value: Value) -> mlua::Result<Vec<serde_json::Value>> {
+    match value {
+        Value::String(s) => Ok(vec![serde_json::Value::String(s.to_str()?.to_string())]),
+        Value::Table(t) => t
+            .sequence_values::<Value>()
+            .map(|v| match v? {
+                Value::String(s) => Ok(serde_json::Value::String(s.to_str()?.to_string())),
+                Value::Integer(n) => Ok(n.into()),
+                other => Err(mlua::Error::RuntimeError(format!(
+                    "expected a string, got {}",
+                    other.type_name()
+                ))),
+            })
+            .collect(),
+        other => Err(mlua::Error::RuntimeError(format!(
+            "expected a string or a table, got {}",
+            other.type_name()
+        ))),
+    }
+}
+
+impl LuaRouter {
+    fn new(lua: &Lua, routes: Table) -> mlua::Result<LuaRouter> {
+        let router = RadixRouter::new().map_err(runtime_error)?;
+        let mut lua_routes = FxHashMap::default();
+        let mut nodes = Vec::new();
+
+        for (index, route) in routes.sequence_values::<Table>().enumerate() {
+            let route = route?;
+            let mut fields = serde_json::Map::new();
+            let mut metadata = Value::Nil;
+            let mut handler = None;
+            for pair in route.pairs::<String, Value>() {
+                let (key, value) = pair?;
+                match key.as_str() {
+                    "metadata" => metadata = value,
+                    "handler" => handler = Some(Function::from_lua(value, lua)?),
+                    "filter_fun" => {
+                        return Err(mlua::Error::RuntimeError(
+                            "filter_fun is not supported".to_string(),
+                        ))
+                    }
+                    field if LIST_FIELDS.contains(&field) => {
+                        fields.insert(key, to_list(value)?.into());
+                    }
+                    // Empty tables carry no array hint; every other list
+                    // field defaults to empty anyway
+                    _ if matches!(&value, Value::Table(t) if t.is_empty()) => {}
+                    _ => {
+                        fields.insert(key, lua.from_value(value)?);
+                    }
+                }
+            }
+
+            let mut config: RouteConfig = serde_json::from_value(fields.into())
+                .with_context(|| format!("Invalid route #{}", index + 1))
+                .map_err(runtime_error)?;
+            if config.id.is_empty() {
+                config.id = (index + 1).to_string();
+            }
+            let node = config.into_node().map_err(runtime_error)?;
+            lua_routes.insert(
+                node.id.clone(),
+                LuaRoute {
+                    metadata: lua.create_registry_value(metadata)?,
+                    handler: handler.map(|h| lua.create_registry_value(h)).transpose()?,
+                },
+            );
+            nodes.push(node);
+        }
+
+        let mut var_names: Vec<String> = nodes
+            .iter()
+            .flat_map(|node| {
+                let exprs = node.vars.iter().flatten().flat_map(Expr::vars);
+                exprs.chain(node.canary.iter().map(|c| c.hash_on.as_str()))
+            })
+            .map(str::to_string)
+            .collect();
+        var_names.sort_unstable();
+        var_names.dedup();
+
+        router.add_routes(nodes).map_err(runtime_error)?;
+        Ok(LuaRouter {
+            router,
+            routes: lua_routes,
+            var_names,
+        })
+    }
+
+    /// Match `path`, filling `opts.matched` when given
+    fn find(&self, path: &str, opts: Option<Table>) -> mlua::Result<Option<&LuaRoute>> {
+        let mut match_opts = RadixMatchOpts::default();
+        let mut matched = None;
+        if let Some(opts) = opts {
+            match_opts.method = opts.get("method")?;
+            match_opts.host = opts.get("host")?;
+            match_opts.remote_addr = opts.get("remote_addr")?;
+            if let Some(vars) = opts.get::<_, Option<Table>>("vars")? {
+                let mut values = HashMap::new();
+                for name in &self.var_names {
+                    if let Some(value) = vars.get::<_, Option<String>>(name.as_str())? {
+                        values.insert(name.clone(), value);
+                    }
+                }
+                match_opts.vars = Some(values);
+            }
+            matched = opts.get::<_, Option<Table>>("matched")?;
+        }
+
+        let Some(result) = self
+            .router
+            .match_route(path, &match_opts)
+            .map_err(runtime_error)?
+        else {
+            return Ok(None);
+        };
+        if let Some(matched) = matched {
+            for (key, value) in result.matched.iter() {
+                matched.set(key, value)?;
+            }
+            // lua-resty-radixtree reports these next to the params
+            matched.set("_path", result.info.pattern)?;
+            matched.set("_method", result.info.method)?;
+            matched.set("_host", result.info.host)?;
+        }
+        Ok(self.routes.get(&result.id))
+    }
+}
+
+impl UserData for LuaRouter {
+    fn add_methods<'lua, M: UserDataMethods<'lua, Self>>(methods: &mut M) {
+        // Metadata of the matching route, or nil
+        methods.add_method(
+            "match",
+            |lua, this, (path, opts): (String, Option<Table>)| match this.find(&path, opts)? {
+                Some(route) => lua.registry_value::<Value>(&route.metadata),
+                None => Ok(Value::Nil),
+            },
+        );
+
+        // Call the matching route's handler with the extra arguments;
+        // true if a route matched
+        methods.add_method(
+            "dispatch",
+            |lua, this, (path, opts, args): (String, Option<Table>, MultiValue)| {
+                let Some(route) = this.find(&path, opts)? else {
+                    return Ok((false, None));
+                };
+                let Some(handler) = &route.handler else {
+                    return Ok((false, Some("missing handler")));
+                };
+                lua.registry_value::<Function>(handler)?
+                    .call::<_, ()>(args)?;
+                Ok((true, None))
+            },
+        );
+    }
+}
+
+/// Build the `resty.radixtree` module table
+pub fn open(lua: &Lua) -> mlua::Result<Table<'_>> {
+    let module = lua.create_table()?;
+    module.set(
+        "new",
+        lua.create_function(|lua, routes: Table| match LuaRouter::new(lua, routes) {
+            Ok(router) => Ok((Some(router), None)),
+            Err(err) => Ok((None, Some(err.to_string()))),
+        })?,
+    )?;
+    Ok(module)
+}
+
+/// Make `require("resty.radixtree")` return this implementation
+pub fn preload(lua: &Lua) -> mlua::Result<()> {
+    let loaders: Table = lua.globals().get::<_, Table>("package")?.get("preload")?;
+    loaders.set("resty.radixtree", lua.create_function(|lua, ()| open(lua))?)
+}
@@ -0,0 +1,130 @@
+//! One router per tenant, chosen by request host
+
+use crate::route::{MatchResult, RadixMatchOpts, RadixNode};
+use crate::router::RadixRouter;
+use anyhow::Result;
+use arc_swap::ArcSwap;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+
+/// Routers of a multi-tenant gateway, keyed by host
+///
+/// Each tenant owns a [`RadixRouter`] registered under its domain, either
+/// exact (`api.acme.com`) or a wildcard covering its subdomains
+/// (`*.acme.com`). A request goes to the router of its host, preferring an
+/// exact key over the longest wildcard; when that router has no match, or
+/// no tenant claims the host, the shared default router is tried.
+///
+/// Tenants are reloaded independently and atomically with
+/// [`reload_tenant`](Self::reload_tenant), so one tenant's push never
+/// disturbs another's traffic. Lookups read the tenant map without locking.
+pub struct RouterManager {
+    tenants: ArcSwap<HashMap<String, Arc<RadixRouter>>>,
+    default: Arc<RadixRouter>,
+    /// Serializes changes to the tenant map
+    writer: Mutex<()>,
+}
+
+impl RouterManager {
+    /// Start with no tenants and an empty default router
+    pub fn new() -> Result<Self> {
+        Ok(Self::with_default(RadixRouter::new()?))
+    }
+
+    /// Start with no tenants and `default` as the shared router
+    pub fn with_default(default: RadixRouter) -> Self {
+        Self {
+            tenants: ArcSwap::default(),
+            default: Arc::new(default),
+            writer: Mutex::new(()),
+        }
+    }
+
+    /// Router tried for hosts no tenant claims and after tenant misses
+    pub fn default_router(&self) -> &Arc<RadixRouter> {
+        &self.default
+    }
+
+    /// Register `router` for `host`, returning the router it replaces
+    pub fn insert_tenant(&self, host: &str, router: RadixRouter) -> Option<Arc<RadixRouter>> {
+        self.update(|tenants| tenants.insert(host.to_lowercase(), Arc::new(router)))
+    }
+
+    /// Unregister the tenant of `host`, returning its router
+    pub fn remove_tenant(&self, host: &str) -> Option<Arc<RadixRouter>> {
+        self.update(|tenants| tenants.remove(&host.to_lowercase()))
+    }
+
+    /// Atomically replace every route of the tenant of `host`
+    ///
+    /// Registers the tenant first if it is new. Like
+    /// [`RadixRouter::replace_routes`], an invalid route set fails the whole
+    /// reload and leaves the tenant's routes untouched.
+    pub fn reload_tenant(&self, host: &str, routes: Vec<RadixNode>) -> Result<()> {
+        if let Some(router) = self.tenant(host) {
+            return router.replace_routes(routes);
+        }
+        let _writer = self.lock_writer();
+        // Another reload may have registered the tenant meanwhile
+        if let Some(router) = self.tenant(host) {
+            return router.replace_routes(routes);
+        }
+        let router = RadixRouter::with_routes(routes)?;
+        let mut tenants = HashMap::clone(&self.tenants.load());
+        tenants.insert(host.to_lowercase(), Arc::new(router));
+        self.tenants.store(Arc::new(tenants));
+        Ok(())
+    }
+
+    /// Router registered under exactly `host` (an exact domain or a
+    /// `*.` wildcard)
+    pub fn tenant(&self, host: &str) -> Option<Arc<RadixRouter>> {
+        self.tenants.load().get(&host.to_lowercase()).cloned()
+    }
+
+    /// Registered tenant keys, sorted
+    pub fn tenants(&self) -> Vec<String> {
+        let mut hosts: Vec<String> = self.tenants.load().keys().cloned().collect();
+        hosts.sort();
+        hosts
+    }
+
+    /// Router of the tenant that claims `host`, if any
+    ///
+    /// An exact key wins over wildcards, and a longer wildcard over a shorter
+    /// one (`*.eu.acme.com` over `*.acme.com`).
+    pub fn router_for(&self, host: &str) -> Option<Arc<RadixRouter>> {
+        let host = host.to_lowercase();
+        let tenants = self.tenants.load();
+        if let Some(router) = tenants.get(&host) {
+            return Some(Arc::clone(router));
+        }
+        host.match_indices('.')
+            .find_map(|(pos, _)| tenants.get(&format!("*{}", &host[pos..])))
+            .cloned()
+    }
+
+    /// Match a request against its tenant's router, then the default one
+    pub fn match_route(&self, path: &str, opts: &RadixMatchOpts) -> Result<Option<MatchResult>> {
+        let tenant = opts.host.as_deref().and_then(|host| self.router_for(host));
+        if let Some(router) = tenant {
+            if let Some(result) = router.match_route(path, opts)? {
+                return Ok(Some(result));
+            }
+        }
+        self.default.match_route(path, opts)
+    }
+
+    /// Copy the tenant map, change it, and publish the copy
+    fn update<T>(&self, f: impl FnOnce(&mut HashMap<String, Arc<RadixRouter>>) -> T) -> T {
+        let _writer = self.lock_writer();
+        let mut tenants = HashMap::clone(&self.tenants.load());
+        let out = f(&mut tenants);
+        self.tenants.store(Arc::new(tenants));
+        out
+    }
+
+    fn lock_writer(&self) -> MutexGuard<'_, ()> {
+        self.writer.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
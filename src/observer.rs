@@ -0,0 +1,27 @@
+//! Callbacks for route lifecycle and match events
+
+use crate::route::{MatchResult, RouteInfo};
+
+/// Observer notified about routing changes and matches
+///
+/// Register with [`RadixRouter::add_observer`](crate::RadixRouter::add_observer).
+/// Every method has an empty default, so implementors only override the
+/// events they care about.
+///
+/// Change callbacks run on the writing thread right after the new route set
+/// is published, while the router's writer lock is held; they see changes
+/// in commit order but must not mutate the router themselves. `on_match`
+/// runs on the matching thread and should stay cheap.
+pub trait RouteObserver: Send + Sync {
+    /// A route id was registered
+    fn on_route_added(&self, _route: &RouteInfo) {}
+
+    /// A route id was removed
+    fn on_route_removed(&self, _route: &RouteInfo) {}
+
+    /// A registered route changed (paths, metadata, priority, ...)
+    fn on_route_updated(&self, _old: &RouteInfo, _new: &RouteInfo) {}
+
+    /// A request matched a route
+    fn on_match(&self, _path: &str, _result: &MatchResult) {}
+}
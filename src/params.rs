@@ -0,0 +1,157 @@
+//! Storage for extracted match values
+
+use crate::capture::Captures;
+use smallvec::SmallVec;
+use std::fmt;
+
+/// Entries stored inline before spilling to the heap
+///
+/// Covers the params of all but unusually long patterns.
+const INLINE: usize = 6;
+
+/// Matched path parameters with a map-like API
+///
+/// Used for [`MatchResult::matched`](crate::MatchResult::matched) and as the
+/// caller-owned buffer of [`RadixRouter::match_route_into`](crate::RadixRouter::match_route_into).
+/// Entries live in a small inline vector and lookups scan linearly, which
+/// beats hashing for the handful of entries a route produces. Clearing
+/// keeps the allocated key and value strings, so a buffer reused across
+/// requests stops allocating once it has seen its largest match.
+#[derive(Clone, Default)]
+pub struct Params {
+    /// Slots beyond `len` are spare storage kept for reuse
+    entries: SmallVec<[(String, String); INLINE]>,
+    len: usize,
+}
+
+impl Params {
+    /// Create an empty buffer
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Value stored under `key`
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.iter().find(|(k, _)| *k == key).map(|(_, v)| v)
+    }
+
+    /// Whether a value is stored under `key`
+    pub fn contains_key(&self, key: &str) -> bool {
+        self.get(key).is_some()
+    }
+
+    /// Number of stored entries
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Whether the buffer holds no entries
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Entries as `(key, value)`, in insertion order
+    pub fn iter(&self) -> ParamsIter<'_> {
+        ParamsIter {
+            inner: self.entries[..self.len].iter(),
+        }
+    }
+
+    /// Remove all entries, keeping the storage
+    pub fn clear(&mut self) {
+        self.len = 0;
+    }
+
+    /// Store `value` under `key`, replacing an existing entry
+    pub fn insert(&mut self, key: &str, value: &str) {
+        self.insert_with(key, |buf| buf.push_str(value));
+    }
+
+    /// Store the value written by `fill` into an empty string under `key`
+    pub(crate) fn insert_with(&mut self, key: &str, fill: impl FnOnce(&mut String)) {
+        let pos = match self.entries[..self.len].iter().position(|(k, _)| k == key) {
+            Some(pos) => pos,
+            None => {
+                if self.len == self.entries.len() {
+                    self.entries.push(Default::default());
+                }
+                let slot = &mut self.entries[self.len].0;
+                slot.clear();
+                slot.push_str(key);
+                self.len += 1;
+                self.len - 1
+            }
+        };
+        let value = &mut self.entries[pos].1;
+        value.clear();
+        fill(value);
+    }
+}
+
+impl fmt::Debug for Params {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
+
+impl PartialEq for Params {
+    /// Same entries, regardless of order
+    fn eq(&self, other: &Self) -> bool {
+        self.len == other.len && self.iter().all(|(k, v)| other.get(k) == Some(v))
+    }
+}
+
+impl Eq for Params {}
+
+impl std::ops::Index<&str> for Params {
+    type Output = str;
+
+    /// Value stored under `key`
+    ///
+    /// # Panics
+    ///
+    /// Panics if `key` is not present.
+    fn index(&self, key: &str) -> &str {
+        self.get(key)
+            .unwrap_or_else(|| panic!("no matched value for key {:?}", key))
+    }
+}
+
+impl<'a> IntoIterator for &'a Params {
+    type Item = (&'a str, &'a str);
+    type IntoIter = ParamsIter<'a>;
+
+    fn into_iter(self) -> ParamsIter<'a> {
+        self.iter()
+    }
+}
+
+/// Iterator over the entries of [`Params`]
+#[derive(Debug, Clone)]
+pub struct ParamsIter<'a> {
+    inner: std::slice::Iter<'a, (String, String)>,
+}
+
+impl<'a> Iterator for ParamsIter<'a> {
+    type Item = (&'a str, &'a str);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next().map(|(k, v)| (k.as_str(), v.as_str()))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+impl ExactSizeIterator for ParamsIter<'_> {}
+
+impl<'p> Captures<'p> for Params {
+    fn param(&mut self, name: &str, value: &'p str) {
+        self.insert(name, value);
+    }
+
+    fn clear(&mut self) {
+        Params::clear(self);
+    }
+}
@@ -0,0 +1,328 @@
+//! Compiled path patterns for param extraction
+
+use anyhow::Result;
+use smallvec::SmallVec;
+use std::borrow::Cow;
+use std::sync::Arc;
+
+/// Rewrite a matchit/axum path pattern into this crate's syntax
+///
+/// `{name}` segments become `:name` and `{*name}` becomes `*name`; `{{` and
+/// `}}` are literal braces. Paths without braces are returned as they are,
+/// so native patterns pass through unchanged. Routes are converted this way
+/// on insert; braces anywhere but around a whole segment are rejected.
+pub fn convert_matchit_path(path: &str) -> Result<Cow<'_, str>> {
+    if !path.contains(['{', '}']) {
+        return Ok(Cow::Borrowed(path));
+    }
+    let segments = path
+        .split('/')
+        .map(|segment| {
+            let param = segment.strip_prefix('{').and_then(|s| s.strip_suffix('}'));
+            if let Some(name) = param.filter(|name| !name.contains(['{', '}'])) {
+                return match name.strip_prefix('*') {
+                    Some("") => anyhow::bail!("{:?}: catch-all without a name", path),
+                    Some(rest) => Ok(format!("*{}", rest)),
+                    None if name.is_empty() => anyhow::bail!("{:?}: param without a name", path),
+                    None => Ok(format!(":{}", name)),
+                };
+            }
+            let mut literal = String::with_capacity(segment.len());
+            let mut chars = segment.chars();
+            while let Some(c) = chars.next() {
+                if matches!(c, '{' | '}') && chars.next() != Some(c) {
+                    anyhow::bail!(
+                        "{:?}: '{}' must wrap a whole segment or be doubled",
+                        path,
+                        c
+                    );
+                }
+                literal.push(c);
+            }
+            Ok(literal)
+        })
+        .collect::<Result<Vec<_>>>()?;
+    Ok(Cow::Owned(segments.join("/")))
+}
+
+/// One `/`-separated piece of a path pattern
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum Segment {
+    /// Must equal the request segment
+    Literal(String),
+    /// `:name`: any non-empty segment
+    Param,
+    /// `*name`: the rest of the path, slashes included (last segment only)
+    Wildcard,
+    /// `**name`: zero or more whole segments, anywhere in the path
+    Glob,
+}
+
+impl Segment {
+    /// Whether a request segment fits; wildcards accept anything
+    fn accepts(&self, part: &str) -> bool {
+        match self {
+            Segment::Literal(literal) => part == literal,
+            Segment::Param => !part.is_empty(),
+            Segment::Wildcard | Segment::Glob => true,
+        }
+    }
+}
+
+/// Segment-matching program for a path with params or wildcards
+///
+/// Matching walks the request path once, comparing literal segments and
+/// slicing param values out in place, so no regex engine is involved.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub(crate) struct PathPattern {
+    segments: Vec<Segment>,
+    /// Capture names in pattern order (`:ext` for an unnamed wildcard,
+    /// `:glob` for an unnamed `**`)
+    names: Vec<String>,
+    /// Index of the `**` segment, if any
+    glob: Option<usize>,
+}
+
+impl PathPattern {
+    /// Compile a path like `/users/:id/files/*path`
+    ///
+    /// Expects a path accepted by `validate_path`: params and wildcards take
+    /// whole segments, a wildcard comes last and there is at most one `**`
+    /// (never alongside a wildcard).
+    pub fn compile(path: &str) -> Self {
+        let mut segments = Vec::new();
+        let mut names = Vec::new();
+        let mut glob = None;
+        for part in path.split('/') {
+            if let Some(name) = part.strip_prefix(':') {
+                names.push(name.to_string());
+                segments.push(Segment::Param);
+            } else if let Some(name) = part.strip_prefix("**") {
+                names.push(if name.is_empty() { ":glob" } else { name }.to_string());
+                glob = Some(segments.len());
+                segments.push(Segment::Glob);
+            } else if let Some(name) = part.strip_prefix('*') {
+                names.push(if name.is_empty() { ":ext" } else { name }.to_string());
+                segments.push(Segment::Wildcard);
+            } else {
+                segments.push(Segment::Literal(part.to_string()));
+            }
+        }
+        Self {
+            segments,
+            names,
+            glob,
+        }
+    }
+
+    /// Capture names in pattern order
+    pub fn names(&self) -> &[String] {
+        &self.names
+    }
+
+    /// Match the whole of `path`, handing each captured value to `capture`
+    ///
+    /// Values are reported in `names` order. Returns `false` (possibly after
+    /// reporting some values) if the path does not match.
+    pub fn captures<'p>(&self, path: &'p str, mut capture: impl FnMut(usize, &'p str)) -> bool {
+        if let Some(glob) = self.glob {
+            return self.captures_glob(glob, path, capture);
+        }
+        let mut rest = path;
+        let mut index = 0;
+        let last = self.segments.len() - 1;
+        for (i, segment) in self.segments.iter().enumerate() {
+            if *segment == Segment::Wildcard {
+                capture(index, rest);
+                return true;
+            }
+
+            let part = if i == last {
+                if rest.contains('/') {
+                    return false;
+                }
+                rest
+            } else {
+                match rest.split_once('/') {
+                    Some((part, tail)) => {
+                        rest = tail;
+                        part
+                    }
+                    None => return false,
+                }
+            };
+
+            match segment {
+                Segment::Literal(literal) if part != literal => return false,
+                Segment::Param if part.is_empty() => return false,
+                Segment::Param => {
+                    capture(index, part);
+                    index += 1;
+                }
+                _ => {}
+            }
+        }
+        true
+    }
+
+    /// [`captures`](Self::captures) for a pattern with a `**` segment
+    ///
+    /// The segments before the glob are matched from the start of the path
+    /// and those after it from the end; the glob takes whatever is between,
+    /// reported as one value without the outer slashes (empty if nothing).
+    fn captures_glob<'p>(
+        &self,
+        glob: usize,
+        path: &'p str,
+        mut capture: impl FnMut(usize, &'p str),
+    ) -> bool {
+        fn fixed<'p>(
+            segments: &[Segment],
+            parts: &[&'p str],
+            index: &mut usize,
+            capture: &mut impl FnMut(usize, &'p str),
+        ) -> bool {
+            for (segment, part) in segments.iter().zip(parts) {
+                if !segment.accepts(part) {
+                    return false;
+                }
+                if *segment == Segment::Param {
+                    capture(*index, part);
+                    *index += 1;
+                }
+            }
+            true
+        }
+
+        let parts: SmallVec<[&'p str; 16]> = path.split('/').collect();
+        let after = &self.segments[glob + 1..];
+        if parts.len() < glob + after.len() {
+            return false;
+        }
+        let tail = parts.len() - after.len();
+
+        let mut index = 0;
+        if !fixed(
+            &self.segments[..glob],
+            &parts[..glob],
+            &mut index,
+            &mut capture,
+        ) {
+            return false;
+        }
+        let skipped = &parts[glob..tail];
+        let value = match (skipped.first(), skipped.last()) {
+            (Some(first), Some(last)) => {
+                // Parts borrow from `path`, so their offsets delimit the span
+                let start = first.as_ptr() as usize - path.as_ptr() as usize;
+                let end = last.as_ptr() as usize - path.as_ptr() as usize + last.len();
+                &path[start..end]
+            }
+            _ => "",
+        };
+        capture(index, value);
+        index += 1;
+        fixed(after, &parts[tail..], &mut index, &mut capture)
+    }
+
+    /// Whether `path` matches, without reporting values
+    pub fn is_match(&self, path: &str) -> bool {
+        self.captures(path, |_, _| {})
+    }
+
+    /// Whether a path already split on `/` matches
+    fn matches_parts(&self, parts: &[&str]) -> bool {
+        if let Some(glob) = self.glob {
+            let after = &self.segments[glob + 1..];
+            return parts.len() >= glob + after.len()
+                && self.segments[..glob]
+                    .iter()
+                    .zip(parts)
+                    .all(|(segment, part)| segment.accepts(part))
+                && after
+                    .iter()
+                    .zip(&parts[parts.len() - after.len()..])
+                    .all(|(segment, part)| segment.accepts(part));
+        }
+        let fixed = match self.segments.last() {
+            Some(Segment::Wildcard) => {
+                if parts.len() < self.segments.len() {
+                    return false;
+                }
+                &self.segments[..self.segments.len() - 1]
+            }
+            _ => {
+                if parts.len() != self.segments.len() {
+                    return false;
+                }
+                &self.segments[..]
+            }
+        };
+        fixed
+            .iter()
+            .zip(parts)
+            .all(|(segment, part)| segment.accepts(part))
+    }
+}
+
+/// Patterns of the param routes sharing one tree key, evaluated together
+///
+/// Dense APIs put many param routes under the same static prefix, often
+/// with the same pattern repeated across methods or hosts. The set splits
+/// the request path once and checks each distinct pattern once, so a
+/// lookup learns which routes of the bucket fit the path before running
+/// any of their other checks.
+#[derive(Debug)]
+pub(crate) struct PatternSet {
+    /// Distinct patterns of the bucket
+    patterns: Vec<Arc<PathPattern>>,
+    /// Per route of the bucket, its entry in `patterns` (`None`: no params)
+    slots: Vec<Option<usize>>,
+}
+
+impl PatternSet {
+    /// Build a set from the patterns of a bucket, in bucket order
+    pub fn new<'a>(routes: impl IntoIterator<Item = Option<&'a Arc<PathPattern>>>) -> Self {
+        let mut patterns: Vec<Arc<PathPattern>> = Vec::new();
+        let mut seen = std::collections::HashMap::new();
+        let slots = routes
+            .into_iter()
+            .map(|pattern| {
+                let pattern = pattern?;
+                Some(*seen.entry(Arc::clone(pattern)).or_insert_with(|| {
+                    patterns.push(Arc::clone(pattern));
+                    patterns.len() - 1
+                }))
+            })
+            .collect();
+        Self { patterns, slots }
+    }
+
+    /// Which routes of the bucket accept `path`, in bucket order
+    ///
+    /// Routes without params always accept it.
+    pub fn matches(&self, path: &str) -> SetMatches {
+        let parts: SmallVec<[&str; 16]> = path.split('/').collect();
+        let hits: SmallVec<[bool; 16]> = self
+            .patterns
+            .iter()
+            .map(|pattern| pattern.matches_parts(&parts))
+            .collect();
+        SetMatches(
+            self.slots
+                .iter()
+                .map(|slot| slot.is_none_or(|i| hits[i]))
+                .collect(),
+        )
+    }
+}
+
+/// Verdicts of [`PatternSet::matches`], indexed like the bucket
+pub(crate) struct SetMatches(SmallVec<[bool; 16]>);
+
+impl SetMatches {
+    /// Whether the route at `index` fits the request path
+    pub fn accepts(&self, index: usize) -> bool {
+        self.0.get(index).copied().unwrap_or(true)
+    }
+}
@@ -0,0 +1,131 @@
+//! One router per listener of a proxy
+
+use crate::route::{MatchResult, RadixMatchOpts, RadixNode};
+use crate::router::RadixRouter;
+use anyhow::{Context, Result};
+use arc_swap::ArcSwap;
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+
+/// Listener a proxy accepts requests on: a local port and a protocol
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Listener {
+    /// Local port
+    pub port: u16,
+    /// Protocol name, lowercased (`http`, `https`, `h2c`, ...)
+    pub protocol: String,
+}
+
+impl Listener {
+    /// Listener on `port` speaking `protocol`
+    pub fn new(port: u16, protocol: &str) -> Self {
+        Self {
+            port,
+            protocol: protocol.to_lowercase(),
+        }
+    }
+}
+
+impl std::fmt::Display for Listener {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}/{}", self.protocol, self.port)
+    }
+}
+
+/// Independent routers of a proxy terminating several listeners
+///
+/// Each [`Listener`] gets its own [`RadixRouter`], and requests are matched
+/// through [`match_route`](Self::match_route) with the listener that
+/// accepted them. Listeners are added, removed and reloaded independently;
+/// lookups read the listener map without locking.
+#[derive(Default)]
+pub struct ListenerRegistry {
+    routers: ArcSwap<BTreeMap<Listener, Arc<RadixRouter>>>,
+    /// Serializes changes to the listener map
+    writer: Mutex<()>,
+}
+
+impl ListenerRegistry {
+    /// Start with no listeners
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register `router` for `listener`, returning the router it replaces
+    pub fn insert(&self, listener: Listener, router: RadixRouter) -> Option<Arc<RadixRouter>> {
+        self.update(|routers| routers.insert(listener, Arc::new(router)))
+    }
+
+    /// Unregister `listener`, returning its router
+    pub fn remove(&self, listener: &Listener) -> Option<Arc<RadixRouter>> {
+        self.update(|routers| routers.remove(listener))
+    }
+
+    /// Router of `listener`
+    pub fn get(&self, listener: &Listener) -> Option<Arc<RadixRouter>> {
+        self.routers.load().get(listener).cloned()
+    }
+
+    /// Registered listeners, ordered by port then protocol
+    pub fn listeners(&self) -> Vec<Listener> {
+        self.routers.load().keys().cloned().collect()
+    }
+
+    /// Atomically replace every route of `listener`, registering it if new
+    ///
+    /// Like [`RadixRouter::replace_routes`], an invalid route set fails the
+    /// whole reload and leaves the listener's routes untouched.
+    pub fn reload(&self, listener: &Listener, routes: Vec<RadixNode>) -> Result<()> {
+        if let Some(router) = self.get(listener) {
+            return router.replace_routes(routes);
+        }
+        let _writer = self.lock_writer();
+        // Another reload may have registered the listener meanwhile
+        if let Some(router) = self.get(listener) {
+            return router.replace_routes(routes);
+        }
+        let router = RadixRouter::with_routes(routes)?;
+        let mut routers = BTreeMap::clone(&self.routers.load());
+        routers.insert(listener.clone(), Arc::new(router));
+        self.routers.store(Arc::new(routers));
+        Ok(())
+    }
+
+    /// Match a request accepted on `listener`
+    ///
+    /// `opts.server_port` defaults to the listener's port, so routes with
+    /// `server_ports` work unchanged. Fails if the listener is not
+    /// registered.
+    pub fn match_route(
+        &self,
+        listener: &Listener,
+        path: &str,
+        opts: &RadixMatchOpts,
+    ) -> Result<Option<MatchResult>> {
+        let router = self
+            .get(listener)
+            .with_context(|| format!("No router for listener {}", listener))?;
+        let opts = match opts.server_port {
+            Some(_) => Cow::Borrowed(opts),
+            None => Cow::Owned(RadixMatchOpts {
+                server_port: Some(listener.port),
+                ..opts.clone()
+            }),
+        };
+        router.match_route(path, &opts)
+    }
+
+    /// Copy the listener map, change it, and publish the copy
+    fn update<T>(&self, f: impl FnOnce(&mut BTreeMap<Listener, Arc<RadixRouter>>) -> T) -> T {
+        let _writer = self.lock_writer();
+        let mut routers = BTreeMap::clone(&self.routers.load());
+        let out = f(&mut routers);
+        self.routers.store(Arc::new(routers));
+        out
+    }
+
+    fn lock_writer(&self) -> MutexGuard<'_, ()> {
+        self.writer.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
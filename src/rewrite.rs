@@ -0,0 +1,124 @@
+//! Upstream path rewriting for matched routes
+
+use crate::params::Params;
+use anyhow::Result;
+
+/// Compiled `strip_prefix` / `rewrite` directive of a route path
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum Rewrite {
+    /// Drop the literal prefix of the route path
+    StripPrefix,
+    /// Fill a template with captured values
+    Template {
+        /// Template as written, e.g. `/internal/$1`
+        source: String,
+        pieces: Vec<Piece>,
+    },
+}
+
+/// Part of a rewrite template
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum Piece {
+    Literal(String),
+    /// Captured value, by capture name
+    Capture(String),
+}
+
+impl Rewrite {
+    /// Compile a template against the capture names of one route path
+    ///
+    /// `$1`..`$9` refer to captures in pattern order, `${name}` to a capture
+    /// by name and `$$` to a literal `$`. References to captures the path
+    /// doesn't have are rejected.
+    pub fn template(source: &str, names: &[&str]) -> Result<Self> {
+        let mut pieces = Vec::new();
+        let mut literal = String::new();
+        let mut chars = source.char_indices().peekable();
+        while let Some((pos, c)) = chars.next() {
+            if c != '$' {
+                literal.push(c);
+                continue;
+            }
+            let name = match chars.next() {
+                Some((_, '$')) => {
+                    literal.push('$');
+                    continue;
+                }
+                Some((_, digit @ '1'..='9')) => {
+                    let index = digit as usize - '1' as usize;
+                    match names.get(index) {
+                        Some(name) => name.to_string(),
+                        None => anyhow::bail!(
+                            "{:?}: ${} refers past the {} capture(s) of the path",
+                            source,
+                            digit,
+                            names.len()
+                        ),
+                    }
+                }
+                Some((start, '{')) => {
+                    let rest = &source[start + 1..];
+                    let Some(end) = rest.find('}') else {
+                        anyhow::bail!("{:?}: unclosed '${{' (at byte {})", source, pos);
+                    };
+                    let name = &rest[..end];
+                    if !names.contains(&name) {
+                        anyhow::bail!("{:?}: unknown capture '{}'", source, name);
+                    }
+                    for _ in 0..=name.chars().count() {
+                        chars.next();
+                    }
+                    name.to_string()
+                }
+                _ => anyhow::bail!(
+                    "{:?}: '$' must be followed by 1-9, '{{name}}' or '$' (at byte {})",
+                    source,
+                    pos
+                ),
+            };
+            if !literal.is_empty() {
+                pieces.push(Piece::Literal(std::mem::take(&mut literal)));
+            }
+            pieces.push(Piece::Capture(name));
+        }
+        if !literal.is_empty() {
+            pieces.push(Piece::Literal(literal));
+        }
+        Ok(Rewrite::Template {
+            source: source.to_string(),
+            pieces,
+        })
+    }
+
+    /// Template as written, `None` for `StripPrefix`
+    pub fn source(&self) -> Option<&str> {
+        match self {
+            Rewrite::StripPrefix => None,
+            Rewrite::Template { source, .. } => Some(source),
+        }
+    }
+
+    /// Upstream path for a request to `path` that matched a route keyed by
+    /// `prefix`, with `matched` captured
+    pub fn apply(&self, prefix: &str, path: &str, matched: &Params) -> String {
+        match self {
+            Rewrite::StripPrefix => {
+                // Keep the slash that separates the prefix from the rest
+                let prefix = prefix.strip_suffix('/').unwrap_or(prefix);
+                let rest = path.strip_prefix(prefix).unwrap_or(path);
+                if rest.starts_with('/') {
+                    rest.to_string()
+                } else {
+                    format!("/{}", rest)
+                }
+            }
+            Rewrite::Template { pieces, .. } => pieces
+                .iter()
+                .map(|piece| match piece {
+                    Piece::Literal(literal) => literal.as_str(),
+                    Piece::Capture(name) => matched.get(name).unwrap_or(""),
+                })
+                .collect(),
+        }
+    }
+}
@@ -1,7 +1,21 @@
 //! Route definitions and data structures
 
+use crate::bucket::Bucketer;
+use crate::config::{expr_from_value, expr_to_value};
+use crate::ip::IpTrie;
+use crate::params::Params;
+use crate::pattern::{convert_matchit_path, PathPattern};
+use crate::rewrite::Rewrite;
+use crate::snapshot::ExprRecord;
 use bitflags::bitflags;
-use std::{collections::HashMap, sync::Arc};
+use std::{
+    any::Any,
+    borrow::Cow,
+    cmp::Reverse,
+    collections::HashMap,
+    sync::Arc,
+    time::{Duration, SystemTime},
+};
 
 bitflags! {
     /// HTTP methods represented as bit flags
@@ -17,11 +31,29 @@ bitflags! {
         const CONNECT = 1 << 7;
         const TRACE   = 1 << 8;
         const PURGE   = 1 << 9;
+        /// Any non-standard method (`REPORT`, `LOCK`, vendor verbs)
+        ///
+        /// To accept only specific ones, list them in `RadixNode::custom_methods`.
+        const OTHER   = 1 << 10;
+
+        /// Every method, the standard ones as well as `OTHER`
+        const ALL = Self::GET.bits()
+            | Self::POST.bits()
+            | Self::PUT.bits()
+            | Self::DELETE.bits()
+            | Self::PATCH.bits()
+            | Self::HEAD.bits()
+            | Self::OPTIONS.bits()
+            | Self::CONNECT.bits()
+            | Self::TRACE.bits()
+            | Self::PURGE.bits()
+            | Self::OTHER.bits();
     }
 }
 
 impl RadixHttpMethod {
     /// Parse HTTP method from string
+    #[allow(clippy::should_implement_trait)]
     pub fn from_str(s: &str) -> Option<Self> {
         match s.to_uppercase().as_str() {
             "GET" => Some(RadixHttpMethod::GET),
@@ -60,10 +92,10 @@ pub struct HostPattern {
 impl HostPattern {
     /// Create a new host pattern
     pub fn new(pattern: &str) -> Self {
-        if pattern.starts_with('*') {
+        if let Some(suffix) = pattern.strip_prefix('*') {
             Self {
                 is_wildcard: true,
-                pattern: pattern[1..].to_lowercase(),
+                pattern: suffix.to_lowercase(),
             }
         } else {
             Self {
@@ -84,6 +116,16 @@ impl HostPattern {
     }
 }
 
+impl std::fmt::Display for HostPattern {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.is_wildcard {
+            write!(f, "*{}", self.pattern)
+        } else {
+            f.write_str(&self.pattern)
+        }
+    }
+}
+
 /// Expression for variable matching (simplified version)
 #[derive(Debug, Clone)]
 pub enum Expr {
@@ -98,7 +140,16 @@ pub enum Expr {
     /// In array: var in [values]
     In(String, Vec<String>),
     /// Regex match: var =~ pattern
+    ///
+    /// Patterns from configs, snapshots and [`expr!`] are compiled with the
+    /// size and nesting guards of [`compile_regex`].
     Regex(String, regex::Regex),
+    /// Sticky bucket: the var's [`Bucketer::default`] bucket (0-99) falls in the range
+    Bucket(String, std::ops::Range<u32>),
+    /// Every expression holds (true when empty)
+    All(Vec<Expr>),
+    /// At least one expression holds (false when empty)
+    Any(Vec<Expr>),
 }
 
 impl Expr {
@@ -111,6 +162,10 @@ impl Expr {
             Expr::Regex(key, pattern) => {
                 vars.get(key).map(|v| pattern.is_match(v)).unwrap_or(false)
             }
+            Expr::Bucket(key, range) => vars
+                .get(key)
+                .map(|v| range.contains(&Bucketer::default().bucket(v)))
+                .unwrap_or(false),
             Expr::Gt(key, value) => vars
                 .get(key)
                 .and_then(|v| {
@@ -127,34 +182,545 @@ impl Expr {
                     Some(vn < val)
                 })
                 .unwrap_or(false),
+            Expr::All(exprs) => exprs.iter().all(|expr| expr.eval(vars)),
+            Expr::Any(exprs) => exprs.iter().any(|expr| expr.eval(vars)),
+        }
+    }
+}
+
+/// Longest regex source accepted in a var expression, in bytes
+pub const MAX_REGEX_LEN: usize = 1024;
+
+/// Deepest nesting of groups and repetitions accepted in a regex
+const REGEX_NEST_LIMIT: u32 = 16;
+
+/// Largest compiled program of a regex, in bytes
+const REGEX_SIZE_LIMIT: usize = 256 * 1024;
+
+/// Largest lazy DFA cache of a regex, in bytes
+const REGEX_DFA_SIZE_LIMIT: usize = 1024 * 1024;
+
+/// Compile a user-supplied regex with guards against pathological patterns
+///
+/// Route configs may come from untrusted tenants, so besides being valid a
+/// pattern must be at most [`MAX_REGEX_LEN`] bytes, nest at most 16 levels
+/// deep and compile to a program of at most 256 KiB, which bounds the
+/// memory and build time one expression can take (repetition counts such
+/// as `a{1000}{1000}` expand during compilation).
+pub fn compile_regex(pattern: &str) -> anyhow::Result<regex::Regex> {
+    if pattern.len() > MAX_REGEX_LEN {
+        anyhow::bail!(
+            "regex is {} bytes, at most {} allowed",
+            pattern.len(),
+            MAX_REGEX_LEN
+        );
+    }
+    Ok(regex::RegexBuilder::new(pattern)
+        .nest_limit(REGEX_NEST_LIMIT)
+        .size_limit(REGEX_SIZE_LIMIT)
+        .dfa_size_limit(REGEX_DFA_SIZE_LIMIT)
+        .build()?)
+}
+
+/// Human-readable formats (JSON, YAML) use the `[var, op, value]` form of
+/// [`VarConfig`](crate::VarConfig), with `["AND", ...]` and `["OR", ...]`
+/// for groups; binary ones a compact tagged form. Regexes are stored as
+/// their source and recompiled on load.
+impl serde::Serialize for Expr {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        if serializer.is_human_readable() {
+            expr_to_value(self).serialize(serializer)
+        } else {
+            ExprRecord::from(self).serialize(serializer)
         }
     }
 }
 
+impl<'de> serde::Deserialize<'de> for Expr {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        use serde::de::Error;
+        let expr = if deserializer.is_human_readable() {
+            expr_from_value(serde_json::Value::deserialize(deserializer)?)
+        } else {
+            Expr::try_from(ExprRecord::deserialize(deserializer)?)
+        };
+        expr.map_err(|err| D::Error::custom(format!("{:#}", err)))
+    }
+}
+
+impl Expr {
+    /// Regex condition for [`expr!`], which has no way to report errors
+    #[doc(hidden)]
+    pub fn regex(var: &str, pattern: &str) -> Expr {
+        let regex = compile_regex(pattern)
+            .unwrap_or_else(|err| panic!("Invalid regex in expr! for {}: {:#}", var, err));
+        Expr::Regex(var.to_string(), regex)
+    }
+
+    /// Visit the names of the variables the expression reads, for renaming
+    pub(crate) fn for_each_var_mut(&mut self, f: &mut impl FnMut(&mut String)) {
+        match self {
+            Expr::Eq(k, _)
+            | Expr::Neq(k, _)
+            | Expr::Gt(k, _)
+            | Expr::Lt(k, _)
+            | Expr::In(k, _)
+            | Expr::Regex(k, _)
+            | Expr::Bucket(k, _) => f(k),
+            Expr::All(exprs) | Expr::Any(exprs) => {
+                exprs.iter_mut().for_each(|expr| expr.for_each_var_mut(f))
+            }
+        }
+    }
+
+    /// Total length of the regex sources in the expression, in bytes
+    pub(crate) fn regex_bytes(&self) -> usize {
+        match self {
+            Expr::Regex(_, regex) => regex.as_str().len(),
+            Expr::All(exprs) | Expr::Any(exprs) => exprs.iter().map(Expr::regex_bytes).sum(),
+            _ => 0,
+        }
+    }
+
+    /// Names of the variables the expression reads
+    #[cfg(feature = "lua")]
+    pub(crate) fn vars(&self) -> Vec<&str> {
+        match self {
+            Expr::Eq(k, _)
+            | Expr::Neq(k, _)
+            | Expr::Gt(k, _)
+            | Expr::Lt(k, _)
+            | Expr::In(k, _)
+            | Expr::Regex(k, _)
+            | Expr::Bucket(k, _) => vec![k],
+            Expr::All(exprs) | Expr::Any(exprs) => exprs.iter().flat_map(Expr::vars).collect(),
+        }
+    }
+
+    /// Feed the expression into a hasher (regexes hash by pattern source)
+    pub(crate) fn hash_into<H: std::hash::Hasher>(&self, state: &mut H) {
+        use std::hash::Hash;
+
+        std::mem::discriminant(self).hash(state);
+        match self {
+            Expr::Eq(k, v) | Expr::Neq(k, v) | Expr::Gt(k, v) | Expr::Lt(k, v) => {
+                k.hash(state);
+                v.hash(state);
+            }
+            Expr::In(k, values) => {
+                k.hash(state);
+                values.hash(state);
+            }
+            Expr::Regex(k, re) => {
+                k.hash(state);
+                re.as_str().hash(state);
+            }
+            Expr::Bucket(k, range) => {
+                k.hash(state);
+                range.hash(state);
+            }
+            Expr::All(exprs) | Expr::Any(exprs) => {
+                exprs.len().hash(state);
+                exprs.iter().for_each(|expr| expr.hash_into(state));
+            }
+        }
+    }
+}
+
+/// Build an [`Expr`] from an infix condition
+///
+/// Conditions compare a var (an identifier) with `==`, `!=`, `>`, `<`, `=~`
+/// (a regex, which panics if invalid) or `in [..]`, and combine with `&&`,
+/// `||` and parentheses; `&&` binds tighter than `||`. Values are any
+/// expressions implementing `ToString`.
+///
+/// ```
+/// use router_radix::expr;
+/// use std::collections::HashMap;
+///
+/// let cond = expr!(env == "prod" && (region in ["us", "eu"] || tier == "vip"));
+/// let vars = HashMap::from([
+///     ("env".to_string(), "prod".to_string()),
+///     ("tier".to_string(), "vip".to_string()),
+/// ]);
+/// assert!(cond.eval(&vars));
+/// ```
+#[macro_export]
+macro_rules! expr {
+    // Split on `||`, then on `&&`, then parse each term
+    (@or [$($alts:tt)*] [$($cur:tt)*] || $($rest:tt)+) => {
+        $crate::expr!(@or [$($alts)* [$($cur)*]] [] $($rest)+)
+    };
+    (@or [$($alts:tt)*] [$($cur:tt)*] $next:tt $($rest:tt)*) => {
+        $crate::expr!(@or [$($alts)*] [$($cur)* $next] $($rest)*)
+    };
+    (@or [] [$($cur:tt)+]) => {
+        $crate::expr!(@and [] [] $($cur)+)
+    };
+    (@or [$([$($alt:tt)+])+] [$($cur:tt)+]) => {
+        $crate::Expr::Any(vec![
+            $($crate::expr!(@and [] [] $($alt)+),)+
+            $crate::expr!(@and [] [] $($cur)+)
+        ])
+    };
+    (@and [$($terms:tt)*] [$($cur:tt)*] && $($rest:tt)+) => {
+        $crate::expr!(@and [$($terms)* [$($cur)*]] [] $($rest)+)
+    };
+    (@and [$($terms:tt)*] [$($cur:tt)*] $next:tt $($rest:tt)*) => {
+        $crate::expr!(@and [$($terms)*] [$($cur)* $next] $($rest)*)
+    };
+    (@and [] [$($cur:tt)+]) => {
+        $crate::expr!(@term $($cur)+)
+    };
+    (@and [$([$($term:tt)+])+] [$($cur:tt)+]) => {
+        $crate::Expr::All(vec![
+            $($crate::expr!(@term $($term)+),)+
+            $crate::expr!(@term $($cur)+)
+        ])
+    };
+    (@term ($($inner:tt)+)) => {
+        $crate::expr!($($inner)+)
+    };
+    (@term $var:ident == $value:expr) => {
+        $crate::Expr::Eq(stringify!($var).to_string(), $value.to_string())
+    };
+    (@term $var:ident != $value:expr) => {
+        $crate::Expr::Neq(stringify!($var).to_string(), $value.to_string())
+    };
+    (@term $var:ident > $value:expr) => {
+        $crate::Expr::Gt(stringify!($var).to_string(), $value.to_string())
+    };
+    (@term $var:ident < $value:expr) => {
+        $crate::Expr::Lt(stringify!($var).to_string(), $value.to_string())
+    };
+    (@term $var:ident =~ $value:expr) => {
+        $crate::Expr::regex(stringify!($var), &$value.to_string())
+    };
+    (@term $var:ident in [$($value:expr),* $(,)?]) => {
+        $crate::Expr::In(stringify!($var).to_string(), vec![$($value.to_string()),*])
+    };
+    ($($tokens:tt)+) => {
+        $crate::expr!(@or [] [] $($tokens)+)
+    };
+}
+
+/// Build a `Vec<RadixNode>` from a compact route table
+///
+/// Each entry lists its methods (`GET|POST`, or `ANY` for all), a path
+/// literal, optional `id "..."`, `priority N` and `host "..."` clauses in
+/// that order, and the metadata after `=>`. Routes without an id get
+/// `"<METHODS> <path>"`, e.g. `"GET|POST /api/user/:id"`.
+///
+/// ```
+/// use router_radix::routes;
+/// use serde_json::json;
+///
+/// let routes = routes! {
+///     GET "/api/users" => json!({"handler": "list"}),
+///     GET|POST "/api/user/:id" priority 10 => json!({"handler": "user"}),
+///     ANY "/health" id "health" host "*.internal" => json!(null),
+/// };
+/// assert_eq!(routes[1].id, "GET|POST /api/user/:id");
+/// assert_eq!(routes[2].id, "health");
+/// ```
+#[macro_export]
+macro_rules! routes {
+    (@methods ANY) => {
+        None
+    };
+    (@methods $($method:ident)|+) => {
+        Some($($crate::RadixHttpMethod::$method)|+)
+    };
+    (@id [$id:literal] [$($method:ident)|+] $path:literal) => {
+        $id.to_string()
+    };
+    (@id [] [$($method:ident)|+] $path:literal) => {
+        format!("{} {}", [$(stringify!($method)),+].join("|"), $path)
+    };
+    (@priority) => {
+        0
+    };
+    (@priority $priority:literal) => {
+        $priority
+    };
+    (@hosts) => {
+        None
+    };
+    (@hosts $host:literal) => {
+        Some(vec![$host.to_string()])
+    };
+    ($(
+        $($method:ident)|+ $path:literal
+        $(id $id:literal)?
+        $(priority $priority:literal)?
+        $(host $host:literal)?
+        => $metadata:expr
+    ),* $(,)?) => {
+        vec![$(
+            $crate::RadixNode {
+                id: $crate::routes!(@id [$($id)?] [$($method)|+] $path),
+                paths: vec![$path.to_string()],
+                methods: $crate::routes!(@methods $($method)|+),
+                hosts: $crate::routes!(@hosts $($host)?),
+                priority: $crate::routes!(@priority $($priority)?),
+                metadata: $metadata,
+                ..::core::default::Default::default()
+            }
+        ),*]
+    };
+}
+
 /// Filter function type
 pub type FilterFn = Arc<dyn Fn(&HashMap<String, String>, &RadixMatchOpts) -> bool + Send + Sync>;
 
-/// RadixNode definition - represents a route node in the radix tree
+/// Filter that can fail, e.g. because a policy backend is unreachable
+///
+/// `Ok(false)` rejects the route like a [`FilterFn`]; errors are handled
+/// according to the route's [`FilterErrorPolicy`].
+pub type TryFilterFn =
+    Arc<dyn Fn(&HashMap<String, String>, &RadixMatchOpts) -> anyhow::Result<bool> + Send + Sync>;
+
+/// Filter over a caller-defined request context
+///
+/// Built from a closure over any `'static` type, typically the framework's
+/// own request object, which [`RadixRouter::match_route_with`] passes in.
+/// Lookups made without a context, or with a context of another type,
+/// reject the route.
+///
+/// [`RadixRouter::match_route_with`]: crate::RadixRouter::match_route_with
 #[derive(Clone)]
+pub struct ContextFilter(ErasedFilter);
+
+/// Context filter with the context type erased
+type ErasedFilter = Arc<dyn Fn(&dyn Any, &RadixMatchOpts) -> bool + Send + Sync>;
+
+impl ContextFilter {
+    pub fn new<C, F>(filter: F) -> Self
+    where
+        C: Any,
+        F: Fn(&C, &RadixMatchOpts) -> bool + Send + Sync + 'static,
+    {
+        Self(Arc::new(move |context, opts| {
+            context
+                .downcast_ref::<C>()
+                .is_some_and(|context| filter(context, opts))
+        }))
+    }
+
+    /// Whether the filter accepts the request
+    pub(crate) fn accepts(&self, context: Option<&dyn Any>, opts: &RadixMatchOpts) -> bool {
+        context.is_some_and(|context| (self.0)(context, opts))
+    }
+
+    /// Identity of the wrapped closure, for change detection
+    fn addr(&self) -> usize {
+        Arc::as_ptr(&self.0) as *const () as usize
+    }
+}
+
+impl std::fmt::Debug for ContextFilter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("ContextFilter").finish_non_exhaustive()
+    }
+}
+
+/// Ordering for routes tying on priority and path length
+///
+/// See [`RadixRouter::set_tie_breaker`](crate::RadixRouter::set_tie_breaker).
+pub type TieBreaker = Arc<dyn Fn(&RouteInfo, &RouteInfo) -> std::cmp::Ordering + Send + Sync>;
+
+/// Rewrite of the metadata a match returns
+///
+/// Called with each result about to be returned; a returned value replaces
+/// the result's metadata, `None` keeps it. See
+/// [`RadixRouter::set_metadata_hook`](crate::RadixRouter::set_metadata_hook).
+pub type MetadataHook =
+    Arc<dyn Fn(&MatchResult, &RadixMatchOpts) -> Option<serde_json::Value> + Send + Sync>;
+
+/// Metadata hook filling `{name}` placeholders from the matched values
+///
+/// Every string in the metadata is scanned, so `{"upstream":
+/// "http://{tenant}.internal"}` on `/:tenant/*` points each request at its
+/// tenant's upstream. Placeholders name path parameters; unknown ones are
+/// left as they are.
+pub fn param_template() -> MetadataHook {
+    Arc::new(|result, _| expand_placeholders(&result.metadata, &result.matched))
+}
+
+/// Copy of `value` with placeholders expanded, or `None` if it has none
+fn expand_placeholders(value: &serde_json::Value, params: &Params) -> Option<serde_json::Value> {
+    use serde_json::Value;
+
+    match value {
+        Value::String(text) => {
+            let mut expanded = String::new();
+            let mut rest = text.as_str();
+            let mut changed = false;
+            while let Some(start) = rest.find('{') {
+                let Some(len) = rest[start + 1..].find('}') else {
+                    break;
+                };
+                let end = start + len + 2;
+                match params.get(&rest[start + 1..end - 1]) {
+                    Some(param) => {
+                        expanded.push_str(&rest[..start]);
+                        expanded.push_str(param);
+                        changed = true;
+                    }
+                    None => expanded.push_str(&rest[..end]),
+                }
+                rest = &rest[end..];
+            }
+            changed.then(|| Value::String(expanded + rest))
+        }
+        Value::Array(items) => {
+            let expanded: Vec<_> = items
+                .iter()
+                .map(|item| expand_placeholders(item, params))
+                .collect();
+            expanded.iter().any(Option::is_some).then(|| {
+                let items = items.iter().zip(expanded);
+                Value::Array(
+                    items
+                        .map(|(old, new)| new.unwrap_or_else(|| old.clone()))
+                        .collect(),
+                )
+            })
+        }
+        Value::Object(fields) => {
+            let expanded: Vec<_> = fields
+                .values()
+                .map(|field| expand_placeholders(field, params))
+                .collect();
+            expanded.iter().any(Option::is_some).then(|| {
+                let fields = fields.iter().zip(expanded);
+                Value::Object(
+                    fields
+                        .map(|((key, old), new)| (key.clone(), new.unwrap_or_else(|| old.clone())))
+                        .collect(),
+                )
+            })
+        }
+        _ => None,
+    }
+}
+
+/// RadixNode definition - represents a route node in the radix tree
+///
+/// Implements `Default`, so routes can spell out only the fields they use
+/// and finish the literal with `..Default::default()`.
+#[derive(Clone, Default)]
 pub struct RadixNode {
     /// Unique route ID
     pub id: String,
     /// Path(s) for this route
     pub paths: Vec<String>,
+    /// File extensions the last path segment may end in, without the dot
+    /// (None means all)
+    ///
+    /// Compared case-insensitively; multi-part extensions such as `tar.gz`
+    /// work too. The extension found is reported under
+    /// [`EXTENSION_PARAM`]. Every path of the route must end in a wildcard.
+    pub extensions: Option<Vec<String>>,
+    /// Report the request path without the route's literal prefix as
+    /// [`MatchResult::upstream_path`] (`/api/*rest` turns `/api/users` into
+    /// `/users`)
+    pub strip_prefix: bool,
+    /// Template for [`MatchResult::upstream_path`], e.g. `/internal/$1`
+    ///
+    /// `$1`..`$9` are the captured values in pattern order, `${name}` one
+    /// by name and `$$` a literal `$`. Cannot be combined with
+    /// `strip_prefix`.
+    pub rewrite: Option<String>,
     /// Allowed HTTP methods (None means all)
     pub methods: Option<RadixHttpMethod>,
+    /// Additional non-standard methods to accept (e.g. `REPORT`, `LOCK`)
+    ///
+    /// Compared case-insensitively. Setting this restricts the route to
+    /// `methods` plus these verbs, even when `methods` is None.
+    pub custom_methods: Option<Vec<String>>,
     /// Host patterns (None means all)
     pub hosts: Option<Vec<String>>,
     /// Remote address filters (CIDR notation)
     pub remote_addrs: Option<Vec<String>>,
+    /// Allowed request schemes, e.g. `http`/`https` (None means all)
+    pub schemes: Option<Vec<String>>,
+    /// TLS SNI patterns, wildcards allowed like hosts (None means all)
+    pub snis: Option<Vec<String>>,
+    /// Listening ports this route is served on (None means all)
+    pub server_ports: Option<Vec<u16>>,
+    /// Route becomes active at this time (inclusive)
+    pub valid_from: Option<SystemTime>,
+    /// Route stops matching at this time (exclusive)
+    pub valid_until: Option<SystemTime>,
+    /// Time to live, counted from insertion
+    pub ttl: Option<Duration>,
     /// Variable expressions
     pub vars: Option<Vec<Expr>>,
     /// Custom filter function
     pub filter_fn: Option<FilterFn>,
+    /// Names of filters registered with `RadixRouter::register_filter`
+    ///
+    /// Evaluated in order after `filter_fn`; the first filter rejecting the
+    /// request ends the chain. Lets concerns such as auth, tenancy and
+    /// feature flags be attached independently.
+    pub filters: Vec<String>,
+    /// Filter over the request context, evaluated after `filters`
+    pub context_filter: Option<ContextFilter>,
+    /// Fallible filter, evaluated after `context_filter`
+    pub try_filter_fn: Option<TryFilterFn>,
+    /// What a `try_filter_fn` error means for the match
+    pub filter_errors: FilterErrorPolicy,
     /// Route priority (higher = more important)
     pub priority: i32,
+    /// Priority overrides for individual entries of `paths`
+    ///
+    /// Lets one alias of a route, e.g. its exact path, outrank another, e.g.
+    /// its wildcard, without duplicating the node. Paths not listed use
+    /// `priority`; listing a path the route does not have is an error.
+    pub path_priorities: Option<Vec<(String, i32)>>,
+    /// Relative share among weighted routes tying on path and priority
+    ///
+    /// When several weighted routes of equal rank accept a request, one of
+    /// them is picked with probability proportional to its weight, seeded by
+    /// the [`WEIGHT_SEED_VAR`] request var when present. Unweighted routes
+    /// keep the plain first-match behaviour.
+    pub weight: Option<u32>,
+    /// Restrict the route to a stable percentage of clients
+    pub canary: Option<Canary>,
+    /// Mirror-only route: never wins a match, but is reported in
+    /// `MatchResult::shadows` whenever it accepts the request
+    pub shadow: bool,
     /// Metadata associated with the route
     pub metadata: serde_json::Value,
+    /// Metadata overrides by request method, checked in order
+    ///
+    /// The first entry whose method set contains the request method supplies
+    /// the result metadata (e.g. `GET` to a read upstream, `POST | PUT` to a
+    /// write upstream); other methods fall back to `metadata`. This does not
+    /// restrict which methods match; use `methods` for that.
+    pub method_metadata: Option<Vec<(RadixHttpMethod, serde_json::Value)>>,
+    /// Free-form labels for bulk operations (e.g. `team:payments`)
+    pub tags: Vec<String>,
+    /// Name of a template registered with
+    /// [`RadixRouter::register_template`](crate::RadixRouter::register_template)
+    /// this route inherits unset fields from (see [`RadixNode::inherit`])
+    pub extends: Option<String>,
+}
+
+/// Percentage-based canary release settings
+///
+/// The request var named by `hash_on` is hashed into 100 buckets and the
+/// route only matches requests landing in the first `percent` of them, so
+/// the same clients stay in the canary across requests. Raising `percent`
+/// only adds clients; nobody already in the canary drops out. Requests
+/// without the var never match the canary route.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Canary {
+    /// Share of clients routed to the canary (0-100)
+    pub percent: u8,
+    /// Request var to hash, e.g. `user_id`
+    pub hash_on: String,
 }
 
 /// Match options for route matching (input only)
@@ -166,8 +732,100 @@ pub struct RadixMatchOpts {
     pub host: Option<String>,
     /// Remote address
     pub remote_addr: Option<String>,
+    /// Request scheme (`http`, `https`, ...)
+    pub scheme: Option<String>,
+    /// TLS server name indication sent by the client
+    pub sni: Option<String>,
+    /// Local port of the listener that accepted the request
+    pub server_port: Option<u16>,
+    /// Request time for route validity windows (defaults to the current time)
+    pub now: Option<SystemTime>,
     /// Request variables
+    ///
+    /// Lookups add the request data under nginx names, unless already
+    /// set here: `request_method`, `host`, `remote_addr`, `scheme`,
+    /// `server_port`, `http_*`, `arg_*` and `cookie_*`. Expressions written
+    /// for lua-resty-radixtree (APISIX) therefore work unchanged.
     pub vars: Option<HashMap<String, String>>,
+    /// Request headers, exposed as `http_<name>` (lowercased, `-` as `_`)
+    pub headers: Option<HashMap<String, String>>,
+    /// Query arguments, exposed as `arg_<name>`
+    pub args: Option<HashMap<String, String>>,
+    /// Cookies, exposed as `cookie_<name>`
+    ///
+    /// Parsed from the `Cookie` header when unset.
+    pub cookies: Option<HashMap<String, String>>,
+}
+
+impl RadixMatchOpts {
+    /// Merge the args of a query string (`limit=10&q=a+b`) into `args`
+    ///
+    /// Names and values are percent-decoded, with `+` meaning a space. Like
+    /// nginx's `$arg_*`, the first occurrence of a repeated name counts, and
+    /// args already present in `args` are kept.
+    pub fn add_query_args(&mut self, query: &str) {
+        let args = self.args.get_or_insert_with(HashMap::new);
+        for pair in query.split('&').filter(|pair| !pair.is_empty()) {
+            let (name, value) = pair.split_once('=').unwrap_or((pair, ""));
+            args.entry(percent_decode(name))
+                .or_insert_with(|| percent_decode(value));
+        }
+    }
+
+    /// Add the request data to `vars` under nginx names
+    ///
+    /// Entries the caller already set in `vars` are kept.
+    pub(crate) fn expose_request_data(&mut self) {
+        let mut derived: Vec<(String, &str)> = Vec::new();
+        let fields = [
+            ("request_method", &self.method),
+            ("host", &self.host),
+            ("remote_addr", &self.remote_addr),
+            ("scheme", &self.scheme),
+        ];
+        for (name, value) in fields {
+            if let Some(value) = value {
+                derived.push((name.to_string(), value));
+            }
+        }
+        let port = self.server_port.map(|port| port.to_string());
+        if let Some(port) = &port {
+            derived.push(("server_port".to_string(), port));
+        }
+        let mut cookie_header = None;
+        for (name, value) in self.headers.iter().flatten() {
+            let name = name.to_lowercase().replace('-', "_");
+            if name == "cookie" {
+                cookie_header = Some(value.as_str());
+            }
+            derived.push((format!("http_{}", name), value));
+        }
+        for (name, value) in self.args.iter().flatten() {
+            derived.push((format!("arg_{}", name), value));
+        }
+        match &self.cookies {
+            Some(cookies) => {
+                for (name, value) in cookies {
+                    derived.push((format!("cookie_{}", name), value));
+                }
+            }
+            None => {
+                let pairs = cookie_header.into_iter().flat_map(|h| h.split(';'));
+                for (name, value) in pairs.filter_map(|pair| pair.split_once('=')) {
+                    derived.push((format!("cookie_{}", name.trim()), value.trim()));
+                }
+            }
+        }
+        if derived.is_empty() {
+            return;
+        }
+
+        let mut vars = self.vars.take().unwrap_or_default();
+        for (name, value) in derived {
+            vars.entry(name).or_insert_with(|| value.to_string());
+        }
+        self.vars = Some(vars);
+    }
 }
 
 /// Match result containing metadata and extracted parameters
@@ -175,12 +833,644 @@ pub struct RadixMatchOpts {
 pub struct MatchResult {
     /// Route ID
     pub id: String,
+    /// Priority of the matched route
+    pub priority: i32,
+    /// Route metadata, shared with the router (cloning it is cheap)
+    pub metadata: Arc<serde_json::Value>,
+    /// Path parameters, keyed by name
+    pub matched: Params,
+    /// How the request matched: pattern, host, method and mount point
+    pub info: MatchInfo,
+    /// Path to forward upstream, for routes with `strip_prefix` or `rewrite`
+    pub upstream_path: Option<String>,
+    /// Part of the request path consumed by a trailing wildcard, always
+    /// starting with `/` (`/api/*rest` leaves `/users/1` of `/api/users/1`)
+    ///
+    /// `None` for routes without a trailing wildcard.
+    pub remaining_path: Option<String>,
+    /// Version of the route table that served the request (see
+    /// [`RadixRouter::version`](crate::RadixRouter::version))
+    pub version: u64,
+    /// Shadow routes that also accepted the request, in match order
+    ///
+    /// Proxies can mirror a copy of the request to these routes' upstreams.
+    pub shadows: Vec<MatchResult>,
+}
+
+/// Request attributes of a match, kept apart from the path parameters
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MatchInfo {
+    /// Path pattern the request matched, as registered (including any
+    /// mount prefix)
+    pub pattern: String,
+    /// Host pattern the request matched, for routes restricted to hosts
+    ///
+    /// Wildcard patterns are reported as written (`*.example.com`).
+    pub host: Option<String>,
+    /// Request method, when one was given
+    pub method: Option<String>,
+    /// Prefix the route was mounted under (see `RadixRouter::mount`)
+    pub mount: Option<String>,
+}
+
+/// Route chosen by a lookup, shared with the router
+///
+/// Returned by [`RadixRouter::match_route_into`](crate::RadixRouter::match_route_into);
+/// reading the id, pattern or metadata copies nothing.
+#[derive(Clone)]
+pub struct MatchedRoute {
+    route: Arc<RouteOpts>,
+    method: Option<RadixHttpMethod>,
+}
+
+impl MatchedRoute {
+    pub(crate) fn new(route: Arc<RouteOpts>, opts: &RadixMatchOpts) -> Self {
+        Self {
+            route,
+            method: opts.method.as_deref().and_then(RadixHttpMethod::from_str),
+        }
+    }
+
+    /// Route ID
+    pub fn id(&self) -> &str {
+        &self.route.id
+    }
+
+    /// Route metadata (honouring `method_metadata`)
+    pub fn metadata(&self) -> &Arc<serde_json::Value> {
+        self.route.metadata_for_method(self.method)
+    }
+
+    /// Registered path pattern that matched, e.g. `/users/:id`
+    pub fn pattern(&self) -> &str {
+        &self.route.path_org
+    }
+
+    /// Prefix the route was mounted under, if any
+    pub fn mount(&self) -> Option<&str> {
+        self.route.mount.as_deref()
+    }
+
+    /// Names of the pattern's params and wildcards, in pattern order
+    pub(crate) fn param_names(&self) -> impl Iterator<Item = &str> {
+        self.route.capture_names()
+    }
+}
+
+impl std::fmt::Debug for MatchedRoute {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("MatchedRoute")
+            .field("id", &self.id())
+            .field("pattern", &self.pattern())
+            .finish()
+    }
+}
+
+/// Match result borrowing from the request path
+///
+/// Returned by [`RadixRouter::match_route_ref`](crate::RadixRouter::match_route_ref).
+/// Param values are slices of the matched path and the route data is shared
+/// with the router, so building it allocates no strings. The request method
+/// and host of a [`MatchInfo`] are not included; read them from the request.
+#[derive(Clone)]
+pub struct MatchRef<'p> {
+    route: MatchedRoute,
+    /// Param values aligned with the route's pattern names
+    values: Vec<&'p str>,
+}
+
+impl<'p> MatchRef<'p> {
+    pub(crate) fn new(route: MatchedRoute, values: Vec<&'p str>) -> Self {
+        Self { route, values }
+    }
+
+    /// The matched route
+    pub fn route(&self) -> &MatchedRoute {
+        &self.route
+    }
+
+    /// Route ID
+    pub fn id(&self) -> &str {
+        self.route.id()
+    }
+
+    /// Route metadata (honouring `method_metadata`)
+    pub fn metadata(&self) -> &serde_json::Value {
+        self.route.metadata()
+    }
+
+    /// Registered path pattern that matched, e.g. `/users/:id`
+    pub fn pattern(&self) -> &str {
+        self.route.pattern()
+    }
+
+    /// Prefix the route was mounted under, if any
+    pub fn mount(&self) -> Option<&str> {
+        self.route.mount()
+    }
+
+    /// Value of the param or wildcard `name`
+    pub fn param(&self, name: &str) -> Option<&'p str> {
+        self.params()
+            .find(|(param, _)| *param == name)
+            .map(|(_, value)| value)
+    }
+
+    /// All captured params as `(name, value)`, in pattern order
+    pub fn params(&self) -> impl Iterator<Item = (&str, &'p str)> + '_ {
+        self.route.param_names().zip(self.values.iter().copied())
+    }
+}
+
+impl std::fmt::Debug for MatchRef<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("MatchRef")
+            .field("id", &self.id())
+            .field("pattern", &self.pattern())
+            .field("params", &self.params().collect::<Vec<_>>())
+            .finish()
+    }
+}
+
+/// Read-only summary of a registered route
+#[derive(Debug, Clone, PartialEq)]
+pub struct RouteInfo {
+    /// Route ID
+    pub id: String,
+    /// Registered paths, in insertion order
+    pub paths: Vec<String>,
+    /// Allowed HTTP methods (None means all)
+    pub methods: Option<RadixHttpMethod>,
+    /// Host patterns (None means all)
+    pub hosts: Option<Vec<String>>,
+    /// Route priority
+    pub priority: i32,
     /// Route metadata
     pub metadata: serde_json::Value,
-    /// Matched path parameters and other extracted values
-    pub matched: HashMap<String, String>,
+    /// Route tags
+    pub tags: Vec<String>,
+}
+
+impl RouteInfo {
+    pub(crate) fn new(route: &RouteOpts, paths: Vec<String>) -> Self {
+        Self {
+            id: route.id.clone(),
+            paths,
+            methods: (!route.methods.is_empty()).then_some(route.methods),
+            hosts: route
+                .hosts
+                .as_ref()
+                .map(|hosts| hosts.iter().map(|h| h.to_string()).collect()),
+            priority: route.priority,
+            metadata: serde_json::Value::clone(&route.metadata),
+            tags: route.tags.clone(),
+        }
+    }
+}
+
+/// Filter for [`RadixRouter::find_routes`](crate::RadixRouter::find_routes)
+///
+/// Unset fields match every route; set fields must all match.
+#[derive(Debug, Clone, Default)]
+pub struct RouteQuery {
+    /// Some path pattern of the route starts with this text (`/api/v1`)
+    pub path_prefix: Option<String>,
+    /// The route is restricted to hosts and one of its patterns accepts
+    /// this host, or is this wildcard (`*.example.com`)
+    pub host: Option<String>,
+    /// The route accepts this method; routes without a method restriction
+    /// accept every method
+    pub method: Option<RadixHttpMethod>,
+    /// The route carries this tag
+    pub tag: Option<String>,
+}
+
+impl RouteQuery {
+    /// Whether route `route`, registered under `paths`, passes the filter
+    pub(crate) fn accepts(&self, route: &RouteOpts, paths: &[String]) -> bool {
+        if let Some(prefix) = &self.path_prefix {
+            if !paths.iter().any(|path| path.starts_with(prefix.as_str())) {
+                return false;
+            }
+        }
+        if let Some(host) = &self.host {
+            if !route
+                .hosts
+                .iter()
+                .flatten()
+                .any(|pattern| pattern.matches(host))
+            {
+                return false;
+            }
+        }
+        if let Some(method) = self.method {
+            if !route.methods.is_empty() && !route.methods.contains(method) {
+                return false;
+            }
+        }
+        if let Some(tag) = &self.tag {
+            if !route.tags.contains(tag) {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+/// Conflict policy for [`RadixRouter::merge`](crate::RadixRouter::merge)
+///
+/// A conflict is a route id defined in both routers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MergePolicy {
+    /// Abort the merge and leave the router unchanged
+    #[default]
+    Error,
+    /// Keep the route already present in the router being merged into
+    PreferSelf,
+    /// Replace it with the route from the other router
+    PreferOther,
+}
+
+/// Handling of a failing [`TryFilterFn`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum FilterErrorPolicy {
+    /// Abort the match and return the error from `match_route`, so callers
+    /// can tell an infrastructure failure from "no route"
+    #[default]
+    Propagate,
+    /// Treat the route as accepted
+    FailOpen,
+    /// Treat the route as rejected and keep looking
+    FailClosed,
+}
+
+/// Handling of a route added under an id that is already registered
+///
+/// Set with [`RadixRouter::set_id_policy`](crate::RadixRouter::set_id_policy).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum IdPolicy {
+    /// Register the new paths under the same id (historical behaviour)
+    ///
+    /// Deleting by id then removes every definition sharing it.
+    #[default]
+    Allow,
+    /// Fail the insert, so every id names exactly one definition
+    Reject,
+    /// Remove the previous definition first and return it from `add_route`
+    Replace,
+}
+
+/// How request var names are compared with the names routes use
+///
+/// Set with [`RadixRouter::set_var_case`](crate::RadixRouter::set_var_case).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum VarCase {
+    /// Names must match exactly
+    #[default]
+    Sensitive,
+    /// Names are lowercased on insert and lookup, so `User-Agent` and
+    /// `user-agent` are the same var (like HTTP header names)
+    Insensitive,
+}
+
+/// What the router does with path traversal in wildcard captures
+///
+/// Set with [`RadixRouter::set_traversal_policy`](crate::RadixRouter::set_traversal_policy).
+/// Wildcard values are often joined to filesystem paths by file-serving
+/// handlers, so a `..` segment in them, plain or percent-encoded (`%2e%2e`,
+/// `%252e%252e`, with `/` or `\` separators), can escape the served
+/// directory.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TraversalPolicy {
+    /// Captures are returned as requested
+    #[default]
+    Allow,
+    /// Routes with a wildcard, glob or suffix reject requests whose
+    /// wildcard part contains a `..` segment
+    Reject,
+}
+
+impl TraversalPolicy {
+    /// Whether `value` contains a `..` segment, decoding escapes first
+    pub(crate) fn is_traversal(value: &str) -> bool {
+        if !value.contains('.') && !value.contains('%') {
+            return false;
+        }
+        let mut decoded = value.to_ascii_lowercase();
+        // Undo double encoding (`%252e` -> `%2e`) before the final pass
+        while decoded.contains("%25") {
+            decoded = decoded.replace("%25", "%");
+        }
+        let decoded = decoded
+            .replace("%2e", ".")
+            .replace("%2f", "/")
+            .replace("%5c", "\\");
+        decoded.split(['/', '\\']).any(|segment| segment == "..")
+    }
+}
+
+/// Whether request paths are percent-decoded before lookup
+///
+/// Set with [`RadixRouter::set_path_decoding`](crate::RadixRouter::set_path_decoding).
+/// Front proxies differ in what they forward: some pass the path as the
+/// client sent it, others decode it first. The decoding modes make the
+/// router see `/api/caf%C3%A9` as `/api/café`, and differ only in what an
+/// encoded slash means: `/api/user%2F1` can be the three segments of
+/// `/api/user/1`, one `user%2F1` segment, or an invalid request. Escapes
+/// are decoded once, malformed ones are kept as written and invalid UTF-8
+/// is replaced.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PathDecoding {
+    /// Match the path as received
+    #[default]
+    Raw,
+    /// Decode every escape; `%2F` becomes a segment separator
+    Decode,
+    /// Decode every escape except `%2F`, which stays inside its segment
+    KeepEncodedSlash,
+    /// Decode every escape; paths containing `%2F` match nothing
+    RejectEncodedSlash,
+}
+
+impl PathDecoding {
+    /// The path routes are matched against, `None` if the request is rejected
+    ///
+    /// Borrows `path` when there is nothing to decode.
+    pub fn decode<'a>(&self, path: &'a str) -> Option<Cow<'a, str>> {
+        if *self == PathDecoding::Raw || !path.contains('%') {
+            return Some(Cow::Borrowed(path));
+        }
+        let keep_slash = match self {
+            PathDecoding::KeepEncodedSlash => true,
+            PathDecoding::RejectEncodedSlash
+                if path
+                    .as_bytes()
+                    .windows(3)
+                    .any(|w| w.eq_ignore_ascii_case(b"%2f")) =>
+            {
+                return None
+            }
+            _ => false,
+        };
+        Some(Cow::Owned(unescape(path, false, keep_slash)))
+    }
+}
+
+/// Size limits on request paths, enforced before any route is tried
+///
+/// Set with [`RadixRouter::set_limits`](crate::RadixRouter::set_limits).
+/// Requests over a path limit match nothing; a route whose captured value
+/// is over `max_param_len` rejects the request like a pattern mismatch.
+/// `None` leaves a dimension unlimited.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Limits {
+    /// Longest request path accepted, in bytes
+    pub max_path_len: Option<usize>,
+    /// Longest value a param, wildcard or glob may capture, in bytes
+    pub max_param_len: Option<usize>,
+    /// Most path segments accepted (`/a/b` has two)
+    pub max_segments: Option<usize>,
+}
+
+impl Limits {
+    /// Whether `path` is over the length or segment limit
+    pub(crate) fn rejects_path(&self, path: &str) -> bool {
+        self.max_path_len.is_some_and(|max| path.len() > max)
+            || self
+                .max_segments
+                .is_some_and(|max| path.bytes().filter(|&b| b == b'/').count() > max)
+    }
+}
+
+/// Size caps on the route table, enforced on every change
+///
+/// Set with [`RadixRouter::set_table_limits`](crate::RadixRouter::set_table_limits).
+/// A change that would take the table over a cap fails with an error and
+/// leaves the published routes untouched, so one tenant of a shared router
+/// cannot degrade it for the others. `None` leaves a dimension unlimited.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct TableLimits {
+    /// Most route ids registered
+    pub max_routes: Option<usize>,
+    /// Most bytes of regex source across the var expressions of all routes
+    pub max_regex_bytes: Option<usize>,
+    /// Most routes sharing one lookup key (an exact path or a literal prefix),
+    /// all of which a request for that key may have to try
+    pub max_candidates: Option<usize>,
+}
+
+/// How the router ranks candidates that all accept a request
+///
+/// Chosen once via [`RadixRouter::with_policy`](crate::RadixRouter::with_policy).
+/// Specificity means: exact paths beat prefixes, longer literal prefixes
+/// beat shorter ones, then fewer wildcards and fewer params win.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MatchPolicy {
+    /// Scan from the most specific path bucket down and take the first
+    /// accepting route; `priority` orders routes sharing a bucket
+    ///
+    /// This is the historical behaviour and the cheapest policy, since the
+    /// scan stops at the first hit.
+    #[default]
+    PriorityFirst,
+    /// The most specific accepting route wins regardless of priority;
+    /// priority only breaks specificity ties
+    MostSpecificFirst,
+    /// The highest priority accepting route wins across all buckets;
+    /// specificity breaks priority ties
+    PriorityThenSpecificity,
+}
+
+/// Outcome of a routing decision
+///
+/// Returned by [`RadixRouter::match_outcome`](crate::RadixRouter::match_outcome).
+/// The enum is `non_exhaustive` so new decision types can be added without
+/// breaking downstream `match` statements.
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+// `Matched` is the hot variant; boxing it would cost an allocation per match
+#[allow(clippy::large_enum_variant)]
+pub enum RouteOutcome {
+    /// A route matched the request
+    Matched(MatchResult),
+    /// The path is routable, but no route accepts the request method
+    MethodNotAllowed {
+        /// Union of the methods of the routes that would accept the
+        /// request if only its method differed; routes with
+        /// `custom_methods` contribute `RadixHttpMethod::OTHER`
+        allowed: RadixHttpMethod,
+    },
+    /// No route matched the request
+    NotFound,
+}
+
+impl RouteOutcome {
+    /// Whether a route matched
+    pub fn is_match(&self) -> bool {
+        matches!(self, RouteOutcome::Matched(_))
+    }
+
+    /// Borrow the match result, if any
+    pub fn as_match(&self) -> Option<&MatchResult> {
+        match self {
+            RouteOutcome::Matched(result) => Some(result),
+            _ => None,
+        }
+    }
+
+    /// Convert into the `Option` shape returned by `match_route`
+    pub fn into_match(self) -> Option<MatchResult> {
+        match self {
+            RouteOutcome::Matched(result) => Some(result),
+            _ => None,
+        }
+    }
+}
+
+impl MatchResult {
+    /// JSON form with `id`, `metadata`, `matched` and shadow route ids
+    pub fn to_json(&self) -> serde_json::Value {
+        let matched: serde_json::Map<String, serde_json::Value> = self
+            .matched
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.into()))
+            .collect();
+        serde_json::json!({
+            "id": self.id,
+            "priority": self.priority,
+            "metadata": *self.metadata,
+            "matched": matched,
+            "info": {
+                "pattern": self.info.pattern,
+                "host": self.info.host,
+                "method": self.info.method,
+                "mount": self.info.mount,
+            },
+            "upstream_path": self.upstream_path,
+            "remaining_path": self.remaining_path,
+            "version": self.version,
+            "shadows": self.shadows.iter().map(|s| s.id.as_str()).collect::<Vec<_>>(),
+        })
+    }
+}
+
+impl From<Option<MatchResult>> for RouteOutcome {
+    fn from(result: Option<MatchResult>) -> Self {
+        match result {
+            Some(result) => RouteOutcome::Matched(result),
+            None => RouteOutcome::NotFound,
+        }
+    }
+}
+
+impl From<RouteOutcome> for Option<MatchResult> {
+    fn from(outcome: RouteOutcome) -> Self {
+        outcome.into_match()
+    }
+}
+
+/// Check that rejected a candidate route during matching
+///
+/// Reported by [`RadixRouter::explain`](crate::RadixRouter::explain).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Rejection {
+    /// Outside the validity window or past its TTL
+    Inactive,
+    /// Request method not allowed
+    Method,
+    /// Host not matched
+    Host,
+    /// Scheme not matched
+    Scheme,
+    /// SNI not matched
+    Sni,
+    /// Listener port not matched
+    ServerPort,
+    /// Remote address outside the allowed CIDRs
+    RemoteAddr,
+    /// Path does not fit the param/wildcard pattern
+    Params,
+    /// File extension not among the allowed ones
+    Extension,
+    /// A variable expression evaluated to false
+    Vars,
+    /// Request not in the canary bucket range
+    Canary,
+    /// Wildcard part of the path contains a `..` segment (see
+    /// [`TraversalPolicy::Reject`])
+    Traversal,
+    /// Custom filter function returned false
+    Filter,
+    /// Fallible filter failed (see [`FilterErrorPolicy`])
+    FilterError,
+}
+
+impl std::fmt::Display for Rejection {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let check = match self {
+            Rejection::Inactive => "inactive",
+            Rejection::Method => "method",
+            Rejection::Host => "host",
+            Rejection::Scheme => "scheme",
+            Rejection::Sni => "sni",
+            Rejection::ServerPort => "server port",
+            Rejection::RemoteAddr => "remote address",
+            Rejection::Params => "params",
+            Rejection::Extension => "extension",
+            Rejection::Vars => "vars",
+            Rejection::Canary => "canary",
+            Rejection::Traversal => "traversal",
+            Rejection::Filter => "filter",
+            Rejection::FilterError => "filter error",
+        };
+        f.write_str(check)
+    }
+}
+
+/// Decode a query string component (`%XX` escapes, `+` as space)
+///
+/// Malformed escapes are kept as written; invalid UTF-8 is replaced.
+fn percent_decode(text: &str) -> String {
+    unescape(text, true, false)
+}
+
+/// Decode `%XX` escapes, optionally reading `+` as a space and keeping
+/// `%2F` as written
+///
+/// Malformed escapes are kept as written; invalid UTF-8 is replaced.
+fn unescape(text: &str, plus_as_space: bool, keep_slash: bool) -> String {
+    let bytes = text.as_bytes();
+    let hex = |b: u8| (b as char).to_digit(16).map(|d| d as u8);
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'+' if plus_as_space => decoded.push(b' '),
+            b'%' => match (
+                bytes.get(i + 1).copied().and_then(hex),
+                bytes.get(i + 2).copied().and_then(hex),
+            ) {
+                (Some(high), Some(low)) if !(keep_slash && high << 4 | low == b'/') => {
+                    decoded.push(high << 4 | low);
+                    i += 2;
+                }
+                _ => decoded.push(b'%'),
+            },
+            byte => decoded.push(byte),
+        }
+        i += 1;
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
 }
 
+/// Name under which a route with `extensions` reports the matched extension
+pub const EXTENSION_PARAM: &str = "_ext";
+
+/// Request var whose value (e.g. a session id) makes weighted selection
+/// deterministic; without it, weighted routes are picked at random
+pub const WEIGHT_SEED_VAR: &str = "weight_seed";
+
 /// Path operation type
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum PathOp {
@@ -188,13 +1478,16 @@ pub enum PathOp {
     Equal,
     /// Prefix match (<=)
     PrefixMatch,
+    /// Suffix match (`*.php`)
+    SuffixMatch,
 }
 
 /// Internal route options (processed route)
 #[derive(Clone)]
 pub(crate) struct RouteOpts {
     pub id: String,
-    /// Actual match path (truncated at param/wildcard)
+    /// Actual match path (truncated at param/wildcard; the suffix of a
+    /// suffix route)
     pub path: String,
     /// Original path
     pub path_org: String,
@@ -202,21 +1495,249 @@ pub(crate) struct RouteOpts {
     pub path_op: PathOp,
     /// Whether path contains parameters
     pub has_param: bool,
+    /// Prefix the route was mounted under (see `RadixRouter::mount`)
+    pub mount: Option<String>,
+    /// Lowercased allowed file extensions, longest first
+    pub extensions: Option<Vec<String>>,
+    /// How the upstream path of a match is derived
+    pub rewrite: Option<Rewrite>,
 
     pub methods: RadixHttpMethod,
+    /// Uppercased non-standard methods
+    pub custom_methods: Option<Vec<String>>,
     pub hosts: Option<Vec<HostPattern>>,
+    /// Lowercased allowed schemes
+    pub schemes: Option<Vec<String>>,
+    pub snis: Option<Vec<HostPattern>>,
+    pub server_ports: Option<Vec<u16>>,
+    /// Prefix trie built from the route's CIDR list
+    pub remote_addrs: Option<Arc<IpTrie>>,
+    pub valid_from: Option<SystemTime>,
+    pub valid_until: Option<SystemTime>,
+    /// Insertion time plus TTL
+    pub expires_at: Option<SystemTime>,
     pub vars: Option<Vec<Expr>>,
     pub filter_fn: Option<FilterFn>,
+    /// Named filters, resolved when the route was added
+    pub filters: Vec<(String, FilterFn)>,
+    pub context_filter: Option<ContextFilter>,
+    pub try_filter_fn: Option<TryFilterFn>,
+    pub filter_errors: FilterErrorPolicy,
 
     pub priority: i32,
-    pub metadata: serde_json::Value,
+    pub weight: Option<u32>,
+    pub canary: Option<Canary>,
+    pub shadow: bool,
+    /// Shared so matches hand out the metadata without a deep copy
+    pub metadata: Arc<serde_json::Value>,
+    pub method_metadata: Option<Vec<(RadixHttpMethod, Arc<serde_json::Value>)>>,
+    pub tags: Vec<String>,
 
-    /// Pre-compiled regex pattern for parameter extraction (if has_param=true)
+    /// Pre-compiled segment pattern for parameter extraction (if has_param=true)
     /// Using Arc to make cloning cheap
-    pub compiled_pattern: Option<std::sync::Arc<(regex::Regex, Vec<String>)>>,
+    pub compiled_pattern: Option<std::sync::Arc<PathPattern>>,
+
+    /// Insertion sequence number, the final tie-breaker between equal routes
+    pub seq: u64,
 }
 
 impl RouteOpts {
+    /// Allowed methods for display (`*` when unrestricted)
+    pub fn methods_label(&self) -> String {
+        let mut names: Vec<String> = self
+            .methods
+            .iter_names()
+            .filter(|(name, _)| *name != "ALL")
+            .map(|(name, _)| name.to_string())
+            .collect();
+        if self.methods.is_all() {
+            names = vec!["ALL".to_string()];
+        }
+        names.extend(self.custom_methods.iter().flatten().cloned());
+        if names.is_empty() {
+            "*".to_string()
+        } else {
+            names.join("|")
+        }
+    }
+
+    /// Host patterns for display (`*` when unrestricted)
+    pub fn hosts_label(&self) -> String {
+        match &self.hosts {
+            Some(hosts) => hosts
+                .iter()
+                .map(|h| h.to_string())
+                .collect::<Vec<_>>()
+                .join(","),
+            None => "*".to_string(),
+        }
+    }
+
+    /// Names of the values a match captures, in capture order
+    ///
+    /// The pattern's params and wildcards, then the extension if the route
+    /// has an extension constraint.
+    pub fn capture_names(&self) -> impl Iterator<Item = &str> {
+        self.compiled_pattern
+            .iter()
+            .flat_map(|p| p.names())
+            .map(String::as_str)
+            .chain(self.extensions.is_some().then_some(EXTENSION_PARAM))
+    }
+
+    /// Compile the route's `strip_prefix` / `rewrite` directive against the
+    /// captures of this path
+    pub fn compile_rewrite(
+        &mut self,
+        strip_prefix: bool,
+        template: Option<&str>,
+    ) -> anyhow::Result<()> {
+        self.rewrite = match template {
+            _ if strip_prefix && self.path_op == PathOp::SuffixMatch => {
+                anyhow::bail!("strip_prefix needs a path route, not {}", self.path_org)
+            }
+            _ if strip_prefix => Some(Rewrite::StripPrefix),
+            Some(template) => {
+                let names: Vec<&str> = self.capture_names().collect();
+                Some(Rewrite::template(template, &names)?)
+            }
+            None => None,
+        };
+        Ok(())
+    }
+
+    /// Part of `path` consumed by the pattern's trailing wildcard, from the
+    /// slash before it
+    pub fn remaining_path(&self, path: &str) -> Option<String> {
+        if self.path_op != PathOp::PrefixMatch {
+            return None;
+        }
+        let (fixed, last) = self.path_org.rsplit_once('/')?;
+        if !last.starts_with('*') {
+            return None;
+        }
+        // Every segment before the wildcard ends at one slash of the path
+        let (start, _) = path.match_indices('/').nth(fixed.matches('/').count())?;
+        Some(path[start..].to_string())
+    }
+
+    /// Extension of the last segment of `path` among the allowed ones
+    pub fn extension_of<'p>(&self, path: &'p str) -> Option<&'p str> {
+        let extensions = self.extensions.as_ref()?;
+        let segment = path.rsplit('/').next().unwrap_or(path);
+        extensions.iter().find_map(|ext| {
+            let start = segment.len().checked_sub(ext.len() + 1)?;
+            let value = segment.get(start + 1..)?;
+            // Require a file name before the dot
+            (start > 0 && segment.as_bytes()[start] == b'.' && value.eq_ignore_ascii_case(ext))
+                .then_some(value)
+        })
+    }
+
+    /// Ranking key for `MatchPolicy`
+    ///
+    /// Exact beats prefix, then longer literal prefix, then fewer
+    /// wildcards, then fewer params.
+    pub fn specificity(&self) -> (bool, usize, Reverse<usize>, Reverse<usize>) {
+        (
+            self.path_op == PathOp::Equal,
+            self.path.len(),
+            Reverse(self.path_org.matches('*').count()),
+            Reverse(self.path_org.matches(':').count()),
+        )
+    }
+
+    /// Metadata to report for a request using `method`
+    pub fn metadata_for(&self, method: Option<&str>) -> &Arc<serde_json::Value> {
+        self.metadata_for_method(method.and_then(RadixHttpMethod::from_str))
+    }
+
+    /// Metadata to report for a request using an already parsed method
+    pub fn metadata_for_method(&self, method: Option<RadixHttpMethod>) -> &Arc<serde_json::Value> {
+        let Some(method) = method else {
+            return &self.metadata;
+        };
+        self.method_metadata
+            .iter()
+            .flatten()
+            .find(|(methods, _)| methods.contains(method))
+            .map(|(_, metadata)| metadata)
+            .unwrap_or(&self.metadata)
+    }
+
+    /// Whether the route's validity window contains `now`
+    pub fn is_active_at(&self, now: SystemTime) -> bool {
+        self.valid_from.is_none_or(|from| now >= from) && !self.is_expired_at(now)
+    }
+
+    /// Whether the route's TTL or validity window has run out at `now`
+    pub fn is_expired_at(&self, now: SystemTime) -> bool {
+        self.valid_until.is_some_and(|until| now >= until)
+            || self.expires_at.is_some_and(|expires| now >= expires)
+    }
+
+    /// Whether the route has a validity window or TTL at all
+    pub fn is_time_bound(&self) -> bool {
+        self.valid_from.is_some() || self.valid_until.is_some() || self.expires_at.is_some()
+    }
+
+    /// Whether this route, tried before `other` for the same lookup key,
+    /// accepts every request `other` would
+    ///
+    /// Conservative: only unconditional routes with the same path shape and
+    /// constraints that include `other`'s qualify.
+    pub(crate) fn covers(&self, other: &RouteOpts) -> bool {
+        fn shape(path: &str) -> Vec<&str> {
+            path.split('/')
+                .map(|seg| match seg.as_bytes().first() {
+                    Some(b':') => ":",
+                    Some(b'*') => "*",
+                    _ => seg,
+                })
+                .collect()
+        }
+        fn includes<T: PartialEq>(outer: &Option<Vec<T>>, inner: &Option<Vec<T>>) -> bool {
+            match (outer, inner) {
+                (None, _) => true,
+                (Some(_), None) => false,
+                (Some(outer), Some(inner)) => inner.iter().all(|x| outer.contains(x)),
+            }
+        }
+        let labels = |hosts: &Option<Vec<HostPattern>>| {
+            hosts
+                .as_ref()
+                .map(|hosts| hosts.iter().map(|h| h.to_string()).collect::<Vec<_>>())
+        };
+
+        let unconditional = !self.shadow
+            && self.vars.is_none()
+            && self.filter_fn.is_none()
+            && self.filters.is_empty()
+            && self.context_filter.is_none()
+            && self.try_filter_fn.is_none()
+            && self.weight.is_none()
+            && self.canary.is_none()
+            && self.remote_addrs.is_none()
+            && !self.is_time_bound();
+        let any_method = |r: &RouteOpts| r.methods.is_empty() && r.custom_methods.is_none();
+        let methods = any_method(self)
+            || (!any_method(other)
+                && self.methods.contains(other.methods)
+                && includes(&self.custom_methods, &other.custom_methods));
+
+        unconditional
+            && !other.shadow
+            && other.weight.is_none()
+            && self.mount == other.mount
+            && shape(&self.path_org) == shape(&other.path_org)
+            && methods
+            && includes(&labels(&self.hosts), &labels(&other.hosts))
+            && includes(&self.schemes, &other.schemes)
+            && includes(&labels(&self.snis), &labels(&other.snis))
+            && includes(&self.server_ports, &other.server_ports)
+            && includes(&self.extensions, &other.extensions)
+    }
+
     /// Compare priority (for sorting)
     pub fn cmp_priority(&self, other: &Self) -> std::cmp::Ordering {
         match other.priority.cmp(&self.priority) {
@@ -229,14 +1750,237 @@ impl RouteOpts {
     }
 }
 
+impl RadixNode {
+    /// Fill the fields this route leaves unset from `template`
+    ///
+    /// Options, paths, a zero priority and the default filter error policy
+    /// count as unset. Vars, named filters and tags add up, the template's
+    /// first; `strip_prefix` and `shadow` hold if either sets them; object
+    /// metadata is merged key by key, the route's keys winning, and other
+    /// metadata is taken from the template only when the route's is null.
+    /// The template's id and `extends` are ignored.
+    pub fn inherit(self, template: &RadixNode) -> RadixNode {
+        fn union<T: Clone + PartialEq>(first: &[T], then: Vec<T>) -> Vec<T> {
+            let mut all = first.to_vec();
+            all.extend(then.into_iter().filter(|item| !first.contains(item)));
+            all
+        }
+        let vars = match (&template.vars, self.vars) {
+            (Some(shared), Some(own)) => Some(shared.iter().cloned().chain(own).collect()),
+            (shared, own) => own.or_else(|| shared.clone()),
+        };
+        let metadata = match (&template.metadata, self.metadata) {
+            (serde_json::Value::Object(shared), serde_json::Value::Object(own)) => {
+                let mut merged = shared.clone();
+                merged.extend(own);
+                serde_json::Value::Object(merged)
+            }
+            (shared, serde_json::Value::Null) => shared.clone(),
+            (_, own) => own,
+        };
+        RadixNode {
+            id: self.id,
+            paths: if self.paths.is_empty() {
+                template.paths.clone()
+            } else {
+                self.paths
+            },
+            extensions: self.extensions.or_else(|| template.extensions.clone()),
+            strip_prefix: self.strip_prefix || template.strip_prefix,
+            rewrite: self.rewrite.or_else(|| template.rewrite.clone()),
+            methods: self.methods.or(template.methods),
+            custom_methods: self
+                .custom_methods
+                .or_else(|| template.custom_methods.clone()),
+            hosts: self.hosts.or_else(|| template.hosts.clone()),
+            remote_addrs: self.remote_addrs.or_else(|| template.remote_addrs.clone()),
+            schemes: self.schemes.or_else(|| template.schemes.clone()),
+            snis: self.snis.or_else(|| template.snis.clone()),
+            server_ports: self.server_ports.or_else(|| template.server_ports.clone()),
+            valid_from: self.valid_from.or(template.valid_from),
+            valid_until: self.valid_until.or(template.valid_until),
+            ttl: self.ttl.or(template.ttl),
+            vars,
+            filter_fn: self.filter_fn.or_else(|| template.filter_fn.clone()),
+            filters: union(&template.filters, self.filters),
+            context_filter: self
+                .context_filter
+                .or_else(|| template.context_filter.clone()),
+            try_filter_fn: self
+                .try_filter_fn
+                .or_else(|| template.try_filter_fn.clone()),
+            filter_errors: if self.filter_errors == FilterErrorPolicy::default() {
+                template.filter_errors
+            } else {
+                self.filter_errors
+            },
+            priority: if self.priority == 0 {
+                template.priority
+            } else {
+                self.priority
+            },
+            path_priorities: self
+                .path_priorities
+                .or_else(|| template.path_priorities.clone()),
+            weight: self.weight.or(template.weight),
+            canary: self.canary.or_else(|| template.canary.clone()),
+            shadow: self.shadow || template.shadow,
+            metadata,
+            method_metadata: self
+                .method_metadata
+                .or_else(|| template.method_metadata.clone()),
+            tags: union(&template.tags, self.tags),
+            extends: None,
+        }
+    }
+
+    /// Hash of the route definition, used to detect changed routes
+    ///
+    /// Filter functions contribute their pointer identity only.
+    pub fn content_hash(&self) -> u64 {
+        self.settings_hash()
+            .wrapping_add(paths_hash(&self.paths))
+            .wrapping_add(metadata_hash(&self.metadata))
+    }
+
+    /// `content_hash` of a definition whose `paths` changed from `old` to
+    /// `new`, given its current hash
+    ///
+    /// Like the metadata, the paths are a separate term of the hash, and
+    /// path priorities refer to paths by position, so mounting a route
+    /// under a prefix can rehash it from the registered paths alone.
+    pub(crate) fn rehash_paths(hash: u64, old: &[String], new: &[String]) -> u64 {
+        hash.wrapping_sub(paths_hash(old))
+            .wrapping_add(paths_hash(new))
+    }
+
+    /// `content_hash` of a definition whose `metadata` changed from `old`
+    /// to `new`, given its current hash
+    ///
+    /// The metadata is a separate term of the hash, so metadata-only updates
+    /// keep the stored hash exact without the full definition.
+    pub(crate) fn rehash_metadata(
+        hash: u64,
+        old: &serde_json::Value,
+        new: &serde_json::Value,
+    ) -> u64 {
+        hash.wrapping_sub(metadata_hash(old))
+            .wrapping_add(metadata_hash(new))
+    }
+
+    /// Hash of every field but `paths` and `metadata`
+    fn settings_hash(&self) -> u64 {
+        use std::hash::{Hash, Hasher};
+
+        let mut state = std::collections::hash_map::DefaultHasher::new();
+        self.id.hash(&mut state);
+        self.extensions.hash(&mut state);
+        self.strip_prefix.hash(&mut state);
+        self.rewrite.hash(&mut state);
+        self.methods.hash(&mut state);
+        self.custom_methods.hash(&mut state);
+        self.hosts.hash(&mut state);
+        self.remote_addrs.hash(&mut state);
+        self.schemes.hash(&mut state);
+        self.snis.hash(&mut state);
+        self.server_ports.hash(&mut state);
+        self.valid_from.hash(&mut state);
+        self.valid_until.hash(&mut state);
+        self.ttl.hash(&mut state);
+        match &self.vars {
+            Some(vars) => {
+                vars.len().hash(&mut state);
+                for expr in vars {
+                    expr.hash_into(&mut state);
+                }
+            }
+            None => usize::MAX.hash(&mut state),
+        }
+        self.filter_fn
+            .as_ref()
+            .map(|f| Arc::as_ptr(f) as *const () as usize)
+            .hash(&mut state);
+        self.filters.hash(&mut state);
+        self.context_filter
+            .as_ref()
+            .map(ContextFilter::addr)
+            .hash(&mut state);
+        self.try_filter_fn
+            .as_ref()
+            .map(|f| Arc::as_ptr(f) as *const () as usize)
+            .hash(&mut state);
+        self.filter_errors.hash(&mut state);
+        self.priority.hash(&mut state);
+        if let Some(priorities) = &self.path_priorities {
+            priorities.len().hash(&mut state);
+            for (path, priority) in priorities {
+                self.paths.iter().position(|p| p == path).hash(&mut state);
+                priority.hash(&mut state);
+            }
+        }
+        self.weight.hash(&mut state);
+        self.canary.hash(&mut state);
+        self.shadow.hash(&mut state);
+        if let Some(entries) = &self.method_metadata {
+            entries.len().hash(&mut state);
+            for (methods, metadata) in entries {
+                methods.hash(&mut state);
+                metadata.to_string().hash(&mut state);
+            }
+        }
+        self.tags.hash(&mut state);
+        self.extends.hash(&mut state);
+        state.finish()
+    }
+}
+
+/// Hash of route paths, in the native syntax they are registered with
+fn paths_hash(paths: &[String]) -> u64 {
+    use std::hash::{Hash, Hasher};
+
+    let mut state = std::collections::hash_map::DefaultHasher::new();
+    paths.len().hash(&mut state);
+    for path in paths {
+        convert_matchit_path(path)
+            .unwrap_or(Cow::Borrowed(path))
+            .hash(&mut state);
+    }
+    state.finish()
+}
+
+/// Hash of route metadata
+fn metadata_hash(metadata: &serde_json::Value) -> u64 {
+    use std::hash::{Hash, Hasher};
+
+    let mut state = std::collections::hash_map::DefaultHasher::new();
+    // serde_json::Value has no Hash impl; its canonical text is stable
+    metadata.to_string().hash(&mut state);
+    state.finish()
+}
+
 impl std::fmt::Debug for RadixNode {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.debug_struct("RadixNode")
             .field("id", &self.id)
             .field("paths", &self.paths)
+            .field("extensions", &self.extensions)
+            .field("strip_prefix", &self.strip_prefix)
+            .field("rewrite", &self.rewrite)
             .field("methods", &self.methods)
+            .field("custom_methods", &self.custom_methods)
             .field("hosts", &self.hosts)
+            .field("schemes", &self.schemes)
+            .field("snis", &self.snis)
+            .field("server_ports", &self.server_ports)
+            .field("filters", &self.filters)
+            .field("filter_errors", &self.filter_errors)
             .field("priority", &self.priority)
+            .field("path_priorities", &self.path_priorities)
+            .field("weight", &self.weight)
+            .field("canary", &self.canary)
+            .field("shadow", &self.shadow)
+            .field("tags", &self.tags)
+            .field("extends", &self.extends)
             .finish()
     }
 }
@@ -249,8 +1993,10 @@ impl std::fmt::Debug for RouteOpts {
             .field("path_org", &self.path_org)
             .field("path_op", &self.path_op)
             .field("has_param", &self.has_param)
+            .field("mount", &self.mount)
             .field("methods", &self.methods)
             .field("priority", &self.priority)
+            .field("seq", &self.seq)
             .finish()
     }
 }
@@ -1,82 +1,632 @@
 //! Core router implementation
 
+use crate::audit::{AuditAction, AuditEntry, AuditSink, AuditTrail};
+use crate::bucket::{stable_hash, Bucketer};
+use crate::capture::{Captures, PathValues};
+use crate::explain::{CandidateSource, CandidateTrace, Conflict, Explanation, ShadowReport};
 use crate::ffi::RadixTreeRaw;
+use crate::ip::IpTrie;
+use crate::observer::RouteObserver;
+use crate::params::Params;
+use crate::pattern::{convert_matchit_path, PathPattern, PatternSet};
 use crate::route::*;
+use crate::snapshot::{self, DefinitionRecord, RouteRecord, TableSnapshot};
+use crate::telemetry::{debug_event, MatchTimer};
+use crate::transaction::{ChangeReport, Transaction, TxOp};
+use crate::validate::{invalid, InvalidField, ValidationIssue};
 use anyhow::{Context, Result};
-use regex::Regex;
-use std::collections::HashMap;
-use std::sync::RwLock;
+use arc_swap::{ArcSwap, Guard};
+use rustc_hash::{FxHashMap, FxHashSet};
+use smallvec::SmallVec;
+use std::any::Any;
+use std::borrow::Cow;
+use std::collections::{BTreeMap, BTreeSet, HashSet, VecDeque};
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+use std::time::SystemTime;
 
 /// High-performance radix tree based router (optimized for concurrent reads)
 ///
 /// This router is designed for optimal read performance:
-/// - After initialization, all route data is immutable
 /// - `match_route()` requires only `&self` and uses temporary iterators for lock-free queries
-/// - Each query creates its own iterator, making it fully thread-safe and async-safe
-/// - Regex patterns are pre-compiled during route registration (zero runtime compilation)
+/// - Each query uses its own iterator (pooled per thread), making it fully thread-safe and async-safe
+/// - Param patterns are compiled into segment matchers during route registration (no regex at match time)
 /// - Multiple threads/tasks can call `match_route()` concurrently without contention
+///
+/// The route table is an immutable snapshot published through an
+/// `ArcSwap`: lookups load the current snapshot without taking any lock,
+/// and every mutation builds a new table off to the side and swaps it in.
+/// Readers are never blocked by writers and always see either the old or
+/// the new route set, never a mix. Each mutation copies the table, so batch
+/// changes with `add_routes`, `transaction` or `replace_routes` when many
+/// routes change at once.
 pub struct RadixRouter {
-    /// C-based radix tree (RwLock only for insert/remove operations)
-    tree: RwLock<RadixTreeRaw>,
-    /// Route storage: index -> Vec<RouteOpts> (immutable after construction)
-    match_data: HashMap<usize, Vec<RouteOpts>>,
+    /// Current route table snapshot, shared with every [`RouterView`]
+    table: Arc<ArcSwap<RouteTable>>,
+    /// Serializes writers so batched updates never interleave
+    writer: Mutex<()>,
+    /// How competing candidates are ranked
+    policy: MatchPolicy,
+    /// Registered lifecycle observers
+    observers: ArcSwap<Vec<Arc<dyn RouteObserver>>>,
+    /// Audit log of published route changes
+    audit: Mutex<AuditTrail>,
+    /// Previously published tables kept for `rollback`
+    history: Mutex<History>,
+}
+
+/// Previously published tables, oldest first
+#[derive(Default)]
+struct History {
+    /// Most tables kept (0 keeps none)
+    capacity: usize,
+    tables: VecDeque<Arc<RouteTable>>,
+}
+
+impl History {
+    fn push(&mut self, table: Arc<RouteTable>) {
+        if self.capacity == 0 {
+            return;
+        }
+        if self.tables.len() == self.capacity {
+            self.tables.pop_front();
+        }
+        self.tables.push_back(table);
+    }
+}
+
+/// Route data shared by all lookups
+///
+/// Lookup maps use FxHash rather than SipHash: their keys come from
+/// validated route definitions and lookups never insert, so request paths
+/// cannot be used to flood them with collisions.
+struct RouteTable {
+    /// C-based radix tree
+    ///
+    /// Both trees are shared with copies made by `share`, which never
+    /// change keys; every other copy builds its own.
+    tree: Arc<RadixTreeRaw>,
+    /// Radix tree of suffix routes, keyed by the reversed suffix
+    suffix_tree: Arc<RadixTreeRaw>,
+    /// Route storage: index -> routes sharing that tree key (both trees
+    /// share the index space)
+    match_data: FxHashMap<usize, Bucket>,
     /// Current maximum index
     match_data_index: usize,
-    /// Hash-based exact path matching: path -> Vec<RouteOpts> (immutable after construction)
-    hash_path: HashMap<String, Vec<RouteOpts>>,
+    /// Hash-based exact path matching: path -> routes
+    hash_path: FxHashMap<String, Bucket>,
+    /// Route registry: id -> registered paths and content hash
+    routes: BTreeMap<String, RouteEntry>,
+    /// Whether any shadow route is registered (refreshed by `seal`)
+    has_shadows: bool,
+    /// Whether any suffix route is registered (refreshed by `seal`)
+    has_suffixes: bool,
+    /// Whether any route reads vars, so lookups expose the request data
+    /// under var names (refreshed by `seal`)
+    reads_vars: bool,
+    /// Batched patterns of prefix buckets with several param routes
+    /// (refreshed by `seal`)
+    pattern_sets: FxHashMap<usize, PatternSet>,
+    /// First path segments that some route requires, or `None` when a
+    /// route accepts any first segment (refreshed by `seal`)
+    first_segments: Option<FxHashSet<String>>,
+    /// Interned param patterns, shared by routes with the same template
+    /// (pruned by `seal`)
+    patterns: FxHashSet<Arc<PathPattern>>,
+    /// User ordering for candidates tying on priority and path length
+    tie_breaker: Option<TieBreaker>,
+    /// Rewrite applied to the metadata of returned matches
+    metadata_hook: Option<MetadataHook>,
+    /// Last insertion sequence number handed out
+    next_seq: u64,
+    /// How adding an already registered id is handled
+    id_policy: IdPolicy,
+    /// Whether var names are lowercased on insert and lookup
+    var_case: VarCase,
+    /// Request size limits checked before any route is tried
+    limits: Limits,
+    /// Whether traversal in wildcard parts of the path rejects routes
+    traversal: TraversalPolicy,
+    /// Whether request paths are percent-decoded before lookup
+    path_decoding: PathDecoding,
+    /// Caps on the table size, checked before a change is published
+    table_limits: TableLimits,
+    /// Number of changes published before this table (set by `publish`)
+    version: u64,
+    /// Filters routes reference by name (see `register_filter`)
+    filters: FxHashMap<String, FilterFn>,
+    /// Templates routes extend by name (see `register_template`)
+    templates: FxHashMap<String, RadixNode>,
 }
 
-impl RadixRouter {
-    /// Create a new empty router
-    pub fn new() -> Result<Self> {
+/// Routes sharing one lookup key, in match order
+///
+/// Entries are reference counted so copying a table for a mutation and
+/// handing a matched route to the caller don't deep-copy route data.
+type Bucket = Vec<Arc<RouteOpts>>;
+
+/// Route validated and compiled off the table, ready to be inserted
+struct PreparedRoute {
+    id: String,
+    /// One processed entry per path
+    opts: Vec<RouteOpts>,
+    content_hash: u64,
+    /// Definition as given, for routes extending a template
+    definition: Option<RadixNode>,
+}
+
+/// Filter inputs and outcome shared by the checks of one lookup
+#[derive(Default)]
+struct FilterEval<'c> {
+    /// Request context for `ContextFilter`s (see `match_route_with`)
+    context: Option<&'c dyn Any>,
+    /// Filter error that aborts the lookup (`FilterErrorPolicy::Propagate`)
+    failure: Option<anyhow::Error>,
+}
+
+/// State of one lookup while candidate buckets are scanned
+struct Scan<'a, 'c, C> {
+    policy: MatchPolicy,
+    /// Best primary route that accepted the request so far
+    best: Option<Candidate<'a, C>>,
+    /// Shadow routes that accepted the request
+    shadows: Vec<MatchResult>,
+    filters: FilterEval<'c>,
+}
+
+/// Primary route that accepted the request
+struct Candidate<'a, C> {
+    route: &'a Arc<RouteOpts>,
+    /// Bucket the route was found in (for weighted selection)
+    bucket: &'a [Arc<RouteOpts>],
+    matched: C,
+}
+
+/// Route chosen by a lookup, with the values captured for it
+struct Winner<'a, C> {
+    route: &'a Arc<RouteOpts>,
+    matched: C,
+    shadows: Vec<MatchResult>,
+}
+
+impl<'a, 'c, C> Scan<'a, 'c, C> {
+    fn new(policy: MatchPolicy, context: Option<&'c dyn Any>) -> Self {
+        Self {
+            policy,
+            best: None,
+            shadows: Vec::new(),
+            filters: FilterEval {
+                context,
+                failure: None,
+            },
+        }
+    }
+
+    /// Whether no later primary candidate can win anymore
+    fn settled(&self) -> bool {
+        self.policy == MatchPolicy::PriorityFirst && self.best.is_some()
+    }
+
+    /// Keep `candidate` if it beats the current best under the policy
+    ///
+    /// Ties keep the earlier candidate, i.e. scan order decides.
+    fn offer(&mut self, candidate: Candidate<'a, C>) {
+        let better = match &self.best {
+            None => true,
+            Some(best) => {
+                let (new, old) = (candidate.route, best.route);
+                let ord = match self.policy {
+                    MatchPolicy::PriorityFirst => std::cmp::Ordering::Equal,
+                    MatchPolicy::MostSpecificFirst => new
+                        .specificity()
+                        .cmp(&old.specificity())
+                        .then(new.priority.cmp(&old.priority)),
+                    MatchPolicy::PriorityThenSpecificity => new
+                        .priority
+                        .cmp(&old.priority)
+                        .then(new.specificity().cmp(&old.specificity())),
+                };
+                ord.is_gt()
+            }
+        };
+        if better {
+            self.best = Some(candidate);
+        }
+    }
+}
+
+/// Registry entry for a route id
+#[derive(Debug, Clone, Default)]
+struct RouteEntry {
+    /// Registered paths in insertion order
+    paths: Vec<String>,
+    /// Content hash of the latest definition (see `RadixNode::content_hash`)
+    content_hash: u64,
+    /// Definition as given, kept while it extends a template so the route
+    /// can be resolved again when the template changes
+    definition: Option<RadixNode>,
+}
+
+impl RouteTable {
+    /// Create a new empty table
+    fn new() -> Result<Self> {
         Ok(Self {
-            tree: RwLock::new(RadixTreeRaw::new().context("Failed to create radix tree")?),
-            match_data: HashMap::new(),
+            tree: Arc::new(RadixTreeRaw::new().context("Failed to create radix tree")?),
+            suffix_tree: Arc::new(RadixTreeRaw::new().context("Failed to create radix tree")?),
+            match_data: FxHashMap::default(),
             match_data_index: 0,
-            hash_path: HashMap::new(),
+            hash_path: FxHashMap::default(),
+            routes: BTreeMap::new(),
+            has_shadows: false,
+            has_suffixes: false,
+            reads_vars: false,
+            pattern_sets: FxHashMap::default(),
+            first_segments: None,
+            patterns: FxHashSet::default(),
+            tie_breaker: None,
+            metadata_hook: None,
+            next_seq: 0,
+            id_policy: IdPolicy::default(),
+            var_case: VarCase::default(),
+            limits: Limits::default(),
+            traversal: TraversalPolicy::default(),
+            path_decoding: PathDecoding::default(),
+            table_limits: TableLimits::default(),
+            version: 0,
+            filters: FxHashMap::default(),
+            templates: FxHashMap::default(),
         })
     }
 
-    /// Add multiple routes to the router
-    pub fn add_routes(&mut self, routes: Vec<RadixNode>) -> Result<()> {
-        for route in routes {
-            self.add_route(route)?;
+    /// Create an empty table with the same settings as this one
+    fn empty_like(&self) -> Result<Self> {
+        let mut table = Self::new()?;
+        table.tie_breaker = self.tie_breaker.clone();
+        table.metadata_hook = self.metadata_hook.clone();
+        table.id_policy = self.id_policy;
+        table.var_case = self.var_case;
+        table.limits = self.limits;
+        table.traversal = self.traversal;
+        table.path_decoding = self.path_decoding;
+        table.table_limits = self.table_limits;
+        table.filters = self.filters.clone();
+        table.templates = self.templates.clone();
+        Ok(table)
+    }
+
+    /// Refresh derived lookup hints before the table is published
+    fn seal(&mut self) {
+        let has_shadows = self.iter_route_opts().any(|route| route.shadow);
+        self.has_shadows = has_shadows;
+        let has_suffixes = self
+            .iter_route_opts()
+            .any(|route| route.path_op == PathOp::SuffixMatch);
+        self.has_suffixes = has_suffixes;
+        let reads_vars = self.iter_route_opts().any(|route| {
+            route.vars.is_some()
+                || route.canary.is_some()
+                || route.filter_fn.is_some()
+                || !route.filters.is_empty()
+                || route.try_filter_fn.is_some()
+        });
+        self.reads_vars = reads_vars;
+        self.pattern_sets = self
+            .match_data
+            .iter()
+            .filter(|(_, routes)| routes.iter().filter(|r| r.has_param).count() >= 2)
+            .map(|(idx, routes)| {
+                let patterns = routes.iter().map(|r| r.compiled_pattern.as_ref());
+                (*idx, PatternSet::new(patterns))
+            })
+            .collect();
+        self.patterns = self
+            .iter_route_opts()
+            .filter_map(|route| route.compiled_pattern.clone())
+            .collect();
+        self.first_segments = self.collect_first_segments();
+    }
+
+    /// First segments of every lookup key, for the fast-miss prefilter
+    ///
+    /// Exact keys name their whole first segment. Prefix keys only do when
+    /// they extend past it (`/api/` for `/api/:id`); a shorter prefix such
+    /// as `/` for `/*` accepts any first segment and disables the filter,
+    /// as does any suffix route.
+    fn collect_first_segments(&self) -> Option<FxHashSet<String>> {
+        if self.has_suffixes {
+            return None;
+        }
+        let mut segments = FxHashSet::default();
+        for key in self.hash_path.keys() {
+            let rest = key.strip_prefix('/')?;
+            let segment = rest.split_once('/').map_or(rest, |(segment, _)| segment);
+            segments.insert(segment.to_string());
+        }
+        for routes in self.match_data.values() {
+            let Some(route) = routes.first() else {
+                continue;
+            };
+            let rest = route.path.strip_prefix('/')?;
+            let (segment, _) = rest.split_once('/')?;
+            segments.insert(segment.to_string());
+        }
+        Some(segments)
+    }
+
+    /// Whether some route might accept `path`, judged by its first segment
+    fn may_route(&self, path: &str) -> bool {
+        let Some(segments) = &self.first_segments else {
+            return true;
+        };
+        path.strip_prefix('/').is_some_and(|rest| {
+            let segment = rest.split_once('/').map_or(rest, |(segment, _)| segment);
+            segments.contains(segment)
+        })
+    }
+
+    /// Fail if the table is over one of its [`TableLimits`]
+    fn check_table_limits(&self) -> Result<()> {
+        let limits = &self.table_limits;
+        if let Some(max) = limits.max_routes {
+            if self.routes.len() > max {
+                anyhow::bail!(
+                    "Route table limit exceeded: {} routes, at most {} allowed",
+                    self.routes.len(),
+                    max
+                );
+            }
+        }
+        if let Some(max) = limits.max_regex_bytes {
+            // Count each definition once, not once per path
+            let mut seen = FxHashSet::default();
+            let bytes: usize = self
+                .iter_route_opts()
+                .filter(|route| seen.insert(route.id.as_str()))
+                .flat_map(|route| route.vars.iter().flatten())
+                .map(Expr::regex_bytes)
+                .sum();
+            if bytes > max {
+                anyhow::bail!(
+                    "Route table limit exceeded: {} bytes of regex, at most {} allowed",
+                    bytes,
+                    max
+                );
+            }
+        }
+        if let Some(max) = limits.max_candidates {
+            let crowded = self
+                .hash_path
+                .values()
+                .chain(self.match_data.values())
+                .find(|routes| routes.len() > max);
+            if let Some(routes) = crowded {
+                anyhow::bail!(
+                    "Route table limit exceeded: {} routes share the lookup key {}, at most {} allowed",
+                    routes.len(),
+                    routes[0].path,
+                    max
+                );
+            }
         }
         Ok(())
     }
 
     /// Add a single route to the router
-    pub fn add_route(&mut self, route: RadixNode) -> Result<()> {
-        for path in &route.paths {
-            self.insert_route(path, &route)?;
+    ///
+    /// Returns the replaced definition under `IdPolicy::Replace`.
+    fn add_route(&mut self, route: RadixNode) -> Result<Option<RouteInfo>> {
+        let prepared = self.prepare_route(&route)?;
+        self.insert_prepared(prepared)
+    }
+
+    /// Add a batch of routes, preparing them up front
+    ///
+    /// With the `parallel` feature, validation and pattern compilation run
+    /// on the rayon thread pool; tree inserts stay sequential and keep the
+    /// batch order.
+    fn add_routes(&mut self, routes: &[RadixNode]) -> Result<()> {
+        #[cfg(feature = "parallel")]
+        let results: Vec<Result<PreparedRoute>> = {
+            use rayon::prelude::*;
+            routes
+                .par_iter()
+                .map(|route| self.prepare_route(route))
+                .collect()
+        };
+        #[cfg(not(feature = "parallel"))]
+        let results: Vec<Result<PreparedRoute>> = routes
+            .iter()
+            .map(|route| self.prepare_route(route))
+            .collect();
+
+        // Report every invalid route, not just the first
+        let mut prepared = Vec::with_capacity(results.len());
+        let mut errors = Vec::new();
+        for (route, result) in routes.iter().zip(results) {
+            match result {
+                Ok(route) => prepared.push(route),
+                Err(err) => errors.push((route.id.as_str(), err)),
+            }
+        }
+        if self.id_policy == IdPolicy::Reject {
+            let mut seen = FxHashSet::default();
+            for route in &prepared {
+                if self.routes.contains_key(&route.id) || !seen.insert(route.id.as_str()) {
+                    errors.push((
+                        route.id.as_str(),
+                        invalid("id", format!("Duplicate route id: {}", route.id)),
+                    ));
+                }
+            }
+        }
+        match errors.len() {
+            0 => {}
+            1 => return Err(errors.remove(0).1),
+            count => {
+                let details: Vec<String> = errors
+                    .iter()
+                    .map(|(id, err)| format!("{}: {:#}", id, err))
+                    .collect();
+                anyhow::bail!("{} invalid routes: {}", count, details.join("; "));
+            }
+        }
+
+        for route in prepared {
+            self.insert_prepared(route)?;
         }
         Ok(())
     }
 
-    /// Insert a route with specific path
-    fn insert_route(&mut self, path: &str, route: &RadixNode) -> Result<()> {
-        // Process route data
-        let route_opts = self.process_route(path, route)?;
+    /// Validate and compile every path of a route without touching the table
+    fn prepare_route(&self, route: &RadixNode) -> Result<PreparedRoute> {
+        let content_hash = route.content_hash();
+        let definition = route.extends.is_some().then(|| route.clone());
+        let resolved;
+        let route = match &route.extends {
+            Some(name) => {
+                let template = self.templates.get(name).with_context(|| {
+                    InvalidField::new(
+                        "extends",
+                        format!("Unknown template {} for route {}", name, route.id),
+                    )
+                })?;
+                resolved = route.clone().inherit(template);
+                &resolved
+            }
+            None => route,
+        };
+        let native;
+        let route = if route.paths.iter().any(|path| path.contains(['{', '}'])) {
+            native = Self::native_paths(route)?;
+            &native
+        } else {
+            route
+        };
+        if route.strip_prefix && route.rewrite.is_some() {
+            return Err(invalid(
+                "rewrite",
+                format!("Route {} sets both strip_prefix and rewrite", route.id),
+            ));
+        }
+        for ext in route.extensions.iter().flatten() {
+            if ext.trim_start_matches('.').is_empty() {
+                return Err(invalid(
+                    "extensions",
+                    format!("Empty extension for route: {}", route.id),
+                ));
+            }
+        }
+        if route.extensions.is_some() {
+            let tail_wildcard = |path: &String| {
+                path.starts_with('/') && path.rsplit('/').next().is_some_and(|s| s.starts_with('*'))
+            };
+            if let Some(path) = route.paths.iter().find(|path| !tail_wildcard(path)) {
+                return Err(invalid(
+                    "extensions",
+                    format!(
+                        "Extension constraint of route {} needs a wildcard path: {}",
+                        route.id, path
+                    ),
+                ));
+            }
+        }
+        for (path, _) in route.path_priorities.iter().flatten() {
+            if !route.paths.contains(path) {
+                return Err(invalid(
+                    "path_priorities",
+                    format!(
+                        "Priority override for a path route {} does not have: {}",
+                        route.id, path
+                    ),
+                ));
+            }
+        }
+        Ok(PreparedRoute {
+            id: route.id.clone(),
+            opts: route
+                .paths
+                .iter()
+                .map(|path| self.process_route(path, route))
+                .collect::<Result<_>>()?,
+            content_hash,
+            definition,
+        })
+    }
+
+    /// Copy of `route` with matchit-style paths in native syntax
+    fn native_paths(route: &RadixNode) -> Result<RadixNode> {
+        let convert = |path: &String| {
+            convert_matchit_path(path)
+                .map(Cow::into_owned)
+                .with_context(|| {
+                    InvalidField::new("paths", format!("Invalid path for route: {}", route.id))
+                })
+        };
+        let mut native = route.clone();
+        native.paths = route.paths.iter().map(convert).collect::<Result<_>>()?;
+        if let Some(priorities) = &mut native.path_priorities {
+            for (path, _) in priorities.iter_mut() {
+                *path = convert(path)?;
+            }
+        }
+        Ok(native)
+    }
+
+    /// Register a prepared route, applying the id policy
+    fn insert_prepared(&mut self, route: PreparedRoute) -> Result<Option<RouteInfo>> {
+        let previous = match self.id_policy {
+            IdPolicy::Allow => None,
+            IdPolicy::Reject if self.routes.contains_key(&route.id) => {
+                return Err(invalid("id", format!("Duplicate route id: {}", route.id)));
+            }
+            IdPolicy::Reject => None,
+            IdPolicy::Replace => {
+                let previous = self.route_info(&route.id);
+                if previous.is_some() {
+                    self.delete_by_id(&route.id)?;
+                }
+                previous
+            }
+        };
+
+        for route_opts in route.opts {
+            self.insert_opts(route_opts)?;
+        }
+        if let Some(entry) = self.routes.get_mut(&route.id) {
+            entry.content_hash = route.content_hash;
+            entry.definition = route.definition;
+        }
+        Ok(previous)
+    }
+
+    /// Store an already processed route entry and register its path
+    fn insert_opts(&mut self, mut route_opts: RouteOpts) -> Result<()> {
+        self.intern_pattern(&mut route_opts);
+        if self.var_case == VarCase::Insensitive {
+            Self::lowercase_vars(&mut route_opts);
+        }
+        self.next_seq += 1;
+        route_opts.seq = self.next_seq;
+        self.routes
+            .entry(route_opts.id.clone())
+            .or_default()
+            .paths
+            .push(route_opts.path_org.clone());
 
         // Optimization: use hash map for exact path matching (always enabled)
         if route_opts.path_op == PathOp::Equal {
             let routes = self.hash_path.entry(route_opts.path.clone()).or_default();
-            routes.push(route_opts);
-            routes.sort_by(|a, b| a.cmp_priority(b));
+            routes.push(Arc::new(route_opts));
+            sort_bucket(routes, self.tie_breaker.as_ref(), &self.routes);
             return Ok(());
         }
 
         // Check if path already exists in radix tree
-        if let Some(idx) = self
-            .tree
-            .read()
-            .map_err(|e| anyhow::anyhow!("RwLock poisoned: {}", e))?
-            .find(route_opts.path.as_bytes())
-        {
+        let key = Self::tree_key(route_opts.path_op, &route_opts.path).into_owned();
+        if let Some(idx) = self.tree_for(route_opts.path_op).find(&key) {
             // Path exists, add to existing route array
             if let Some(routes) = self.match_data.get_mut(&idx) {
-                routes.push(route_opts);
-                routes.sort_by(|a, b| a.cmp_priority(b));
+                routes.push(Arc::new(route_opts));
+                sort_bucket(routes, self.tie_breaker.as_ref(), &self.routes);
                 return Ok(());
             }
         }
@@ -85,378 +635,3524 @@ impl RadixRouter {
         self.match_data_index += 1;
         let idx = self.match_data_index;
 
-        self.match_data.insert(idx, vec![route_opts.clone()]);
-
         // Insert into radix tree
         if !self
-            .tree
-            .write()
-            .map_err(|e| anyhow::anyhow!("RwLock poisoned: {}", e))?
-            .insert(route_opts.path.as_bytes(), idx as i32)
+            .tree_for_mut(route_opts.path_op)
+            .insert(&key, idx as i32)
         {
             anyhow::bail!("Failed to insert path: {}", route_opts.path);
         }
+        self.match_data.insert(idx, vec![Arc::new(route_opts)]);
 
         Ok(())
     }
 
+    /// Tree key of a non-exact lookup key
+    ///
+    /// Suffix keys are stored reversed, so walking up from the reversed
+    /// request path finds every suffix the path ends with.
+    fn tree_key(path_op: PathOp, key: &str) -> Cow<'_, [u8]> {
+        match path_op {
+            PathOp::SuffixMatch => Cow::Owned(key.bytes().rev().collect()),
+            _ => Cow::Borrowed(key.as_bytes()),
+        }
+    }
+
+    /// Radix tree holding non-exact keys of kind `path_op`
+    fn tree_for(&self, path_op: PathOp) -> &RadixTreeRaw {
+        match path_op {
+            PathOp::SuffixMatch => &self.suffix_tree,
+            _ => &self.tree,
+        }
+    }
+
+    /// Mutable [`tree_for`](Self::tree_for)
+    ///
+    /// # Panics
+    ///
+    /// On a copy made by `share`, whose trees belong to the published table.
+    fn tree_for_mut(&mut self, path_op: PathOp) -> &mut RadixTreeRaw {
+        let tree = match path_op {
+            PathOp::SuffixMatch => &mut self.suffix_tree,
+            _ => &mut self.tree,
+        };
+        Arc::get_mut(tree).expect("radix tree is shared with another table")
+    }
+
     /// Process route data
     fn process_route(&self, path: &str, route: &RadixNode) -> Result<RouteOpts> {
         // Process HTTP methods
         let methods = route.methods.unwrap_or(RadixHttpMethod::empty());
 
+        let custom_methods = route
+            .custom_methods
+            .as_ref()
+            .map(|methods| methods.iter().map(|m| m.to_uppercase()).collect());
+
         // Process hosts
         let hosts = route
             .hosts
             .as_ref()
             .map(|hosts| hosts.iter().map(|h| HostPattern::new(h)).collect());
 
+        // Process schemes and SNI patterns
+        let schemes = route
+            .schemes
+            .as_ref()
+            .map(|schemes| schemes.iter().map(|s| s.to_lowercase()).collect());
+        let snis = route
+            .snis
+            .as_ref()
+            .map(|snis| snis.iter().map(|s| HostPattern::new(s)).collect());
+
+        // Build the remote address prefix trie
+        let remote_addrs = match &route.remote_addrs {
+            Some(cidrs) => Some(std::sync::Arc::new(
+                IpTrie::from_cidrs(cidrs).with_context(|| {
+                    InvalidField::new(
+                        "remote_addrs",
+                        format!("Invalid remote_addrs for route: {}", route.id),
+                    )
+                })?,
+            )),
+            None => None,
+        };
+
+        if let Some(canary) = &route.canary {
+            if canary.percent > 100 {
+                return Err(invalid(
+                    "canary",
+                    format!(
+                        "Invalid canary percent for route {}: {}",
+                        route.id, canary.percent
+                    ),
+                ));
+            }
+        }
+
+        self.validate_path(path).with_context(|| {
+            InvalidField::new("paths", format!("Invalid path for route: {}", route.id))
+        })?;
+
         // Process path (extract parameters)
         let (actual_path, path_op, has_param) = self.parse_path(path);
 
-        // Pre-compile regex pattern if path has parameters
-        let compiled_pattern = if has_param {
-            let (pattern, names) = self.generate_pattern(path)?;
-            Some(std::sync::Arc::new((pattern, names)))
-        } else {
-            None
-        };
+        // Pre-compile the segment pattern if path has parameters
+        let compiled_pattern = has_param.then(|| Arc::new(PathPattern::compile(path)));
 
-        // Clone filter function if present
-        let filter_fn = if let Some(ref f) = route.filter_fn {
-            Some(f.clone())
-        } else {
-            None
-        };
+        // Clone filter functions if present
+        let filter_fn = route.filter_fn.clone();
+        let filters = route
+            .filters
+            .iter()
+            .map(|name| match self.filters.get(name) {
+                Some(filter) => Ok((name.clone(), filter.clone())),
+                None => Err(invalid(
+                    "filters",
+                    format!("Unknown filter for route {}: {}", route.id, name),
+                )),
+            })
+            .collect::<Result<_>>()?;
+        let context_filter = route.context_filter.clone();
+        let try_filter_fn = route.try_filter_fn.clone();
 
-        Ok(RouteOpts {
+        let mut opts = RouteOpts {
             id: route.id.clone(),
             path: actual_path,
             path_org: path.to_string(),
             path_op,
             has_param,
+            mount: None,
+            extensions: route.extensions.as_ref().map(|extensions| {
+                let mut extensions: Vec<String> = extensions
+                    .iter()
+                    .map(|ext| ext.trim_start_matches('.').to_lowercase())
+                    .collect();
+                // Longest first, so `tar.gz` wins over `gz`
+                extensions.sort_by_key(|ext| std::cmp::Reverse(ext.len()));
+                extensions
+            }),
+            rewrite: None,
             methods,
+            custom_methods,
             hosts,
+            schemes,
+            snis,
+            server_ports: route.server_ports.clone(),
+            remote_addrs,
+            valid_from: route.valid_from,
+            valid_until: route.valid_until,
+            expires_at: route.ttl.map(|ttl| SystemTime::now() + ttl),
             vars: route.vars.clone(),
             filter_fn,
-            priority: route.priority,
-            metadata: route.metadata.clone(),
+            filters,
+            context_filter,
+            try_filter_fn,
+            filter_errors: route.filter_errors,
+            priority: route
+                .path_priorities
+                .iter()
+                .flatten()
+                .find(|(p, _)| p == path)
+                .map_or(route.priority, |(_, priority)| *priority),
+            weight: route.weight,
+            canary: route.canary.clone(),
+            shadow: route.shadow,
+            metadata: Arc::new(route.metadata.clone()),
+            method_metadata: route.method_metadata.as_ref().map(|entries| {
+                entries
+                    .iter()
+                    .map(|(methods, metadata)| (*methods, Arc::new(metadata.clone())))
+                    .collect()
+            }),
+            tags: route.tags.clone(),
             compiled_pattern,
-        })
-    }
-
-    /// Parse path and extract parameter information
-    fn parse_path(&self, path: &str) -> (String, PathOp, bool) {
-        // Check for parameter :param
-        if let Some(pos) = path.find(':') {
-            let actual_path = &path[..pos];
-            return (actual_path.to_string(), PathOp::PrefixMatch, true);
-        }
-
-        // Check for wildcard *
-        if let Some(pos) = path.find('*') {
-            let actual_path = &path[..pos];
-            let has_param = pos != path.len() - 1;
-            return (actual_path.to_string(), PathOp::PrefixMatch, has_param);
-        }
-
-        // Exact path match
-        (path.to_string(), PathOp::Equal, false)
+            seq: 0,
+        };
+        opts.compile_rewrite(route.strip_prefix, route.rewrite.as_deref())
+            .with_context(|| {
+                InvalidField::new(
+                    "rewrite",
+                    format!("Invalid rewrite for route: {}", route.id),
+                )
+            })?;
+        Ok(opts)
     }
 
-    /// Match a route (thread-safe, immutable)
+    /// Choose among weighted routes that tie with the accepted `first`
     ///
-    /// Returns:
-    /// - `Ok(Some(MatchResult))` - Found a matching route
-    /// - `Ok(None)` - No matching route found
-    /// - `Err(_)` - System error (e.g., RwLock poisoned)
-    pub fn match_route(&self, path: &str, opts: &RadixMatchOpts) -> Result<Option<MatchResult>> {
-        // Normalize host to lowercase if present
-        let normalized_opts = if let Some(host) = &opts.host {
-            let mut new_opts = opts.clone();
-            new_opts.host = Some(host.to_lowercase());
-            new_opts
-        } else {
-            opts.clone()
+    /// Candidates are the weighted routes of the same bucket that rank equal
+    /// to `first` (see `RouteOpts::cmp_priority`) and also accept the
+    /// request. Unweighted winners are returned as-is.
+    fn pick_weighted<'a, 'p, C: Captures<'p>>(
+        &self,
+        routes: &'a [Arc<RouteOpts>],
+        first: &'a Arc<RouteOpts>,
+        path: &'p str,
+        opts: &RadixMatchOpts,
+        matched: C,
+        filters: &mut FilterEval<'_>,
+    ) -> (&'a Arc<RouteOpts>, C) {
+        let Some(weight) = first.weight else {
+            return (first, matched);
         };
 
-        // Storage for matched parameters
-        let mut matched = HashMap::new();
-
-        // Priority 1: Check hash_path for exact match (lock-free read)
-        if let Some(routes) = self.hash_path.get(path) {
-            for route in routes.iter() {
-                if self.match_route_opts(route, path, &normalized_opts, &mut matched) {
-                    matched.insert("_path".to_string(), path.to_string());
-                    return Ok(Some(MatchResult {
-                        id: route.id.clone(),
-                        metadata: route.metadata.clone(),
-                        matched,
-                    }));
-                }
-                matched.clear(); // Clear for next iteration
+        let mut candidates = vec![(first, weight, matched)];
+        let rest = routes
+            .iter()
+            .skip_while(|r| !Arc::ptr_eq(r, first))
+            .skip(1)
+            .take_while(|r| r.cmp_priority(first) == std::cmp::Ordering::Equal);
+        for route in rest {
+            let Some(weight) = route.weight.filter(|_| !route.shadow) else {
+                continue;
+            };
+            let mut matched = C::default();
+            if self
+                .match_route_opts(route, path, opts, &mut matched, filters)
+                .is_ok()
+            {
+                candidates.push((route, weight, matched));
             }
         }
 
-        // Priority 2: Use radix tree for prefix matching
-        // Create a temporary iterator for this query (thread-safe and async-safe)
-        let tree_guard = self
-            .tree
-            .read()
-            .map_err(|e| anyhow::anyhow!("Failed to acquire read lock on radix tree: {}", e))?;
-
-        let mut iterator = tree_guard
-            .new_iterator()
-            .context("Failed to create radix tree iterator")?;
-
-        // Search for matching prefixes
-        if !iterator.search(tree_guard.tree_ptr(), path.as_bytes()) {
-            return Ok(None);
-        }
-
-        // Iterate through matching routes (lock-free read from match_data)
-        while let Some(idx) = iterator.tree_up(path.as_bytes()) {
-            if let Some(routes) = self.match_data.get(&idx) {
-                for route in routes.iter() {
-                    if self.match_route_opts(route, path, &normalized_opts, &mut matched) {
-                        matched.insert("_path".to_string(), route.path_org.clone());
-                        return Ok(Some(MatchResult {
-                            id: route.id.clone(),
-                            metadata: route.metadata.clone(),
-                            matched,
-                        }));
-                    }
-                    matched.clear(); // Clear for next iteration
-                }
-            }
+        let total: u64 = candidates.iter().map(|(_, w, _)| *w as u64).sum();
+        if candidates.len() == 1 || total == 0 {
+            let (route, _, matched) = candidates.swap_remove(0);
+            return (route, matched);
         }
 
-        Ok(None)
+        let seed = opts
+            .vars
+            .as_ref()
+            .and_then(|vars| vars.get(WEIGHT_SEED_VAR));
+        let roll = match seed {
+            Some(seed) => stable_hash(seed),
+            None => random_u64(),
+        } % total;
+        let mut acc = 0;
+        let pos = candidates
+            .iter()
+            .position(|(_, w, _)| {
+                acc += *w as u64;
+                roll < acc
+            })
+            .unwrap_or(0);
+        let (route, _, matched) = candidates.swap_remove(pos);
+        (route, matched)
     }
 
-    /// Match route options
-    fn match_route_opts(
+    /// Build the result for a route that accepted the request
+    fn matched_result(
         &self,
         route: &RouteOpts,
         path: &str,
         opts: &RadixMatchOpts,
-        matched: &mut HashMap<String, String>,
-    ) -> bool {
-        // 1. HTTP method matching
-        if !route.methods.is_empty() {
-            if let Some(method) = &opts.method {
-                if let Some(m) = RadixHttpMethod::from_str(method) {
-                    if !route.methods.contains(m) {
-                        return false;
-                    }
-                } else {
-                    return false;
-                }
-            }
+        matched: Params,
+    ) -> MatchResult {
+        let host = match (&route.hosts, &opts.host) {
+            (Some(hosts), Some(host)) => hosts
+                .iter()
+                .find(|pattern| pattern.matches(host))
+                .map(HostPattern::to_string),
+            _ => None,
+        };
+        let upstream_path = route
+            .rewrite
+            .as_ref()
+            .map(|rewrite| rewrite.apply(&route.path, path, &matched));
+        MatchResult {
+            id: route.id.clone(),
+            priority: route.priority,
+            metadata: Arc::clone(route.metadata_for(opts.method.as_deref())),
+            remaining_path: route.remaining_path(path),
+            matched,
+            upstream_path,
+            info: MatchInfo {
+                pattern: route.path_org.clone(),
+                host,
+                method: opts.method.clone(),
+                mount: route.mount.clone(),
+            },
+            version: self.version,
+            shadows: Vec::new(),
         }
+    }
 
-        if let Some(method) = &opts.method {
-            matched.insert("_method".to_string(), method.clone());
+    /// Re-root a processed route under `prefix`
+    ///
+    /// The path is re-parsed and its pattern recompiled so params and
+    /// wildcards keep matching below the new prefix.
+    fn mount_opts(&self, route: &RouteOpts, prefix: &str) -> Result<RouteOpts> {
+        if route.path_op == PathOp::SuffixMatch {
+            anyhow::bail!("Suffix route cannot be mounted: {}", route.id);
         }
+        let path = format!("{}{}", prefix, route.path_org);
+        let (actual_path, path_op, has_param) = self.parse_path(&path);
+        let compiled_pattern = has_param.then(|| Arc::new(PathPattern::compile(&path)));
 
-        // 2. Host matching
-        if let Some(hosts) = &route.hosts {
-            let mut matched_host = false;
-            if let Some(host) = &opts.host {
-                for pattern in hosts {
-                    if pattern.matches(host) {
-                        let host_value = if pattern.is_wildcard {
-                            format!("*{}", pattern.pattern)
-                        } else {
-                            host.clone()
-                        };
-                        matched.insert("_host".to_string(), host_value);
-                        matched_host = true;
-                        break;
-                    }
-                }
-            }
+        let mut mounted = route.clone();
+        mounted.mount = Some(match &route.mount {
+            Some(inner) => format!("{}{}", prefix, inner),
+            None => prefix.to_string(),
+        });
+        mounted.path = actual_path;
+        mounted.path_org = path;
+        mounted.path_op = path_op;
+        mounted.has_param = has_param;
+        mounted.compiled_pattern = compiled_pattern;
+        Ok(mounted)
+    }
 
-            if !matched_host {
-                return false;
+    /// Reject malformed path patterns before they reach the tree
+    ///
+    /// Params (`:name`), wildcards (`*` / `*name`) and globs (`**` /
+    /// `**name`) must take a whole segment, names must be unique
+    /// identifiers, and a wildcard must be the last segment. A path has at
+    /// most one glob and never both a glob and a wildcard. Errors carry the
+    /// byte offset of the offending character.
+    ///
+    /// A suffix route (`*.php`, `*/healthz`) is a leading `*` followed by a
+    /// non-empty literal suffix.
+    fn validate_path(&self, path: &str) -> Result<()> {
+        if let Some(suffix) = path.strip_prefix('*') {
+            if suffix.is_empty() {
+                anyhow::bail!("{:?}: suffix route without a suffix", path);
+            }
+            if let Some(pos) = suffix.find([':', '*']) {
+                anyhow::bail!("{:?}: suffix must be literal (at byte {})", path, 1 + pos);
             }
+            return Ok(());
         }
-
-        // 3. Parameter matching
-        if !self.compare_param(path, route, matched) {
-            return false;
+        if !path.starts_with('/') {
+            anyhow::bail!("Path must start with '/': {:?}", path);
         }
 
-        // 4. Variable expression matching
-        if let Some(vars) = &route.vars {
-            if let Some(req_vars) = &opts.vars {
-                for expr in vars {
-                    if !expr.eval(req_vars) {
-                        return false;
-                    }
+        let mut names = HashSet::new();
+        let mut has_glob = false;
+        let mut offset = 0;
+        let mut segments = path.split('/').peekable();
+        while let Some(segment) = segments.next() {
+            let is_last = segments.peek().is_none();
+
+            if let Some(pos) = segment.find([':', '*']).filter(|pos| *pos > 0) {
+                anyhow::bail!(
+                    "{:?}: '{}' must start a segment (at byte {})",
+                    path,
+                    &segment[pos..pos + 1],
+                    offset + pos
+                );
+            }
+
+            let (kind, name) = if let Some(name) = segment.strip_prefix(':') {
+                if name.is_empty() {
+                    anyhow::bail!("{:?}: parameter without a name (at byte {})", path, offset);
+                }
+                ("parameter", name)
+            } else if let Some(name) = segment.strip_prefix("**") {
+                if has_glob {
+                    anyhow::bail!("{:?}: more than one '**' (at byte {})", path, offset);
+                }
+                has_glob = true;
+                ("glob", name)
+            } else if let Some(name) = segment.strip_prefix('*') {
+                if !is_last {
+                    anyhow::bail!(
+                        "{:?}: wildcard must be the last segment (at byte {})",
+                        path,
+                        offset
+                    );
+                }
+                if has_glob {
+                    anyhow::bail!(
+                        "{:?}: wildcard cannot follow '**' (at byte {})",
+                        path,
+                        offset
+                    );
                 }
+                ("wildcard", name)
             } else {
-                return false;
+                offset += segment.len() + 1;
+                continue;
+            };
+
+            if let Some(pos) =
+                name.find(|c: char| !(c.is_ascii_alphanumeric() || c == '_' || c == '-'))
+            {
+                anyhow::bail!(
+                    "{:?}: invalid character in {} name (at byte {})",
+                    path,
+                    kind,
+                    offset + 1 + pos
+                );
             }
+            if !name.is_empty() && !names.insert(name) {
+                anyhow::bail!(
+                    "{:?}: duplicate {} name '{}' (at byte {})",
+                    path,
+                    kind,
+                    name,
+                    offset
+                );
+            }
+            offset += segment.len() + 1;
         }
+        Ok(())
+    }
 
-        // 5. Custom filter function
-        if let Some(filter_fn) = &route.filter_fn {
-            let vars = opts.vars.as_ref().cloned().unwrap_or_default();
-            if !filter_fn(&vars, opts) {
-                return false;
+    /// Share the compiled pattern of `route_opts` with identical ones
+    ///
+    /// Large tables often repeat a template across methods, hosts or
+    /// versions; interning keeps one copy of each distinct pattern.
+    fn intern_pattern(&mut self, route_opts: &mut RouteOpts) {
+        let Some(pattern) = &mut route_opts.compiled_pattern else {
+            return;
+        };
+        match self.patterns.get(pattern) {
+            Some(shared) => *pattern = Arc::clone(shared),
+            None => {
+                self.patterns.insert(Arc::clone(pattern));
             }
         }
-
-        true
     }
 
-    /// Extract parameters from path
-    fn compare_param(
-        &self,
-        req_path: &str,
-        route: &RouteOpts,
-        matched: &mut HashMap<String, String>,
-    ) -> bool {
-        if !route.has_param {
-            return true;
+    /// Parse path and extract parameter information
+    fn parse_path(&self, path: &str) -> (String, PathOp, bool) {
+        // Suffix route: the tree key is the suffix
+        if let Some(suffix) = path.strip_prefix('*') {
+            return (suffix.to_string(), PathOp::SuffixMatch, false);
         }
 
-        // Use pre-compiled pattern (no cache lookup needed!)
-        let (pattern, names) = match &route.compiled_pattern {
-            Some(compiled) => {
-                let arc_ref = compiled.as_ref();
-                (&arc_ref.0, &arc_ref.1)
-            }
-            None => return true, // No pattern means no parameters to extract
-        };
-
-        if names.is_empty() {
-            return true;
+        // The tree key is the literal prefix before the first param, glob
+        // or wildcard
+        if let Some(pos) = path.find([':', '*']) {
+            let actual_path = &path[..pos];
+            // Only an unnamed tail wildcard needs no pattern
+            let has_param = pos != path.len() - 1;
+            return (actual_path.to_string(), PathOp::PrefixMatch, has_param);
         }
 
-        // Match and extract parameters
-        if let Some(captures) = pattern.captures(req_path) {
-            // Check if full path matches
-            if captures.get(0).map(|m| m.as_str()) != Some(req_path) {
-                return false;
-            }
-
-            // Extract parameters
-            for (i, name) in names.iter().enumerate() {
-                if let Some(cap) = captures.get(i + 1) {
-                    matched.insert(name.clone(), cap.as_str().to_string());
-                }
-            }
-
-            true
-        } else {
-            false
-        }
+        // Exact path match
+        (path.to_string(), PathOp::Equal, false)
     }
 
-    /// Generate regex pattern for path with parameters
-    fn generate_pattern(&self, path: &str) -> Result<(Regex, Vec<String>)> {
-        let mut names = Vec::new();
-        let parts: Vec<&str> = path.split('/').collect();
-        let mut pattern_parts = Vec::new();
-
-        for part in parts {
-            if part.is_empty() {
-                pattern_parts.push("".to_string());
+    /// Shared lookup for `match_route` and `match_outcome`
+    ///
+    /// When `allowed` is provided, the methods of candidates rejected only
+    /// because of the request method are accumulated into it.
+    fn lookup(
+        &self,
+        path: &str,
+        opts: &RadixMatchOpts,
+        policy: MatchPolicy,
+        allowed: Option<&mut RadixHttpMethod>,
+        context: Option<&dyn Any>,
+    ) -> Result<Option<MatchResult>> {
+        self.decoded(path, |path| {
+            let winner = self.find::<Params>(path, opts, policy, allowed, context)?;
+            Ok(winner.map(|winner| {
+                let mut result = self.matched_result(winner.route, path, opts, winner.matched);
+                result.shadows = winner.shadows;
+                if let Some(hook) = &self.metadata_hook {
+                    let rewrite = |result: &mut MatchResult| {
+                        if let Some(metadata) = hook(result, opts) {
+                            result.metadata = Arc::new(metadata);
+                        }
+                    };
+                    result.shadows.iter_mut().for_each(rewrite);
+                    rewrite(&mut result);
+                }
+                result
+            }))
+        })
+    }
+
+    /// Run `lookup` on the path as the decoding mode presents it
+    ///
+    /// A path the mode rejects matches nothing.
+    fn decoded<T>(
+        &self,
+        path: &str,
+        lookup: impl FnOnce(&str) -> Result<Option<T>>,
+    ) -> Result<Option<T>> {
+        match self.path_decoding.decode(path) {
+            Some(path) => lookup(&path),
+            None => {
+                debug_event!(path, "encoded slash rejected by the path decoding mode");
+                Ok(None)
+            }
+        }
+    }
+
+    /// Find the winning route for a request, capturing values into `C`
+    fn find<'p, C: Captures<'p>>(
+        &self,
+        path: &'p str,
+        opts: &RadixMatchOpts,
+        policy: MatchPolicy,
+        mut allowed: Option<&mut RadixHttpMethod>,
+        context: Option<&dyn Any>,
+    ) -> Result<Option<Winner<'_, C>>> {
+        if self.limits.rejects_path(path) {
+            debug_event!(path, "request path over the router limits");
+            return Ok(None);
+        }
+
+        // Fast miss: no route starts with this path's first segment
+        if !self.may_route(path) {
+            debug_event!(path, "no route shares the first path segment");
+            return Ok(None);
+        }
+
+        let normalized_opts = self.normalize_opts(opts);
+
+        let mut scan = Scan::new(policy, context);
+
+        // Priority 1: Check hash_path for exact match (lock-free read)
+        if let Some(routes) = self.hash_path.get(path) {
+            if self.scan_bucket(
+                routes,
+                None,
+                path,
+                &normalized_opts,
+                &mut scan,
+                allowed.as_deref_mut(),
+            ) {
+                return self.finish_scan(scan, path, &normalized_opts);
+            }
+        }
+
+        // Priority 2: Use radix tree for prefix matching
+        // Take an iterator for this query from the thread's pool (thread-safe and async-safe)
+        let mut iterator = self
+            .tree
+            .new_iterator()
+            .context("Failed to create radix tree iterator")?;
+
+        // Search for matching prefixes, then (longest first) suffixes
+        let reversed: SmallVec<[u8; 128]> = if self.has_suffixes {
+            path.bytes().rev().collect()
+        } else {
+            SmallVec::new()
+        };
+        let walks = [
+            (&self.tree, path.as_bytes()),
+            (&self.suffix_tree, reversed.as_slice()),
+        ];
+        for &(tree, key) in &walks[..1 + usize::from(self.has_suffixes)] {
+            if !iterator.search(tree, key) {
                 continue;
             }
+            // Iterate through matching routes (lock-free read from match_data)
+            while let Some(idx) = iterator.tree_up(key) {
+                if let Some(routes) = self.match_data.get(&idx) {
+                    if self.scan_bucket(
+                        routes,
+                        self.pattern_sets.get(&idx),
+                        path,
+                        &normalized_opts,
+                        &mut scan,
+                        allowed.as_deref_mut(),
+                    ) {
+                        return self.finish_scan(scan, path, &normalized_opts);
+                    }
+                }
+            }
+        }
 
-            if part.starts_with(':') {
-                // Parameter: :name
-                names.push(part[1..].to_string());
-                pattern_parts.push(r"([^/]+)".to_string());
-            } else if part.starts_with('*') {
-                // Wildcard: *name or *
-                let name = if part.len() > 1 {
-                    part[1..].to_string()
-                } else {
-                    ":ext".to_string()
+        self.finish_scan(scan, path, &normalized_opts)
+    }
+
+    /// Normalize host (and var names, if case-insensitive) to lowercase
+    ///
+    /// When some route reads vars, the request data is exposed as vars too.
+    fn normalize_opts(&self, opts: &RadixMatchOpts) -> RadixMatchOpts {
+        let mut normalized = opts.clone();
+        if let Some(host) = &mut normalized.host {
+            *host = host.to_lowercase();
+        }
+        if self.reads_vars {
+            normalized.expose_request_data();
+        }
+        if self.var_case == VarCase::Insensitive {
+            if let Some(vars) = &mut normalized.vars {
+                *vars = vars
+                    .drain()
+                    .map(|(name, value)| (name.to_lowercase(), value))
+                    .collect();
+            }
+        }
+        normalized
+    }
+
+    /// Lowercase the var names a route reads
+    fn lowercase_vars(route: &mut RouteOpts) {
+        for expr in route.vars.iter_mut().flatten() {
+            expr.for_each_var_mut(&mut |name| *name = name.to_lowercase());
+        }
+        if let Some(canary) = &mut route.canary {
+            canary.hash_on = canary.hash_on.to_lowercase();
+        }
+    }
+
+    /// Switch var name handling, lowercasing registered routes if needed
+    fn set_var_case(&mut self, case: VarCase) {
+        self.var_case = case;
+        if case == VarCase::Insensitive {
+            for route in self.iter_route_opts_mut() {
+                Self::lowercase_vars(Arc::make_mut(route));
+            }
+        }
+    }
+
+    /// Evaluate every candidate for `path` and record each verdict
+    fn trace(&self, path: &str, opts: &RadixMatchOpts) -> Result<Vec<CandidateTrace>> {
+        let Some(path) = self.path_decoding.decode(path) else {
+            return Ok(Vec::new());
+        };
+        let path = path.as_ref();
+        if self.limits.rejects_path(path) {
+            return Ok(Vec::new());
+        }
+        let opts = self.normalize_opts(opts);
+        let mut traces = Vec::new();
+        let mut record = |routes: &Bucket, source: CandidateSource| {
+            for route in routes {
+                let mut matched = ();
+                // Propagated errors show up as `Rejection::FilterError`
+                let mut filters = FilterEval::default();
+                traces.push(CandidateTrace {
+                    id: route.id.clone(),
+                    pattern: route.path_org.clone(),
+                    source,
+                    priority: route.priority,
+                    shadow: route.shadow,
+                    rejection: self
+                        .match_route_opts(route, path, &opts, &mut matched, &mut filters)
+                        .err(),
+                });
+            }
+        };
+
+        if let Some(routes) = self.hash_path.get(path) {
+            record(routes, CandidateSource::Exact);
+        }
+
+        let mut iterator = self
+            .tree
+            .new_iterator()
+            .context("Failed to create radix tree iterator")?;
+        if iterator.search(&self.tree, path.as_bytes()) {
+            while let Some(idx) = iterator.tree_up(path.as_bytes()) {
+                if let Some(routes) = self.match_data.get(&idx) {
+                    record(routes, CandidateSource::Prefix);
+                }
+            }
+        }
+        let reversed: Vec<u8> = path.bytes().rev().collect();
+        if self.has_suffixes && iterator.search(&self.suffix_tree, &reversed) {
+            while let Some(idx) = iterator.tree_up(&reversed) {
+                if let Some(routes) = self.match_data.get(&idx) {
+                    record(routes, CandidateSource::Suffix);
+                }
+            }
+        }
+        Ok(traces)
+    }
+
+    /// Settle weighted ties and hand out the winning candidate
+    ///
+    /// Fails with the first filter error the lookup ran into.
+    fn finish_scan<'a, 'p, C: Captures<'p>>(
+        &self,
+        mut scan: Scan<'a, '_, C>,
+        path: &'p str,
+        opts: &RadixMatchOpts,
+    ) -> Result<Option<Winner<'a, C>>> {
+        if let Some(err) = scan.filters.failure.take() {
+            return Err(err);
+        }
+        let Some(best) = scan.best else {
+            return Ok(None);
+        };
+        let (route, matched) = self.pick_weighted(
+            best.bucket,
+            best.route,
+            path,
+            opts,
+            best.matched,
+            &mut scan.filters,
+        );
+        if let Some(err) = scan.filters.failure {
+            return Err(err);
+        }
+        Ok(Some(Winner {
+            route,
+            matched,
+            shadows: scan.shadows,
+        }))
+    }
+
+    /// Evaluate the candidates of one bucket
+    ///
+    /// Offers accepted primary routes to the scan and collects accepted
+    /// shadow routes. With a pattern set, routes whose pattern doesn't fit
+    /// the path are rejected up front. Returns `true` once nothing further
+    /// can change the outcome.
+    fn scan_bucket<'a, 'p, C: Captures<'p>>(
+        &self,
+        routes: &'a [Arc<RouteOpts>],
+        set: Option<&PatternSet>,
+        path: &'p str,
+        opts: &RadixMatchOpts,
+        scan: &mut Scan<'a, '_, C>,
+        mut allowed: Option<&mut RadixHttpMethod>,
+    ) -> bool {
+        // Storage for matched parameters
+        let mut matched = C::default();
+        let fits = set.map(|set| set.matches(path));
+
+        for (i, route) in routes.iter().enumerate() {
+            if fits.as_ref().is_some_and(|fits| !fits.accepts(i)) {
+                debug_event!(
+                    route_id = %route.id,
+                    pattern = %route.path_org,
+                    reason = %Rejection::Params,
+                    "candidate rejected"
+                );
+                continue;
+            }
+            if route.shadow {
+                let mut shadow = Params::new();
+                if self
+                    .match_route_opts(route, path, opts, &mut shadow, &mut scan.filters)
+                    .is_ok()
+                {
+                    scan.shadows
+                        .push(self.matched_result(route, path, opts, shadow));
+                }
+                if scan.filters.failure.is_some() {
+                    return true;
+                }
+                continue;
+            }
+            if scan.settled() {
+                continue;
+            }
+
+            match self.match_route_opts(route, path, opts, &mut matched, &mut scan.filters) {
+                Ok(()) => {
+                    scan.offer(Candidate {
+                        route,
+                        bucket: routes,
+                        matched: std::mem::take(&mut matched),
+                    });
+                    if scan.settled() && !self.has_shadows {
+                        return true;
+                    }
+                }
+                Err(rejection) => {
+                    debug_event!(
+                        route_id = %route.id,
+                        pattern = %route.path_org,
+                        reason = %rejection,
+                        "candidate rejected"
+                    );
+                    if scan.filters.failure.is_some() {
+                        return true;
+                    }
+                    // Only count the route as allowing other methods
+                    // if the method was the sole reason to reject it
+                    if rejection == Rejection::Method {
+                        if let Some(allowed) = allowed.as_deref_mut() {
+                            matched.clear();
+                            let mut probe = FilterEval {
+                                context: scan.filters.context,
+                                failure: None,
+                            };
+                            if self
+                                .match_route_conditions(route, path, opts, &mut matched, &mut probe)
+                                .is_ok()
+                            {
+                                *allowed |= route.methods;
+                                // Custom verbs have no bit of their own
+                                if route.custom_methods.is_some() {
+                                    *allowed |= RadixHttpMethod::OTHER;
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+            matched.clear(); // Clear for next iteration
+        }
+
+        scan.settled() && !self.has_shadows
+    }
+
+    /// Match route options
+    ///
+    /// Returns the first check that rejected the route, if any. A filter
+    /// error under `FilterErrorPolicy::Propagate` is stored in `filters`
+    /// (keeping an earlier one) and rejects with `Rejection::FilterError`.
+    fn match_route_opts<'p, C: Captures<'p>>(
+        &self,
+        route: &RouteOpts,
+        path: &'p str,
+        opts: &RadixMatchOpts,
+        matched: &mut C,
+        filters: &mut FilterEval<'_>,
+    ) -> std::result::Result<(), Rejection> {
+        // 1. Validity window
+        if route.is_time_bound() {
+            let now = opts.now.unwrap_or_else(SystemTime::now);
+            if !route.is_active_at(now) {
+                return Err(Rejection::Inactive);
+            }
+        }
+
+        // 2. HTTP method matching
+        if !route.methods.is_empty() || route.custom_methods.is_some() {
+            if let Some(method) = &opts.method {
+                let accepted = match RadixHttpMethod::from_str(method) {
+                    Some(m) => route.methods.contains(m),
+                    None => {
+                        route.methods.contains(RadixHttpMethod::OTHER)
+                            || route
+                                .custom_methods
+                                .iter()
+                                .flatten()
+                                .any(|m| m.eq_ignore_ascii_case(method))
+                    }
                 };
-                names.push(name);
-                pattern_parts.push(r"(.*)".to_string());
+                if !accepted {
+                    return Err(Rejection::Method);
+                }
+            }
+        }
+
+        self.match_route_conditions(route, path, opts, matched, filters)
+    }
+
+    /// Checks of `match_route_opts` past the validity window and method
+    ///
+    /// Also run on its own for routes rejected by method only, to tell
+    /// whether the route would have accepted the request otherwise.
+    fn match_route_conditions<'p, C: Captures<'p>>(
+        &self,
+        route: &RouteOpts,
+        path: &'p str,
+        opts: &RadixMatchOpts,
+        matched: &mut C,
+        filters: &mut FilterEval<'_>,
+    ) -> std::result::Result<(), Rejection> {
+        // 3. Host matching
+        if let Some(hosts) = &route.hosts {
+            let accepted = opts
+                .host
+                .as_ref()
+                .is_some_and(|host| hosts.iter().any(|pattern| pattern.matches(host)));
+            if !accepted {
+                return Err(Rejection::Host);
+            }
+        }
+
+        // 4. Scheme and SNI matching
+        if let Some(schemes) = &route.schemes {
+            let matched_scheme = opts
+                .scheme
+                .as_ref()
+                .map(|scheme| schemes.iter().any(|s| s.eq_ignore_ascii_case(scheme)))
+                .unwrap_or(false);
+            if !matched_scheme {
+                return Err(Rejection::Scheme);
+            }
+        }
+
+        if let Some(snis) = &route.snis {
+            let matched_sni = opts
+                .sni
+                .as_ref()
+                .map(|sni| snis.iter().any(|pattern| pattern.matches(sni)))
+                .unwrap_or(false);
+            if !matched_sni {
+                return Err(Rejection::Sni);
+            }
+        }
+
+        // 5. Listener port matching
+        if let Some(ports) = &route.server_ports {
+            if !opts.server_port.is_some_and(|port| ports.contains(&port)) {
+                return Err(Rejection::ServerPort);
+            }
+        }
+
+        // 6. Remote address matching
+        if let Some(remote_addrs) = &route.remote_addrs {
+            let matched_addr = opts
+                .remote_addr
+                .as_ref()
+                .map(|addr| remote_addrs.contains_str(addr))
+                .unwrap_or(false);
+            if !matched_addr {
+                return Err(Rejection::RemoteAddr);
+            }
+        }
+
+        // 7. Parameter matching
+        if !self.compare_param(path, route, matched) {
+            return Err(Rejection::Params);
+        }
+        if self.traversal == TraversalPolicy::Reject && route.path_org.contains('*') {
+            // Everything past the literal prefix may end up in a capture
+            let wild = match route.path_op {
+                PathOp::SuffixMatch => path,
+                _ => &path[route.path.len()..],
+            };
+            if TraversalPolicy::is_traversal(wild) {
+                return Err(Rejection::Traversal);
+            }
+        }
+        if route.extensions.is_some() {
+            match route.extension_of(path) {
+                Some(ext) => matched.param(EXTENSION_PARAM, ext),
+                None => return Err(Rejection::Extension),
+            }
+        }
+
+        // 8. Variable expression matching
+        if let Some(vars) = &route.vars {
+            if let Some(req_vars) = &opts.vars {
+                for expr in vars {
+                    if !expr.eval(req_vars) {
+                        return Err(Rejection::Vars);
+                    }
+                }
             } else {
-                pattern_parts.push(regex::escape(part));
+                return Err(Rejection::Vars);
+            }
+        }
+
+        // 9. Canary bucketing
+        if let Some(canary) = &route.canary {
+            let in_canary = opts
+                .vars
+                .as_ref()
+                .and_then(|vars| vars.get(&canary.hash_on))
+                .is_some_and(|value| Bucketer::default().bucket(value) < canary.percent as u32);
+            if !in_canary {
+                return Err(Rejection::Canary);
             }
         }
 
-        let pattern_str = format!("^{}$", pattern_parts.join("/"));
-        let pattern = Regex::new(&pattern_str)
-            .with_context(|| format!("Failed to compile regex pattern for path: {}", path))?;
+        // 10. Custom filter functions
+        let vars = match (&route.filter_fn, &route.try_filter_fn) {
+            (None, None) if route.filters.is_empty() => Default::default(),
+            _ => opts.vars.clone().unwrap_or_default(),
+        };
+        if let Some(filter_fn) = &route.filter_fn {
+            if !filter_fn(&vars, opts) {
+                return Err(Rejection::Filter);
+            }
+        }
+        if !route.filters.iter().all(|(_, filter)| filter(&vars, opts)) {
+            return Err(Rejection::Filter);
+        }
+        if let Some(context_filter) = &route.context_filter {
+            if !context_filter.accepts(filters.context, opts) {
+                return Err(Rejection::Filter);
+            }
+        }
+        if let Some(try_filter_fn) = &route.try_filter_fn {
+            match try_filter_fn(&vars, opts) {
+                Ok(true) => {}
+                Ok(false) => return Err(Rejection::Filter),
+                Err(err) => match route.filter_errors {
+                    FilterErrorPolicy::FailOpen => {
+                        debug_event!(route_id = %route.id, error = %err, "filter failed open");
+                    }
+                    FilterErrorPolicy::FailClosed => return Err(Rejection::FilterError),
+                    FilterErrorPolicy::Propagate => {
+                        filters.failure.get_or_insert_with(|| {
+                            err.context(format!("Filter of route {} failed", route.id))
+                        });
+                        return Err(Rejection::FilterError);
+                    }
+                },
+            }
+        }
 
-        Ok((pattern, names))
+        Ok(())
     }
 
-    /// Update an existing route
-    pub fn update_route(&mut self, old_route: RadixNode, new_route: RadixNode) -> Result<()> {
-        // Remove old route
-        self.delete_route(old_route)?;
-        // Add new route
-        self.add_route(new_route)?;
+    /// Extract parameters from path
+    ///
+    /// On a mismatch some values may already have been captured; callers
+    /// clear `matched` before trying the next route.
+    fn compare_param<'p, C: Captures<'p>>(
+        &self,
+        req_path: &'p str,
+        route: &RouteOpts,
+        matched: &mut C,
+    ) -> bool {
+        if !route.has_param {
+            return true;
+        }
+
+        // Use pre-compiled pattern (no cache lookup needed!)
+        let Some(pattern) = &route.compiled_pattern else {
+            return true; // No pattern means no parameters to extract
+        };
+
+        let max_len = match self.limits.max_param_len {
+            None if !C::EXTRACT => return pattern.is_match(req_path),
+            max_len => max_len.unwrap_or(usize::MAX),
+        };
+
+        let names = pattern.names();
+        let mut fits = true;
+        let matches = pattern.captures(req_path, |i, value| {
+            fits &= value.len() <= max_len;
+            matched.param(&names[i], value);
+        });
+        matches && fits
+    }
+
+    /// Replace the metadata of a registered route in place
+    ///
+    /// Tree entries and compiled patterns are left untouched, so frequent
+    /// upstream changes don't churn the radix tree or recompile patterns.
+    /// The content hash follows the new metadata.
+    fn update_metadata(&mut self, id: &str, metadata: serde_json::Value) -> Result<()> {
+        let Some(entry) = self.routes.get(id) else {
+            anyhow::bail!("Route not found: {}", id);
+        };
+        let old = self
+            .find_route_opts(id, &entry.paths[0])
+            .map(|route| Arc::clone(&route.metadata))
+            .unwrap_or_default();
+        let Some(entry) = self.routes.get_mut(id) else {
+            anyhow::bail!("Route not found: {}", id);
+        };
+        let metadata = match &mut entry.definition {
+            // Keep the definition in step, so resolving it again against its
+            // template keeps the update
+            Some(definition) => {
+                definition.metadata = metadata;
+                entry.content_hash = definition.content_hash();
+                let template = definition
+                    .extends
+                    .as_ref()
+                    .and_then(|name| self.templates.get(name));
+                match template {
+                    Some(template) => definition.clone().inherit(template).metadata,
+                    None => definition.metadata.clone(),
+                }
+            }
+            None => {
+                entry.content_hash =
+                    RadixNode::rehash_metadata(entry.content_hash, &old, &metadata);
+                metadata
+            }
+        };
+
+        let metadata = Arc::new(metadata);
+        for route in self.iter_route_opts_mut().filter(|r| r.id == id) {
+            Arc::make_mut(route).metadata = Arc::clone(&metadata);
+        }
         Ok(())
     }
 
     /// Delete a route
-    pub fn delete_route(&mut self, route: RadixNode) -> Result<()> {
+    fn delete_route(&mut self, route: RadixNode) -> Result<()> {
         for path in &route.paths {
-            self.remove_route(path, &route)?;
+            self.remove_route(path, &route.id)?;
+        }
+        Ok(())
+    }
+
+    /// Delete a route and all of its paths by id
+    fn delete_by_id(&mut self, id: &str) -> Result<()> {
+        let paths = self
+            .routes
+            .get(id)
+            .map(|entry| entry.paths.clone())
+            .with_context(|| format!("Route not found: {}", id))?;
+        for path in &paths {
+            self.remove_route(path, id)?;
         }
         Ok(())
     }
 
+    /// Summary of the route registered under `id`
+    fn route_info(&self, id: &str) -> Option<RouteInfo> {
+        let entry = self.routes.get(id)?;
+        let route = self.find_route_opts(id, entry.paths.first()?)?;
+        Some(RouteInfo::new(route, entry.paths.clone()))
+    }
+
+    /// Find the stored entry of route `id` registered under `path`
+    fn find_route_opts(&self, id: &str, path: &str) -> Option<&RouteOpts> {
+        let (key, path_op, _) = self.parse_path(path);
+        let bucket = if path_op == PathOp::Equal {
+            self.hash_path.get(&key)?
+        } else {
+            let idx = self
+                .tree_for(path_op)
+                .find(&Self::tree_key(path_op, &key))?;
+            self.match_data.get(&idx)?
+        };
+        bucket
+            .iter()
+            .find(|r| r.id == id && r.path_org == path)
+            .map(Arc::as_ref)
+    }
+
+    /// All stored route entries (one per registered path)
+    fn iter_route_opts(&self) -> impl Iterator<Item = &RouteOpts> {
+        self.hash_path
+            .values()
+            .chain(self.match_data.values())
+            .flatten()
+            .map(Arc::as_ref)
+    }
+
+    /// Mutable access to all stored route entries
+    ///
+    /// Entries are shared with published snapshots; modify them through
+    /// `Arc::make_mut`.
+    fn iter_route_opts_mut(&mut self) -> impl Iterator<Item = &mut Arc<RouteOpts>> {
+        self.hash_path
+            .values_mut()
+            .chain(self.match_data.values_mut())
+            .flatten()
+    }
+
+    /// Remove every stored route entry matching `pred`
+    ///
+    /// Returns the number of distinct route ids that lost at least one entry.
+    fn remove_where<F>(&mut self, pred: F) -> usize
+    where
+        F: Fn(&RouteOpts) -> bool,
+    {
+        let mut removed = Vec::new();
+
+        self.hash_path.retain(|_, routes| {
+            routes.retain(|r| {
+                let hit = pred(r);
+                if hit {
+                    removed.push((r.id.clone(), r.path_org.clone()));
+                }
+                !hit
+            });
+            !routes.is_empty()
+        });
+
+        let mut emptied = Vec::new();
+        for (idx, routes) in self.match_data.iter_mut() {
+            // All entries of a bucket share the tree key
+            let key = match routes.first() {
+                Some(route) => (route.path_op, route.path.clone()),
+                None => continue,
+            };
+            routes.retain(|r| {
+                let hit = pred(r);
+                if hit {
+                    removed.push((r.id.clone(), r.path_org.clone()));
+                }
+                !hit
+            });
+            if routes.is_empty() {
+                emptied.push((*idx, key));
+            }
+        }
+
+        for (idx, (path_op, key)) in emptied {
+            self.match_data.remove(&idx);
+            let key = Self::tree_key(path_op, &key).into_owned();
+            self.tree_for_mut(path_op).remove(&key);
+        }
+
+        let mut ids = HashSet::new();
+        for (id, path) in removed {
+            self.unregister(&id, &path);
+            ids.insert(id);
+        }
+        ids.len()
+    }
+
+    /// Drop `path` from the registry entry of `id`
+    fn unregister(&mut self, id: &str, path: &str) {
+        if let Some(entry) = self.routes.get_mut(id) {
+            if let Some(pos) = entry.paths.iter().position(|p| p == path) {
+                entry.paths.remove(pos);
+            }
+            if entry.paths.is_empty() {
+                self.routes.remove(id);
+            }
+        }
+    }
+
     /// Remove a specific route from a path
-    fn remove_route(&mut self, path: &str, route: &RadixNode) -> Result<()> {
-        let route_opts = self.process_route(path, route)?;
+    ///
+    /// Only the entry registered for `path` goes; other paths of the route
+    /// may share the bucket (`/u/:x` and `/u/*y` both key on `/u/`).
+    fn remove_route(&mut self, path: &str, id: &str) -> Result<()> {
+        let (key, path_op, _) = self.parse_path(path);
+        let is_entry = |r: &Arc<RouteOpts>| r.id == id && r.path_org == path;
 
         // Check hash_path first (for exact match routes)
-        if route_opts.path_op == PathOp::Equal {
-            if let Some(routes) = self.hash_path.get_mut(&route_opts.path) {
-                routes.retain(|r| r.id != route_opts.id);
-                if routes.is_empty() {
-                    self.hash_path.remove(&route_opts.path);
+        if path_op == PathOp::Equal {
+            if let Some(routes) = self.hash_path.get_mut(&key) {
+                if let Some(pos) = routes.iter().position(is_entry) {
+                    routes.remove(pos);
+                    if routes.is_empty() {
+                        self.hash_path.remove(&key);
+                    }
+                    self.unregister(id, path);
+                    return Ok(());
                 }
-                return Ok(());
             }
-            anyhow::bail!("Route not found in hash_path: {}", route.id);
+            anyhow::bail!("Route not found in hash_path: {}", id);
         }
 
         // Find in radix tree
-        if let Some(idx) = self
-            .tree
-            .read()
-            .map_err(|e| anyhow::anyhow!("RwLock poisoned: {}", e))?
-            .find(route_opts.path.as_bytes())
-        {
+        let tree_key = Self::tree_key(path_op, &key).into_owned();
+        if let Some(idx) = self.tree_for(path_op).find(&tree_key) {
             if let Some(routes) = self.match_data.get_mut(&idx) {
-                routes.retain(|r| r.id != route_opts.id);
+                let Some(pos) = routes.iter().position(is_entry) else {
+                    anyhow::bail!("Route not found: {}", id);
+                };
+                routes.remove(pos);
 
                 if routes.is_empty() {
                     // Remove from tree if no routes left
                     self.match_data.remove(&idx);
-                    self.tree
-                        .write()
-                        .map_err(|e| anyhow::anyhow!("RwLock poisoned: {}", e))?
-                        .remove(route_opts.path.as_bytes());
+                    self.tree_for_mut(path_op).remove(&tree_key);
                 }
+                self.unregister(id, path);
                 return Ok(());
             }
         }
 
-        anyhow::bail!("Route not found: {}", route.id)
+        anyhow::bail!("Route not found: {}", id)
     }
-}
 
-impl std::fmt::Debug for RadixRouter {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        f.debug_struct("RadixRouter")
-            .field("match_data_index", &self.match_data_index)
-            .field("hash_path_count", &self.hash_path.len())
-            .field("match_data_count", &self.match_data.len())
-            .finish()
+    /// Deep copy of the route table with a freshly built radix tree
+    fn duplicate(&self) -> Result<Self> {
+        let mut tree = RadixTreeRaw::new().context("Failed to create radix tree")?;
+        let mut suffix_tree = RadixTreeRaw::new().context("Failed to create radix tree")?;
+        for (idx, routes) in &self.match_data {
+            if let Some(route) = routes.first() {
+                let target = match route.path_op {
+                    PathOp::SuffixMatch => &mut suffix_tree,
+                    _ => &mut tree,
+                };
+                let key = Self::tree_key(route.path_op, &route.path);
+                if !target.insert(&key, *idx as i32) {
+                    anyhow::bail!("Failed to insert path: {}", route.path);
+                }
+            }
+        }
+
+        Ok(Self {
+            tree: Arc::new(tree),
+            suffix_tree: Arc::new(suffix_tree),
+            match_data: self.match_data.clone(),
+            match_data_index: self.match_data_index,
+            hash_path: self.hash_path.clone(),
+            routes: self.routes.clone(),
+            has_shadows: self.has_shadows,
+            has_suffixes: self.has_suffixes,
+            reads_vars: self.reads_vars,
+            pattern_sets: FxHashMap::default(),
+            first_segments: None,
+            patterns: self.patterns.clone(),
+            tie_breaker: self.tie_breaker.clone(),
+            metadata_hook: self.metadata_hook.clone(),
+            next_seq: self.next_seq,
+            id_policy: self.id_policy,
+            var_case: self.var_case,
+            limits: self.limits,
+            traversal: self.traversal,
+            path_decoding: self.path_decoding,
+            table_limits: self.table_limits,
+            version: self.version,
+            filters: self.filters.clone(),
+            templates: self.templates.clone(),
+        })
+    }
+
+    /// Copy of the table sharing its radix trees
+    ///
+    /// Cheap next to [`duplicate`](Self::duplicate), which rebuilds both
+    /// trees, but only fit for changes that keep every tree key and bucket
+    /// index, such as metadata updates: inserting or removing keys panics.
+    fn share(&self) -> Self {
+        Self {
+            tree: Arc::clone(&self.tree),
+            suffix_tree: Arc::clone(&self.suffix_tree),
+            match_data: self.match_data.clone(),
+            match_data_index: self.match_data_index,
+            hash_path: self.hash_path.clone(),
+            routes: self.routes.clone(),
+            has_shadows: self.has_shadows,
+            has_suffixes: self.has_suffixes,
+            reads_vars: self.reads_vars,
+            pattern_sets: FxHashMap::default(),
+            first_segments: None,
+            patterns: self.patterns.clone(),
+            tie_breaker: self.tie_breaker.clone(),
+            metadata_hook: self.metadata_hook.clone(),
+            next_seq: self.next_seq,
+            id_policy: self.id_policy,
+            var_case: self.var_case,
+            limits: self.limits,
+            traversal: self.traversal,
+            path_decoding: self.path_decoding,
+            table_limits: self.table_limits,
+            version: self.version,
+            filters: self.filters.clone(),
+            templates: self.templates.clone(),
+        }
+    }
+
+    /// Copy of the table with dense indices and minimal capacity
+    ///
+    /// Tree indices are renumbered from 1 in their current order, so lookups
+    /// behave exactly as before; only storage left behind by deletions is
+    /// dropped.
+    fn compacted(&self) -> Result<Self> {
+        let mut tree = RadixTreeRaw::new().context("Failed to create radix tree")?;
+        let mut suffix_tree = RadixTreeRaw::new().context("Failed to create radix tree")?;
+        let mut indices: Vec<&usize> = self.match_data.keys().collect();
+        indices.sort_unstable();
+
+        let mut match_data = FxHashMap::with_capacity_and_hasher(indices.len(), Default::default());
+        for (new_idx, old_idx) in indices.into_iter().enumerate() {
+            let idx = new_idx + 1;
+            let mut routes = self.match_data[old_idx].clone();
+            routes.shrink_to_fit();
+            if let Some(route) = routes.first() {
+                let target = match route.path_op {
+                    PathOp::SuffixMatch => &mut suffix_tree,
+                    _ => &mut tree,
+                };
+                let key = Self::tree_key(route.path_op, &route.path);
+                if !target.insert(&key, idx as i32) {
+                    anyhow::bail!("Failed to insert path: {}", route.path);
+                }
+            }
+            match_data.insert(idx, routes);
+        }
+
+        let mut hash_path =
+            FxHashMap::with_capacity_and_hasher(self.hash_path.len(), Default::default());
+        for (path, routes) in &self.hash_path {
+            let mut routes = routes.clone();
+            routes.shrink_to_fit();
+            hash_path.insert(path.clone(), routes);
+        }
+
+        Ok(Self {
+            tree: Arc::new(tree),
+            suffix_tree: Arc::new(suffix_tree),
+            match_data_index: match_data.len(),
+            match_data,
+            hash_path,
+            routes: self.routes.clone(),
+            has_shadows: self.has_shadows,
+            has_suffixes: self.has_suffixes,
+            reads_vars: self.reads_vars,
+            pattern_sets: FxHashMap::default(),
+            first_segments: None,
+            patterns: self.patterns.clone(),
+            tie_breaker: self.tie_breaker.clone(),
+            metadata_hook: self.metadata_hook.clone(),
+            next_seq: self.next_seq,
+            id_policy: self.id_policy,
+            var_case: self.var_case,
+            limits: self.limits,
+            traversal: self.traversal,
+            path_decoding: self.path_decoding,
+            table_limits: self.table_limits,
+            version: self.version,
+            filters: self.filters.clone(),
+            templates: self.templates.clone(),
+        })
+    }
+
+    /// Capture the table for [`RadixRouter::to_snapshot`]
+    fn to_snapshot(&self, policy: MatchPolicy) -> Result<TableSnapshot> {
+        let records = |routes: &Bucket| -> Result<Vec<RouteRecord>> {
+            routes.iter().map(|r| RouteRecord::from_opts(r)).collect()
+        };
+
+        let mut match_data = self
+            .match_data
+            .iter()
+            .map(|(idx, routes)| Ok((*idx, records(routes)?)))
+            .collect::<Result<Vec<_>>>()?;
+        match_data.sort_unstable_by_key(|(idx, _)| *idx);
+        let mut hash_path = self
+            .hash_path
+            .iter()
+            .map(|(path, routes)| Ok((path.clone(), records(routes)?)))
+            .collect::<Result<Vec<_>>>()?;
+        hash_path.sort_unstable_by(|a, b| a.0.cmp(&b.0));
+
+        Ok(TableSnapshot {
+            policy: snapshot::encode_match_policy(policy),
+            id_policy: snapshot::encode_id_policy(self.id_policy),
+            insensitive_vars: self.var_case == VarCase::Insensitive,
+            limits: snapshot::encode_limits(self.limits),
+            reject_traversal: self.traversal == TraversalPolicy::Reject,
+            path_decoding: snapshot::encode_path_decoding(self.path_decoding),
+            table_limits: snapshot::encode_table_limits(self.table_limits),
+            match_data_index: self.match_data_index,
+            next_seq: self.next_seq,
+            match_data,
+            hash_path,
+            routes: self
+                .routes
+                .iter()
+                .map(|(id, entry)| {
+                    let definition = match &entry.definition {
+                        Some(definition) => Some(DefinitionRecord::from_node(definition)?),
+                        None => None,
+                    };
+                    Ok((
+                        id.clone(),
+                        entry.paths.clone(),
+                        entry.content_hash,
+                        definition,
+                    ))
+                })
+                .collect::<Result<_>>()?,
+        })
+    }
+
+    /// Rebuild a table captured by [`RouteTable::to_snapshot`]
+    fn from_snapshot(snapshot: TableSnapshot) -> Result<(Self, MatchPolicy)> {
+        let policy = snapshot::decode_match_policy(snapshot.policy)?;
+        let mut table = Self::new()?;
+        table.id_policy = snapshot::decode_id_policy(snapshot.id_policy)?;
+        if snapshot.insensitive_vars {
+            table.var_case = VarCase::Insensitive;
+        }
+        table.limits = snapshot::decode_limits(snapshot.limits);
+        if snapshot.reject_traversal {
+            table.traversal = TraversalPolicy::Reject;
+        }
+        table.path_decoding = snapshot::decode_path_decoding(snapshot.path_decoding)?;
+        table.table_limits = snapshot::decode_table_limits(snapshot.table_limits);
+        table.match_data_index = snapshot.match_data_index;
+        table.next_seq = snapshot.next_seq;
+
+        let opts = |table: &mut Self, records: Vec<RouteRecord>| -> Result<Bucket> {
+            records
+                .into_iter()
+                .map(|record| {
+                    let mut opts = record.into_opts()?;
+                    table.intern_pattern(&mut opts);
+                    Ok(Arc::new(opts))
+                })
+                .collect()
+        };
+        for (idx, records) in snapshot.match_data {
+            let routes = opts(&mut table, records)?;
+            let Some(first) = routes.first() else {
+                continue;
+            };
+            let key = Self::tree_key(first.path_op, &first.path).into_owned();
+            if !table.tree_for_mut(first.path_op).insert(&key, idx as i32) {
+                anyhow::bail!("Failed to insert path: {}", first.path);
+            }
+            table.match_data.insert(idx, routes);
+        }
+        for (path, records) in snapshot.hash_path {
+            let routes = opts(&mut table, records)?;
+            table.hash_path.insert(path, routes);
+        }
+        for (id, paths, content_hash, definition) in snapshot.routes {
+            table.routes.insert(
+                id,
+                RouteEntry {
+                    paths,
+                    content_hash,
+                    definition: definition.map(DefinitionRecord::into_node).transpose()?,
+                },
+            );
+        }
+        table.seal();
+        Ok((table, policy))
+    }
+
+    /// Copy every path of route `id` from `other` into this table
+    fn copy_route_from(&mut self, other: &RouteTable, id: &str) -> Result<()> {
+        let entry = other
+            .routes
+            .get(id)
+            .with_context(|| format!("Route not found: {}", id))?;
+        for path in &entry.paths {
+            let route = other
+                .find_route_opts(id, path)
+                .with_context(|| format!("Route not found: {}", id))?;
+            self.insert_opts(RouteOpts::clone(route))?;
+        }
+        if let Some(own) = self.routes.get_mut(id) {
+            own.content_hash = entry.content_hash;
+            own.definition = entry.definition.clone();
+        }
+        Ok(())
+    }
+
+    /// Copy every route of `other` into this table below `prefix`
+    fn mount_from(&mut self, other: &RouteTable, prefix: &str) -> Result<()> {
+        for (id, entry) in &other.routes {
+            if self.routes.contains_key(id) {
+                anyhow::bail!("Conflicting route id: {}", id);
+            }
+            for path in &entry.paths {
+                let route = other
+                    .find_route_opts(id, path)
+                    .with_context(|| format!("Route not found: {}", id))?;
+                let mounted = self.mount_opts(route, prefix)?;
+                self.insert_opts(mounted)?;
+            }
+            if let Some(own) = self.routes.get_mut(id) {
+                // Hash the mounted definition, i.e. with prefixed paths;
+                // routes taking their paths from a template have none
+                let inherits_paths = entry
+                    .definition
+                    .as_ref()
+                    .is_some_and(|definition| definition.paths.is_empty());
+                own.content_hash = if inherits_paths {
+                    entry.content_hash
+                } else {
+                    RadixNode::rehash_paths(entry.content_hash, &entry.paths, &own.paths)
+                };
+            }
+        }
+        Ok(())
+    }
+
+    /// Buckets grouped by lookup key, sorted so prefixes precede extensions
+    ///
+    /// Each entry carries the key and its routes, tagged with the structure
+    /// they live in. Suffix buckets are keyed by their `*suffix` pattern so
+    /// they never merge with a path key.
+    fn buckets_by_key(&self) -> BTreeMap<&str, Vec<(&RouteOpts, CandidateSource)>> {
+        let mut keys: BTreeMap<&str, Vec<(&RouteOpts, CandidateSource)>> = BTreeMap::new();
+        for (key, routes) in &self.hash_path {
+            let entry = keys.entry(key.as_str()).or_default();
+            entry.extend(routes.iter().map(|r| (&**r, CandidateSource::Exact)));
+        }
+        for routes in self.match_data.values() {
+            if let Some(first) = routes.first() {
+                let (key, source) = match first.path_op {
+                    PathOp::SuffixMatch => (first.path_org.as_str(), CandidateSource::Suffix),
+                    _ => (first.path.as_str(), CandidateSource::Prefix),
+                };
+                let entry = keys.entry(key).or_default();
+                entry.extend(routes.iter().map(|r| (&**r, source)));
+            }
+        }
+        keys
+    }
+
+    /// Human-readable tree of lookup keys and their routes
+    fn dump(&self) -> String {
+        use std::fmt::Write;
+
+        let mut out = String::new();
+        let mut ancestors: Vec<&str> = Vec::new();
+        for (key, routes) in self.buckets_by_key() {
+            while ancestors.last().is_some_and(|a| !key.starts_with(a)) {
+                ancestors.pop();
+            }
+            let indent = "  ".repeat(ancestors.len());
+            let _ = writeln!(out, "{}{}", indent, key);
+            for (route, source) in routes {
+                let source = match source {
+                    CandidateSource::Exact => "exact",
+                    CandidateSource::Prefix => "prefix",
+                    CandidateSource::Suffix => "suffix",
+                };
+                let _ = writeln!(
+                    out,
+                    "{}  - {} [{}] {} priority={} methods={} hosts={}",
+                    indent,
+                    route.id,
+                    source,
+                    route.path_org,
+                    route.priority,
+                    route.methods_label(),
+                    route.hosts_label(),
+                );
+            }
+            ancestors.push(key);
+        }
+        out
+    }
+
+    /// Summaries of the routes `query` accepts, sorted by id
+    fn find_routes(&self, query: &RouteQuery) -> Vec<RouteInfo> {
+        let mut ids: Vec<&String> = self.routes.keys().collect();
+        ids.sort();
+        ids.into_iter()
+            .filter_map(|id| {
+                let entry = &self.routes[id];
+                let route = self.find_route_opts(id, entry.paths.first()?)?;
+                query
+                    .accepts(route, &entry.paths)
+                    .then(|| RouteInfo::new(route, entry.paths.clone()))
+            })
+            .collect()
+    }
+
+    /// Aligned text table with one row per route path, sorted by id
+    fn table(&self) -> String {
+        const HEADER: [&str; 6] = ["ID", "METHODS", "HOSTS", "PATH", "PRIORITY", "UPSTREAM"];
+
+        let mut ids: Vec<&String> = self.routes.keys().collect();
+        ids.sort();
+        let mut rows = vec![HEADER.map(str::to_string)];
+        for id in ids {
+            for path in &self.routes[id].paths {
+                let Some(route) = self.find_route_opts(id, path) else {
+                    continue;
+                };
+                let upstream = match route.metadata.get("upstream") {
+                    Some(serde_json::Value::String(upstream)) => upstream.clone(),
+                    Some(upstream) => upstream.to_string(),
+                    None => "-".to_string(),
+                };
+                rows.push([
+                    id.clone(),
+                    route.methods_label(),
+                    route.hosts_label(),
+                    route.path_org.clone(),
+                    route.priority.to_string(),
+                    upstream,
+                ]);
+            }
+        }
+
+        let widths: Vec<usize> = (0..HEADER.len())
+            .map(|col| {
+                rows.iter()
+                    .map(|row| row[col].chars().count())
+                    .max()
+                    .unwrap_or(0)
+            })
+            .collect();
+        let mut out = String::new();
+        for row in &rows {
+            let line: Vec<String> = row
+                .iter()
+                .zip(&widths)
+                .map(|(cell, width)| format!("{:<width$}", cell, width = *width))
+                .collect();
+            out.push_str(line.join("  ").trim_end());
+            out.push('\n');
+        }
+        out
+    }
+
+    /// Routes shadowed by an earlier route of the same bucket
+    fn conflicts(&self) -> Vec<Conflict> {
+        let mut found = Vec::new();
+        for routes in self.buckets_by_key().into_values() {
+            for (n, (route, _)) in routes.iter().enumerate() {
+                if let Some((earlier, _)) = routes[..n].iter().find(|(e, _)| e.covers(route)) {
+                    found.push(Conflict {
+                        id: route.id.clone(),
+                        pattern: route.path_org.clone(),
+                        shadowed_by: earlier.id.clone(),
+                    });
+                }
+            }
+        }
+        found
+    }
+
+    /// Graphviz DOT rendering of the key tree with route annotations
+    fn to_dot(&self) -> String {
+        use std::fmt::Write;
+
+        fn escape(text: &str) -> String {
+            text.replace('\\', "\\\\").replace('"', "\\\"")
+        }
+
+        let mut out = String::from("digraph routes {\n    rankdir=LR;\n");
+        out.push_str("    root [label=\"/\", shape=point];\n");
+        let mut ancestors: Vec<(&str, usize)> = Vec::new();
+        for (n, (key, routes)) in self.buckets_by_key().into_iter().enumerate() {
+            while ancestors.last().is_some_and(|(a, _)| !key.starts_with(a)) {
+                ancestors.pop();
+            }
+            let (parent, edge_label) = match ancestors.last() {
+                Some((a, i)) => (format!("k{}", i), &key[a.len()..]),
+                None => ("root".to_string(), key),
+            };
+            let _ = writeln!(
+                out,
+                "    k{} [label=\"{}\", shape=ellipse];",
+                n,
+                escape(key)
+            );
+            let _ = writeln!(
+                out,
+                "    {} -> k{} [label=\"{}\"];",
+                parent,
+                n,
+                escape(edge_label)
+            );
+            for (i, (route, source)) in routes.iter().enumerate() {
+                let source = match source {
+                    CandidateSource::Exact => "exact",
+                    CandidateSource::Prefix => "prefix",
+                    CandidateSource::Suffix => "suffix",
+                };
+                let label = format!(
+                    "{}\\n{} [{}]\\npriority={} methods={} hosts={}",
+                    escape(&route.id),
+                    escape(&route.path_org),
+                    source,
+                    route.priority,
+                    escape(&route.methods_label()),
+                    escape(&route.hosts_label()),
+                );
+                let _ = writeln!(out, "    k{}r{} [label=\"{}\", shape=box];", n, i, label);
+                let _ = writeln!(out, "    k{} -> k{}r{} [style=dashed];", n, n, i);
+            }
+            ancestors.push((key, n));
+        }
+        out.push_str("}\n");
+        out
+    }
+
+    /// Register a named filter that routes reference through `filters`
+    fn register_filter(&mut self, name: String, filter: FilterFn) -> Result<()> {
+        if self.filters.contains_key(&name) {
+            anyhow::bail!("Filter already registered: {}", name);
+        }
+        self.filters.insert(name, filter);
+        Ok(())
+    }
+
+    /// Register or replace a template, re-resolving the routes extending it
+    fn register_template(&mut self, name: String, template: RadixNode) -> Result<()> {
+        if template.extends.is_some() {
+            anyhow::bail!("Template {} cannot extend another template", name);
+        }
+        self.templates.insert(name.clone(), template);
+        let extending: Vec<RadixNode> = self
+            .routes
+            .values()
+            .filter_map(|entry| entry.definition.clone())
+            .filter(|definition| definition.extends.as_deref() == Some(name.as_str()))
+            .collect();
+        for definition in extending {
+            self.delete_by_id(&definition.id)?;
+            self.add_route(definition)?;
+        }
+        Ok(())
+    }
+
+    /// Install a tie-breaker and re-sort every bucket with it
+    fn set_tie_breaker(&mut self, tie_breaker: Option<TieBreaker>) {
+        self.tie_breaker = tie_breaker;
+        for bucket in self
+            .hash_path
+            .values_mut()
+            .chain(self.match_data.values_mut())
+        {
+            sort_bucket(bucket, self.tie_breaker.as_ref(), &self.routes);
+        }
+    }
+
+    /// Apply recorded transaction operations in order
+    fn apply_ops(&mut self, ops: Vec<TxOp>) -> Result<()> {
+        for (i, op) in ops.into_iter().enumerate() {
+            self.apply_op(op)
+                .with_context(|| format!("Transaction aborted at operation {}", i))?;
+        }
+        Ok(())
+    }
+
+    /// Apply a single recorded transaction operation
+    fn apply_op(&mut self, op: TxOp) -> Result<()> {
+        match op {
+            TxOp::Add(route) => self.add_route(route).map(drop),
+            TxOp::Delete(route) => self.delete_route(route),
+            TxOp::DeleteById(id) => self.delete_by_id(&id),
+            TxOp::UpdateMetadata(id, metadata) => self.update_metadata(&id, metadata),
+        }
+    }
+}
+
+/// Split a request target into its path and the options to match it with
+///
+/// The options are only copied when there is a query string to merge.
+fn split_target<'u, 'o>(
+    uri: &'u str,
+    opts: &'o RadixMatchOpts,
+) -> (&'u str, Cow<'o, RadixMatchOpts>) {
+    let uri = uri.split_once('#').map_or(uri, |(target, _)| target);
+    match uri.split_once('?') {
+        Some((path, query)) => {
+            let mut opts = opts.clone();
+            opts.add_query_args(query);
+            (path, Cow::Owned(opts))
+        }
+        None => (uri, Cow::Borrowed(opts)),
+    }
+}
+
+/// Write the path params `match_route` reports in `matched` into `params`
+fn fill_params(route: &RouteOpts, values: &[&str], params: &mut Params) {
+    let names = route.capture_names();
+    for (name, value) in names.zip(values) {
+        params.insert(name, value);
+    }
+}
+
+/// Order a bucket by priority, then path length, then the tie-breaker
+///
+/// Entries still tied are kept in insertion order, so the winner never
+/// depends on sort implementation details.
+fn sort_bucket(
+    bucket: &mut [Arc<RouteOpts>],
+    tie_breaker: Option<&TieBreaker>,
+    registry: &BTreeMap<String, RouteEntry>,
+) {
+    let Some(tie_breaker) = tie_breaker else {
+        bucket.sort_by(|a, b| a.cmp_priority(b).then(a.seq.cmp(&b.seq)));
+        return;
+    };
+
+    // Describe each route once instead of on every comparison
+    let mut keyed: Vec<(RouteInfo, Arc<RouteOpts>)> = bucket
+        .iter()
+        .map(|route| {
+            let paths = registry
+                .get(&route.id)
+                .map(|entry| entry.paths.clone())
+                .unwrap_or_default();
+            (RouteInfo::new(route, paths), Arc::clone(route))
+        })
+        .collect();
+    keyed.sort_by(|(info_a, a), (info_b, b)| {
+        a.cmp_priority(b)
+            .then_with(|| tie_breaker(info_a, info_b))
+            .then(a.seq.cmp(&b.seq))
+    });
+    for (slot, (_, route)) in bucket.iter_mut().zip(keyed) {
+        *slot = route;
+    }
+}
+
+/// Per-call random number from the std hasher's random keys
+fn random_u64() -> u64 {
+    use std::hash::{BuildHasher, Hasher};
+
+    let mut hasher = std::collections::hash_map::RandomState::new().build_hasher();
+    hasher.write_u64(
+        SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .map(|d| d.as_nanos() as u64)
+            .unwrap_or_default(),
+    );
+    hasher.finish()
+}
+
+impl RadixRouter {
+    /// Create a new empty router
+    pub fn new() -> Result<Self> {
+        Self::with_policy(MatchPolicy::default())
+    }
+
+    /// Create a new empty router that ranks candidates with `policy`
+    pub fn with_policy(policy: MatchPolicy) -> Result<Self> {
+        Ok(Self {
+            table: Arc::new(ArcSwap::from_pointee(RouteTable::new()?)),
+            writer: Mutex::new(()),
+            policy,
+            observers: ArcSwap::default(),
+            audit: Mutex::default(),
+            history: Mutex::default(),
+        })
+    }
+
+    /// Create a router holding `routes`, built in one pass
+    ///
+    /// Every route is validated first and any invalid one fails the whole
+    /// call, as with [`add_routes`](Self::add_routes). Meant for cold starts
+    /// with large tables: the routes are prepared as one batch, in parallel
+    /// when the `parallel` feature is enabled, and the table is published
+    /// once.
+    pub fn with_routes(routes: Vec<RadixNode>) -> Result<Self> {
+        let router = Self::new()?;
+        router.add_routes(routes)?;
+        Ok(router)
+    }
+
+    /// Former name of [`with_routes`](Self::with_routes)
+    #[deprecated(since = "0.4.0", note = "renamed to `with_routes`")]
+    pub fn from_routes(routes: Vec<RadixNode>) -> Result<Self> {
+        Self::with_routes(routes)
+    }
+
+    /// Candidate ranking policy chosen at construction
+    pub fn policy(&self) -> MatchPolicy {
+        self.policy
+    }
+
+    /// Load the current table snapshot (lock-free)
+    fn snapshot(&self) -> Guard<Arc<RouteTable>> {
+        self.table.load()
+    }
+
+    /// Serialize writers
+    ///
+    /// A panicking writer never publishes its half-built copy, so a
+    /// poisoned lock carries no broken state and is simply recovered.
+    fn lock_writer(&self) -> MutexGuard<'_, ()> {
+        self.writer.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn lock_history(&self) -> MutexGuard<'_, History> {
+        self.history.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Apply a mutation to a private copy and publish it on success
+    ///
+    /// Readers keep using the current snapshot while the copy is modified.
+    fn rebuild<T, F>(&self, f: F) -> Result<T>
+    where
+        F: FnOnce(&mut RouteTable) -> Result<T>,
+    {
+        self.rebuild_from(RouteTable::duplicate, f)
+    }
+
+    /// [`rebuild`](Self::rebuild) for changes that keep every tree key
+    ///
+    /// The copy shares the radix trees of the current snapshot instead of
+    /// building its own (see `RouteTable::share`).
+    fn rebuild_shared<T, F>(&self, f: F) -> Result<T>
+    where
+        F: FnOnce(&mut RouteTable) -> Result<T>,
+    {
+        self.rebuild_from(|table| Ok(table.share()), f)
+    }
+
+    /// Copy the current snapshot with `copy`, mutate it and publish it
+    fn rebuild_from<T, C, F>(&self, copy: C, f: F) -> Result<T>
+    where
+        C: FnOnce(&RouteTable) -> Result<RouteTable>,
+        F: FnOnce(&mut RouteTable) -> Result<T>,
+    {
+        let _writer = self.lock_writer();
+        let mut working = copy(&self.snapshot())?;
+        let out = f(&mut working)?;
+        working.check_table_limits()?;
+        self.publish(working);
+        Ok(out)
+    }
+
+    /// Publish a new table and notify observers of the changes
+    ///
+    /// Must be called with the writer lock held.
+    fn publish(&self, mut table: RouteTable) {
+        table.seal();
+        table.version = self.snapshot().version + 1;
+        let table = Arc::new(table);
+        let old = self.table.swap(Arc::clone(&table));
+        self.audit_changes(&old, &table);
+        self.lock_history().push(Arc::clone(&old));
+
+        let observers = self.observers.load();
+        if observers.is_empty() {
+            return;
+        }
+        let ids: BTreeSet<&String> = old.routes.keys().chain(table.routes.keys()).collect();
+        for id in ids {
+            match (old.route_info(id), table.route_info(id)) {
+                (None, Some(added)) => observers.iter().for_each(|o| o.on_route_added(&added)),
+                (Some(removed), None) => {
+                    observers.iter().for_each(|o| o.on_route_removed(&removed))
+                }
+                (Some(before), Some(after)) if before != after => observers
+                    .iter()
+                    .for_each(|o| o.on_route_updated(&before, &after)),
+                _ => {}
+            }
+        }
+    }
+
+    /// Record the route changes between two tables in the audit trail
+    fn audit_changes(&self, old: &RouteTable, new: &RouteTable) {
+        let mut audit = self.audit.lock().unwrap_or_else(PoisonError::into_inner);
+        if !audit.is_enabled() {
+            return;
+        }
+        let at = SystemTime::now();
+        let ids: BTreeSet<&String> = old.routes.keys().chain(new.routes.keys()).collect();
+        for id in ids {
+            let before = old.routes.get(id).map(|entry| entry.content_hash);
+            let after = new.routes.get(id).map(|entry| entry.content_hash);
+            let action = match (before, after) {
+                (None, Some(_)) => AuditAction::Added,
+                (Some(_), None) => AuditAction::Removed,
+                (Some(b), Some(a)) if b != a || old.route_info(id) != new.route_info(id) => {
+                    AuditAction::Updated
+                }
+                _ => continue,
+            };
+            audit.record(AuditEntry {
+                at,
+                action,
+                id: id.clone(),
+                before,
+                after,
+            });
+        }
+    }
+
+    /// Keep the last `capacity` route changes in memory (0 turns it off)
+    ///
+    /// Every published add, update and removal is recorded from then on,
+    /// whichever API made it; see [`audit_log`](Self::audit_log). The log
+    /// is not carried over to clones of the router.
+    pub fn enable_audit_log(&self, capacity: usize) {
+        let _writer = self.lock_writer();
+        let mut audit = self.audit.lock().unwrap_or_else(PoisonError::into_inner);
+        audit.set_capacity(capacity);
+    }
+
+    /// Forward every route change to `sink`, with or without the in-memory log
+    pub fn add_audit_sink(&self, sink: Arc<dyn AuditSink>) {
+        let _writer = self.lock_writer();
+        let mut audit = self.audit.lock().unwrap_or_else(PoisonError::into_inner);
+        audit.add_sink(sink);
+    }
+
+    /// Route changes kept by [`enable_audit_log`](Self::enable_audit_log),
+    /// oldest first
+    pub fn audit_log(&self) -> Vec<AuditEntry> {
+        let audit = self.audit.lock().unwrap_or_else(PoisonError::into_inner);
+        audit.entries()
+    }
+
+    /// Register an observer for route changes and matches
+    ///
+    /// Observers are not carried over to clones of the router.
+    pub fn add_observer(&self, observer: Arc<dyn RouteObserver>) {
+        let _writer = self.lock_writer();
+        let mut observers = Vec::clone(&self.observers.load());
+        observers.push(observer);
+        self.observers.store(Arc::new(observers));
+    }
+
+    /// Notify observers about a match
+    fn notify_match(&self, path: &str, result: Option<&MatchResult>) {
+        if let Some(result) = result {
+            for observer in self.observers.load().iter() {
+                observer.on_match(path, result);
+            }
+        }
+    }
+
+    /// Add multiple routes to the router
+    ///
+    /// The batch is published as a single snapshot: either every route is
+    /// added or, on error, none is. Routes are validated and compiled before
+    /// any is inserted; the `parallel` feature spreads that work over the
+    /// rayon thread pool. When several routes are invalid (or, under
+    /// `IdPolicy::Reject`, reuse an id) the error names each of them.
+    pub fn add_routes(&self, routes: Vec<RadixNode>) -> Result<()> {
+        self.rebuild(|table| table.add_routes(&routes))
+    }
+
+    /// Add a single route to the router
+    ///
+    /// What happens when `route.id` is already registered depends on the
+    /// [`IdPolicy`] (see [`set_id_policy`](Self::set_id_policy)); under
+    /// `IdPolicy::Replace` the previous definition is returned.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "debug", skip_all, fields(route_id = %route.id))
+    )]
+    pub fn add_route(&self, route: RadixNode) -> Result<Option<RouteInfo>> {
+        self.rebuild(|table| table.add_route(route))
+    }
+
+    /// Add a route from its methods, path and metadata alone
+    ///
+    /// `None` methods accept every method. Without an `id`, the route is
+    /// named after its methods and path the way [`routes!`](crate::routes)
+    /// names them, e.g. `"GET|POST /api/users"`. Returns the id.
+    pub fn add_endpoint(
+        &self,
+        id: Option<&str>,
+        methods: Option<RadixHttpMethod>,
+        path: &str,
+        metadata: serde_json::Value,
+    ) -> Result<String> {
+        let id = match id {
+            Some(id) => id.to_string(),
+            None => {
+                let names = match methods {
+                    Some(methods) => methods
+                        .iter_names()
+                        .map(|(name, _)| name)
+                        .collect::<Vec<_>>()
+                        .join("|"),
+                    None => "ANY".to_string(),
+                };
+                format!("{} {}", names, path)
+            }
+        };
+        self.add_route(RadixNode {
+            id: id.clone(),
+            paths: vec![path.to_string()],
+            methods,
+            metadata,
+            ..Default::default()
+        })?;
+        Ok(id)
+    }
+
+    /// Add a `GET` route with a generated id (see [`add_endpoint`](Self::add_endpoint))
+    pub fn get(&self, path: &str, metadata: serde_json::Value) -> Result<String> {
+        self.add_endpoint(None, Some(RadixHttpMethod::GET), path, metadata)
+    }
+
+    /// Add a `POST` route with a generated id (see [`add_endpoint`](Self::add_endpoint))
+    pub fn post(&self, path: &str, metadata: serde_json::Value) -> Result<String> {
+        self.add_endpoint(None, Some(RadixHttpMethod::POST), path, metadata)
+    }
+
+    /// Add a `PUT` route with a generated id (see [`add_endpoint`](Self::add_endpoint))
+    pub fn put(&self, path: &str, metadata: serde_json::Value) -> Result<String> {
+        self.add_endpoint(None, Some(RadixHttpMethod::PUT), path, metadata)
+    }
+
+    /// Add a `DELETE` route with a generated id (see [`add_endpoint`](Self::add_endpoint))
+    ///
+    /// Removing routes is [`delete_by_id`](Self::delete_by_id).
+    pub fn delete(&self, path: &str, metadata: serde_json::Value) -> Result<String> {
+        self.add_endpoint(None, Some(RadixHttpMethod::DELETE), path, metadata)
+    }
+
+    /// Add a route for every method with a generated id (see [`add_endpoint`](Self::add_endpoint))
+    pub fn any(&self, path: &str, metadata: serde_json::Value) -> Result<String> {
+        self.add_endpoint(None, None, path, metadata)
+    }
+
+    /// Choose how adding an already registered route id is handled
+    ///
+    /// Applies to every later insert, including `add_routes`, transactions
+    /// and reloads. Existing duplicates are left as they are.
+    pub fn set_id_policy(&self, policy: IdPolicy) -> Result<()> {
+        self.rebuild(|table| {
+            table.id_policy = policy;
+            Ok(())
+        })
+    }
+
+    /// Choose whether var names are case-sensitive
+    ///
+    /// With [`VarCase::Insensitive`], the var names of registered and future
+    /// routes and of every lookup's `RadixMatchOpts::vars` are lowercased,
+    /// so expressions, canaries and filters see `user-agent` whichever case
+    /// the caller used. Switching back leaves existing routes lowercased.
+    pub fn set_var_case(&self, case: VarCase) -> Result<()> {
+        self.rebuild(|table| {
+            table.set_var_case(case);
+            Ok(())
+        })
+    }
+
+    /// Replace the whole route table
+    ///
+    /// The new table is built off to the side and swapped in only once every
+    /// route has been processed, so a failing reload leaves the current
+    /// routes untouched and no intermediate route set is ever observable.
+    pub fn replace_routes(&self, routes: Vec<RadixNode>) -> Result<()> {
+        let _writer = self.lock_writer();
+        let mut fresh = self.snapshot().empty_like()?;
+        fresh.add_routes(&routes)?;
+        fresh.check_table_limits()?;
+        self.publish(fresh);
+        Ok(())
+    }
+
+    /// Serialize the route table into a compact binary snapshot
+    ///
+    /// The snapshot holds every route already processed (parsed paths, host
+    /// patterns, IP tries, bucket order), so [`RadixRouter::from_snapshot`]
+    /// can restore a large table at startup without re-validating and
+    /// re-sorting it. Routes with a filter function cannot be captured and make
+    /// this fail. Request and table limits, the traversal policy and path
+    /// decoding are captured with the routes; the tie-breaker, metadata
+    /// hook, named filters, templates and observers are not part of the
+    /// snapshot.
+    pub fn to_snapshot(&self) -> Result<Vec<u8>> {
+        self.snapshot().to_snapshot(self.policy)?.encode()
+    }
+
+    /// Restore a router from bytes produced by [`RadixRouter::to_snapshot`]
+    ///
+    /// The match policy, id policy, var case, request and table limits,
+    /// traversal policy and path decoding are restored with the routes. TTLs
+    /// keep counting from the original insertion time.
+    pub fn from_snapshot(bytes: &[u8]) -> Result<Self> {
+        let (table, policy) = RouteTable::from_snapshot(TableSnapshot::decode(bytes)?)?;
+        Ok(Self {
+            table: Arc::new(ArcSwap::from_pointee(table)),
+            writer: Mutex::new(()),
+            policy,
+            observers: ArcSwap::default(),
+            audit: Mutex::default(),
+            history: Mutex::default(),
+        })
+    }
+
+    /// Write a snapshot file for [`RadixRouter::open_snapshot`]
+    ///
+    /// Lets build tooling compile the route table offline and ship it as an
+    /// artifact.
+    pub fn write_snapshot(&self, path: impl AsRef<std::path::Path>) -> Result<()> {
+        let path = path.as_ref();
+        std::fs::write(path, self.to_snapshot()?)
+            .with_context(|| format!("Failed to write {}", path.display()))
+    }
+
+    /// Open a snapshot file written by [`RadixRouter::write_snapshot`]
+    ///
+    /// Reads the file and decodes it like [`RadixRouter::from_snapshot`], so
+    /// short-lived processes skip processing route definitions. The router
+    /// does not keep the file open.
+    pub fn open_snapshot(path: impl AsRef<std::path::Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let bytes =
+            std::fs::read(path).with_context(|| format!("Failed to read {}", path.display()))?;
+        Self::from_snapshot(&bytes)
+            .with_context(|| format!("Failed to load snapshot {}", path.display()))
+    }
+
+    /// Replace the routes with those of a snapshot, keeping this router's settings
+    ///
+    /// Like `replace_routes`, the swap is atomic and observers see the
+    /// difference. The match policy, tie-breaker, metadata hook, registered
+    /// filters and templates, request and table limits, traversal policy and
+    /// path decoding stay as configured here; the id policy and var case
+    /// come from the snapshot.
+    pub fn restore_snapshot(&self, bytes: &[u8]) -> Result<()> {
+        let (mut table, _) = RouteTable::from_snapshot(TableSnapshot::decode(bytes)?)?;
+        let _writer = self.lock_writer();
+        let current = self.snapshot();
+        if let Some(tie_breaker) = current.tie_breaker.clone() {
+            table.set_tie_breaker(Some(tie_breaker));
+        }
+        table.metadata_hook = current.metadata_hook.clone();
+        table.filters = current.filters.clone();
+        table.templates = current.templates.clone();
+        table.limits = current.limits;
+        table.traversal = current.traversal;
+        table.path_decoding = current.path_decoding;
+        table.table_limits = current.table_limits;
+        table.check_table_limits()?;
+        self.publish(table);
+        Ok(())
+    }
+
+    /// Rebuild internal structures at minimal size
+    ///
+    /// Deleting routes leaves gaps in the tree index space and spare capacity
+    /// in the lookup maps. Long-running routers with heavy add/delete churn
+    /// can call this periodically to reclaim that space; the route set and
+    /// match results stay the same.
+    pub fn compact(&self) -> Result<()> {
+        let _writer = self.lock_writer();
+        let compacted = self.snapshot().compacted()?;
+        self.publish(compacted);
+        Ok(())
+    }
+
+    /// Set the request size limits (see [`Limits`])
+    ///
+    /// Internet-facing gateways use them to keep pathological paths away
+    /// from pattern matching and captures.
+    pub fn set_limits(&self, limits: Limits) -> Result<()> {
+        self.rebuild(|table| {
+            table.limits = limits;
+            Ok(())
+        })
+    }
+
+    /// Choose whether `..` in wildcard parts of the path rejects routes
+    ///
+    /// See [`TraversalPolicy`]; the default returns captures as requested.
+    pub fn set_traversal_policy(&self, policy: TraversalPolicy) -> Result<()> {
+        self.rebuild(|table| {
+            table.traversal = policy;
+            Ok(())
+        })
+    }
+
+    /// Set the size caps of the route table (see [`TableLimits`])
+    ///
+    /// Fails, keeping the previous caps, if the current routes are already
+    /// over the new ones.
+    pub fn set_table_limits(&self, limits: TableLimits) -> Result<()> {
+        self.rebuild(|table| {
+            table.table_limits = limits;
+            Ok(())
+        })
+    }
+
+    /// Choose whether request paths are percent-decoded before lookup
+    ///
+    /// See [`PathDecoding`]; the default matches paths as received.
+    /// [`match_route_ref`](Self::match_route_ref) borrows its values from
+    /// the path it is given and so always matches it as is; decode it
+    /// first with [`PathDecoding::decode`] in the other modes.
+    pub fn set_path_decoding(&self, decoding: PathDecoding) -> Result<()> {
+        self.rebuild(|table| {
+            table.path_decoding = decoding;
+            Ok(())
+        })
+    }
+
+    /// Order routes that tie on priority and path length with `tie_breaker`
+    ///
+    /// Routes comparing `Less` are tried first, e.g. ordering by id or by a
+    /// version stored in metadata makes "which route wins" explicit instead
+    /// of depending on registration order. Existing routes are re-sorted
+    /// immediately; pass `None` to restore the default order.
+    pub fn set_tie_breaker(&self, tie_breaker: Option<TieBreaker>) -> Result<()> {
+        self.rebuild(|table| {
+            table.set_tie_breaker(tie_breaker);
+            Ok(())
+        })
+    }
+
+    /// Register a filter that routes reference by name in `RadixNode::filters`
+    ///
+    /// Routes naming a filter that isn't registered are rejected when added,
+    /// so register filters before the routes using them. Names cannot be
+    /// registered twice.
+    pub fn register_filter(&self, name: impl Into<String>, filter: FilterFn) -> Result<()> {
+        let name = name.into();
+        self.rebuild(|table| table.register_filter(name, filter))
+    }
+
+    /// Register a template routes can extend by name
+    ///
+    /// A route whose `extends` names the template inherits the fields it
+    /// leaves unset when it is added (see [`RadixNode::inherit`]), so hosts,
+    /// vars or metadata defaults shared by many routes are written once.
+    /// Registering a template again under the same name re-resolves every
+    /// route extending it, in one atomic change that fails as a whole if a
+    /// route no longer validates. Templates cannot extend templates. Routes
+    /// restored from a snapshot or merged from another router keep their
+    /// link to the template; metadata set with
+    /// [`update_metadata`](Self::update_metadata) survives re-resolution.
+    pub fn register_template(&self, name: impl Into<String>, template: RadixNode) -> Result<()> {
+        let name = name.into();
+        self.rebuild(|table| table.register_template(name, template))
+    }
+
+    /// Rewrite the metadata of every match with `hook`
+    ///
+    /// Lets the router do the post-match step callers would otherwise
+    /// repeat, such as [`param_template`] filling params into an upstream
+    /// URL. Applies to the results of `match_route`, `match_route_with` and
+    /// `match_outcome`, shadows included; the allocation-free lookups return
+    /// the stored metadata. Pass `None` to remove the hook.
+    pub fn set_metadata_hook(&self, hook: Option<MetadataHook>) -> Result<()> {
+        self.rebuild(|table| {
+            table.metadata_hook = hook;
+            Ok(())
+        })
+    }
+
+    /// Update an existing route
+    pub fn update_route(&self, old_route: RadixNode, new_route: RadixNode) -> Result<()> {
+        self.rebuild(|table| {
+            // Remove old route
+            table.delete_route(old_route)?;
+            // Add new route
+            table.add_route(new_route).map(drop)
+        })
+    }
+
+    /// Replace the metadata of a registered route in place
+    ///
+    /// The published copy shares the radix trees and compiled patterns of
+    /// the current table; only the route maps are copied, so frequent
+    /// upstream changes don't churn the radix tree or recompile patterns.
+    pub fn update_metadata(&self, id: &str, metadata: serde_json::Value) -> Result<()> {
+        self.rebuild_shared(|table| table.update_metadata(id, metadata))
+    }
+
+    /// Delete a route
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "debug", skip_all, fields(route_id = %route.id))
+    )]
+    pub fn delete_route(&self, route: RadixNode) -> Result<()> {
+        self.rebuild(|table| table.delete_route(route))
+    }
+
+    /// Delete a route and all of its paths by id
+    pub fn delete_by_id(&self, id: &str) -> Result<()> {
+        self.rebuild(|table| table.delete_by_id(id))
+    }
+
+    /// Delete every route carrying `tag`
+    ///
+    /// Returns the number of distinct route ids removed.
+    pub fn delete_by_tag(&self, tag: &str) -> Result<usize> {
+        self.rebuild(|table| Ok(table.remove_where(|route| route.tags.iter().any(|t| t == tag))))
+    }
+
+    /// Remove routes whose TTL or validity window has run out
+    ///
+    /// Expired routes already stop matching on their own; this reclaims
+    /// their storage. Returns the number of distinct route ids removed.
+    pub fn purge_expired(&self) -> Result<usize> {
+        let now = SystemTime::now();
+        self.rebuild(|table| Ok(table.remove_where(|route| route.is_expired_at(now))))
+    }
+
+    /// Apply a batch of mutations atomically
+    ///
+    /// The closure records operations on a [`Transaction`]; they are then
+    /// applied in order to a private copy of the route table, which replaces
+    /// the live table only if every operation succeeds. On error the router
+    /// is left exactly as it was.
+    ///
+    /// ```rust
+    /// # use router_radix::{RadixRouter, RadixNode};
+    /// # fn main() -> anyhow::Result<()> {
+    /// let router = RadixRouter::new()?;
+    /// router.transaction(|tx| {
+    ///     tx.add(RadixNode {
+    ///         id: "users".to_string(),
+    ///         paths: vec!["/api/users".to_string()],
+    ///         ..Default::default()
+    ///     });
+    ///     tx.update_metadata("users", serde_json::json!({"upstream": "users-v2"}));
+    /// })?;
+    /// assert_eq!(router.len(), 1);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn transaction<F>(&self, f: F) -> Result<()>
+    where
+        F: FnOnce(&mut Transaction),
+    {
+        let mut tx = Transaction::default();
+        f(&mut tx);
+        if tx.is_empty() {
+            return Ok(());
+        }
+        self.rebuild(|table| table.apply_ops(tx.into_ops()))
+    }
+
+    /// Reconcile the router with a desired route set
+    ///
+    /// Routes are compared by id and content hash: ids missing from
+    /// `desired` are deleted, new ids are added, and routes whose definition
+    /// changed are replaced. Unchanged routes are not touched at all. The
+    /// delta is applied atomically, like a [`transaction`](Self::transaction).
+    ///
+    /// Filter functions are compared by pointer, so reuse the same
+    /// `FilterFn` across syncs to keep a route counted as unchanged.
+    pub fn apply_diff(&self, desired: Vec<RadixNode>) -> Result<ChangeReport> {
+        self.rebuild(|table| {
+            let mut report = ChangeReport::default();
+            let mut ops = Vec::new();
+            let mut seen = HashSet::new();
+
+            for route in desired {
+                if !seen.insert(route.id.clone()) {
+                    anyhow::bail!("Duplicate route id in desired set: {}", route.id);
+                }
+                match table.routes.get(&route.id) {
+                    None => {
+                        report.added.push(route.id.clone());
+                        ops.push(TxOp::Add(route));
+                    }
+                    Some(entry) if entry.content_hash != route.content_hash() => {
+                        report.changed.push(route.id.clone());
+                        ops.push(TxOp::DeleteById(route.id.clone()));
+                        ops.push(TxOp::Add(route));
+                    }
+                    Some(_) => report.unchanged += 1,
+                }
+            }
+
+            for id in table.routes.keys() {
+                if !seen.contains(id) {
+                    report.removed.push(id.clone());
+                    ops.push(TxOp::DeleteById(id.clone()));
+                }
+            }
+
+            table.apply_ops(ops)?;
+            Ok(report)
+        })
+    }
+
+    /// Merge the routes of `other` into this router
+    ///
+    /// Routes are combined by id; `policy` decides what happens when both
+    /// routers define the same id. The merge is applied atomically: with
+    /// [`MergePolicy::Error`] a single conflict leaves this router unchanged.
+    /// `other` is only read, so independently built routers can be composed
+    /// without giving them up.
+    pub fn merge(&self, other: &RadixRouter, policy: MergePolicy) -> Result<()> {
+        let theirs = other.snapshot();
+        self.rebuild(|table| {
+            for id in theirs.routes.keys() {
+                if table.routes.contains_key(id) {
+                    match policy {
+                        MergePolicy::Error => anyhow::bail!("Conflicting route id: {}", id),
+                        MergePolicy::PreferSelf => continue,
+                        MergePolicy::PreferOther => table.delete_by_id(id)?,
+                    }
+                }
+                table.copy_route_from(&theirs, id)?;
+            }
+            Ok(())
+        })
+    }
+
+    /// Mount the routes of `sub` below `prefix`
+    ///
+    /// Every route of `sub` is re-registered with `prefix` prepended to its
+    /// paths (`/users/:id` mounted at `/api/v2` matches `/api/v2/users/42`),
+    /// and matches report the mount point in `MatchInfo::mount`. Content
+    /// hashes are those of the definitions with prefixed paths, so a later
+    /// `apply_diff` with such definitions leaves the routes alone. Mounting
+    /// is atomic and fails without changes
+    /// if a route id of `sub` is already registered here.
+    pub fn mount(&self, prefix: &str, sub: &RadixRouter) -> Result<()> {
+        if !prefix.starts_with('/') {
+            anyhow::bail!("Mount prefix must start with '/': {}", prefix);
+        }
+        if prefix.contains([':', '*']) {
+            anyhow::bail!(
+                "Mount prefix must not contain params or wildcards: {}",
+                prefix
+            );
+        }
+        let prefix = prefix.trim_end_matches('/');
+        let theirs = sub.snapshot();
+        self.rebuild(|table| table.mount_from(&theirs, prefix))
+    }
+
+    /// Match a route (thread-safe)
+    ///
+    /// Returns:
+    /// - `Ok(Some(MatchResult))` - Found a matching route
+    /// - `Ok(None)` - No matching route found
+    /// - `Err(_)` - System error (e.g., radix tree iterator allocation failure)
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "debug", skip(self, opts))
+    )]
+    pub fn match_route(&self, path: &str, opts: &RadixMatchOpts) -> Result<Option<MatchResult>> {
+        let timer = MatchTimer::start();
+        let result = self
+            .snapshot()
+            .lookup(path, opts, self.policy, None, None)?;
+        timer.finish(path, result.as_ref().map(|r| r.id.as_str()));
+        self.notify_match(path, result.as_ref());
+        Ok(result)
+    }
+
+    /// Match a request target such as `/api/users?limit=10`
+    ///
+    /// The query string and any fragment are split off before matching;
+    /// the query args are merged into `opts.args` (see
+    /// [`RadixMatchOpts::add_query_args`]), so `arg_*` vars and filters see
+    /// them. Otherwise the same as [`match_route`](Self::match_route).
+    pub fn match_uri(&self, uri: &str, opts: &RadixMatchOpts) -> Result<Option<MatchResult>> {
+        let (path, opts) = split_target(uri, opts);
+        self.match_route(path, &opts)
+    }
+
+    /// Match a route, handing `context` to the routes' context filters
+    ///
+    /// Same as [`match_route`](Self::match_route), except that each
+    /// [`ContextFilter`] sees `context`, e.g. the framework's own request
+    /// object, instead of only the string vars. Routes whose context filter
+    /// was built for another type are rejected.
+    pub fn match_route_with<C: Any>(
+        &self,
+        path: &str,
+        opts: &RadixMatchOpts,
+        context: &C,
+    ) -> Result<Option<MatchResult>> {
+        let timer = MatchTimer::start();
+        let result = self
+            .snapshot()
+            .lookup(path, opts, self.policy, None, Some(context))?;
+        timer.finish(path, result.as_ref().map(|r| r.id.as_str()));
+        self.notify_match(path, result.as_ref());
+        Ok(result)
+    }
+
+    /// Match a route without allocating the result
+    ///
+    /// Like [`match_route`](Self::match_route), but param values are
+    /// borrowed from `path` and the route's id, pattern and metadata are
+    /// shared with the router instead of copied. Meant for hot paths that
+    /// only read the result. Shadow routes are not evaluated and observers
+    /// are not notified.
+    pub fn match_route_ref<'p>(
+        &self,
+        path: &'p str,
+        opts: &RadixMatchOpts,
+    ) -> Result<Option<MatchRef<'p>>> {
+        let timer = MatchTimer::start();
+        let table = self.snapshot();
+        let result = table
+            .find::<PathValues>(path, opts, self.policy, None, None)?
+            .map(|winner| {
+                let route = MatchedRoute::new(Arc::clone(winner.route), opts);
+                MatchRef::new(route, winner.matched.0)
+            });
+        timer.finish(path, result.as_ref().map(MatchRef::id));
+        Ok(result)
+    }
+
+    /// Find which route a request matches, without extracting any values
+    ///
+    /// Runs the same checks as [`match_route`](Self::match_route) but skips
+    /// param capture and builds no map, which suits access-control
+    /// pre-checks and metrics labels. Shadow routes are not evaluated and
+    /// observers are not notified.
+    pub fn matches(&self, path: &str, opts: &RadixMatchOpts) -> Result<Option<MatchedRoute>> {
+        let timer = MatchTimer::start();
+        let table = self.snapshot();
+        let result = table.decoded(path, |path| {
+            Ok(table
+                .find::<()>(path, opts, self.policy, None, None)?
+                .map(|winner| MatchedRoute::new(Arc::clone(winner.route), opts)))
+        })?;
+        timer.finish(path, result.as_ref().map(MatchedRoute::id));
+        Ok(result)
+    }
+
+    /// Match a route, writing the extracted values into a caller-owned buffer
+    ///
+    /// `params` receives the path params [`MatchResult::matched`] would hold;
+    /// the returned route gives the pattern and mount point. It is cleared
+    /// first and keeps
+    /// its storage, so high-QPS callers can reuse one buffer per worker
+    /// instead of allocating a map per request. Shadow routes are not
+    /// evaluated and observers are not notified.
+    pub fn match_route_into(
+        &self,
+        path: &str,
+        opts: &RadixMatchOpts,
+        params: &mut Params,
+    ) -> Result<Option<MatchedRoute>> {
+        let timer = MatchTimer::start();
+        params.clear();
+        let table = self.snapshot();
+        let result = table.decoded(path, |path| {
+            Ok(table
+                .find::<PathValues>(path, opts, self.policy, None, None)?
+                .map(|winner| {
+                    fill_params(winner.route, &winner.matched.0, params);
+                    MatchedRoute::new(Arc::clone(winner.route), opts)
+                }))
+        })?;
+        timer.finish(path, result.as_ref().map(MatchedRoute::id));
+        Ok(result)
+    }
+
+    /// Match a route and classify the routing decision
+    ///
+    /// Unlike [`match_route`](Self::match_route), a miss is reported as either
+    /// `RouteOutcome::NotFound` or `RouteOutcome::MethodNotAllowed` (the path
+    /// is routable but no candidate accepts the request method), so callers
+    /// can answer 404 and 405 correctly without a second lookup.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "debug", skip(self, opts))
+    )]
+    pub fn match_outcome(&self, path: &str, opts: &RadixMatchOpts) -> Result<RouteOutcome> {
+        let timer = MatchTimer::start();
+        let mut allowed = RadixHttpMethod::empty();
+        let result = self
+            .snapshot()
+            .lookup(path, opts, self.policy, Some(&mut allowed), None)?;
+        timer.finish(path, result.as_ref().map(|r| r.id.as_str()));
+        self.notify_match(path, result.as_ref());
+        match result {
+            Some(result) => Ok(RouteOutcome::Matched(result)),
+            None if !allowed.is_empty() => Ok(RouteOutcome::MethodNotAllowed { allowed }),
+            None => Ok(RouteOutcome::NotFound),
+        }
+    }
+
+    /// Trace how a request is routed
+    ///
+    /// Lists every candidate route found for `path` (exact table first, then
+    /// tree prefixes from longest to shortest) with the first check that
+    /// rejected it, alongside the result `match_route` would return (none
+    /// when a filter error would abort it). Meant for debugging; it
+    /// evaluates all candidates and is slower than a match.
+    pub fn explain(&self, path: &str, opts: &RadixMatchOpts) -> Result<Explanation> {
+        let table = self.snapshot();
+        let candidates = table.trace(path, opts)?;
+        // A failing filter is reported as its candidate's rejection
+        let result = match table.lookup(path, opts, self.policy, None, None) {
+            Err(_)
+                if candidates
+                    .iter()
+                    .any(|c| c.rejection == Some(Rejection::FilterError)) =>
+            {
+                None
+            }
+            result => result?,
+        };
+        Ok(Explanation {
+            path: path.to_string(),
+            candidates,
+            result,
+        })
+    }
+
+    /// Render the routing table as an indented tree
+    ///
+    /// Each line is a lookup key (an exact path or the literal prefix of a
+    /// param/wildcard pattern), nested under the keys it extends, followed
+    /// by its routes in the order they are tried:
+    ///
+    /// ```text
+    /// /api/
+    ///   - fallback [prefix] /api/* priority=0 methods=* hosts=*
+    ///   /api/users
+    ///     - users [exact] /api/users priority=10 methods=GET|POST hosts=*
+    /// ```
+    pub fn dump(&self) -> String {
+        self.snapshot().dump()
+    }
+
+    /// Render the routes as an aligned text table
+    ///
+    /// One row per route path, sorted by id, with the `upstream` metadata
+    /// key shown as the upstream (`-` when absent). This is also what the
+    /// router's `Display` prints:
+    ///
+    /// ```text
+    /// ID      METHODS   HOSTS          PATH        PRIORITY  UPSTREAM
+    /// health  *         *              /health     0         -
+    /// users   GET|POST  *.example.com  /api/users  10        users:8080
+    /// ```
+    pub fn table(&self) -> String {
+        self.snapshot().table()
+    }
+
+    /// Routes that can never be selected
+    ///
+    /// A route is reported when an earlier candidate for the same lookup key
+    /// has the same path shape (param names aside) and unconditionally
+    /// accepts every request it would: no vars, filter, weight, canary or
+    /// validity window, and methods, hosts, schemes, SNIs and ports that
+    /// include the later route's. The check is conservative, so a clean
+    /// report does not prove every route reachable.
+    pub fn conflicts(&self) -> Vec<Conflict> {
+        self.snapshot().conflicts()
+    }
+
+    /// Report what adding `route` would do to reachability, without adding it
+    ///
+    /// The route is added to a private copy of the table, as
+    /// [`add_route`](Self::add_route) would, and the copy is checked the way
+    /// [`conflicts`](Self::conflicts) checks the live table: the report lists
+    /// existing routes the new one would shadow (it is tried first, by
+    /// priority and specificity, and accepts all their requests) and those
+    /// that would shadow it. Conflicts already present are left out. Fails
+    /// if the route would be rejected, also for exceeding the
+    /// [`TableLimits`]. Meant for CI checks that block
+    /// accidental route takeovers.
+    pub fn dry_run_add(&self, route: &RadixNode) -> Result<ShadowReport> {
+        let mut table = self.snapshot().duplicate()?;
+        let replaces = table.add_route(route.clone())?;
+        table.check_table_limits()?;
+        let (shadowed_by, shadows) = table
+            .conflicts()
+            .into_iter()
+            .filter(|conflict| (conflict.id == route.id) != (conflict.shadowed_by == route.id))
+            .partition(|conflict| conflict.id == route.id);
+        Ok(ShadowReport {
+            shadows,
+            shadowed_by,
+            replaces,
+        })
+    }
+
+    /// Check a route set without loading it
+    ///
+    /// Applies `routes` to a private copy of the table, the way
+    /// [`add_routes`](Self::add_routes) would, and reports every problem
+    /// instead of stopping at the first: errors that would reject a route
+    /// or the set (bad path, unknown filter or template, duplicate id, table
+    /// limits, ...) and warnings about routes that load but do nothing, such
+    /// as routes without paths or shadowed by another route (see
+    /// [`conflicts`](Self::conflicts)). The router is left untouched.
+    pub fn validate_routes(&self, routes: &[RadixNode]) -> Vec<ValidationIssue> {
+        let mut table = match self.snapshot().duplicate() {
+            Ok(table) => table,
+            Err(err) => return vec![ValidationIssue::from_error(None, &err)],
+        };
+        let mut issues = Vec::new();
+        let mut seen = FxHashSet::default();
+        for route in routes {
+            if !seen.insert(route.id.as_str()) && table.id_policy != IdPolicy::Reject {
+                issues.push(ValidationIssue::warning(
+                    &route.id,
+                    "id",
+                    format!("Route id {} is defined more than once", route.id),
+                ));
+            }
+            if route.paths.is_empty() {
+                issues.push(ValidationIssue::warning(
+                    &route.id,
+                    "paths",
+                    format!("Route {} has no paths and never matches", route.id),
+                ));
+            }
+            if let Err(err) = table.add_route(route.clone()) {
+                issues.push(ValidationIssue::from_error(Some(&route.id), &err));
+            }
+        }
+        for conflict in table.conflicts() {
+            if seen.contains(conflict.id.as_str()) || seen.contains(conflict.shadowed_by.as_str()) {
+                issues.push(ValidationIssue::warning(
+                    &conflict.id,
+                    "paths",
+                    conflict.to_string(),
+                ));
+            }
+        }
+        if let Err(err) = table.check_table_limits() {
+            issues.push(ValidationIssue::from_error(None, &err));
+        }
+        issues
+    }
+
+    /// Export the routing table as a Graphviz DOT graph
+    ///
+    /// Lookup keys form the tree (edges are labelled with the path segment
+    /// each key adds to its parent) and every key links to box nodes for
+    /// its routes in the order they are tried. Render with e.g.
+    /// `dot -Tsvg routes.dot -o routes.svg`.
+    pub fn to_dot(&self) -> String {
+        self.snapshot().to_dot()
+    }
+
+    /// Number of registered routes (distinct ids)
+    pub fn len(&self) -> usize {
+        self.snapshot().routes.len()
+    }
+
+    /// Whether no routes are registered
+    pub fn is_empty(&self) -> bool {
+        self.snapshot().routes.is_empty()
+    }
+
+    /// Version of the current route table
+    ///
+    /// Starts at 0 and goes up by one with every published change, batched
+    /// ones included, so access logs can record it from
+    /// [`MatchResult::version`] and tell which table served a request.
+    pub fn version(&self) -> u64 {
+        self.snapshot().version
+    }
+
+    /// Keep the last `len` replaced tables for [`rollback`](Self::rollback)
+    ///
+    /// Tables are immutable snapshots shared with in-flight lookups, so
+    /// keeping one costs its memory but no copying. 0 (the default) keeps
+    /// none; lowering the length drops the oldest tables now.
+    pub fn set_history_len(&self, len: usize) {
+        let _writer = self.lock_writer();
+        let mut history = self.lock_history();
+        history.capacity = len;
+        while history.tables.len() > len {
+            history.tables.pop_front();
+        }
+    }
+
+    /// Versions [`rollback`](Self::rollback) can return to, oldest first
+    pub fn history(&self) -> Vec<u64> {
+        let history = self.lock_history();
+        history.tables.iter().map(|table| table.version).collect()
+    }
+
+    /// Publish the routes of an earlier version again
+    ///
+    /// The table kept for `version` (see [`set_history_len`](Self::set_history_len))
+    /// is republished as a new version, with the settings it had; observers
+    /// and the audit log see the difference like any other change, and the
+    /// rolled-back table stays in the history, so a rollback can itself be
+    /// undone.
+    pub fn rollback(&self, version: u64) -> Result<()> {
+        let _writer = self.lock_writer();
+        let kept = self
+            .lock_history()
+            .tables
+            .iter()
+            .find(|table| table.version == version)
+            .cloned()
+            .with_context(|| format!("Version {} is not in the kept history", version))?;
+        self.publish(kept.duplicate()?);
+        Ok(())
+    }
+
+    /// Look up a registered route by id
+    pub fn get_route(&self, id: &str) -> Option<RouteInfo> {
+        self.snapshot().route_info(id)
+    }
+
+    /// Iterate over all registered routes, ordered by id
+    ///
+    /// The iterator walks the snapshot current at call time, so it stays
+    /// valid while other threads keep mutating the router.
+    pub fn routes(&self) -> impl Iterator<Item = RouteInfo> {
+        let table = self.snapshot();
+        let infos: Vec<RouteInfo> = table
+            .routes
+            .keys()
+            .filter_map(|id| table.route_info(id))
+            .collect();
+        infos.into_iter()
+    }
+
+    /// Ids of all routes carrying `tag`, sorted
+    pub fn routes_with_tag(&self, tag: &str) -> Vec<String> {
+        let table = self.snapshot();
+        let ids: std::collections::BTreeSet<&str> = table
+            .iter_route_opts()
+            .filter(|r| r.tags.iter().any(|t| t == tag))
+            .map(|r| r.id.as_str())
+            .collect();
+        ids.into_iter().map(str::to_string).collect()
+    }
+
+    /// Summaries of the routes matching `query`, sorted by id
+    ///
+    /// For admin UIs over large tables, e.g. every route under `/api/v1`
+    /// that is restricted to `*.example.com` and accepts `DELETE`:
+    ///
+    /// ```rust
+    /// # use router_radix::{RadixHttpMethod, RadixRouter, RouteQuery};
+    /// # fn main() -> anyhow::Result<()> {
+    /// let router = RadixRouter::new()?;
+    /// let query = RouteQuery {
+    ///     path_prefix: Some("/api/v1".to_string()),
+    ///     host: Some("*.example.com".to_string()),
+    ///     method: Some(RadixHttpMethod::DELETE),
+    ///     ..Default::default()
+    /// };
+    /// for route in router.find_routes(&query) {
+    ///     println!("{} {:?}", route.id, route.paths);
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn find_routes(&self, query: &RouteQuery) -> Vec<RouteInfo> {
+        self.snapshot().find_routes(query)
+    }
+}
+
+/// Route table under construction, frozen into a [`Router`] by [`build`](Self::build)
+///
+/// Unlike [`RadixRouter`], which copies its table on every change so it can
+/// keep serving lookups, the builder mutates its table in place. Every step
+/// consumes the builder, so one that fails leaves nothing half-built behind.
+///
+/// ```
+/// use router_radix::{RadixNode, RouterBuilder};
+///
+/// # fn main() -> anyhow::Result<()> {
+/// let router = RouterBuilder::new()?
+///     .route(RadixNode {
+///         id: "users".to_string(),
+///         paths: vec!["/users/:id".to_string()],
+///         ..Default::default()
+///     })?
+///     .validate()?
+///     .build();
+/// assert!(router.match_route("/users/7", &Default::default())?.is_some());
+/// # Ok(())
+/// # }
+/// ```
+pub struct RouterBuilder {
+    table: RouteTable,
+    policy: MatchPolicy,
+}
+
+impl RouterBuilder {
+    /// Start an empty table
+    pub fn new() -> Result<Self> {
+        Self::with_policy(MatchPolicy::default())
+    }
+
+    /// Start an empty table whose router ranks candidates with `policy`
+    pub fn with_policy(policy: MatchPolicy) -> Result<Self> {
+        Ok(Self {
+            table: RouteTable::new()?,
+            policy,
+        })
+    }
+
+    /// Add a route (see [`RadixRouter::add_route`])
+    pub fn route(mut self, route: RadixNode) -> Result<Self> {
+        self.table.add_route(route)?;
+        self.table.check_table_limits()?;
+        Ok(self)
+    }
+
+    /// Add a batch of routes (see [`RadixRouter::add_routes`])
+    pub fn routes(mut self, routes: Vec<RadixNode>) -> Result<Self> {
+        self.table.add_routes(&routes)?;
+        self.table.check_table_limits()?;
+        Ok(self)
+    }
+
+    /// Remove every path of the route registered under `id`
+    pub fn delete_by_id(mut self, id: &str) -> Result<Self> {
+        self.table.delete_by_id(id)?;
+        Ok(self)
+    }
+
+    /// Register a template (see [`RadixRouter::register_template`])
+    ///
+    /// Routes extending it must be added after it.
+    pub fn template(mut self, name: impl Into<String>, template: RadixNode) -> Result<Self> {
+        self.table.register_template(name.into(), template)?;
+        Ok(self)
+    }
+
+    /// Register a named filter (see [`RadixRouter::register_filter`])
+    pub fn filter(mut self, name: impl Into<String>, filter: FilterFn) -> Result<Self> {
+        self.table.register_filter(name.into(), filter)?;
+        Ok(self)
+    }
+
+    /// Handling of duplicate ids (see [`RadixRouter::set_id_policy`])
+    pub fn id_policy(mut self, policy: IdPolicy) -> Self {
+        self.table.id_policy = policy;
+        self
+    }
+
+    /// Var name handling (see [`RadixRouter::set_var_case`])
+    pub fn var_case(mut self, case: VarCase) -> Self {
+        self.table.set_var_case(case);
+        self
+    }
+
+    /// Request size limits (see [`RadixRouter::set_limits`])
+    pub fn limits(mut self, limits: Limits) -> Self {
+        self.table.limits = limits;
+        self
+    }
+
+    /// Traversal handling (see [`RadixRouter::set_traversal_policy`])
+    pub fn traversal_policy(mut self, policy: TraversalPolicy) -> Self {
+        self.table.traversal = policy;
+        self
+    }
+
+    /// Table size caps (see [`RadixRouter::set_table_limits`])
+    ///
+    /// Checked each time routes are added after it.
+    pub fn table_limits(mut self, limits: TableLimits) -> Self {
+        self.table.table_limits = limits;
+        self
+    }
+
+    /// Request path decoding (see [`RadixRouter::set_path_decoding`])
+    pub fn path_decoding(mut self, decoding: PathDecoding) -> Self {
+        self.table.path_decoding = decoding;
+        self
+    }
+
+    /// Tie breaker (see [`RadixRouter::set_tie_breaker`])
+    pub fn tie_breaker(mut self, tie_breaker: Option<TieBreaker>) -> Self {
+        self.table.set_tie_breaker(tie_breaker);
+        self
+    }
+
+    /// Metadata hook (see [`RadixRouter::set_metadata_hook`])
+    pub fn metadata_hook(mut self, hook: Option<MetadataHook>) -> Self {
+        self.table.metadata_hook = hook;
+        self
+    }
+
+    /// Fail if any route can never be selected (see [`RadixRouter::conflicts`])
+    pub fn validate(self) -> Result<Self> {
+        let conflicts = self.table.conflicts();
+        if let Some(first) = conflicts.first() {
+            anyhow::bail!("{} unreachable route(s), first: {}", conflicts.len(), first);
+        }
+        Ok(self)
+    }
+
+    /// Freeze the table
+    pub fn build(mut self) -> Router {
+        self.table.seal();
+        Router {
+            table: Arc::new(self.table),
+            policy: self.policy,
+        }
+    }
+}
+
+/// Frozen route table, built by [`RouterBuilder`] or [`RadixRouter::freeze`]
+///
+/// Lookups behave like [`RadixRouter`]'s but read the table directly, with
+/// no snapshot load, and the routes cannot change: there are no mutating
+/// methods. Clones share the table. Observers do not apply.
+#[derive(Clone)]
+pub struct Router {
+    table: Arc<RouteTable>,
+    policy: MatchPolicy,
+}
+
+/// Read-only handle on a [`RadixRouter`]'s routes that follows its changes
+///
+/// Returned by [`RadixRouter::view`]. A view is a reference-counted pointer
+/// to the router's published table: cloning one is cheap, every lookup
+/// reads the current table through a lock-free guard without touching its
+/// reference count (only [`load`](Self::load) takes one), and changes made
+/// through the router are seen as soon as they are published. Hand one to
+/// each worker instead of wrapping the router in `Arc<RwLock<..>>`. Like
+/// [`Router`], a view has no mutating methods and does not notify observers.
+#[derive(Clone)]
+pub struct RouterView {
+    table: Arc<ArcSwap<RouteTable>>,
+    policy: MatchPolicy,
+}
+
+impl RouterView {
+    /// Freeze the current routes, e.g. to run several lookups against one
+    /// consistent table
+    pub fn load(&self) -> Router {
+        Router {
+            table: self.table.load_full(),
+            policy: self.policy,
+        }
+    }
+
+    /// Match a route (see [`RadixRouter::match_route`])
+    pub fn match_route(&self, path: &str, opts: &RadixMatchOpts) -> Result<Option<MatchResult>> {
+        self.table
+            .load()
+            .timed_lookup(path, opts, self.policy, None)
+    }
+
+    /// Match a request target (see [`RadixRouter::match_uri`])
+    pub fn match_uri(&self, uri: &str, opts: &RadixMatchOpts) -> Result<Option<MatchResult>> {
+        let (path, opts) = split_target(uri, opts);
+        self.match_route(path, &opts)
+    }
+
+    /// Match a route with a context (see [`RadixRouter::match_route_with`])
+    pub fn match_route_with<C: Any>(
+        &self,
+        path: &str,
+        opts: &RadixMatchOpts,
+        context: &C,
+    ) -> Result<Option<MatchResult>> {
+        self.table
+            .load()
+            .timed_lookup(path, opts, self.policy, Some(context))
+    }
+
+    /// Match without allocating the result (see [`RadixRouter::match_route_ref`])
+    pub fn match_route_ref<'p>(
+        &self,
+        path: &'p str,
+        opts: &RadixMatchOpts,
+    ) -> Result<Option<MatchRef<'p>>> {
+        self.table.load().match_ref(path, opts, self.policy)
+    }
+
+    /// Find the matching route only (see [`RadixRouter::matches`])
+    pub fn matches(&self, path: &str, opts: &RadixMatchOpts) -> Result<Option<MatchedRoute>> {
+        self.table.load().match_only(path, opts, self.policy)
+    }
+
+    /// Match into a reused buffer (see [`RadixRouter::match_route_into`])
+    pub fn match_route_into(
+        &self,
+        path: &str,
+        opts: &RadixMatchOpts,
+        params: &mut Params,
+    ) -> Result<Option<MatchedRoute>> {
+        self.table
+            .load()
+            .match_into(path, opts, self.policy, params)
+    }
+
+    /// Match and classify the decision (see [`RadixRouter::match_outcome`])
+    pub fn match_outcome(&self, path: &str, opts: &RadixMatchOpts) -> Result<RouteOutcome> {
+        self.table.load().outcome(path, opts, self.policy)
+    }
+
+    /// Version of the current route table (see [`RadixRouter::version`])
+    pub fn version(&self) -> u64 {
+        self.table.load().version
+    }
+
+    /// Number of registered routes (distinct ids)
+    pub fn len(&self) -> usize {
+        self.table.load().routes.len()
+    }
+
+    /// Whether no routes are registered
+    pub fn is_empty(&self) -> bool {
+        self.table.load().routes.is_empty()
+    }
+
+    /// Look up a registered route by id
+    pub fn get_route(&self, id: &str) -> Option<RouteInfo> {
+        self.table.load().route_info(id)
+    }
+}
+
+impl RadixRouter {
+    /// Read-only handle that follows this router's changes (see [`RouterView`])
+    pub fn view(&self) -> RouterView {
+        RouterView {
+            table: Arc::clone(&self.table),
+            policy: self.policy,
+        }
+    }
+
+    /// Freeze the current routes into a [`Router`]
+    ///
+    /// Shares the current snapshot; later changes to this router do not
+    /// affect the frozen one.
+    pub fn freeze(&self) -> Router {
+        Router {
+            table: self.table.load_full(),
+            policy: self.policy,
+        }
+    }
+}
+
+// Timed lookups shared by `Router` and `RouterView`
+impl RouteTable {
+    /// `lookup` without an allow list, recorded by the match timer
+    fn timed_lookup(
+        &self,
+        path: &str,
+        opts: &RadixMatchOpts,
+        policy: MatchPolicy,
+        context: Option<&dyn Any>,
+    ) -> Result<Option<MatchResult>> {
+        let timer = MatchTimer::start();
+        let result = self.lookup(path, opts, policy, None, context)?;
+        timer.finish(path, result.as_ref().map(|r| r.id.as_str()));
+        Ok(result)
+    }
+
+    /// Match without allocating the result
+    fn match_ref<'p>(
+        &self,
+        path: &'p str,
+        opts: &RadixMatchOpts,
+        policy: MatchPolicy,
+    ) -> Result<Option<MatchRef<'p>>> {
+        let timer = MatchTimer::start();
+        let result = self
+            .find::<PathValues>(path, opts, policy, None, None)?
+            .map(|winner| {
+                let route = MatchedRoute::new(Arc::clone(winner.route), opts);
+                MatchRef::new(route, winner.matched.0)
+            });
+        timer.finish(path, result.as_ref().map(MatchRef::id));
+        Ok(result)
+    }
+
+    /// Find the matching route only
+    fn match_only(
+        &self,
+        path: &str,
+        opts: &RadixMatchOpts,
+        policy: MatchPolicy,
+    ) -> Result<Option<MatchedRoute>> {
+        let timer = MatchTimer::start();
+        let result = self.decoded(path, |path| {
+            Ok(self
+                .find::<()>(path, opts, policy, None, None)?
+                .map(|winner| MatchedRoute::new(Arc::clone(winner.route), opts)))
+        })?;
+        timer.finish(path, result.as_ref().map(MatchedRoute::id));
+        Ok(result)
+    }
+
+    /// Match into a reused buffer
+    fn match_into(
+        &self,
+        path: &str,
+        opts: &RadixMatchOpts,
+        policy: MatchPolicy,
+        params: &mut Params,
+    ) -> Result<Option<MatchedRoute>> {
+        let timer = MatchTimer::start();
+        params.clear();
+        let result = self.decoded(path, |path| {
+            Ok(self
+                .find::<PathValues>(path, opts, policy, None, None)?
+                .map(|winner| {
+                    fill_params(winner.route, &winner.matched.0, params);
+                    MatchedRoute::new(Arc::clone(winner.route), opts)
+                }))
+        })?;
+        timer.finish(path, result.as_ref().map(MatchedRoute::id));
+        Ok(result)
+    }
+
+    /// Match and classify the decision
+    fn outcome(
+        &self,
+        path: &str,
+        opts: &RadixMatchOpts,
+        policy: MatchPolicy,
+    ) -> Result<RouteOutcome> {
+        let timer = MatchTimer::start();
+        let mut allowed = RadixHttpMethod::empty();
+        let result = self.lookup(path, opts, policy, Some(&mut allowed), None)?;
+        timer.finish(path, result.as_ref().map(|r| r.id.as_str()));
+        match result {
+            Some(result) => Ok(RouteOutcome::Matched(result)),
+            None if !allowed.is_empty() => Ok(RouteOutcome::MethodNotAllowed { allowed }),
+            None => Ok(RouteOutcome::NotFound),
+        }
+    }
+}
+
+impl Router {
+    /// Candidate ranking policy
+    pub fn policy(&self) -> MatchPolicy {
+        self.policy
+    }
+
+    /// Match a route (see [`RadixRouter::match_route`])
+    pub fn match_route(&self, path: &str, opts: &RadixMatchOpts) -> Result<Option<MatchResult>> {
+        self.table.timed_lookup(path, opts, self.policy, None)
+    }
+
+    /// Match a request target (see [`RadixRouter::match_uri`])
+    pub fn match_uri(&self, uri: &str, opts: &RadixMatchOpts) -> Result<Option<MatchResult>> {
+        let (path, opts) = split_target(uri, opts);
+        self.match_route(path, &opts)
+    }
+
+    /// Match a route with a context (see [`RadixRouter::match_route_with`])
+    pub fn match_route_with<C: Any>(
+        &self,
+        path: &str,
+        opts: &RadixMatchOpts,
+        context: &C,
+    ) -> Result<Option<MatchResult>> {
+        self.table
+            .timed_lookup(path, opts, self.policy, Some(context))
+    }
+
+    /// Match without allocating the result (see [`RadixRouter::match_route_ref`])
+    pub fn match_route_ref<'p>(
+        &self,
+        path: &'p str,
+        opts: &RadixMatchOpts,
+    ) -> Result<Option<MatchRef<'p>>> {
+        self.table.match_ref(path, opts, self.policy)
+    }
+
+    /// Find the matching route only (see [`RadixRouter::matches`])
+    pub fn matches(&self, path: &str, opts: &RadixMatchOpts) -> Result<Option<MatchedRoute>> {
+        self.table.match_only(path, opts, self.policy)
+    }
+
+    /// Match into a reused buffer (see [`RadixRouter::match_route_into`])
+    pub fn match_route_into(
+        &self,
+        path: &str,
+        opts: &RadixMatchOpts,
+        params: &mut Params,
+    ) -> Result<Option<MatchedRoute>> {
+        self.table.match_into(path, opts, self.policy, params)
+    }
+
+    /// Match and classify the decision (see [`RadixRouter::match_outcome`])
+    pub fn match_outcome(&self, path: &str, opts: &RadixMatchOpts) -> Result<RouteOutcome> {
+        self.table.outcome(path, opts, self.policy)
+    }
+
+    /// Routes that can never be selected (see [`RadixRouter::conflicts`])
+    pub fn conflicts(&self) -> Vec<Conflict> {
+        self.table.conflicts()
+    }
+
+    /// Render the routing table (see [`RadixRouter::dump`])
+    pub fn dump(&self) -> String {
+        self.table.dump()
+    }
+
+    /// Routes matching `query` (see [`RadixRouter::find_routes`])
+    pub fn find_routes(&self, query: &RouteQuery) -> Vec<RouteInfo> {
+        self.table.find_routes(query)
+    }
+
+    /// Render the routes as a text table (see [`RadixRouter::table`])
+    pub fn table(&self) -> String {
+        self.table.table()
+    }
+
+    /// Number of registered routes (distinct ids)
+    pub fn len(&self) -> usize {
+        self.table.routes.len()
+    }
+
+    /// Whether no routes are registered
+    pub fn is_empty(&self) -> bool {
+        self.table.routes.is_empty()
+    }
+
+    /// Version of the frozen table (see [`RadixRouter::version`])
+    pub fn version(&self) -> u64 {
+        self.table.version
+    }
+
+    /// Look up a registered route by id
+    pub fn get_route(&self, id: &str) -> Option<RouteInfo> {
+        self.table.route_info(id)
+    }
+
+    /// Serialize the table (see [`RadixRouter::to_snapshot`])
+    pub fn to_snapshot(&self) -> Result<Vec<u8>> {
+        self.table.to_snapshot(self.policy)?.encode()
+    }
+}
+
+impl std::fmt::Debug for Router {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Router")
+            .field("policy", &self.policy)
+            .field("route_count", &self.table.routes.len())
+            .finish()
+    }
+}
+
+impl std::fmt::Display for Router {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.table())
+    }
+}
+
+/// Empty router with the default policy
+///
+/// # Panics
+///
+/// If the radix tree cannot be allocated; use [`RadixRouter::new`] to
+/// handle that as an error.
+impl Default for RadixRouter {
+    fn default() -> Self {
+        Self::new().expect("Failed to allocate radix tree")
+    }
+}
+
+/// Cloning takes a consistent copy of the current route set
+///
+/// The clone starts out sharing the live snapshot, which is immutable, and
+/// diverges on its first mutation: changes to either router are never
+/// visible to the other. This makes "what-if" experiments against
+/// production routes cheap.
+impl Clone for RadixRouter {
+    fn clone(&self) -> Self {
+        Self {
+            table: Arc::new(ArcSwap::new(self.table.load_full())),
+            writer: Mutex::new(()),
+            policy: self.policy,
+            observers: ArcSwap::default(),
+            audit: Mutex::default(),
+            history: Mutex::default(),
+        }
+    }
+}
+
+impl std::fmt::Debug for RadixRouter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let table = self.snapshot();
+        f.debug_struct("RadixRouter")
+            .field("match_data_index", &table.match_data_index)
+            .field("hash_path_count", &table.hash_path.len())
+            .field("match_data_count", &table.match_data.len())
+            .field("route_count", &table.routes.len())
+            .field("pattern_count", &table.patterns.len())
+            .finish()
+    }
+}
+
+impl std::fmt::Display for RadixRouter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.table())
     }
 }
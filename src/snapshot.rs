@@ -0,0 +1,562 @@
+//! Binary snapshots of a route table
+
+use crate::ip::IpTrie;
+use crate::pattern::PathPattern;
+use crate::rewrite::Rewrite;
+use crate::route::{
+    compile_regex, Canary, Expr, FilterErrorPolicy, HostPattern, IdPolicy, Limits, MatchPolicy,
+    PathDecoding, PathOp, RadixHttpMethod, RadixNode, RouteOpts, TableLimits,
+};
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::ops::Range;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+
+/// Leading bytes of every snapshot
+const MAGIC: &[u8; 4] = b"RRSN";
+/// Bumped whenever the encoded layout or the meaning of a value changes
+const FORMAT_VERSION: u32 = 8;
+
+/// Serialized form of a whole route table
+///
+/// Buckets are stored already processed and sorted, with their tree
+/// indices, so restoring skips path validation, parsing and sorting.
+#[derive(Serialize, Deserialize)]
+pub(crate) struct TableSnapshot {
+    pub policy: u8,
+    pub id_policy: u8,
+    /// Whether var names were lowercased (`VarCase::Insensitive`)
+    pub insensitive_vars: bool,
+    /// Request limits: path length, param length, segments
+    pub limits: [Option<usize>; 3],
+    /// Whether traversal in wildcard captures rejected routes
+    /// (`TraversalPolicy::Reject`)
+    pub reject_traversal: bool,
+    pub path_decoding: u8,
+    /// Table limits: routes, regex bytes, candidates per key
+    pub table_limits: [Option<usize>; 3],
+    pub match_data_index: usize,
+    pub next_seq: u64,
+    pub match_data: Vec<(usize, Vec<RouteRecord>)>,
+    pub hash_path: Vec<(String, Vec<RouteRecord>)>,
+    /// Registry entries: id, registered paths, content hash, and the
+    /// definition kept for routes extending a template
+    pub routes: Vec<(String, Vec<String>, u64, Option<DefinitionRecord>)>,
+}
+
+impl TableSnapshot {
+    /// Encode with the format header
+    pub fn encode(&self) -> Result<Vec<u8>> {
+        let mut bytes = MAGIC.to_vec();
+        bytes.extend_from_slice(&FORMAT_VERSION.to_le_bytes());
+        bincode::serialize_into(&mut bytes, self).context("Failed to encode snapshot")?;
+        Ok(bytes)
+    }
+
+    /// Decode bytes produced by [`TableSnapshot::encode`]
+    pub fn decode(bytes: &[u8]) -> Result<Self> {
+        let payload = bytes
+            .strip_prefix(MAGIC.as_slice())
+            .context("Not a router snapshot")?;
+        let (version, payload) = payload
+            .split_first_chunk::<4>()
+            .context("Truncated router snapshot")?;
+        let version = u32::from_le_bytes(*version);
+        if version != FORMAT_VERSION {
+            anyhow::bail!("Unsupported snapshot version: {}", version);
+        }
+        bincode::deserialize(payload).context("Failed to decode snapshot")
+    }
+}
+
+/// Serialized form of one processed route entry
+#[derive(Serialize, Deserialize)]
+pub(crate) struct RouteRecord {
+    id: String,
+    path: String,
+    path_org: String,
+    exact: bool,
+    mount: Option<String>,
+    extensions: Option<Vec<String>>,
+    strip_prefix: bool,
+    /// `rewrite` template as written
+    rewrite: Option<String>,
+    methods: u16,
+    custom_methods: Option<Vec<String>>,
+    hosts: Option<Vec<String>>,
+    schemes: Option<Vec<String>>,
+    snis: Option<Vec<String>>,
+    server_ports: Option<Vec<u16>>,
+    remote_addrs: Option<IpTrie>,
+    valid_from: Option<SystemTime>,
+    valid_until: Option<SystemTime>,
+    expires_at: Option<SystemTime>,
+    vars: Option<Vec<Expr>>,
+    priority: i32,
+    weight: Option<u32>,
+    canary: Option<(u8, String)>,
+    shadow: bool,
+    /// JSON text; bincode cannot decode self-describing values
+    metadata: String,
+    method_metadata: Option<Vec<(u16, String)>>,
+    tags: Vec<String>,
+    /// Whether `path_org` compiles to a param pattern
+    has_param: bool,
+    seq: u64,
+}
+
+/// Serialized form of a route definition as given, kept for routes that
+/// extend a template so they can be resolved again after a restore
+#[derive(Serialize, Deserialize)]
+pub(crate) struct DefinitionRecord {
+    id: String,
+    paths: Vec<String>,
+    extensions: Option<Vec<String>>,
+    strip_prefix: bool,
+    rewrite: Option<String>,
+    methods: Option<u16>,
+    custom_methods: Option<Vec<String>>,
+    hosts: Option<Vec<String>>,
+    remote_addrs: Option<Vec<String>>,
+    schemes: Option<Vec<String>>,
+    snis: Option<Vec<String>>,
+    server_ports: Option<Vec<u16>>,
+    valid_from: Option<SystemTime>,
+    valid_until: Option<SystemTime>,
+    ttl: Option<Duration>,
+    vars: Option<Vec<Expr>>,
+    filters: Vec<String>,
+    /// `FilterErrorPolicy` tag
+    filter_errors: u8,
+    priority: i32,
+    path_priorities: Option<Vec<(String, i32)>>,
+    weight: Option<u32>,
+    canary: Option<(u8, String)>,
+    shadow: bool,
+    /// JSON text, as in [`RouteRecord`]
+    metadata: String,
+    method_metadata: Option<Vec<(u16, String)>>,
+    tags: Vec<String>,
+    extends: Option<String>,
+}
+
+impl DefinitionRecord {
+    /// Capture a definition; fails for routes with a filter closure
+    pub fn from_node(route: &RadixNode) -> Result<Self> {
+        if route.filter_fn.is_some()
+            || route.context_filter.is_some()
+            || route.try_filter_fn.is_some()
+        {
+            anyhow::bail!(
+                "Route {} has a filter function, which cannot be snapshotted",
+                route.id
+            );
+        }
+        let method_metadata = match &route.method_metadata {
+            Some(entries) => Some(
+                entries
+                    .iter()
+                    .map(|(methods, value)| Ok((methods.bits(), serde_json::to_string(value)?)))
+                    .collect::<Result<Vec<_>>>()?,
+            ),
+            None => None,
+        };
+        Ok(Self {
+            id: route.id.clone(),
+            paths: route.paths.clone(),
+            extensions: route.extensions.clone(),
+            strip_prefix: route.strip_prefix,
+            rewrite: route.rewrite.clone(),
+            methods: route.methods.map(|methods| methods.bits()),
+            custom_methods: route.custom_methods.clone(),
+            hosts: route.hosts.clone(),
+            remote_addrs: route.remote_addrs.clone(),
+            schemes: route.schemes.clone(),
+            snis: route.snis.clone(),
+            server_ports: route.server_ports.clone(),
+            valid_from: route.valid_from,
+            valid_until: route.valid_until,
+            ttl: route.ttl,
+            vars: route.vars.clone(),
+            filters: route.filters.clone(),
+            filter_errors: match route.filter_errors {
+                FilterErrorPolicy::Propagate => 0,
+                FilterErrorPolicy::FailOpen => 1,
+                FilterErrorPolicy::FailClosed => 2,
+            },
+            priority: route.priority,
+            path_priorities: route.path_priorities.clone(),
+            weight: route.weight,
+            canary: route
+                .canary
+                .as_ref()
+                .map(|c| (c.percent, c.hash_on.clone())),
+            shadow: route.shadow,
+            metadata: serde_json::to_string(&route.metadata)?,
+            method_metadata,
+            tags: route.tags.clone(),
+            extends: route.extends.clone(),
+        })
+    }
+
+    /// Rebuild the definition
+    pub fn into_node(self) -> Result<RadixNode> {
+        let method_metadata = match self.method_metadata {
+            Some(entries) => Some(
+                entries
+                    .into_iter()
+                    .map(|(bits, value)| {
+                        Ok((
+                            RadixHttpMethod::from_bits_truncate(bits),
+                            serde_json::from_str(&value)?,
+                        ))
+                    })
+                    .collect::<Result<Vec<_>>>()?,
+            ),
+            None => None,
+        };
+        Ok(RadixNode {
+            id: self.id,
+            paths: self.paths,
+            extensions: self.extensions,
+            strip_prefix: self.strip_prefix,
+            rewrite: self.rewrite,
+            methods: self.methods.map(RadixHttpMethod::from_bits_truncate),
+            custom_methods: self.custom_methods,
+            hosts: self.hosts,
+            remote_addrs: self.remote_addrs,
+            schemes: self.schemes,
+            snis: self.snis,
+            server_ports: self.server_ports,
+            valid_from: self.valid_from,
+            valid_until: self.valid_until,
+            ttl: self.ttl,
+            vars: self.vars,
+            filter_fn: None,
+            filters: self.filters,
+            context_filter: None,
+            try_filter_fn: None,
+            filter_errors: match self.filter_errors {
+                0 => FilterErrorPolicy::Propagate,
+                1 => FilterErrorPolicy::FailOpen,
+                2 => FilterErrorPolicy::FailClosed,
+                tag => anyhow::bail!("Unknown filter error policy in snapshot: {}", tag),
+            },
+            priority: self.priority,
+            path_priorities: self.path_priorities,
+            weight: self.weight,
+            canary: self
+                .canary
+                .map(|(percent, hash_on)| Canary { percent, hash_on }),
+            shadow: self.shadow,
+            metadata: serde_json::from_str(&self.metadata)?,
+            method_metadata,
+            tags: self.tags,
+            extends: self.extends,
+        })
+    }
+}
+
+/// Binary form of [`Expr`], used by its `Serialize` impl for formats that
+/// are not human-readable
+#[derive(Serialize, Deserialize)]
+pub(crate) enum ExprRecord {
+    Eq(String, String),
+    Neq(String, String),
+    Gt(String, String),
+    Lt(String, String),
+    In(String, Vec<String>),
+    Regex(String, String),
+    Bucket(String, Range<u32>),
+    All(Vec<ExprRecord>),
+    Any(Vec<ExprRecord>),
+}
+
+impl RouteRecord {
+    /// Capture a processed route; fails for routes with a filter closure
+    pub fn from_opts(route: &RouteOpts) -> Result<Self> {
+        if route.filter_fn.is_some()
+            || !route.filters.is_empty()
+            || route.context_filter.is_some()
+            || route.try_filter_fn.is_some()
+        {
+            anyhow::bail!(
+                "Route {} has a filter function, which cannot be snapshotted",
+                route.id
+            );
+        }
+
+        let host_labels = |hosts: &Option<Vec<HostPattern>>| -> Option<Vec<String>> {
+            hosts
+                .as_ref()
+                .map(|hosts| hosts.iter().map(|h| h.to_string()).collect())
+        };
+        let method_metadata = match &route.method_metadata {
+            Some(entries) => Some(
+                entries
+                    .iter()
+                    .map(|(methods, value)| Ok((methods.bits(), serde_json::to_string(value)?)))
+                    .collect::<Result<Vec<_>>>()?,
+            ),
+            None => None,
+        };
+
+        Ok(Self {
+            id: route.id.clone(),
+            path: route.path.clone(),
+            path_org: route.path_org.clone(),
+            exact: route.path_op == PathOp::Equal,
+            mount: route.mount.clone(),
+            extensions: route.extensions.clone(),
+            strip_prefix: route.rewrite == Some(Rewrite::StripPrefix),
+            rewrite: route
+                .rewrite
+                .as_ref()
+                .and_then(Rewrite::source)
+                .map(str::to_string),
+            methods: route.methods.bits(),
+            custom_methods: route.custom_methods.clone(),
+            hosts: host_labels(&route.hosts),
+            schemes: route.schemes.clone(),
+            snis: host_labels(&route.snis),
+            server_ports: route.server_ports.clone(),
+            remote_addrs: route.remote_addrs.as_deref().cloned(),
+            valid_from: route.valid_from,
+            valid_until: route.valid_until,
+            expires_at: route.expires_at,
+            vars: route.vars.clone(),
+            priority: route.priority,
+            weight: route.weight,
+            canary: route
+                .canary
+                .as_ref()
+                .map(|c| (c.percent, c.hash_on.clone())),
+            shadow: route.shadow,
+            metadata: serde_json::to_string(&route.metadata)?,
+            method_metadata,
+            tags: route.tags.clone(),
+            has_param: route.compiled_pattern.is_some(),
+            seq: route.seq,
+        })
+    }
+
+    /// Rebuild the processed route, recompiling its patterns
+    pub fn into_opts(self) -> Result<RouteOpts> {
+        let host_patterns = |hosts: Option<Vec<String>>| {
+            hosts.map(|hosts| hosts.iter().map(|h| HostPattern::new(h)).collect())
+        };
+        let method_metadata = match self.method_metadata {
+            Some(entries) => Some(
+                entries
+                    .into_iter()
+                    .map(|(bits, value)| {
+                        Ok((
+                            RadixHttpMethod::from_bits_truncate(bits),
+                            Arc::new(serde_json::from_str(&value)?),
+                        ))
+                    })
+                    .collect::<Result<Vec<_>>>()?,
+            ),
+            None => None,
+        };
+        let compiled_pattern = self
+            .has_param
+            .then(|| Arc::new(PathPattern::compile(&self.path_org)));
+
+        let path_op = if self.exact {
+            PathOp::Equal
+        } else if self.path_org.starts_with('*') {
+            PathOp::SuffixMatch
+        } else {
+            PathOp::PrefixMatch
+        };
+
+        let mut opts = RouteOpts {
+            path: self.path,
+            path_org: self.path_org,
+            path_op,
+            has_param: compiled_pattern.is_some(),
+            mount: self.mount,
+            extensions: self.extensions,
+            rewrite: None,
+            methods: RadixHttpMethod::from_bits_truncate(self.methods),
+            custom_methods: self.custom_methods,
+            hosts: host_patterns(self.hosts),
+            schemes: self.schemes,
+            snis: host_patterns(self.snis),
+            server_ports: self.server_ports,
+            remote_addrs: self.remote_addrs.map(Arc::new),
+            valid_from: self.valid_from,
+            valid_until: self.valid_until,
+            expires_at: self.expires_at,
+            vars: self.vars,
+            filter_fn: None,
+            filters: Vec::new(),
+            context_filter: None,
+            try_filter_fn: None,
+            filter_errors: FilterErrorPolicy::default(),
+            priority: self.priority,
+            weight: self.weight,
+            canary: self
+                .canary
+                .map(|(percent, hash_on)| Canary { percent, hash_on }),
+            shadow: self.shadow,
+            metadata: Arc::new(serde_json::from_str(&self.metadata)?),
+            method_metadata,
+            tags: self.tags,
+            compiled_pattern,
+            seq: self.seq,
+            id: self.id,
+        };
+        opts.compile_rewrite(self.strip_prefix, self.rewrite.as_deref())?;
+        Ok(opts)
+    }
+}
+
+impl From<&Expr> for ExprRecord {
+    fn from(expr: &Expr) -> Self {
+        match expr.clone() {
+            Expr::Eq(k, v) => ExprRecord::Eq(k, v),
+            Expr::Neq(k, v) => ExprRecord::Neq(k, v),
+            Expr::Gt(k, v) => ExprRecord::Gt(k, v),
+            Expr::Lt(k, v) => ExprRecord::Lt(k, v),
+            Expr::In(k, values) => ExprRecord::In(k, values),
+            Expr::Regex(k, re) => ExprRecord::Regex(k, re.as_str().to_string()),
+            Expr::Bucket(k, range) => ExprRecord::Bucket(k, range),
+            Expr::All(exprs) => ExprRecord::All(exprs.iter().map(ExprRecord::from).collect()),
+            Expr::Any(exprs) => ExprRecord::Any(exprs.iter().map(ExprRecord::from).collect()),
+        }
+    }
+}
+
+impl TryFrom<ExprRecord> for Expr {
+    type Error = anyhow::Error;
+
+    fn try_from(record: ExprRecord) -> Result<Self> {
+        Ok(match record {
+            ExprRecord::Eq(k, v) => Expr::Eq(k, v),
+            ExprRecord::Neq(k, v) => Expr::Neq(k, v),
+            ExprRecord::Gt(k, v) => Expr::Gt(k, v),
+            ExprRecord::Lt(k, v) => Expr::Lt(k, v),
+            ExprRecord::In(k, values) => Expr::In(k, values),
+            ExprRecord::Regex(k, source) => {
+                let re = compile_regex(&source)
+                    .with_context(|| format!("Invalid var regex: {}", source))?;
+                Expr::Regex(k, re)
+            }
+            ExprRecord::Bucket(k, range) => Expr::Bucket(k, range),
+            ExprRecord::All(records) => Expr::All(
+                records
+                    .into_iter()
+                    .map(Expr::try_from)
+                    .collect::<Result<_>>()?,
+            ),
+            ExprRecord::Any(records) => Expr::Any(
+                records
+                    .into_iter()
+                    .map(Expr::try_from)
+                    .collect::<Result<_>>()?,
+            ),
+        })
+    }
+}
+
+/// Stable tag for a match policy
+pub(crate) fn encode_match_policy(policy: MatchPolicy) -> u8 {
+    match policy {
+        MatchPolicy::PriorityFirst => 0,
+        MatchPolicy::MostSpecificFirst => 1,
+        MatchPolicy::PriorityThenSpecificity => 2,
+    }
+}
+
+/// Inverse of [`encode_match_policy`]
+pub(crate) fn decode_match_policy(tag: u8) -> Result<MatchPolicy> {
+    Ok(match tag {
+        0 => MatchPolicy::PriorityFirst,
+        1 => MatchPolicy::MostSpecificFirst,
+        2 => MatchPolicy::PriorityThenSpecificity,
+        _ => anyhow::bail!("Unknown match policy in snapshot: {}", tag),
+    })
+}
+
+/// Stable tag for an id policy
+pub(crate) fn encode_id_policy(policy: IdPolicy) -> u8 {
+    match policy {
+        IdPolicy::Allow => 0,
+        IdPolicy::Reject => 1,
+        IdPolicy::Replace => 2,
+    }
+}
+
+/// Inverse of [`encode_id_policy`]
+pub(crate) fn decode_id_policy(tag: u8) -> Result<IdPolicy> {
+    Ok(match tag {
+        0 => IdPolicy::Allow,
+        1 => IdPolicy::Reject,
+        2 => IdPolicy::Replace,
+        _ => anyhow::bail!("Unknown id policy in snapshot: {}", tag),
+    })
+}
+
+/// Stable tag for a path decoding mode
+pub(crate) fn encode_path_decoding(decoding: PathDecoding) -> u8 {
+    match decoding {
+        PathDecoding::Raw => 0,
+        PathDecoding::Decode => 1,
+        PathDecoding::KeepEncodedSlash => 2,
+        PathDecoding::RejectEncodedSlash => 3,
+    }
+}
+
+/// Inverse of [`encode_path_decoding`]
+pub(crate) fn decode_path_decoding(tag: u8) -> Result<PathDecoding> {
+    Ok(match tag {
+        0 => PathDecoding::Raw,
+        1 => PathDecoding::Decode,
+        2 => PathDecoding::KeepEncodedSlash,
+        3 => PathDecoding::RejectEncodedSlash,
+        _ => anyhow::bail!("Unknown path decoding in snapshot: {}", tag),
+    })
+}
+
+/// Request limits as stored in a snapshot
+pub(crate) fn encode_limits(limits: Limits) -> [Option<usize>; 3] {
+    [
+        limits.max_path_len,
+        limits.max_param_len,
+        limits.max_segments,
+    ]
+}
+
+/// Inverse of [`encode_limits`]
+pub(crate) fn decode_limits(
+    [max_path_len, max_param_len, max_segments]: [Option<usize>; 3],
+) -> Limits {
+    Limits {
+        max_path_len,
+        max_param_len,
+        max_segments,
+    }
+}
+
+/// Table limits as stored in a snapshot
+pub(crate) fn encode_table_limits(limits: TableLimits) -> [Option<usize>; 3] {
+    [
+        limits.max_routes,
+        limits.max_regex_bytes,
+        limits.max_candidates,
+    ]
+}
+
+/// Inverse of [`encode_table_limits`]
+pub(crate) fn decode_table_limits(
+    [max_routes, max_regex_bytes, max_candidates]: [Option<usize>; 3],
+) -> TableLimits {
+    TableLimits {
+        max_routes,
+        max_regex_bytes,
+        max_candidates,
+    }
+}
@@ -0,0 +1,110 @@
+//! Snapshot files exchanged between processes
+
+use crate::router::RadixRouter;
+use anyhow::{Context, Result};
+use std::fs::{self, File};
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Leading bytes of a snapshot file
+const MAGIC: &[u8; 4] = b"RRSH";
+/// Magic plus the little-endian generation counter
+const HEADER_LEN: usize = MAGIC.len() + 8;
+
+/// Versioned snapshot file for prefork / multi-process gateways
+///
+/// One process (typically the master) calls [`SnapshotFile::publish`] after
+/// changing its routes; workers call [`SnapshotFile::sync`] periodically,
+/// which reads only the header unless the generation counter moved. The
+/// payload is a [`RadixRouter::to_snapshot`] image, so workers restore the
+/// processed table instead of rebuilding it from route definitions.
+///
+/// Nothing is shared in memory: every worker decodes the file into its own
+/// route table.
+///
+/// Files are replaced by an atomic rename, so readers always see a complete
+/// generation. Only one process should publish to a given path.
+#[derive(Debug)]
+pub struct SnapshotFile {
+    path: PathBuf,
+    /// Last generation installed by `sync`
+    seen: AtomicU64,
+}
+
+impl SnapshotFile {
+    /// Refer to a snapshot file (it need not exist yet)
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            seen: AtomicU64::new(0),
+        }
+    }
+
+    /// Location of the snapshot file
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Current generation of the file, 0 if nothing was published yet
+    pub fn generation(&self) -> Result<u64> {
+        let mut file = match File::open(&self.path) {
+            Ok(file) => file,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(0),
+            Err(err) => {
+                return Err(err).with_context(|| format!("Failed to open {}", self.path.display()))
+            }
+        };
+        let mut header = [0u8; HEADER_LEN];
+        file.read_exact(&mut header)
+            .with_context(|| format!("Truncated snapshot file: {}", self.path.display()))?;
+        parse_header(&header)
+    }
+
+    /// Write the router's routes as the next generation
+    ///
+    /// Returns the new generation number.
+    pub fn publish(&self, router: &RadixRouter) -> Result<u64> {
+        let generation = self.generation()? + 1;
+        let mut bytes = MAGIC.to_vec();
+        bytes.extend_from_slice(&generation.to_le_bytes());
+        bytes.extend_from_slice(&router.to_snapshot()?);
+
+        let mut tmp = self.path.clone().into_os_string();
+        tmp.push(format!(".{}.tmp", std::process::id()));
+        fs::write(&tmp, &bytes).with_context(|| format!("Failed to write {:?}", tmp))?;
+        fs::rename(&tmp, &self.path)
+            .with_context(|| format!("Failed to publish {}", self.path.display()))?;
+        Ok(generation)
+    }
+
+    /// Install the published routes into `router` if a newer generation exists
+    ///
+    /// Returns whether the router was updated. The router keeps its own
+    /// match policy; routes and id policy come from the published table.
+    pub fn sync(&self, router: &RadixRouter) -> Result<bool> {
+        let seen = self.seen.load(Ordering::Acquire);
+        if self.generation()? == seen {
+            return Ok(false);
+        }
+
+        let bytes = fs::read(&self.path)
+            .with_context(|| format!("Failed to read {}", self.path.display()))?;
+        let header = bytes
+            .get(..HEADER_LEN)
+            .with_context(|| format!("Truncated snapshot file: {}", self.path.display()))?;
+        let generation = parse_header(header)?;
+        router.restore_snapshot(&bytes[HEADER_LEN..])?;
+        self.seen.store(generation, Ordering::Release);
+        Ok(true)
+    }
+}
+
+/// Generation number from a snapshot file header
+fn parse_header(header: &[u8]) -> Result<u64> {
+    let generation = header
+        .strip_prefix(MAGIC.as_slice())
+        .and_then(|rest| rest.first_chunk::<8>())
+        .context("Not a router snapshot file")?;
+    Ok(u64::from_le_bytes(*generation))
+}
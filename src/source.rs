@@ -0,0 +1,108 @@
+//! Pluggable backends that feed routes into a router
+
+use crate::route::RadixNode;
+use crate::router::RadixRouter;
+use anyhow::Result;
+use std::sync::{Arc, Weak};
+
+/// Backend holding the desired route set (a file, etcd, Consul, a database)
+///
+/// Attach one with [`RadixRouter::sync_from`]. Sources always deliver the
+/// complete route set; the router reconciles it with
+/// [`apply_diff`](RadixRouter::apply_diff), so only routes that actually
+/// changed are touched and every backend gets the same semantics.
+pub trait RouteSource: Send + 'static {
+    /// Fetch the current route set
+    fn initial_load(&mut self) -> Result<Vec<RadixNode>>;
+
+    /// Start watching for changes
+    ///
+    /// Whenever the route set may have changed, push the complete new set
+    /// (or the error that prevented loading it) into `sink`. Delivery can
+    /// happen from any thread. Watching must stop when the returned
+    /// [`Subscription`] is dropped; [`RouteSink::push`] returning `false`
+    /// means the router is gone and the source can stop early. Changes made
+    /// between `initial_load` and the subscription taking effect must not
+    /// be lost: a source that cannot rule them out pushes once after
+    /// subscribing (an unchanged set is a no-op).
+    fn subscribe(&mut self, sink: RouteSink) -> Result<Subscription>;
+}
+
+/// Channel from a [`RouteSource`] back to its router
+#[derive(Clone)]
+pub struct RouteSink {
+    router: Weak<RadixRouter>,
+    on_error: Arc<dyn Fn(anyhow::Error) + Send + Sync>,
+}
+
+impl RouteSink {
+    /// Reconcile the router with a freshly loaded route set
+    ///
+    /// Load errors, and route sets the router rejects, are passed to the
+    /// error callback and leave the current routes in place. Returns
+    /// `false` once the router has been dropped.
+    pub fn push(&self, routes: Result<Vec<RadixNode>>) -> bool {
+        let Some(router) = self.router.upgrade() else {
+            return false;
+        };
+        if let Err(err) = routes.and_then(|routes| router.apply_diff(routes)) {
+            (self.on_error)(err);
+        }
+        true
+    }
+
+    /// Whether the router has been dropped
+    pub fn is_closed(&self) -> bool {
+        self.router.strong_count() == 0
+    }
+}
+
+impl std::fmt::Debug for RouteSink {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RouteSink")
+            .field("closed", &self.is_closed())
+            .finish()
+    }
+}
+
+/// Running subscription of a [`RouteSource`]; dropping it stops updates
+pub struct Subscription {
+    _guard: Box<dyn Send>,
+}
+
+impl Subscription {
+    /// Wrap whatever keeps the source's watch alive (a watcher, a thread
+    /// handle with a stop flag, ...)
+    pub fn new(guard: impl Send + 'static) -> Self {
+        Self {
+            _guard: Box::new(guard),
+        }
+    }
+}
+
+impl std::fmt::Debug for Subscription {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Subscription").finish_non_exhaustive()
+    }
+}
+
+impl RadixRouter {
+    /// Load routes from `source` and keep following its changes
+    ///
+    /// The initial load is applied before this returns, so a broken source
+    /// fails startup. Later updates that fail to load or validate are passed
+    /// to `on_error` and leave the current routes in place. The source only
+    /// holds a weak reference to the router, and updates stop when the
+    /// returned subscription is dropped.
+    pub fn sync_from<S, F>(self: &Arc<Self>, mut source: S, on_error: F) -> Result<Subscription>
+    where
+        S: RouteSource,
+        F: Fn(anyhow::Error) + Send + Sync + 'static,
+    {
+        self.apply_diff(source.initial_load()?)?;
+        source.subscribe(RouteSink {
+            router: Arc::downgrade(self),
+            on_error: Arc::new(on_error),
+        })
+    }
+}
@@ -0,0 +1,63 @@
+//! Optional match telemetry
+//!
+//! With the `metrics` feature enabled, lookups report through the
+//! [`metrics`](https://docs.rs/metrics) facade:
+//!
+//! - `radix_router_matches_total{route_id}`: counter of matched requests
+//! - `radix_router_misses_total`: counter of requests no route accepted
+//! - `radix_router_match_duration`: histogram of lookup time in seconds
+//!
+//! With the `tracing` feature enabled, `match_route`, `match_outcome`,
+//! `add_route` and `delete_route` open debug-level spans, and lookups emit
+//! debug events for every rejected candidate (route id, pattern, reason)
+//! and for the final decision.
+//!
+//! Without the features every hook compiles to nothing.
+
+/// Emit a debug-level `tracing` event when the `tracing` feature is enabled
+macro_rules! debug_event {
+    ($($arg:tt)*) => {{
+        #[cfg(feature = "tracing")]
+        tracing::debug!($($arg)*);
+    }};
+}
+pub(crate) use debug_event;
+
+/// Timer started at the beginning of a lookup
+pub(crate) struct MatchTimer {
+    #[cfg(feature = "metrics")]
+    start: std::time::Instant,
+}
+
+impl MatchTimer {
+    #[inline]
+    pub fn start() -> Self {
+        Self {
+            #[cfg(feature = "metrics")]
+            start: std::time::Instant::now(),
+        }
+    }
+
+    /// Record the outcome and duration of the lookup
+    #[inline]
+    pub fn finish(self, path: &str, route_id: Option<&str>) {
+        #[cfg(feature = "tracing")]
+        match route_id {
+            Some(route_id) => tracing::debug!(path, route_id, "route matched"),
+            None => tracing::debug!(path, "no route matched"),
+        }
+        #[cfg(feature = "metrics")]
+        {
+            metrics::histogram!("radix_router_match_duration")
+                .record(self.start.elapsed().as_secs_f64());
+            match route_id {
+                Some(route_id) => {
+                    metrics::counter!("radix_router_matches_total", "route_id" => route_id.to_string())
+                        .increment(1)
+                }
+                None => metrics::counter!("radix_router_misses_total").increment(1),
+            }
+        }
+        let _ = (path, route_id);
+    }
+}
@@ -0,0 +1,93 @@
+//! Batched route mutations applied atomically
+
+use crate::route::RadixNode;
+
+/// Operation recorded by a [`Transaction`]
+pub(crate) enum TxOp {
+    Add(RadixNode),
+    Delete(RadixNode),
+    DeleteById(String),
+    UpdateMetadata(String, serde_json::Value),
+}
+
+/// Recorder for a batch of route mutations
+///
+/// Obtained through [`RadixRouter::transaction`](crate::RadixRouter::transaction).
+/// Operations are only recorded here; they are validated and applied
+/// together once the transaction closure returns.
+#[derive(Default)]
+pub struct Transaction {
+    ops: Vec<TxOp>,
+}
+
+impl Transaction {
+    /// Add a route
+    pub fn add(&mut self, route: RadixNode) -> &mut Self {
+        self.ops.push(TxOp::Add(route));
+        self
+    }
+
+    /// Delete a route by its full definition (id and paths)
+    pub fn delete(&mut self, route: RadixNode) -> &mut Self {
+        self.ops.push(TxOp::Delete(route));
+        self
+    }
+
+    /// Delete a route and all of its paths by id
+    pub fn delete_by_id(&mut self, id: impl Into<String>) -> &mut Self {
+        self.ops.push(TxOp::DeleteById(id.into()));
+        self
+    }
+
+    /// Replace the metadata of a route
+    pub fn update_metadata(
+        &mut self,
+        id: impl Into<String>,
+        metadata: serde_json::Value,
+    ) -> &mut Self {
+        self.ops.push(TxOp::UpdateMetadata(id.into(), metadata));
+        self
+    }
+
+    /// Number of recorded operations
+    pub fn len(&self) -> usize {
+        self.ops.len()
+    }
+
+    /// Whether no operations were recorded
+    pub fn is_empty(&self) -> bool {
+        self.ops.is_empty()
+    }
+
+    pub(crate) fn into_ops(self) -> Vec<TxOp> {
+        self.ops
+    }
+}
+
+/// Summary of the changes applied by [`RadixRouter::apply_diff`](crate::RadixRouter::apply_diff)
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ChangeReport {
+    /// Ids of routes that were added
+    pub added: Vec<String>,
+    /// Ids of routes whose definition changed and were replaced
+    pub changed: Vec<String>,
+    /// Ids of routes that were removed
+    pub removed: Vec<String>,
+    /// Number of routes left untouched
+    pub unchanged: usize,
+}
+
+impl ChangeReport {
+    /// Whether the diff changed anything
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.changed.is_empty() && self.removed.is_empty()
+    }
+}
+
+impl std::fmt::Debug for Transaction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Transaction")
+            .field("ops", &self.ops.len())
+            .finish()
+    }
+}
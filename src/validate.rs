@@ -0,0 +1,91 @@
+//! Dry-run validation of route definitions
+
+use std::fmt;
+
+/// How serious a [`ValidationIssue`] is
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Severity {
+    /// The route set would be rejected
+    Error,
+    /// The route set would load, but likely not as intended
+    Warning,
+}
+
+/// Problem found by [`RadixRouter::validate_routes`](crate::RadixRouter::validate_routes)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ValidationIssue {
+    /// Route the issue is about; `None` for the route set as a whole
+    pub id: Option<String>,
+    /// Offending field, named as in [`RadixNode`](crate::RadixNode)
+    /// (`paths`, `rewrite`, ...), or `route` / `table` when no single
+    /// field is to blame
+    pub field: String,
+    /// Human-readable explanation, the error text loading would report
+    pub reason: String,
+    /// Whether loading would fail
+    pub severity: Severity,
+}
+
+impl ValidationIssue {
+    pub(crate) fn warning(id: &str, field: &str, reason: String) -> Self {
+        Self {
+            id: Some(id.to_string()),
+            field: field.to_string(),
+            reason,
+            severity: Severity::Warning,
+        }
+    }
+
+    /// Error raised while loading route `id` (`None`: the whole set)
+    pub(crate) fn from_error(id: Option<&str>, err: &anyhow::Error) -> Self {
+        let field = match err.downcast_ref::<InvalidField>() {
+            Some(invalid) => invalid.field,
+            None if id.is_some() => "route",
+            None => "table",
+        };
+        Self {
+            id: id.map(str::to_string),
+            field: field.to_string(),
+            reason: format!("{:#}", err),
+            severity: Severity::Error,
+        }
+    }
+}
+
+impl fmt::Display for ValidationIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let level = match self.severity {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+        };
+        write!(f, "{} [{}]: {}", level, self.field, self.reason)
+    }
+}
+
+/// Error message tagged with the route field it is about
+///
+/// Attached where route checks fail, either as the error itself or as
+/// context, so validation can report the field without parsing messages.
+/// Displays as the bare message, leaving error text unchanged.
+#[derive(Debug)]
+pub(crate) struct InvalidField {
+    field: &'static str,
+    message: String,
+}
+
+impl InvalidField {
+    pub fn new(field: &'static str, message: String) -> Self {
+        Self { field, message }
+    }
+}
+
+impl fmt::Display for InvalidField {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+/// Error about `field` of a route
+pub(crate) fn invalid(field: &'static str, message: String) -> anyhow::Error {
+    anyhow::Error::msg(InvalidField::new(field, message))
+}
@@ -0,0 +1,118 @@
+//! Hot reload of route files
+//!
+//! Enabled by the `watch` feature.
+
+use crate::config::RouteConfig;
+use crate::route::RadixNode;
+use crate::router::RadixRouter;
+use crate::source::{RouteSink, RouteSource, Subscription};
+use anyhow::{Context, Result};
+use notify::{EventKind, RecursiveMode, Watcher};
+use std::path::{Path, PathBuf};
+use std::sync::{mpsc, Arc};
+use std::time::Duration;
+
+/// Quiet period after a change before the file is reparsed
+///
+/// Editors and deploy tools often write a file in several steps; waiting
+/// for the burst to settle avoids parsing it half written.
+const DEBOUNCE: Duration = Duration::from_millis(50);
+
+/// [`RouteSource`] backed by a [`RouteConfig`] file
+///
+/// See [`RouteConfig::list_from_file`] for the accepted formats. Changes
+/// are picked up through filesystem notifications, including files
+/// replaced by a rename.
+#[derive(Debug, Clone)]
+pub struct FileSource {
+    path: PathBuf,
+}
+
+impl FileSource {
+    pub fn new(path: impl AsRef<Path>) -> Result<Self> {
+        let path = std::path::absolute(path.as_ref())
+            .with_context(|| format!("Invalid path {}", path.as_ref().display()))?;
+        if path.file_name().is_none() {
+            anyhow::bail!("Not a file path: {}", path.display());
+        }
+        Ok(Self { path })
+    }
+
+    /// File being watched
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    fn load(path: &Path) -> Result<Vec<RadixNode>> {
+        RouteConfig::list_from_file(path)?
+            .into_iter()
+            .map(RouteConfig::into_node)
+            .collect::<Result<Vec<_>>>()
+            .with_context(|| format!("Invalid routes in {}", path.display()))
+    }
+}
+
+impl RouteSource for FileSource {
+    fn initial_load(&mut self) -> Result<Vec<RadixNode>> {
+        Self::load(&self.path)
+    }
+
+    fn subscribe(&mut self, sink: RouteSink) -> Result<Subscription> {
+        let dir = self.path.parent().unwrap_or(Path::new("/")).to_path_buf();
+        let name = self.path.file_name().map(|n| n.to_os_string());
+
+        // Watch the directory rather than the file so replacing the file
+        // (write to a temp file, then rename) is picked up as well
+        let (tx, rx) = mpsc::channel();
+        let mut watcher =
+            notify::recommended_watcher(move |event: notify::Result<notify::Event>| {
+                let relevant = event.map_or(true, |event| {
+                    !matches!(event.kind, EventKind::Access(_) | EventKind::Remove(_))
+                        && event.paths.iter().any(|p| p.file_name() == name.as_deref())
+                });
+                if relevant {
+                    let _ = tx.send(());
+                }
+            })
+            .context("Failed to create file watcher")?;
+        watcher
+            .watch(&dir, RecursiveMode::NonRecursive)
+            .with_context(|| format!("Failed to watch {}", dir.display()))?;
+
+        let path = self.path.clone();
+        std::thread::Builder::new()
+            .name("radix-router-watch".to_string())
+            .spawn(move || {
+                // Catch up on edits made before the watch was in place
+                let mut open = sink.push(Self::load(&path));
+                // Ends once the watcher (and with it the sender) is dropped
+                while open && rx.recv().is_ok() {
+                    while rx.recv_timeout(DEBOUNCE).is_ok() {}
+                    open = sink.push(Self::load(&path));
+                }
+            })
+            .context("Failed to spawn watcher thread")?;
+
+        Ok(Subscription::new(watcher))
+    }
+}
+
+impl RadixRouter {
+    /// Load routes from a file and reload them whenever it changes
+    ///
+    /// Shorthand for [`sync_from`](Self::sync_from) with a [`FileSource`]:
+    /// the file is loaded before this returns, each change is reconciled
+    /// atomically, and a file that fails to parse or validate keeps the
+    /// current routes and is reported to `on_error`. Watching stops when
+    /// the returned subscription is dropped.
+    pub fn watch_file<F>(
+        self: &Arc<Self>,
+        path: impl AsRef<Path>,
+        on_error: F,
+    ) -> Result<Subscription>
+    where
+        F: Fn(anyhow::Error) + Send + Sync + 'static,
+    {
+        self.sync_from(FileSource::new(path)?, on_error)
+    }
+}